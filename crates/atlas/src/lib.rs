@@ -18,6 +18,81 @@ pub mod backend;
 pub const DEFAULT_TILE_SIZE: u32 = 128;
 pub const DEFAULT_ATLAS_SIZE: Size = Size::new(2048, 2048);
 
+/// Controls how textures are split into tiles before being allocated onto an atlas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilingOptions {
+    /// The maximum width and height of a tile.
+    pub tile_size: u32,
+    /// Textures with both sides at most this size are allocated whole instead of being tiled.
+    pub whole_threshold: u32,
+    /// Split textures into evenly sized rectangular tiles, rather than square tiles with thin
+    /// remainders at the right and bottom edges.
+    pub fit_content: bool,
+    /// The amount of pixels to pad each tile with, the padding repeats the edge pixels of the
+    /// tile so that filtering at the edges of a tile never samples a neighbouring allocation.
+    pub gutter: u32,
+}
+
+impl TilingOptions {
+    pub const DEFAULT: Self = Self {
+        tile_size: DEFAULT_TILE_SIZE,
+        whole_threshold: DEFAULT_TILE_SIZE,
+        fit_content: false,
+        gutter: 1,
+    };
+
+    pub const fn new(tile_size: u32) -> Self {
+        Self {
+            tile_size,
+            whole_threshold: tile_size,
+            fit_content: false,
+            gutter: Self::DEFAULT.gutter,
+        }
+    }
+
+    /// Returns the areas of a `width`x`height` texture that each tile covers.
+    pub fn tile_rects(&self, width: u32, height: u32) -> Vec<Box2D<u32>> {
+        if width == 0 || height == 0 {
+            return vec![];
+        }
+        if width <= self.whole_threshold.max(self.tile_size)
+            && height <= self.whole_threshold.max(self.tile_size)
+        {
+            return vec![Box2D::new(Point2D::zero(), Point2D::new(width, height))];
+        }
+
+        let tile_size = self.tile_size.max(1);
+        let cols = width.div_ceil(tile_size);
+        let rows = height.div_ceil(tile_size);
+        let (tile_width, tile_height) = if self.fit_content {
+            (width.div_ceil(cols), height.div_ceil(rows))
+        } else {
+            (tile_size, tile_size)
+        };
+
+        let mut rects = Vec::with_capacity((rows * cols) as usize);
+        for row in 0..rows {
+            for column in 0..cols {
+                let min = Point2D::new(column * tile_width, row * tile_height);
+                let max = Point2D::new(
+                    (min.x + tile_width).min(width),
+                    (min.y + tile_height).min(height),
+                );
+                if max.x > min.x && max.y > min.y {
+                    rects.push(Box2D::new(min, max));
+                }
+            }
+        }
+        rects
+    }
+}
+
+impl Default for TilingOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 pub struct UnallocatedTexture<'a> {
     pub data: &'a [u8],
     pub width: u32,
@@ -36,7 +111,6 @@ impl<'a> UnallocatedTexture<'a> {
 #[derive(Debug, Clone)]
 pub struct AllocatedTexture<T: AtlasFormat, D> {
     tiles: Vec<AllocatedTile>,
-    tile_size: u32,
 
     width: u32,
     height: u32,
@@ -63,8 +137,8 @@ impl<T: AtlasFormat, D> AllocatedTexture<T, D> {
         let area_ratio_h = self.height as f32 / output_size.height;
 
         for tile in &self.tiles {
-            // we use this instead of self.tile_size since we support non-standard tiles for the
-            // edges of an texture e.g. with a tile size of 3
+            // tiles are not guaranteed to share a size (e.g. edge tiles, or rectangular tiles when
+            // fitting content), so use the allocated size and offset of each tile.
             // --------------
             // 111 | 111 | 11
             // 111 | 111 | 11 <- this tile is 2x3
@@ -73,13 +147,10 @@ impl<T: AtlasFormat, D> AllocatedTexture<T, D> {
             // 111 | 111 | 11 <- this tile is 2x1
             // ^-----^
             // these tile are 3x1
-            //
-            // since real_tile_size will always equal self.tile_size (apart from the edges), it is
-            // fine to use it for the second pair of coordinates.
             let real_tile_size = tile.location.rect.to_f32().size();
 
-            let x1 = area.min.x + ((tile.column * self.tile_size) as f32 * area_ratio_w);
-            let y1 = area.min.y + ((tile.row * self.tile_size) as f32 * area_ratio_h);
+            let x1 = area.min.x + (tile.offset.x as f32 * area_ratio_w);
+            let y1 = area.min.y + (tile.offset.y as f32 * area_ratio_h);
 
             let x2 = x1 + (real_tile_size.width * area_ratio_w);
             let y2 = y1 + (real_tile_size.height * area_ratio_h);
@@ -98,8 +169,8 @@ impl<T: AtlasFormat, D> AllocatedTexture<T, D> {
 pub struct UnallocatedTile<T: AtlasFormat> {
     data: Vec<u8>,
 
-    column: u32,
-    row: u32,
+    /// Where this tile starts within the source texture.
+    offset: Point2D<u32>,
 
     width: u32,
     height: u32,
@@ -108,8 +179,8 @@ pub struct UnallocatedTile<T: AtlasFormat> {
 }
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 struct AllocatedTile {
-    column: u32,
-    row: u32,
+    /// Where this tile starts within the source texture.
+    offset: Point2D<u32>,
     location: AtlasLocation,
 }
 impl AllocatedTile {
@@ -137,14 +208,20 @@ pub struct AtlasLocation {
     pub rect: Rectangle,
 }
 
+/// Returned once after the texture view of an atlas was replaced, any bind group holding the
+/// old view has to be rebuilt.
+#[must_use = "bind groups using the atlas must be rebuilt"]
+#[derive(Debug, PartialEq, Eq)]
+pub struct RebindToken(());
+
 pub struct LayeredAtlas<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> {
     layers: Vec<AtlasAllocator>,
     allocations: HashMap<Option<K>, Arc<AllocatedTexture<T, D>>, Hasher>,
 
     size: Size,
-    /// When allocating an area greater than this (e.g. tile_size x tile_size), split it into
-    /// chunks of size at most tile_size, this allows us to pack large textures tighter.
-    tile_size: u32,
+    /// When allocating an area greater than the tile size, split it into chunks of at most the
+    /// tile size, this allows us to pack large textures tighter.
+    tiling: TilingOptions,
 
     texture: Texture,
     pub texture_view: TextureView,
@@ -153,12 +230,12 @@ pub struct LayeredAtlas<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> {
     max_size: Size,
     max_layers: u32,
 
-    pub needs_rebinding: bool,
+    needs_rebinding: bool,
     _marker: PhantomData<T>,
 }
 
 impl<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> LayeredAtlas<T, K, D> {
-    pub fn new(device: &Device, size: Size, tile_size: u32, limits: Limits) -> Self {
+    pub fn new(device: &Device, size: Size, tiling: TilingOptions, limits: Limits) -> Self {
         let max_size = Size::splat(limits.max_texture_dimension_2d as i32);
         let max_layers = limits.max_texture_array_layers as u32;
 
@@ -182,7 +259,7 @@ impl<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> LayeredAtlas<T, K, D> {
             allocations: HashMap::with_hasher(Hasher::new()),
 
             size,
-            tile_size,
+            tiling,
 
             texture,
             texture_view,
@@ -257,6 +334,11 @@ impl<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> LayeredAtlas<T, K, D> {
         encoder.finish()
     }
 
+    /// Takes the pending rebind, the token is only returned once per change of the texture view.
+    pub fn take_rebind_token(&mut self) -> Option<RebindToken> {
+        std::mem::take(&mut self.needs_rebinding).then_some(RebindToken(()))
+    }
+
     pub fn is_allocated(&mut self, key: K) -> Option<Arc<AllocatedTexture<T, D>>> {
         self.allocations.get(&Some(key)).map(|x| x.clone())
     }
@@ -293,7 +375,6 @@ impl<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> LayeredAtlas<T, K, D> {
 
         let allocation = Arc::new(AllocatedTexture {
             tiles: allocated_tiles,
-            tile_size: self.tile_size,
 
             width,
             height,
@@ -314,9 +395,14 @@ impl<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> LayeredAtlas<T, K, D> {
         queue: &wgpu::Queue,
         tile: UnallocatedTile<T>,
     ) -> Result<AllocatedTile> {
+        let gutter = self.tiling.gutter;
+        let (padded_width, padded_height) = (tile.width + gutter * 2, tile.height + gutter * 2);
         // try allocate in existing layers
         for (layer, atlas) in self.layers.iter_mut().enumerate() {
-            if let Some(area) = atlas.allocate(Size::new(tile.width as i32, tile.height as i32)) {
+            if let Some(area) = atlas.allocate(Size::new(padded_width as i32, padded_height as i32))
+            {
+                let stride = T::format().components() as u32;
+                let data = pad_tile(&tile.data, tile.width, tile.height, stride, gutter);
                 queue.write_texture(
                     TexelCopyTextureInfo {
                         texture: &self.texture,
@@ -328,25 +414,26 @@ impl<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> LayeredAtlas<T, K, D> {
                         },
                         aspect: TextureAspect::All,
                     },
-                    tile.data.as_ref(),
+                    data.as_ref(),
                     TexelCopyBufferLayout {
                         offset: 0,
-                        bytes_per_row: Some(tile.width * T::format().components() as u32),
+                        bytes_per_row: Some(padded_width * stride),
                         rows_per_image: None,
                     },
                     Extent3d {
-                        width: tile.width,
-                        height: tile.height,
+                        width: padded_width,
+                        height: padded_height,
                         depth_or_array_layers: 1,
                     },
                 );
+                // the location only covers the tile itself, so the uvs never reach the gutter.
+                let rect = area.rectangle.inflate(-(gutter as i32), -(gutter as i32));
                 return Ok(AllocatedTile {
-                    column: tile.column,
-                    row: tile.row,
+                    offset: tile.offset,
                     location: AtlasLocation {
                         layer: layer as u32,
                         id: area.id,
-                        rect: area.rectangle,
+                        rect,
                     },
                 });
             }
@@ -424,8 +511,7 @@ impl<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> LayeredAtlas<T, K, D> {
             queue,
             UnallocatedTile {
                 data: contents,
-                column: 0,
-                row: 0,
+                offset: Point2D::zero(),
                 width,
                 height,
                 _marker: PhantomData,
@@ -433,7 +519,6 @@ impl<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> LayeredAtlas<T, K, D> {
         )?;
         let allocated = Arc::new(AllocatedTexture {
             tiles: vec![tile],
-            tile_size: self.tile_size,
             width,
             height,
 
@@ -445,47 +530,45 @@ impl<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> LayeredAtlas<T, K, D> {
         Ok(allocated)
     }
 
-    pub fn tile_texture(&self, texture: UnallocatedTexture) -> Vec<UnallocatedTile<T>> {
-        let mut tiles: Vec<UnallocatedTile<T>> = Vec::new();
-        let rows = (texture.height + self.tile_size - 1) / self.tile_size;
-        let cols = (texture.width + self.tile_size - 1) / self.tile_size;
-        tiles.reserve_exact((rows * cols) as usize);
+    pub const fn tiling(&self) -> TilingOptions {
+        self.tiling
+    }
+    /// Changes how future allocations are tiled, existing allocations are left untouched.
+    pub const fn set_tiling(&mut self, tiling: TilingOptions) {
+        self.tiling = tiling;
+    }
 
+    pub fn tile_texture(&self, texture: UnallocatedTexture) -> Vec<UnallocatedTile<T>> {
         let stride = T::format().components() as u32;
 
-        for row in 0..rows {
-            for column in 0..cols {
-                let x = column * self.tile_size;
-                let y = row * self.tile_size;
-
-                let tile_width = (texture.width - x).min(self.tile_size);
-                let tile_height = (texture.height - y).min(self.tile_size);
+        self.tiling
+            .tile_rects(texture.width, texture.height)
+            .into_iter()
+            .map(|rect| {
+                let size = rect.size();
 
                 let mut tile_data = Vec::new();
-                tile_data.reserve_exact((tile_width * tile_height) as usize);
+                tile_data.reserve_exact((size.width * size.height * stride) as usize);
 
-                for ty in 0..tile_height {
-                    let src_start = ((y + ty) * texture.width + x) * stride;
-                    let src_end = src_start + tile_width * stride;
+                for ty in rect.min.y..rect.max.y {
+                    let src_start = (ty * texture.width + rect.min.x) * stride;
+                    let src_end = src_start + size.width * stride;
                     tile_data
                         .extend_from_slice(&texture.data[src_start as usize..src_end as usize]);
                 }
 
-                tiles.push(UnallocatedTile {
+                UnallocatedTile {
                     data: tile_data,
 
-                    column,
-                    row,
+                    offset: rect.min,
 
-                    width: tile_width,
-                    height: tile_height,
+                    width: size.width,
+                    height: size.height,
 
                     _marker: PhantomData,
-                });
-            }
-        }
-
-        tiles
+                }
+            })
+            .collect()
     }
     pub fn deallocate(&mut self) {
         tracing::trace!("calling deallocate on atlas allocation");
@@ -509,6 +592,25 @@ impl<T: AtlasFormat, K: std::hash::Hash + Eq + Clone, D> LayeredAtlas<T, K, D> {
     pub fn rearrange(&mut self) {}
 }
 
+/// Surrounds tightly packed tile data with `gutter` pixels on each side, repeating the pixels at
+/// the edge of the tile.
+fn pad_tile(data: &[u8], width: u32, height: u32, stride: u32, gutter: u32) -> Vec<u8> {
+    if gutter == 0 || width == 0 || height == 0 {
+        return data.to_vec();
+    }
+    let (padded_width, padded_height) = (width + gutter * 2, height + gutter * 2);
+    let mut padded = Vec::with_capacity((padded_width * padded_height * stride) as usize);
+    for py in 0..padded_height {
+        let y = py.saturating_sub(gutter).min(height - 1);
+        for px in 0..padded_width {
+            let x = px.saturating_sub(gutter).min(width - 1);
+            let src = ((y * width + x) * stride) as usize;
+            padded.extend_from_slice(&data[src..src + stride as usize]);
+        }
+    }
+    padded
+}
+
 pub mod formats {
     #[derive(Clone, Copy, Debug)]
     pub struct Rgba8;
@@ -594,3 +696,212 @@ impl TextureVertex {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::sync::mpsc;
+    use std::task::{Context, Poll, Waker};
+
+    use guillotiere::Size;
+
+    use super::{formats, pad_tile, LayeredAtlas, TilingOptions};
+
+    /// The texel of a tightly packed `width` wide tile that a padded pixel should repeat, being
+    /// the closest texel of the tile to it.
+    fn edge_texel(data: &[u8], width: u32, height: u32, stride: u32, x: i32, y: i32) -> &[u8] {
+        let x = x.clamp(0, width as i32 - 1) as u32;
+        let y = y.clamp(0, height as i32 - 1) as u32;
+        let start = ((y * width + x) * stride) as usize;
+        &data[start..start + stride as usize]
+    }
+
+    /// Waits on a future of wgpu, which resolves right away on native backends.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    /// Checks that the tiles cover every pixel of the texture exactly once.
+    fn assert_covers(options: TilingOptions, width: u32, height: u32) {
+        let mut hits = vec![0u8; (width * height) as usize];
+        for rect in options.tile_rects(width, height) {
+            assert!(rect.size().width <= options.tile_size.max(options.whole_threshold));
+            assert!(rect.size().height <= options.tile_size.max(options.whole_threshold));
+            for y in rect.min.y..rect.max.y {
+                for x in rect.min.x..rect.max.x {
+                    hits[(y * width + x) as usize] += 1;
+                }
+            }
+        }
+        assert!(hits.iter().all(|hit| *hit == 1));
+    }
+
+    #[test]
+    fn square_tiles_cover_texture() {
+        assert_covers(TilingOptions::new(16), 50, 33);
+        assert_eq!(TilingOptions::new(16).tile_rects(50, 33).len(), 4 * 3);
+    }
+
+    #[test]
+    fn fitted_tiles_cover_texture() {
+        let options = TilingOptions {
+            fit_content: true,
+            ..TilingOptions::new(16)
+        };
+        assert_covers(options, 50, 33);
+        assert_covers(options, 17, 200);
+
+        // a 17px wide texture is split into two halves rather than a 16px tile and a 1px sliver.
+        let rects = options.tile_rects(17, 4);
+        assert_eq!(rects.len(), 2);
+        assert!(rects.iter().all(|rect| rect.size().width >= 8));
+    }
+
+    #[test]
+    fn small_textures_are_allocated_whole() {
+        let options = TilingOptions {
+            whole_threshold: 64,
+            ..TilingOptions::new(16)
+        };
+        assert_eq!(options.tile_rects(10, 60).len(), 1);
+        assert_covers(options, 10, 60);
+        assert_covers(options, 65, 10);
+        assert!(options.tile_rects(0, 10).is_empty());
+    }
+
+    #[test]
+    fn gutter_repeats_edge_pixels() {
+        // a horizontal gradient, each pixel being its column.
+        let (width, height) = (4, 2);
+        let data = (0..height)
+            .flat_map(|_| 0..width as u8)
+            .collect::<Vec<_>>();
+
+        let padded = pad_tile(&data, width, height, 1, 1);
+        assert_eq!(padded.len(), 6 * 4);
+        for row in padded.chunks(6) {
+            assert_eq!(row, &[0, 0, 1, 2, 3, 3]);
+        }
+        assert_eq!(pad_tile(&data, width, height, 1, 0), data);
+    }
+
+    #[test]
+    fn gutter_repeats_the_edge_texels_on_every_side() {
+        // Every channel of every texel is unique, so a texel repeated from the wrong place shows.
+        let (width, height, stride, gutter) = (3, 2, 4, 2);
+        let data = (0..width * height * stride)
+            .map(|i| i as u8)
+            .collect::<Vec<_>>();
+
+        let padded = pad_tile(&data, width, height, stride, gutter);
+        let padded_width = width + gutter * 2;
+        assert_eq!(
+            padded.len(),
+            (padded_width * (height + gutter * 2) * stride) as usize
+        );
+        for (i, texel) in padded.chunks(stride as usize).enumerate() {
+            let (px, py) = (i as u32 % padded_width, i as u32 / padded_width);
+            let (x, y) = (px as i32 - gutter as i32, py as i32 - gutter as i32);
+            assert_eq!(
+                texel,
+                edge_texel(&data, width, height, stride, x, y),
+                "at {px},{py}"
+            );
+        }
+        // The corners of the gutter take the corner texels.
+        assert_eq!(&padded[..4], &data[..4]);
+        assert_eq!(&padded[padded.len() - 4..], &data[data.len() - 4..]);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn allocated_tiles_are_surrounded_by_their_edge_texels() {
+        let instance = wgpu::Instance::default();
+        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+            .expect("the gpu tests need a gpu");
+        let (device, queue) = block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+            .expect("failed to create a device");
+
+        let mut atlas = LayeredAtlas::<formats::Mask, u32, ()>::new(
+            &device,
+            Size::new(64, 64),
+            TilingOptions::DEFAULT,
+            device.limits(),
+        );
+        let (width, height) = (3, 2);
+        let data = (1..=width * height)
+            .map(|i| i as u8 * 40)
+            .collect::<Vec<_>>();
+        let allocated = atlas
+            .allocate_raw(&device, &queue, data.clone(), width, height, None, ())
+            .unwrap();
+        let rect = allocated.tiles[0].location.rect;
+        assert_eq!((rect.width(), rect.height()), (width as i32, height as i32));
+
+        // Read back the tile with its gutter around it.
+        let gutter = TilingOptions::DEFAULT.gutter as i32;
+        let padded = rect.inflate(gutter, gutter);
+        let extent = wgpu::Extent3d {
+            width: padded.width() as u32,
+            height: padded.height() as u32,
+            depth_or_array_layers: 1,
+        };
+        let row = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: u64::from(row * extent.height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &atlas.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: padded.min.x as u32,
+                    y: padded.min.y as u32,
+                    z: allocated.tiles[0].location.layer,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(row),
+                    rows_per_image: None,
+                },
+            },
+            extent,
+        );
+        queue.submit([encoder.finish()]);
+        let (sender, receiver) = mpsc::channel();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                _ = sender.send(result);
+            });
+        device.poll(wgpu::PollType::Wait).unwrap();
+        receiver.recv().unwrap().unwrap();
+
+        let mapped = buffer.slice(..).get_mapped_range();
+        for (py, line) in mapped.chunks_exact(row as usize).enumerate() {
+            for (px, texel) in line[..extent.width as usize].iter().enumerate() {
+                let (x, y) = (px as i32 - gutter, py as i32 - gutter);
+                assert_eq!(
+                    std::slice::from_ref(texel),
+                    edge_texel(&data, width, height, 1, x, y),
+                    "at {px},{py}"
+                );
+            }
+        }
+    }
+}
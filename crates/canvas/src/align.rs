@@ -0,0 +1,178 @@
+use euclid::default::{Box2D, Vector2D};
+
+/// Which edge or center of the selection the nodes are lined up on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// Lines the horizontal centers up, on the vertical line through the center of the bounds.
+    CenterHorizontal,
+    /// Lines the vertical centers up, on the horizontal line through the center of the bounds.
+    CenterVertical,
+}
+
+impl Alignment {
+    /// Fewer nodes than this have nothing to line up with.
+    pub const MIN_NODES: usize = 2;
+
+    /// How far each box has to move to line up with the combined bounds of all of them.
+    pub fn offsets(self, boxes: &[Box2D<f32>]) -> Vec<Vector2D<f32>> {
+        let Some(bounds) = boxes.iter().copied().reduce(|a, b| a.union(&b)) else {
+            return vec![];
+        };
+        boxes
+            .iter()
+            .map(|item| match self {
+                Alignment::Left => Vector2D::new(bounds.min.x - item.min.x, 0.),
+                Alignment::Right => Vector2D::new(bounds.max.x - item.max.x, 0.),
+                Alignment::Top => Vector2D::new(0., bounds.min.y - item.min.y),
+                Alignment::Bottom => Vector2D::new(0., bounds.max.y - item.max.y),
+                Alignment::CenterHorizontal => {
+                    Vector2D::new(bounds.center().x - item.center().x, 0.)
+                }
+                Alignment::CenterVertical => Vector2D::new(0., bounds.center().y - item.center().y),
+            })
+            .collect()
+    }
+}
+
+/// The axis along which nodes are spread out with equal gaps between them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Distribution {
+    Horizontal,
+    Vertical,
+}
+
+impl Distribution {
+    /// With fewer nodes than this the gaps are already equal.
+    pub const MIN_NODES: usize = 3;
+
+    /// How far each box has to move for the gaps between neighbours to be equal.
+    ///
+    /// The outer edges of the combined bounds stay in place and the boxes keep their order. Boxes
+    /// that together are longer than the bounds end up overlapping by the same amount instead.
+    pub fn offsets(self, boxes: &[Box2D<f32>]) -> Vec<Vector2D<f32>> {
+        let mut offsets = vec![Vector2D::zero(); boxes.len()];
+        if boxes.len() < Self::MIN_NODES {
+            return offsets;
+        }
+        let (start, end): (fn(&Box2D<f32>) -> f32, fn(&Box2D<f32>) -> f32) = match self {
+            Distribution::Horizontal => (|b: &Box2D<f32>| b.min.x, |b: &Box2D<f32>| b.max.x),
+            Distribution::Vertical => (|b: &Box2D<f32>| b.min.y, |b: &Box2D<f32>| b.max.y),
+        };
+        let mut order = (0..boxes.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| start(&boxes[*a]).total_cmp(&start(&boxes[*b])));
+
+        let first = start(&boxes[order[0]]);
+        let last = order
+            .iter()
+            .map(|i| end(&boxes[*i]))
+            .fold(f32::NEG_INFINITY, f32::max);
+        let lengths = boxes.iter().map(|b| end(b) - start(b)).sum::<f32>();
+        let gap = (last - first - lengths) / (boxes.len() - 1) as f32;
+
+        let mut cursor = first;
+        for i in order {
+            let shift = cursor - start(&boxes[i]);
+            offsets[i] = match self {
+                Distribution::Horizontal => Vector2D::new(shift, 0.),
+                Distribution::Vertical => Vector2D::new(0., shift),
+            };
+            cursor += end(&boxes[i]) - start(&boxes[i]) + gap;
+        }
+        offsets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::default::Point2D;
+
+    use super::*;
+
+    fn boxes() -> Vec<Box2D<f32>> {
+        vec![
+            Box2D::new(Point2D::new(0., 0.), Point2D::new(10., 10.)),
+            Box2D::new(Point2D::new(20., 5.), Point2D::new(40., 15.)),
+            Box2D::new(Point2D::new(60., 30.), Point2D::new(70., 50.)),
+        ]
+    }
+
+    #[test]
+    fn boxes_line_up_on_the_bounds() {
+        assert_eq!(
+            Alignment::Left.offsets(&boxes()),
+            [
+                Vector2D::new(0., 0.),
+                Vector2D::new(-20., 0.),
+                Vector2D::new(-60., 0.)
+            ]
+        );
+        assert_eq!(
+            Alignment::Bottom.offsets(&boxes()),
+            [
+                Vector2D::new(0., 40.),
+                Vector2D::new(0., 35.),
+                Vector2D::new(0., 0.)
+            ]
+        );
+        // The bounds are centered on x = 35.
+        assert_eq!(
+            Alignment::CenterHorizontal.offsets(&boxes()),
+            [
+                Vector2D::new(30., 0.),
+                Vector2D::new(5., 0.),
+                Vector2D::new(-30., 0.)
+            ]
+        );
+        assert!(Alignment::Top.offsets(&[]).is_empty());
+    }
+
+    #[test]
+    fn gaps_are_made_equal() {
+        // The boxes are 40 long in total within bounds 70 long, leaving two gaps of 15.
+        assert_eq!(
+            Distribution::Horizontal.offsets(&boxes()),
+            [
+                Vector2D::new(0., 0.),
+                Vector2D::new(5., 0.),
+                Vector2D::new(0., 0.)
+            ]
+        );
+        // The order follows the positions rather than the order the boxes are given in.
+        let mut reversed = boxes();
+        reversed.reverse();
+        assert_eq!(
+            Distribution::Horizontal.offsets(&reversed),
+            [
+                Vector2D::new(0., 0.),
+                Vector2D::new(5., 0.),
+                Vector2D::new(0., 0.)
+            ]
+        );
+    }
+
+    #[test]
+    fn boxes_longer_than_the_bounds_overlap_evenly() {
+        let boxes = [
+            Box2D::new(Point2D::new(0., 0.), Point2D::new(10., 30.)),
+            Box2D::new(Point2D::new(0., 2.), Point2D::new(10., 32.)),
+            Box2D::new(Point2D::new(0., 10.), Point2D::new(10., 40.)),
+        ];
+        // Boxes 90 long in total within bounds 40 long overlap by 25 each.
+        assert_eq!(
+            Distribution::Vertical.offsets(&boxes),
+            [
+                Vector2D::new(0., 0.),
+                Vector2D::new(0., 3.),
+                Vector2D::new(0., 0.)
+            ]
+        );
+        assert_eq!(
+            Distribution::Vertical.offsets(&boxes[..2]),
+            [Vector2D::zero(); 2]
+        );
+    }
+}
@@ -1,60 +1,767 @@
-use crate::scene::Scene;
-use euclid::default::Point2D;
-use graphics::{CanvasCoordinates, Primitive, Systems, Vertex};
+use crate::align::{Alignment, Distribution};
+use crate::guides::Guide;
+use crate::history::{History, HistoryEntry};
+use crate::scene::{DrawBatch, LayerId, NodeId, Scene};
+use crate::{ClickResult, Node};
+use color::{PremulColor, Srgb};
+use euclid::default::{Box2D, Point2D, Vector2D};
+use graphics::primitives::FlipAxis;
+use graphics::{BlendMode, CanvasCoordinates, Drawable, Primitive, Systems, Vertex, VertexKind};
 
 use graphics::Mesh;
 use renderer::GrowableMeshBuffer;
+use tracing::instrument;
 use wgpu::{Device, Queue};
 
 pub struct Canvas {
     pub scene: Scene,
+    pub history: History,
 
     pub render_cache: Mesh<Vertex>,
+    /// The index ranges of `render_cache` to draw with each blend mode, in order.
+    pub batches: Vec<DrawBatch>,
     pub scratch_render_cache: Option<Mesh<Vertex>>,
+    pub scratch_blend_mode: BlendMode,
 
     pub scene_buffer: GrowableMeshBuffer,
-    pub scratch_buffer: GrowableMeshBuffer,
+    /// The bottom-most nodes, tessellated once into `baked_buffer`, see [`Canvas::bake`].
+    baked: Option<BakedLayer>,
+    baked_buffer: GrowableMeshBuffer,
+    /// The node being edited in place, which is left out of the scene mesh while the tool editing
+    /// it draws it, see [`Canvas::set_editing`].
+    editing: Option<NodeId>,
+    /// The scratch mesh is written into the buffer that is not being drawn, and the two are
+    /// swapped once the write is complete, see [`Canvas::double_buffer_scratch`].
+    scratch_buffers: [GrowableMeshBuffer; 2],
+    scratch_front: usize,
+    /// Whether scratch updates ping-pong between two buffers, so a frame always reads a complete
+    /// scratch mesh. When disabled the drawn buffer is overwritten in place.
+    pub double_buffer_scratch: bool,
+
+    /// The world region to export, when unset the content bounds are exported.
+    pub crop: Option<Box2D<f32>>,
+    /// Dims everything outside of the crop.
+    pub crop_buffer: GrowableMeshBuffer,
+
+    /// Counts the changes to the scene, see [`Canvas::revision`].
+    revision: u64,
+}
+
+/// Nodes that were tessellated once into a static mesh which is drawn below the rest of the scene.
+#[derive(Debug)]
+struct BakedLayer {
+    /// The baked nodes in draw order, they are always the bottom-most nodes of the scene.
+    ids: Vec<NodeId>,
+    /// The index ranges of the baked mesh to draw with each blend mode, in order.
+    batches: Vec<DrawBatch>,
+}
+
+/// Soft bounds on the size of the live scene, exceeding either never drops any nodes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SceneLimits {
+    pub max_nodes: usize,
+    /// The size of the tessellated scene mesh, in bytes.
+    pub max_mesh_bytes: usize,
+    /// Bakes the oldest nodes into a static layer once the limits are exceeded, instead of only
+    /// warning, see [`Canvas::bake`].
+    pub auto_bake: bool,
 }
 
+impl Default for SceneLimits {
+    fn default() -> Self {
+        Self {
+            max_nodes: 10_000,
+            max_mesh_bytes: 128 * 1024 * 1024,
+            auto_bake: true,
+        }
+    }
+}
+
+/// How large the scene currently is, to compare against the [`SceneLimits`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SceneStats {
+    /// The nodes that are tessellated whenever the scene changes.
+    pub nodes: usize,
+    /// The nodes in the baked layer, see [`Canvas::bake`].
+    pub baked: usize,
+    pub vertices: usize,
+    pub indices: usize,
+    /// The size of the tessellated scene mesh, in bytes.
+    pub mesh_bytes: usize,
+}
+
+impl SceneStats {
+    pub fn exceeds(&self, limits: SceneLimits) -> bool {
+        self.nodes > limits.max_nodes || self.mesh_bytes > limits.max_mesh_bytes
+    }
+
+    /// How many of the live nodes to bake so that the rest takes up about half of the limits,
+    /// which leaves room to keep drawing before the next bake.
+    pub fn nodes_to_bake(&self, limits: SceneLimits) -> usize {
+        let by_bytes = match self.mesh_bytes {
+            0 => self.nodes,
+            bytes => self.nodes * (limits.max_mesh_bytes / 2) / bytes,
+        };
+        let keep = by_bytes.min(limits.max_nodes / 2).min(self.nodes);
+        self.nodes - keep
+    }
+}
+
+/// How far the dimmed area around the crop extends, in world units.
+const CROP_OVERLAY_EXTENT: f32 = 100_000.;
+const CROP_OVERLAY_COLOR: PremulColor<Srgb> = PremulColor::new([0., 0., 0., 0.5]);
+
 impl Canvas {
     pub fn new(systems: &mut Systems) -> Self {
         let mut scene = Scene::default();
 
-        let render_cache = scene.tessellate(systems);
+        let (render_cache, batches) = scene.tessellate(systems);
 
         let scene_buffer = GrowableMeshBuffer::new(&systems.device, 1024, 2048);
-        let scratch_buffer = GrowableMeshBuffer::new(&systems.device, 1024, 2048);
+        let baked_buffer = GrowableMeshBuffer::new(&systems.device, 1024, 2048);
+        let scratch_buffers = [
+            GrowableMeshBuffer::new(&systems.device, 1024, 2048),
+            GrowableMeshBuffer::new(&systems.device, 1024, 2048),
+        ];
+        let crop_buffer = GrowableMeshBuffer::new(&systems.device, 16, 24);
 
         Self {
             scene,
+            history: History::default(),
 
             render_cache,
+            batches,
             scratch_render_cache: None,
+            scratch_blend_mode: BlendMode::Normal,
 
             scene_buffer,
-            scratch_buffer,
+            baked: None,
+            baked_buffer,
+            editing: None,
+            scratch_buffers,
+            scratch_front: 0,
+            double_buffer_scratch: true,
+
+            crop: None,
+            crop_buffer,
+
+            revision: 0,
         }
     }
 
-    pub fn add_node(&mut self, systems: &mut Systems, node: Primitive<CanvasCoordinates>) {
-        self.scene.add_node(node);
-        self.render_cache = self.scene.tessellate(systems);
-        _ = self.scene_buffer.replace_with_mesh(
-            &systems.device,
-            &systems.queue,
-            &self.render_cache,
-        );
+    /// Recreates the gpu buffers of the canvas after the device was lost, keeping the scene.
+    ///
+    /// The nodes are rebuilt from their serialized form, which drops their render caches and
+    /// atlas allocations. The undo history is cleared since its entries still reference the old
+    /// atlases. If the scene can not be rebuilt, the canvas is left empty.
+    pub fn recover_device(&mut self, systems: &mut Systems) -> anyhow::Result<()> {
+        let scene = self
+            .scene
+            .serialize()
+            .and_then(|serialized| Scene::deserialize(&serialized));
+        let crop = self.crop;
+        let double_buffer_scratch = self.double_buffer_scratch;
+        let limits = self.history.limits();
+        let revision = self.revision;
+
+        *self = Self::new(systems);
+        self.history = History::new(limits);
+        self.double_buffer_scratch = double_buffer_scratch;
+        let result = scene.map(|scene| self.scene = scene);
+        self.set_crop(&systems.device, &systems.queue, crop);
+        self.retessellate(systems);
+        // Rebuilding the same scene is not a change to it.
+        self.revision = revision;
+        result
+    }
+
+    /// Swaps the scene for another, such as a loaded session. The undo history and the crop
+    /// belong to the old scene, so they are cleared.
+    pub fn replace_scene(&mut self, systems: &mut Systems, scene: Scene) {
+        self.scene = scene;
+        self.editing = None;
+        self.history = History::new(self.history.limits());
+        self.set_crop(&systems.device, &systems.queue, None);
+        self.retessellate(systems);
+    }
+
+    /// A number that changes whenever the scene does, including undoing, so a saved revision
+    /// tells whether there are unsaved changes.
+    pub const fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Commits a primitive to the scene, returning the id it can be referenced by.
+    pub fn add_node(
+        &mut self,
+        systems: &mut Systems,
+        node: Primitive<CanvasCoordinates>,
+    ) -> NodeId {
+        let id = self.scene.add_node(node);
+        self.history.push(systems, HistoryEntry::Added(id));
+        self.retessellate(systems);
+        id
+    }
+
+    /// Commits every primitive as a single undoable change, re-tessellating the scene only once.
+    pub fn add_nodes(
+        &mut self,
+        systems: &mut Systems,
+        nodes: impl IntoIterator<Item = Primitive<CanvasCoordinates>>,
+    ) -> Vec<NodeId> {
+        let ids = nodes
+            .into_iter()
+            .map(|node| self.scene.add_node(node))
+            .collect::<Vec<_>>();
+        if ids.is_empty() {
+            return ids;
+        }
+        let entries = ids.iter().copied().map(HistoryEntry::Added).collect();
+        self.history.push(systems, HistoryEntry::Batch(entries));
+        self.retessellate(systems);
+        ids
+    }
+
+    /// Adds a node that is undone along with the change before it.
+    pub fn append_node(
+        &mut self,
+        systems: &mut Systems,
+        node: Primitive<CanvasCoordinates>,
+    ) -> NodeId {
+        let id = self.scene.add_node(node);
+        self.history.append(systems, HistoryEntry::Added(id));
+        self.retessellate(systems);
+        id
     }
 
     pub fn remove_node_id(&mut self, systems: &mut Systems, id: u32) {
-        self.scene.remove_node(id);
-        self.render_cache = self.scene.tessellate(systems);
+        if let Some(entry) = self.take_node(id) {
+            self.history.push(systems, entry);
+        }
+        self.retessellate(systems);
+    }
+
+    /// Removes every node as a single undoable change, re-tessellating the scene only once.
+    pub fn remove_nodes(&mut self, systems: &mut Systems, ids: &[NodeId]) {
+        let entries = ids
+            .iter()
+            .filter_map(|id| self.take_node(*id))
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return;
+        }
+        self.history.push(systems, HistoryEntry::Batch(entries));
+        self.retessellate(systems);
+    }
+
+    /// Removes the node from the scene, returning the history entry that puts it back.
+    fn take_node(&mut self, id: NodeId) -> Option<HistoryEntry> {
+        let layer = self.scene.layer_of(id);
+        let index = self.scene.ordering_index(id)?;
+        let metadata = self.scene.take_metadata(id);
+        let node = self.scene.remove_node(id)?;
+        Some(HistoryEntry::Removed {
+            id,
+            node,
+            layer,
+            index,
+            metadata,
+        })
+    }
+
+    /// Reverts the most recent change to the scene, returning whether there was one.
+    pub fn undo(&mut self, systems: &mut Systems) -> bool {
+        let Some(entry) = self.history.pop() else {
+            return false;
+        };
+        self.revert(entry);
+        self.retessellate(systems);
+        true
+    }
+
+    /// Reverts a change without re-tessellating the scene.
+    fn revert(&mut self, entry: HistoryEntry) {
+        match entry {
+            HistoryEntry::Added(id) => {
+                _ = self.scene.remove_node(id);
+            }
+            HistoryEntry::Removed {
+                id,
+                node,
+                layer,
+                index,
+                metadata,
+            } => {
+                self.scene.restore_node(id, node, layer, index);
+                _ = self.scene.set_metadata(id, metadata);
+            }
+            HistoryEntry::Replaced { id, node } => {
+                if let Some(current) = self.node_mut(id) {
+                    *current = node;
+                }
+            }
+            HistoryEntry::Moved { ids, offset } => {
+                for id in ids {
+                    if let Some(node) = self.node_mut(id) {
+                        node.translate(-offset);
+                    }
+                }
+            }
+            HistoryEntry::Flipped {
+                ids,
+                axis,
+                at,
+                mirror_text,
+            } => {
+                for id in ids {
+                    if let Some(node) = self.node_mut(id) {
+                        flip_node(node, axis, at, mirror_text);
+                    }
+                }
+            }
+            HistoryEntry::Rotated { id, rotation } => {
+                if let Some(node) = self.node_mut(id) {
+                    node.set_rotation(rotation);
+                }
+            }
+            HistoryEntry::Batch(entries) => {
+                for entry in entries.into_iter().rev() {
+                    self.revert(entry);
+                }
+            }
+        }
+    }
+
+    /// Swaps the node for another primitive as a single undoable change.
+    pub fn replace_node(
+        &mut self,
+        systems: &mut Systems,
+        id: NodeId,
+        node: Primitive<CanvasCoordinates>,
+    ) {
+        if let Some(current) = self.node_mut(id) {
+            let node = std::mem::replace(current, node);
+            self.history
+                .push(systems, HistoryEntry::Replaced { id, node });
+        }
+        self.retessellate(systems);
+    }
+
+    /// Moves the nodes by the offset.
+    ///
+    /// With `coalesce` set, a move of the same nodes at the top of the history is extended
+    /// instead of adding another entry, so a burst of small moves is undone at once.
+    pub fn translate_nodes(
+        &mut self,
+        systems: &mut Systems,
+        ids: &[NodeId],
+        offset: Vector2D<f32>,
+        coalesce: bool,
+    ) {
+        for id in ids {
+            if let Some(node) = self.node_mut(*id) {
+                node.translate(offset);
+            }
+        }
+        if coalesce
+            && let Some(HistoryEntry::Moved {
+                ids: moved,
+                offset: total,
+            }) = self.history.last_mut()
+            && moved == ids
+        {
+            *total += offset;
+        } else {
+            self.history.push(
+                systems,
+                HistoryEntry::Moved {
+                    ids: ids.to_vec(),
+                    offset,
+                },
+            );
+        }
+        self.retessellate(systems);
+    }
+
+    /// Mirrors the nodes about the center of their combined bounds as a single undoable change,
+    /// returning whether there was anything to flip.
+    ///
+    /// Text keeps reading the right way unless `mirror_text` is set.
+    pub fn flip_nodes(
+        &mut self,
+        systems: &mut Systems,
+        ids: &[NodeId],
+        axis: FlipAxis,
+        mirror_text: bool,
+    ) -> bool {
+        let Some(bounds) = self.nodes_bounds(ids) else {
+            return false;
+        };
+        let at = match axis {
+            FlipAxis::Horizontal => bounds.center().x,
+            FlipAxis::Vertical => bounds.center().y,
+        };
+        for id in ids {
+            if let Some(node) = self.node_mut(*id) {
+                flip_node(node, axis, at, mirror_text);
+            }
+        }
+        self.history.push(
+            systems,
+            HistoryEntry::Flipped {
+                ids: ids.to_vec(),
+                axis,
+                at,
+                mirror_text,
+            },
+        );
+        self.retessellate(systems);
+        true
+    }
+
+    /// Lines the nodes up on an edge or center of their combined bounds as a single undoable
+    /// change, returning whether there were enough nodes to align.
+    pub fn align_nodes(
+        &mut self,
+        systems: &mut Systems,
+        ids: &[NodeId],
+        alignment: Alignment,
+    ) -> bool {
+        let (ids, boxes) = self.node_boxes(ids);
+        if ids.len() < Alignment::MIN_NODES {
+            return false;
+        }
+        self.move_each(systems, &ids, &alignment.offsets(&boxes));
+        true
+    }
+
+    /// Spreads the nodes out with equal gaps between them as a single undoable change,
+    /// returning whether there were enough nodes to distribute.
+    pub fn distribute_nodes(
+        &mut self,
+        systems: &mut Systems,
+        ids: &[NodeId],
+        distribution: Distribution,
+    ) -> bool {
+        let (ids, boxes) = self.node_boxes(ids);
+        if ids.len() < Distribution::MIN_NODES {
+            return false;
+        }
+        self.move_each(systems, &ids, &distribution.offsets(&boxes));
+        true
+    }
+
+    /// The ids of the nodes that exist, with their bounds.
+    fn node_boxes(&self, ids: &[NodeId]) -> (Vec<NodeId>, Vec<Box2D<f32>>) {
+        ids.iter()
+            .filter_map(|id| Some((*id, self.scene.get_node(*id)?.bounding_box())))
+            .unzip()
+    }
+
+    /// Moves every node by its own offset, recorded as a single history entry.
+    fn move_each(&mut self, systems: &mut Systems, ids: &[NodeId], offsets: &[Vector2D<f32>]) {
+        let mut entries = vec![];
+        for (id, offset) in ids.iter().zip(offsets) {
+            if *offset == Vector2D::zero() {
+                continue;
+            }
+            if let Some(node) = self.node_mut(*id) {
+                node.translate(*offset);
+                entries.push(HistoryEntry::Moved {
+                    ids: vec![*id],
+                    offset: *offset,
+                });
+            }
+        }
+        if entries.is_empty() {
+            return;
+        }
+        self.history.push(systems, HistoryEntry::Batch(entries));
+        self.retessellate(systems);
+    }
+
+    /// Rotates the node about its center, returning whether it can be rotated.
+    ///
+    /// With `coalesce` set, a rotation of the same node at the top of the history is extended
+    /// instead of adding another entry, so a rotation drag is undone at once.
+    pub fn rotate_node(
+        &mut self,
+        systems: &mut Systems,
+        id: NodeId,
+        rotation: f32,
+        coalesce: bool,
+    ) -> bool {
+        let Some(node) = self.node_mut(id) else {
+            return false;
+        };
+        let Some(previous) = node.rotation() else {
+            return false;
+        };
+        node.set_rotation(rotation);
+        let extends = coalesce
+            && matches!(
+                self.history.last_mut(),
+                Some(HistoryEntry::Rotated { id: rotated, .. }) if *rotated == id
+            );
+        if !extends {
+            self.history.push(
+                systems,
+                HistoryEntry::Rotated {
+                    id,
+                    rotation: previous,
+                },
+            );
+        }
+        self.retessellate(systems);
+        true
+    }
+
+    pub fn set_layer_visible(&mut self, systems: &mut Systems, id: LayerId, visible: bool) {
+        self.scene.set_layer_visible(id, visible);
+        self.retessellate(systems);
+    }
+    pub fn set_layer_locked(&mut self, id: LayerId, locked: bool) {
+        self.scene.set_layer_locked(id, locked);
+    }
+
+    /// Passes a click to the node, re-tessellating the scene if the node handled it.
+    pub fn click_node(
+        &mut self,
+        systems: &mut Systems,
+        id: NodeId,
+        position: Point2D<f32>,
+    ) -> ClickResult {
+        let result = self
+            .scene
+            .get_node_mut(id)
+            .map_or(ClickResult::Unhandled, |node| node.handle_click(position));
+        if result == ClickResult::Handled {
+            self.retessellate(systems);
+        }
+        result
+    }
+    /// Passes a drag to a node that previously handled a click.
+    pub fn drag_node(&mut self, systems: &mut Systems, id: NodeId, position: Point2D<f32>) {
+        if let Some(node) = self.node_mut(id) {
+            node.handle_drag(position);
+            self.retessellate(systems);
+        }
+    }
+
+    /// Re-tessellates the scene and uploads it to the scene buffer.
+    #[instrument(name = "Canvas::retessellate", level = "trace", skip_all)]
+    fn retessellate(&mut self, systems: &mut Systems) {
+        self.revision += 1;
+        self.tessellate_live(systems);
+    }
+    /// Tessellates and uploads the nodes above the baked layer.
+    fn tessellate_live(&mut self, systems: &mut Systems) {
+        let mut order = self.drawn_order();
+        if let Some(baked) = &self.baked {
+            if order.starts_with(&baked.ids) {
+                order.drain(..baked.ids.len());
+            } else {
+                // A baked node was removed or hidden, or a node was put below them, so the baked
+                // mesh no longer matches the bottom of the scene.
+                self.baked = None;
+            }
+        }
+        (self.render_cache, self.batches) = self.scene.tessellate_keys(systems, order);
+        #[cfg(debug_assertions)]
+        if let Err(err) = self.render_cache.validate() {
+            tracing::warn!("tessellated an invalid scene mesh: {err}");
+        }
         _ = self.scene_buffer.replace_with_mesh(
             &systems.device,
             &systems.queue,
             &self.render_cache,
         );
     }
+    /// The draw order of the nodes drawn from the scene mesh, which leaves out the node being
+    /// edited.
+    fn drawn_order(&self) -> Vec<NodeId> {
+        let mut order = self.scene.draw_order().to_vec();
+        order.retain(|id| Some(*id) != self.editing);
+        order
+    }
+
+    /// The node being edited in place.
+    pub const fn editing(&self) -> Option<NodeId> {
+        self.editing
+    }
+    /// Stops drawing the node while a tool edits it in place, or draws it again with `None`.
+    ///
+    /// The node itself is left alone, so stopping without changing it puts back what it was.
+    pub fn set_editing(&mut self, systems: &mut Systems, id: Option<NodeId>) {
+        if let Some(id) = id {
+            // Drops the baked layer if the node is drawn from it.
+            _ = self.node_mut(id);
+        }
+        self.editing = id;
+        // Hiding the node only changes how the scene is drawn, not the scene itself.
+        self.tessellate_live(systems);
+    }
+    /// The top-most text whose laid out content is under the position, ignoring text on hidden or
+    /// locked layers.
+    ///
+    /// The area of text is unbounded, so the content is measured instead of only hit testing the
+    /// bounds.
+    pub fn text_at(&mut self, systems: &mut Systems, position: Point2D<f32>) -> Option<NodeId> {
+        self.scene
+            .nodes_at_position(position)
+            .into_iter()
+            .find(|id| match self.scene.get_node_mut(*id) {
+                Some(Primitive::Text(text)) => {
+                    Box2D::from_origin_and_size(text.origin(), text.measure(systems))
+                        .contains(position)
+                }
+                _ => false,
+            })
+    }
+
+    /// The node to change, drawing it live again first if it was baked.
+    fn node_mut(&mut self, id: NodeId) -> Option<&mut Primitive<CanvasCoordinates>> {
+        if self
+            .baked
+            .as_ref()
+            .is_some_and(|baked| baked.ids.contains(&id))
+        {
+            self.baked = None;
+        }
+        self.scene.get_node_mut(id)
+    }
+
+    /// Bakes the bottom-most `count` nodes into a static layer, which is drawn below the rest of
+    /// the scene and isn't tessellated or uploaded again when the scene changes.
+    ///
+    /// The baked nodes stay in the scene, so they can still be selected, saved and exported.
+    /// Changing one of them, or putting a node below them, draws them live again.
+    pub fn bake(&mut self, systems: &mut Systems, count: usize) {
+        let mut ids = self.drawn_order();
+        ids.truncate(count);
+        self.baked = None;
+        if !ids.is_empty() {
+            let (mesh, batches) = self.scene.tessellate_keys(systems, ids.clone());
+            _ = self
+                .baked_buffer
+                .replace_with_mesh(&systems.device, &systems.queue, &mesh);
+            self.baked = Some(BakedLayer { ids, batches });
+        }
+        // Baking only changes how the scene is drawn, not the scene itself.
+        self.tessellate_live(systems);
+    }
+    /// The amount of nodes in the baked layer.
+    pub fn baked_len(&self) -> usize {
+        self.baked.as_ref().map_or(0, |baked| baked.ids.len())
+    }
+    /// The buffers to draw the scene from with the batches of each, the baked layer first.
+    pub fn scene_layers(&self) -> impl Iterator<Item = (&GrowableMeshBuffer, &[DrawBatch])> {
+        let baked = self
+            .baked
+            .as_ref()
+            .map(|baked| (&self.baked_buffer, &baked.batches[..]));
+        baked
+            .into_iter()
+            .chain([(&self.scene_buffer, &self.batches[..])])
+            .filter(|(buffer, _)| buffer.num_indices > 0)
+    }
+
+    /// The size of the scene as of the last tessellation, the baked nodes are only counted by
+    /// `baked` as they are no longer tessellated.
+    pub fn stats(&self) -> SceneStats {
+        let vertices = self.render_cache.vertices.len();
+        let indices = self.render_cache.indices.len();
+        let baked = self.baked_len();
+        SceneStats {
+            nodes: self.scene.len() - baked,
+            baked,
+            vertices,
+            indices,
+            mesh_bytes: vertices * std::mem::size_of::<Vertex>()
+                + indices * std::mem::size_of::<u32>(),
+        }
+    }
+
+    /// Renders every visible node into a single mesh in world coordinates, bottom-most first.
+    ///
+    /// The mesh can be drawn as a static layer without re-tessellating the scene. It draws every
+    /// node with the normal blend mode, and its texture coordinates point into the atlas
+    /// allocations of the nodes, so they have to stay in the scene while the mesh is in use.
+    /// [`Canvas::bake`] draws the bottom of the scene this way while keeping the blend modes.
+    pub fn flatten(&mut self, systems: &mut Systems) -> Mesh<Vertex> {
+        let (mesh, _) = self.scene.tessellate(systems);
+        mesh
+    }
+    /// Tessellates every visible node for an export, along with the batches to draw them in so
+    /// erase strokes still only cut into what is below them.
+    pub fn export_mesh(&mut self, systems: &mut Systems) -> (Mesh<Vertex>, Vec<DrawBatch>) {
+        self.scene.tessellate(systems)
+    }
+    /// Like [`Canvas::export_mesh`], but only tessellates the given nodes, such as the selection.
+    pub fn export_nodes_mesh(
+        &mut self,
+        systems: &mut Systems,
+        ids: &[NodeId],
+    ) -> (Mesh<Vertex>, Vec<DrawBatch>) {
+        self.scene.tessellate_only(systems, ids)
+    }
+
+    /// Iterate over the committed primitives in draw order, bottom-most first, including the ones
+    /// on hidden layers.
+    pub fn primitives(&self) -> impl Iterator<Item = (NodeId, &Primitive<CanvasCoordinates>)> {
+        self.scene.nodes()
+    }
+    pub fn primitive(&self, id: NodeId) -> Option<&Primitive<CanvasCoordinates>> {
+        self.scene.get_node(id)
+    }
+    /// Iterate over the committed primitives that carry metadata, bottom-most first.
+    pub fn tagged_primitives(
+        &self,
+    ) -> impl Iterator<Item = (NodeId, &Primitive<CanvasCoordinates>, &serde_json::Value)> {
+        self.scene.tagged_nodes()
+    }
+    /// The user metadata of the node, which is saved with the scene but ignored by rendering.
+    pub fn metadata(&self, id: NodeId) -> Option<&serde_json::Value> {
+        self.scene.metadata(id)
+    }
+    /// Tags a committed node with metadata, or clears it with `None`.
+    pub fn set_metadata(&mut self, id: NodeId, metadata: Option<serde_json::Value>) {
+        _ = self.scene.set_metadata(id, metadata);
+    }
+
+    pub fn guides(&self) -> &[Guide] {
+        self.scene.guides()
+    }
+    /// Adds a guide, returning its index.
+    ///
+    /// Guides are not part of the undo history, but changing them still moves the
+    /// [`Canvas::revision`] so they count as unsaved changes.
+    pub fn add_guide(&mut self, guide: Guide) -> usize {
+        self.revision += 1;
+        self.scene.add_guide(guide)
+    }
+    /// Moves the guide at the index, see [`Canvas::add_guide`].
+    pub fn set_guide(&mut self, index: usize, guide: Guide) {
+        if self.scene.guides().get(index) != Some(&guide) && self.scene.set_guide(index, guide) {
+            self.revision += 1;
+        }
+    }
+    /// Removes the guide at the index, see [`Canvas::add_guide`].
+    pub fn remove_guide(&mut self, index: usize) -> Option<Guide> {
+        let guide = self.scene.remove_guide(index);
+        if guide.is_some() {
+            self.revision += 1;
+        }
+        guide
+    }
+    /// Removes every guide, see [`Canvas::add_guide`].
+    pub fn clear_guides(&mut self) {
+        if !self.scene.guides().is_empty() {
+            self.scene.clear_guides();
+            self.revision += 1;
+        }
+    }
+
     pub fn get_node_at_position(
         &mut self,
         position: Point2D<f32>,
@@ -64,13 +771,115 @@ impl Canvas {
     pub fn get_node_id_at_position(&mut self, position: Point2D<f32>) -> Option<u32> {
         self.scene.get_node_id_at_position(position)
     }
+    /// Every editable node at the given position, top-most first.
+    pub fn nodes_at_position(&self, position: Point2D<f32>) -> Vec<NodeId> {
+        self.scene.nodes_at_position(position)
+    }
 
+    /// The left edge and first baseline of every visible text node, which newly placed text can
+    /// be snapped to.
+    pub fn text_anchors(&mut self, systems: &mut Systems) -> Vec<Point2D<f32>> {
+        self.scene
+            .draw_order()
+            .to_vec()
+            .into_iter()
+            .filter_map(|id| match self.scene.get_node_mut(id)? {
+                Primitive::Text(text) => {
+                    Some(text.origin() + Vector2D::new(0., text.first_baseline(systems)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The union of the bounding boxes of every visible node.
+    pub fn content_bounds(&self) -> Option<Box2D<f32>> {
+        self.nodes_bounds(self.scene.draw_order())
+    }
+    /// The union of the bounding boxes of the given nodes.
+    pub fn nodes_bounds(&self, ids: &[NodeId]) -> Option<Box2D<f32>> {
+        ids.iter()
+            .filter_map(|id| self.scene.get_node(*id))
+            .map(|node| node.bounding_box())
+            .reduce(|a, b| a.union(&b))
+    }
+    /// The world region an export should cover, the crop if one is set and otherwise the
+    /// content bounds.
+    pub fn export_region(&self) -> Option<Box2D<f32>> {
+        self.crop.or_else(|| self.content_bounds())
+    }
+    /// Sets the export region, rebuilding the overlay that dims everything outside of it.
+    pub fn set_crop(&mut self, device: &Device, queue: &Queue, crop: Option<Box2D<f32>>) {
+        self.crop = crop;
+        match crop {
+            Some(crop) => {
+                let outer = crop.inflate(CROP_OVERLAY_EXTENT, CROP_OVERLAY_EXTENT);
+                let kind = VertexKind::Color(CROP_OVERLAY_COLOR);
+                let mesh = Mesh::from_slice(&[
+                    Mesh::new_color_quad(
+                        Box2D::new(outer.min, Point2D::new(outer.max.x, crop.min.y)),
+                        kind,
+                    ),
+                    Mesh::new_color_quad(
+                        Box2D::new(Point2D::new(outer.min.x, crop.max.y), outer.max),
+                        kind,
+                    ),
+                    Mesh::new_color_quad(
+                        Box2D::new(
+                            Point2D::new(outer.min.x, crop.min.y),
+                            Point2D::new(crop.min.x, crop.max.y),
+                        ),
+                        kind,
+                    ),
+                    Mesh::new_color_quad(
+                        Box2D::new(
+                            Point2D::new(crop.max.x, crop.min.y),
+                            Point2D::new(outer.max.x, crop.max.y),
+                        ),
+                        kind,
+                    ),
+                ]);
+                _ = self.crop_buffer.replace_with_mesh(device, queue, &mesh);
+            }
+            None => self.crop_buffer.reset_no_wipe(),
+        }
+    }
+
+    /// The scratch buffer that should be drawn this frame.
+    pub fn scratch_buffer(&self) -> &GrowableMeshBuffer {
+        &self.scratch_buffers[self.scratch_front]
+    }
     pub fn clear_scratch(&mut self) {
-        self.scratch_buffer.reset_no_wipe();
+        self.scratch_buffers[self.scratch_front].reset_no_wipe();
         self.scratch_render_cache = None;
     }
-    pub fn update_scratch(&mut self, device: &Device, queue: &Queue, mesh: Mesh<Vertex>) {
-        _ = self.scratch_buffer.replace_with_mesh(device, queue, &mesh);
+    pub fn update_scratch(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        mesh: Mesh<Vertex>,
+        blend_mode: BlendMode,
+    ) {
+        if self.double_buffer_scratch {
+            let back = 1 - self.scratch_front;
+            if self.scratch_buffers[back]
+                .replace_with_mesh(device, queue, &mesh)
+                .is_ok()
+            {
+                self.scratch_front = back;
+            }
+        } else {
+            _ = self.scratch_buffers[self.scratch_front].replace_with_mesh(device, queue, &mesh);
+        }
         self.scratch_render_cache = Some(mesh);
+        self.scratch_blend_mode = blend_mode;
+    }
+}
+
+fn flip_node(node: &mut Primitive<CanvasCoordinates>, axis: FlipAxis, at: f32, mirror_text: bool) {
+    if mirror_text {
+        node.mirror(axis, at);
+    } else {
+        node.flip(axis, at);
     }
 }
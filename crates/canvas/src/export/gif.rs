@@ -0,0 +1,426 @@
+//! A minimal animated gif encoder for exporting recorded macros.
+//!
+//! Every frame is quantized to one fixed palette, a color cube with a transparent entry, so the
+//! frames need no palette of their own and transparent overlays stay transparent.
+
+use std::collections::HashMap;
+
+use euclid::default::Size2D;
+
+/// The palette index of fully transparent pixels.
+const TRANSPARENT: u8 = 0;
+/// The levels of each channel in the color cube, green gets an extra level as the eye is the
+/// most sensitive to it.
+const LEVELS: [u8; 3] = [6, 7, 6];
+/// Pixels below this alpha become transparent, gifs have no partial transparency.
+const ALPHA_THRESHOLD: u8 = 128;
+/// The palette indices are 8 bits, so the codes start at 9 bits.
+const MIN_CODE_SIZE: u8 = 8;
+const MAX_CODE_SIZE: u8 = 12;
+const MAX_CODES: u16 = 1 << MAX_CODE_SIZE;
+
+/// Encodes straight alpha rgba8 frames, all of the same `size`, as a looping gif showing each
+/// frame for `delay` hundredths of a second.
+///
+/// # Panics
+///
+/// Panics if a frame does not have exactly four bytes for every pixel of `size`, or the size does
+/// not fit in a gif.
+pub fn encode(size: Size2D<u32>, frames: &[Vec<u8>], delay: u16) -> Vec<u8> {
+    let width = u16::try_from(size.width).expect("the frames are too wide for a gif");
+    let height = u16::try_from(size.height).expect("the frames are too tall for a gif");
+
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend(width.to_le_bytes());
+    gif.extend(height.to_le_bytes());
+    // A global palette of 256 colors with 8 bits per channel, no background color or aspect.
+    gif.extend([0xf7, TRANSPARENT, 0]);
+    gif.extend(palette());
+    // Loop forever.
+    gif.extend([0x21, 0xff, 11]);
+    gif.extend(b"NETSCAPE2.0");
+    gif.extend([3, 1, 0, 0, 0]);
+
+    for frame in frames {
+        assert_eq!(
+            frame.len(),
+            size.area() as usize * 4,
+            "the frame doesn't match the image size"
+        );
+        // Every frame is complete, so the previous one is cleared before it is drawn, with the
+        // transparent index set.
+        gif.extend([0x21, 0xf9, 4, (2 << 2) | 1]);
+        gif.extend(delay.to_le_bytes());
+        gif.extend([TRANSPARENT, 0]);
+
+        gif.push(0x2c);
+        gif.extend([0, 0, 0, 0]);
+        gif.extend(width.to_le_bytes());
+        gif.extend(height.to_le_bytes());
+        gif.push(0);
+
+        let indices = frame.chunks_exact(4).map(quantize).collect::<Vec<_>>();
+        gif.push(MIN_CODE_SIZE);
+        for block in lzw_encode(&indices).chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend_from_slice(block);
+        }
+        gif.push(0);
+    }
+
+    gif.push(0x3b);
+    gif
+}
+
+/// The rgb entries of the palette, the transparent entry followed by the color cube and black
+/// padding.
+fn palette() -> Vec<u8> {
+    let [red, green, blue] = LEVELS.map(u32::from);
+    let mut palette = vec![0; 3];
+    for r in 0..red {
+        for g in 0..green {
+            for b in 0..blue {
+                palette.extend([(r, red), (g, green), (b, blue)].map(level_value));
+            }
+        }
+    }
+    palette.resize(256 * 3, 0);
+    palette
+}
+
+fn level_value((level, levels): (u32, u32)) -> u8 {
+    (level * 255 / (levels - 1)) as u8
+}
+
+/// The palette index closest to a straight alpha rgba8 pixel.
+fn quantize(pixel: &[u8]) -> u8 {
+    if pixel[3] < ALPHA_THRESHOLD {
+        return TRANSPARENT;
+    }
+    let [r, g, b] = [0, 1, 2].map(|channel| {
+        let levels = u32::from(LEVELS[channel]) - 1;
+        (u32::from(pixel[channel]) * levels + 127) / 255
+    });
+    let [_, green, blue] = LEVELS.map(u32::from);
+    (1 + r * green * blue + g * blue + b) as u8
+}
+
+/// Compresses palette indices with the variable length lzw of gifs, resetting the table with a
+/// clear code whenever it fills up.
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << MIN_CODE_SIZE;
+    let end = clear + 1;
+
+    let mut writer = BitWriter::default();
+    let mut table = HashMap::<(u16, u8), u16>::new();
+    let mut size = MIN_CODE_SIZE + 1;
+    let mut next = end + 1;
+    writer.write(clear, size);
+
+    let mut indices = indices.iter().copied();
+    if let Some(first) = indices.next() {
+        let mut prefix = u16::from(first);
+        for index in indices {
+            if let Some(&code) = table.get(&(prefix, index)) {
+                prefix = code;
+                continue;
+            }
+            writer.write(prefix, size);
+            // The decoder widens its codes once the table reaches the next power of two, which
+            // it does one code behind the encoder.
+            if next >= 1 << size && size < MAX_CODE_SIZE {
+                size += 1;
+            }
+            if next < MAX_CODES {
+                table.insert((prefix, index), next);
+                next += 1;
+            } else {
+                writer.write(clear, size);
+                table.clear();
+                size = MIN_CODE_SIZE + 1;
+                next = end + 1;
+            }
+            prefix = u16::from(index);
+        }
+        writer.write(prefix, size);
+        if next >= 1 << size && size < MAX_CODE_SIZE {
+            size += 1;
+        }
+    }
+    writer.write(end, size);
+    writer.finish()
+}
+
+/// Packs codes least significant bit first, as gifs store them.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= u32::from(code) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes an lzw stream the way a gif decoder does.
+    fn lzw_decode(data: &[u8]) -> Vec<u8> {
+        let clear = 1usize << MIN_CODE_SIZE;
+        let end = clear + 1;
+        let reset = || {
+            (0..=255u8)
+                .map(|index| vec![index])
+                .chain([vec![], vec![]])
+                .collect::<Vec<_>>()
+        };
+
+        let mut table = reset();
+        let mut size = MIN_CODE_SIZE + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut position = 0;
+        let mut indices = vec![];
+        loop {
+            let mut code = 0;
+            for bit in 0..size as usize {
+                let byte = data[(position + bit) / 8];
+                code |= (((byte >> ((position + bit) % 8)) & 1) as usize) << bit;
+            }
+            position += size as usize;
+
+            if code == clear {
+                table = reset();
+                size = MIN_CODE_SIZE + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return indices;
+            }
+            let entry = match (table.get(code), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => [&previous[..], &previous[..1]].concat(),
+                (None, None) => panic!("the stream starts with an unknown code"),
+            };
+            if let Some(previous) = previous
+                && table.len() < MAX_CODES as usize
+            {
+                table.push([&previous[..], &entry[..1]].concat());
+            }
+            if table.len() == 1 << size && size < MAX_CODE_SIZE {
+                size += 1;
+            }
+            indices.extend_from_slice(&entry);
+            previous = Some(entry);
+        }
+    }
+
+    /// A gif as the frames a viewer would show, read from the file alone.
+    #[derive(Debug, Default)]
+    struct Decoded {
+        size: (u16, u16),
+        looping: bool,
+        delays: Vec<u16>,
+        /// Straight alpha rgba8, with transparent pixels being all zero.
+        frames: Vec<Vec<u8>>,
+    }
+
+    /// Decodes a gif following the layout of the gif89a specification, block by block.
+    fn decode(gif: &[u8]) -> Decoded {
+        assert_eq!(&gif[..6], b"GIF89a");
+        let mut decoded = Decoded {
+            size: (
+                u16::from_le_bytes([gif[6], gif[7]]),
+                u16::from_le_bytes([gif[8], gif[9]]),
+            ),
+            ..Default::default()
+        };
+        let flags = gif[10];
+        assert_ne!(flags & 0x80, 0, "the gif has no global palette");
+        let palette_len = 3 << ((flags & 0b111) + 1);
+        let palette = &gif[13..13 + palette_len];
+
+        let mut rest = &gif[13 + palette_len..];
+        let (mut delay, mut transparent) = (0, None);
+        loop {
+            match rest[0] {
+                0x21 => {
+                    let (data, after) = sub_blocks(&rest[2..]);
+                    match rest[1] {
+                        0xf9 => {
+                            delay = u16::from_le_bytes([data[1], data[2]]);
+                            transparent = (data[0] & 1 == 1).then_some(data[3]);
+                        }
+                        0xff => decoded.looping |= data.starts_with(b"NETSCAPE2.0"),
+                        _ => {}
+                    }
+                    rest = after;
+                }
+                0x2c => {
+                    let field =
+                        |offset: usize| u16::from_le_bytes([rest[offset], rest[offset + 1]]);
+                    assert_eq!((field(1), field(3)), (0, 0), "the frame is offset");
+                    assert_eq!((field(5), field(7)), decoded.size, "the frame is partial");
+                    assert_eq!(rest[9], 0, "the frame has its own palette or is interlaced");
+                    assert_eq!(rest[10], MIN_CODE_SIZE);
+
+                    let (data, after) = sub_blocks(&rest[11..]);
+                    let indices = lzw_decode(&data);
+                    assert_eq!(
+                        indices.len(),
+                        decoded.size.0 as usize * decoded.size.1 as usize
+                    );
+                    let frame = indices
+                        .iter()
+                        .flat_map(|&index| match transparent {
+                            Some(transparent) if index == transparent => [0; 4],
+                            _ => {
+                                let color = &palette[index as usize * 3..index as usize * 3 + 3];
+                                [color[0], color[1], color[2], 255]
+                            }
+                        })
+                        .collect();
+                    decoded.frames.push(frame);
+                    decoded.delays.push(delay);
+                    rest = after;
+                }
+                0x3b => {
+                    assert_eq!(rest.len(), 1, "there is data after the trailer");
+                    return decoded;
+                }
+                block => panic!("unknown gif block {block:#x}"),
+            }
+        }
+    }
+
+    /// Joins the data sub-blocks at the start of `data`, returning them along with what follows
+    /// their terminator.
+    fn sub_blocks(mut data: &[u8]) -> (Vec<u8>, &[u8]) {
+        let mut joined = vec![];
+        loop {
+            let len = data[0] as usize;
+            if len == 0 {
+                return (joined, &data[1..]);
+            }
+            joined.extend_from_slice(&data[1..1 + len]);
+            data = &data[1 + len..];
+        }
+    }
+
+    /// Colors that are in the palette as they are, so they decode back unchanged.
+    const EXACT: [[u8; 4]; 6] = [
+        [255, 255, 255, 255],
+        [0, 0, 0, 255],
+        [255, 0, 0, 255],
+        [0, 0, 255, 255],
+        [51, 85, 204, 255],
+        [0, 0, 0, 0],
+    ];
+
+    #[test]
+    fn frames_round_trip_through_a_decoder() {
+        let first = [0, 1, 2, 3, 4, 5].map(|i| EXACT[i]).concat();
+        let second = [5, 4, 3, 2, 1, 0].map(|i| EXACT[i]).concat();
+        let gif = encode(Size2D::new(3, 2), &[first.clone(), second.clone()], 7);
+
+        let decoded = decode(&gif);
+        assert_eq!(decoded.size, (3, 2));
+        assert!(decoded.looping);
+        assert_eq!(decoded.delays, [7, 7]);
+        assert_eq!(decoded.frames, [first, second]);
+    }
+
+    #[test]
+    fn noisy_frames_round_trip_through_a_decoder() {
+        // Noise fills the lzw table many times over, so the frames go through clear codes.
+        let mut state = 0x9e37_79b9u32;
+        let mut frame = || {
+            (0..200 * 150)
+                .flat_map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    EXACT[state as usize % EXACT.len()]
+                })
+                .collect::<Vec<_>>()
+        };
+        let frames = [frame(), frame()];
+
+        let decoded = decode(&encode(Size2D::new(200, 150), &frames, 2));
+        assert_eq!(decoded.frames, frames);
+    }
+
+    #[test]
+    fn lzw_round_trips() {
+        assert_eq!(lzw_decode(&lzw_encode(&[])), []);
+        assert_eq!(lzw_decode(&lzw_encode(&[7])), [7]);
+
+        let repeating = [1, 2, 1, 2, 1, 2, 1, 2, 3, 3, 3, 3, 3];
+        assert_eq!(lzw_decode(&lzw_encode(&repeating)), repeating);
+    }
+
+    #[test]
+    fn lzw_round_trips_past_a_full_table() {
+        // Noise fills the table quickly, forcing wider codes and a clear code along the way.
+        let mut state = 0x2545_f491u32;
+        let indices = (0..40_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state % 200) as u8
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lzw_decode(&lzw_encode(&indices)), indices);
+    }
+
+    #[test]
+    fn pixels_quantize_to_the_palette() {
+        let palette = palette();
+        assert_eq!(palette.len(), 256 * 3);
+        for pixel in [[255, 255, 255, 255], [255, 0, 0, 200], [0, 85, 255, 255]] {
+            let index = quantize(&pixel) as usize;
+            assert_eq!(palette[index * 3..index * 3 + 3], pixel[..3]);
+        }
+        assert_eq!(quantize(&[255, 255, 255, 100]), TRANSPARENT);
+    }
+
+    #[test]
+    fn frames_are_delimited() {
+        let frame = vec![255; 2 * 2 * 4];
+        let gif = encode(Size2D::new(2, 2), &[frame.clone(), frame], 5);
+        assert_eq!(&gif[..6], b"GIF89a");
+        assert_eq!(gif[6..10], [2, 0, 2, 0]);
+        assert_eq!(gif.last(), Some(&0x3b));
+
+        let controls = gif
+            .windows(4)
+            .filter(|window| window[..3] == [0x21, 0xf9, 4])
+            .count();
+        assert_eq!(controls, 2);
+        let delay = gif
+            .windows(3)
+            .position(|window| window == [0x21, 0xf9, 4])
+            .unwrap()
+            + 4;
+        assert_eq!(gif[delay..delay + 2], [5, 0]);
+    }
+}
@@ -0,0 +1,429 @@
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use anyhow::{ensure, Context, Result};
+use color::{PremulColor, Srgb};
+use euclid::default::{Box2D, Size2D};
+use graphics::{systems::TextureState, Drawable, Mesh, Systems, Vertex};
+use lyon::math::Point;
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+
+use crate::canvas::Canvas;
+use crate::pipeline::{ColorCorrection, DrawPipeline, ProjectionBind};
+use crate::projection::{Projection, YDirection};
+use crate::recorder::Macro;
+use crate::scene::DrawBatch;
+use crate::tools::ToolMessage;
+
+pub mod gif;
+pub mod png;
+
+/// The resolution a png opens at when no scale hint is given, in dots per inch.
+const BASE_DPI: f32 = 96.;
+const METERS_PER_INCH: f32 = 0.0254;
+
+/// How the alpha channel of an exported image is handled.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AlphaMode {
+    /// Keeps the alpha channel, for overlays that are composited elsewhere.
+    Keep,
+    /// Composites the content onto an opaque background color.
+    Flatten(PremulColor<Srgb>),
+}
+
+/// Options for exporting the canvas as a png.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportOptions {
+    pub alpha: AlphaMode,
+    /// The scale the image was rendered at, embedded as a dpi hint so it opens at the same
+    /// physical size as the overlay. `None` leaves the dpi out.
+    pub scale: Option<f32>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            alpha: AlphaMode::Keep,
+            scale: None,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Options for documents, a white background with the dpi of the given scale embedded.
+    pub fn document(scale: f32) -> Self {
+        Self {
+            alpha: AlphaMode::Flatten(PremulColor::WHITE),
+            scale: Some(scale),
+        }
+    }
+
+    /// The dpi hint to embed, in pixels per meter as stored in the png `pHYs` chunk.
+    pub fn pixels_per_meter(&self) -> Option<u32> {
+        self.scale
+            .map(|scale| (BASE_DPI * scale / METERS_PER_INCH).round() as u32)
+    }
+
+    /// Converts premultiplied rgba8 pixels, as read back from the gpu, to the straight alpha
+    /// pixels a png stores.
+    pub fn prepare_pixels(&self, pixels: &mut [u8]) {
+        match self.alpha {
+            AlphaMode::Keep => {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    let alpha = pixel[3] as f32 / 255.;
+                    if alpha > 0. {
+                        for channel in &mut pixel[..3] {
+                            *channel = (*channel as f32 / alpha).round().min(255.) as u8;
+                        }
+                    }
+                }
+            }
+            AlphaMode::Flatten(background) => {
+                // Compositing premultiplied content over an opaque background is `src + bg *
+                // (1 - src_alpha)`, and the result is opaque so it is already straight alpha.
+                let [r, g, b, _] = background.un_premultiply().components;
+                for pixel in pixels.chunks_exact_mut(4) {
+                    let coverage = 1. - pixel[3] as f32 / 255.;
+                    for (channel, background) in pixel[..3].iter_mut().zip([r, g, b]) {
+                        *channel = (*channel as f32 + background * coverage * 255.)
+                            .round()
+                            .min(255.) as u8;
+                    }
+                    pixel[3] = 255;
+                }
+            }
+        }
+    }
+}
+
+/// Pixels read back from the gpu, as rgba8 with premultiplied alpha.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub size: Size2D<u32>,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Encodes the image as a png with the alpha handling and dpi of the options.
+    pub fn encode_png(mut self, options: &ExportOptions) -> Vec<u8> {
+        options.prepare_pixels(&mut self.pixels);
+        png::encode(self.size, &self.pixels, options.pixels_per_meter())
+    }
+}
+
+/// Where [`export_animation`] writes the frames of a playback to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnimationOutput {
+    /// A looping gif at the path.
+    Gif(PathBuf),
+    /// A png for every frame in the directory, named `frame-0000.png` onwards.
+    Frames(PathBuf),
+}
+
+/// Replays a recorded macro onto an empty canvas and renders a frame of it every `1 / fps`
+/// seconds, following the timing of the recording.
+///
+/// Every frame covers the bounds of everything the macro commits, at one pixel per world unit.
+/// Zooming while recording only moved the view, so it is not part of the animation.
+pub fn export_animation(
+    state: &renderer::State,
+    systems: &mut Systems,
+    log: &Macro,
+    fps: f32,
+    out: &AnimationOutput,
+    y_direction: YDirection,
+) -> Result<()> {
+    ensure!(fps > 0., "the frame rate must be positive, not {fps}");
+    let region = log
+        .messages()
+        .iter()
+        .filter_map(|message| match message {
+            ToolMessage::Commit(primitive)
+            | ToolMessage::ReplaceCommit(primitive)
+            | ToolMessage::AppendCommit(primitive) => Some(primitive.bounding_box()),
+            _ => None,
+        })
+        .reduce(|region, bounds| region.union(&bounds))
+        .context("the recording has nothing to export")?;
+
+    let mut canvas = Canvas::new(systems);
+    let mut committed = None;
+    let mut applied = 0;
+    let mut frames = vec![];
+    for step in log.frame_steps(fps) {
+        for message in &log.messages()[applied..step] {
+            match message {
+                ToolMessage::Commit(primitive) | ToolMessage::AppendCommit(primitive) => {
+                    committed = Some(canvas.add_node(systems, primitive.clone()));
+                }
+                ToolMessage::ReplaceCommit(primitive) => {
+                    if let Some(id) = committed {
+                        canvas.replace_node(systems, id, primitive.clone());
+                    }
+                }
+                ToolMessage::Erase(point) => {
+                    if let Some(id) = canvas.get_node_id_at_position(*point) {
+                        canvas.remove_node_id(systems, id);
+                    }
+                }
+                _ => {}
+            }
+        }
+        applied = step;
+
+        let (mesh, batches) = canvas.export_mesh(systems);
+        frames.push(render(
+            state,
+            systems.texture,
+            &mesh,
+            &batches,
+            region,
+            y_direction,
+        )?);
+    }
+
+    let options = ExportOptions::default();
+    match out {
+        AnimationOutput::Gif(path) => {
+            let size = frames.first().context("the recording has no frames")?.size;
+            ensure!(
+                size.width <= u16::MAX as u32 && size.height <= u16::MAX as u32,
+                "an animation of {}x{} pixels is larger than a gif supports",
+                size.width,
+                size.height
+            );
+            let frames = frames
+                .into_iter()
+                .map(|mut image| {
+                    options.prepare_pixels(&mut image.pixels);
+                    image.pixels
+                })
+                .collect::<Vec<_>>();
+            // Gifs count the delay in hundredths of a second.
+            let delay = (100. / fps).round().clamp(1., u16::MAX as f32) as u16;
+            std::fs::write(path, gif::encode(size, &frames, delay))
+                .with_context(|| format!("failed to write the animation {path:?}"))?;
+        }
+        AnimationOutput::Frames(directory) => {
+            std::fs::create_dir_all(directory)
+                .with_context(|| format!("failed to create the frame directory {directory:?}"))?;
+            for (index, image) in frames.into_iter().enumerate() {
+                let path = directory.join(format!("frame-{index:04}.png"));
+                std::fs::write(&path, image.encode_png(&options))
+                    .with_context(|| format!("failed to write the frame {path:?}"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renders a scene mesh offscreen, one pixel per world unit across the world `region`, and reads
+/// the pixels back.
+///
+/// The batches are drawn in order with their blend modes, as on screen. The texture coordinates
+/// of the mesh point into the atlases of `texture`, which are sampled as they are.
+pub fn render(
+    state: &renderer::State,
+    texture: &TextureState,
+    mesh: &Mesh<Vertex>,
+    batches: &[DrawBatch],
+    region: Box2D<f32>,
+    y_direction: YDirection,
+) -> Result<Image> {
+    let size = region.size().ceil().to_u32();
+    ensure!(!size.is_empty(), "there is nothing to export");
+    let max = state.device.limits().max_texture_dimension_2d;
+    ensure!(
+        size.width <= max && size.height <= max,
+        "an export of {}x{} pixels is larger than the gpu supports",
+        size.width,
+        size.height
+    );
+
+    // The same encoding as the screen, so the colors come out as they are shown.
+    let format = if state.texture_format.is_srgb() {
+        wgpu::TextureFormat::Rgba8UnormSrgb
+    } else {
+        wgpu::TextureFormat::Rgba8Unorm
+    };
+    let pipeline = DrawPipeline::new(&state.device, format);
+
+    let mut projection = Projection::with_axes(size.to_f32(), y_direction, Point::origin());
+    let transform = projection
+        .fit_transform(region, 0.)
+        .context("there is nothing to export")?;
+    projection.set_transform(transform);
+    let projection = ProjectionBind::new(
+        state,
+        &pipeline.bind_group_layouts,
+        &projection,
+        ColorCorrection::default(),
+    );
+    let atlases = pipeline.bind_group_layouts.new_texture_atlas_bind_group(
+        &state.device,
+        &texture.mask_atlas.texture_view,
+        &texture.color_atlas.texture_view,
+        &pipeline.sampler,
+    );
+
+    let extent = wgpu::Extent3d {
+        width: size.width,
+        height: size.height,
+        depth_or_array_layers: 1,
+    };
+    let target = state.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Export Texture"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let mut encoder = state
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Export Encoder"),
+        });
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Export Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        if !mesh.indices.is_empty() {
+            let vertices = state
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Export Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&mesh.vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                });
+            let indices = state
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Export Index Buffer"),
+                    contents: bytemuck::cast_slice(&mesh.indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                });
+            render_pass.set_bind_group(0, &projection.bind_group, &[]);
+            render_pass.set_bind_group(1, &atlases, &[]);
+            render_pass.set_vertex_buffer(0, vertices.slice(..));
+            render_pass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint32);
+            for batch in batches {
+                render_pass.set_pipeline(pipeline.for_blend_mode(batch.blend_mode));
+                render_pass.draw_indexed(batch.indices.clone(), 0, 0..1);
+            }
+        }
+    }
+
+    let pixels = read_back(state, encoder, &target, extent)?;
+    Ok(Image { size, pixels })
+}
+
+/// Copies the rgba8 texture into a buffer, submitting the encoder along with it, and waits for the
+/// pixels.
+fn read_back(
+    state: &renderer::State,
+    mut encoder: wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    extent: wgpu::Extent3d,
+) -> Result<Vec<u8>> {
+    let row = extent.width * 4;
+    // Buffer copies need every row to be aligned, the padding is dropped again below.
+    let padded_row = row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Export Readback Buffer"),
+        size: padded_row as u64 * extent.height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(extent.height),
+            },
+        },
+        extent,
+    );
+    state.queue.submit(std::iter::once(encoder.finish()));
+
+    let (sender, receiver) = mpsc::channel();
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        _ = sender.send(result);
+    });
+    state
+        .device
+        .poll(wgpu::PollType::Wait)
+        .context("failed to wait for the export to render")?;
+    receiver
+        .recv()
+        .context("the export readback was dropped")?
+        .context("failed to map the export readback")?;
+
+    let mut pixels = Vec::with_capacity((row * extent.height) as usize);
+    {
+        let mapped = slice.get_mapped_range();
+        for line in mapped.chunks_exact(padded_row as usize) {
+            pixels.extend_from_slice(&line[..row as usize]);
+        }
+    }
+    buffer.unmap();
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flattening_composites_premultiplied_content() {
+        let options = ExportOptions {
+            alpha: AlphaMode::Flatten(PremulColor::new([0., 0., 1., 1.])),
+            scale: None,
+        };
+        // Half transparent red, premultiplied, then fully transparent and fully opaque green.
+        let mut pixels = [128, 0, 0, 128, 0, 0, 0, 0, 0, 255, 0, 255];
+        options.prepare_pixels(&mut pixels);
+        assert_eq!(pixels, [128, 0, 127, 255, 0, 0, 255, 255, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn keeping_alpha_unpremultiplies() {
+        let mut pixels = [40, 120, 0, 120, 0, 0, 0, 0];
+        ExportOptions::default().prepare_pixels(&mut pixels);
+        assert_eq!(pixels, [85, 255, 0, 120, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn documents_embed_the_scale_as_dpi() {
+        // 96 dpi is 3780 pixels per meter, rounded.
+        assert_eq!(ExportOptions::document(1.).pixels_per_meter(), Some(3780));
+        assert_eq!(ExportOptions::document(2.).pixels_per_meter(), Some(7559));
+        assert_eq!(ExportOptions::default().pixels_per_meter(), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! A minimal png encoder for exports, compressing the pixels through miniz_oxide.
+//!
+//! Only rgba8 without filtering is written, which every program reading the exports supports.
+
+use euclid::default::Size2D;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// The zlib compression level, the default of zlib itself.
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// Encodes straight alpha rgba8 pixels as a png, embedding the resolution when
+/// `pixels_per_meter` is set.
+///
+/// # Panics
+///
+/// Panics if there are not exactly four bytes for every pixel of `size`.
+pub fn encode(size: Size2D<u32>, pixels: &[u8], pixels_per_meter: Option<u32>) -> Vec<u8> {
+    let row = size.width as usize * 4;
+    assert_eq!(
+        pixels.len(),
+        row * size.height as usize,
+        "the pixels don't match the image size"
+    );
+
+    let mut png = SIGNATURE.to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend(size.width.to_be_bytes());
+    header.extend(size.height.to_be_bytes());
+    // 8 bits per channel of rgba, with the default compression, filtering and no interlacing.
+    header.extend([8, 6, 0, 0, 0]);
+    write_chunk(&mut png, b"IHDR", &header);
+
+    if let Some(pixels_per_meter) = pixels_per_meter {
+        let mut physical = Vec::with_capacity(9);
+        physical.extend(pixels_per_meter.to_be_bytes());
+        physical.extend(pixels_per_meter.to_be_bytes());
+        // The unit is the meter.
+        physical.push(1);
+        write_chunk(&mut png, b"pHYs", &physical);
+    }
+
+    // Every scanline starts with its filter type, which is always none here.
+    let mut scanlines = Vec::with_capacity((row + 1) * size.height as usize);
+    for line in pixels.chunks_exact(row.max(1)).take(size.height as usize) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(line);
+    }
+    write_chunk(
+        &mut png,
+        b"IDAT",
+        &miniz_oxide::deflate::compress_to_vec_zlib(&scanlines, COMPRESSION_LEVEL),
+    );
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32fast::hash(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use graphics::png;
+
+    use super::*;
+
+    /// The chunks of a png as (type, data), checking each crc on the way.
+    fn chunks(png: &[u8]) -> Vec<(&[u8], &[u8])> {
+        assert_eq!(png[..8], SIGNATURE);
+        let mut chunks = vec![];
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+            let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+            assert_eq!(crc, crc32fast::hash(&rest[4..8 + len]));
+            chunks.push((kind, data));
+            rest = &rest[12 + len..];
+        }
+        chunks
+    }
+
+    #[test]
+    fn pixels_round_trip() {
+        let pixels = (0..2 * 3 * 4).map(|i| i as u8 * 10).collect::<Vec<_>>();
+        let encoded = encode(Size2D::new(2, 3), &pixels, None);
+        let kinds = chunks(&encoded)
+            .iter()
+            .map(|(kind, _)| *kind)
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, [&b"IHDR"[..], b"IDAT", b"IEND"]);
+
+        let decoded = png::decode(&encoded).unwrap();
+        assert_eq!((decoded.width, decoded.height), (2, 3));
+        assert_eq!(decoded.pixels, pixels);
+    }
+
+    #[test]
+    fn large_images_round_trip() {
+        let pixels = (0..300 * 200 * 4)
+            .map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect::<Vec<_>>();
+        let encoded = encode(Size2D::new(300, 200), &pixels, None);
+        let decoded = png::decode(&encoded).unwrap();
+        assert_eq!((decoded.width, decoded.height), (300, 200));
+        assert_eq!(decoded.pixels, pixels);
+    }
+
+    #[test]
+    fn flat_images_are_compressed() {
+        let pixels = vec![7; 200 * 200 * 4];
+        let encoded = encode(Size2D::new(200, 200), &pixels, None);
+        assert!(encoded.len() < pixels.len() / 10);
+        assert_eq!(png::decode(&encoded).unwrap().pixels, pixels);
+    }
+
+    #[test]
+    fn resolution_is_embedded() {
+        let encoded = encode(Size2D::new(1, 1), &[0; 4], Some(3780));
+        let chunks = chunks(&encoded);
+        let (kind, data) = chunks[1];
+        assert_eq!(kind, b"pHYs");
+        assert_eq!(data, [0, 0, 0x0e, 0xc4, 0, 0, 0x0e, 0xc4, 1]);
+        assert!(png::decode(&encoded).is_ok());
+    }
+}
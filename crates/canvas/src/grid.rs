@@ -0,0 +1,199 @@
+use color::{PremulColor, Srgb};
+use graphics::{Mesh, Vertex, VertexKind};
+use lyon::math::{Box2D, Point};
+
+use crate::projection::Projection;
+
+/// Grid marks closer together than this on screen are left out, in viewport pixels.
+const MIN_SPACING: f32 = 8.;
+
+/// How the grid marks the world, see [`GridStyle`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GridKind {
+    /// Lines across the whole viewport.
+    #[default]
+    Lines,
+    /// A dot at every intersection.
+    Dots,
+    /// A small cross at every intersection.
+    Crosses,
+}
+
+impl GridKind {
+    /// The kind after this one, or `None` after the last, for cycling through the kinds.
+    pub const fn next(self) -> Option<Self> {
+        match self {
+            GridKind::Lines => Some(GridKind::Dots),
+            GridKind::Dots => Some(GridKind::Crosses),
+            GridKind::Crosses => None,
+        }
+    }
+}
+
+/// A background grid drawn behind the scene.
+///
+/// The spacing is in world units so the grid zooms with the content, while the marks themselves
+/// are sized in viewport pixels so they stay legible at any zoom. Minor marks are left out once
+/// they get too dense to read, leaving only the major ones.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridStyle {
+    pub kind: GridKind,
+    /// The distance between minor marks, in world units.
+    pub spacing: f32,
+    /// Every this many minor marks is a major mark, one makes every mark major.
+    pub major_every: u32,
+    pub minor_color: PremulColor<Srgb>,
+    pub major_color: PremulColor<Srgb>,
+    /// Scales the alpha of both colors.
+    pub opacity: f32,
+    /// The width of the lines and of the arms of the crosses, in viewport pixels.
+    pub line_width: f32,
+    /// The size of the dots and the length of the crosses, in viewport pixels.
+    pub mark_size: f32,
+}
+
+impl Default for GridStyle {
+    fn default() -> Self {
+        Self {
+            kind: GridKind::default(),
+            spacing: 20.,
+            major_every: 5,
+            minor_color: PremulColor::new([0.1, 0.1, 0.1, 0.2]),
+            major_color: PremulColor::new([0.2, 0.2, 0.2, 0.4]),
+            opacity: 1.,
+            line_width: 1.,
+            mark_size: 5.,
+        }
+    }
+}
+
+impl GridStyle {
+    /// The grid over the visible part of the world, in viewport coordinates.
+    pub fn mesh(&self, projection: &Projection) -> Mesh<Vertex> {
+        let mut mesh = Mesh::empty();
+        let viewport = projection.get_viewport();
+        let pixels = self.spacing * projection.scale();
+        let every = self.major_every.max(1);
+        let show_minor = pixels >= MIN_SPACING;
+        if !(self.spacing > 0.) || pixels * (every as f32) < MIN_SPACING {
+            return mesh;
+        }
+
+        let visible = Box2D::from_points(
+            [
+                Point::origin(),
+                Point::new(viewport.width, 0.),
+                Point::new(0., viewport.height),
+                Point::new(viewport.width, viewport.height),
+            ]
+            .map(|corner| projection.viewport_to_world(corner)),
+        );
+        // The viewport position of every visible mark along an axis, with whether it is major.
+        let marks = |min: f32, max: f32, to_viewport: &dyn Fn(f32) -> f32| {
+            ((min / self.spacing).floor() as i64..=(max / self.spacing).ceil() as i64)
+                .map(|i| {
+                    (
+                        to_viewport(i as f32 * self.spacing),
+                        i.rem_euclid(every as i64) == 0,
+                    )
+                })
+                .filter(|(_, major)| *major || show_minor)
+                .collect::<Vec<_>>()
+        };
+        let xs = marks(visible.min.x, visible.max.x, &|x| {
+            projection.world_to_viewport(Point::new(x, 0.)).x
+        });
+        let ys = marks(visible.min.y, visible.max.y, &|y| {
+            projection.world_to_viewport(Point::new(0., y)).y
+        });
+
+        let minor = VertexKind::ColorViewport(self.color(self.minor_color));
+        let major = VertexKind::ColorViewport(self.color(self.major_color));
+        // Major marks are drawn last so they sit on top where they cross minor lines.
+        let mut major_mesh = Mesh::empty();
+        let mut push = |area: Box2D, is_major: bool| {
+            if is_major {
+                major_mesh.append(&Mesh::new_color_quad(area, major));
+            } else {
+                mesh.append(&Mesh::new_color_quad(area, minor));
+            }
+        };
+
+        let half_width = self.line_width / 2.;
+        match self.kind {
+            GridKind::Lines => {
+                for (x, is_major) in &xs {
+                    let area = Box2D::new(
+                        Point::new(x - half_width, 0.),
+                        Point::new(x + half_width, viewport.height),
+                    );
+                    push(area, *is_major);
+                }
+                for (y, is_major) in &ys {
+                    let area = Box2D::new(
+                        Point::new(0., y - half_width),
+                        Point::new(viewport.width, y + half_width),
+                    );
+                    push(area, *is_major);
+                }
+            }
+            GridKind::Dots | GridKind::Crosses => {
+                let half_size = self.mark_size / 2.;
+                for (x, x_major) in &xs {
+                    for (y, y_major) in &ys {
+                        let center = Box2D::new(Point::new(*x, *y), Point::new(*x, *y));
+                        let is_major = *x_major && *y_major;
+                        if self.kind == GridKind::Dots {
+                            // At a few pixels across a square reads the same as a circle.
+                            push(center.inflate(half_size, half_size), is_major);
+                        } else {
+                            push(center.inflate(half_size, half_width), is_major);
+                            push(center.inflate(half_width, half_size), is_major);
+                        }
+                    }
+                }
+            }
+        }
+        mesh.append(&major_mesh);
+        mesh
+    }
+
+    fn color(&self, color: PremulColor<Srgb>) -> PremulColor<Srgb> {
+        let opacity = self.opacity.clamp(0., 1.);
+        PremulColor::new(color.components.map(|component| component * opacity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon::math::Size;
+
+    use super::*;
+
+    /// The number of quads in a grid over a 100 pixel square viewport zoomed by `scale`.
+    fn marks(kind: GridKind, scale: f32) -> usize {
+        let mut projection = Projection::new(Size::new(100., 100.));
+        projection.zoom_at(Point::origin(), scale);
+        let style = GridStyle {
+            kind,
+            ..Default::default()
+        };
+        style.mesh(&projection).vertices.len() / 4
+    }
+
+    #[test]
+    fn every_mark_is_drawn_when_they_are_far_apart() {
+        // Lines every 20 pixels from 0 to 100 on both axes.
+        assert_eq!(marks(GridKind::Lines, 1.), 12);
+        assert_eq!(marks(GridKind::Dots, 1.), 36);
+        assert_eq!(marks(GridKind::Crosses, 1.), 72);
+    }
+
+    #[test]
+    fn dense_minor_marks_are_left_out() {
+        // Minor lines are 5 pixels apart, leaving the major lines 25 pixels apart.
+        assert_eq!(marks(GridKind::Lines, 0.25), 10);
+        // Even the major lines would be 5 pixels apart.
+        assert_eq!(marks(GridKind::Lines, 0.05), 0);
+    }
+}
@@ -0,0 +1,316 @@
+use color::{PremulColor, Srgb};
+use graphics::{Mesh, Vertex, VertexKind};
+use lyon::math::{Box2D, Point};
+use serde::{Deserialize, Serialize};
+
+use crate::projection::Projection;
+
+/// Ruler ticks closer together than this on screen are left out, in viewport pixels.
+const MIN_TICK_SPACING: f32 = 8.;
+
+/// Which way a guide runs across the world.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GuideAxis {
+    /// A line of constant y, pulled from the top ruler.
+    Horizontal,
+    /// A line of constant x, pulled from the left ruler.
+    Vertical,
+}
+
+/// A line across the whole world that positions can be snapped to.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Guide {
+    pub axis: GuideAxis,
+    /// The y of a horizontal guide or the x of a vertical one, in world units.
+    pub position: f32,
+}
+
+impl Guide {
+    /// The guide through the given world point.
+    pub fn through(axis: GuideAxis, point: Point) -> Self {
+        let position = match axis {
+            GuideAxis::Horizontal => point.y,
+            GuideAxis::Vertical => point.x,
+        };
+        Self { axis, position }
+    }
+
+    /// How far the viewport point is from the guide, in viewport pixels.
+    pub fn distance(&self, projection: &Projection, viewport_point: Point) -> f32 {
+        let on_guide = projection.world_to_viewport(Point::new(self.position, self.position));
+        match self.axis {
+            GuideAxis::Horizontal => (viewport_point.y - on_guide.y).abs(),
+            GuideAxis::Vertical => (viewport_point.x - on_guide.x).abs(),
+        }
+    }
+}
+
+/// The rulers along the top and left of the viewport, and the guides pulled from them.
+///
+/// The rulers are sized in viewport pixels and stay put, while their ticks and the guides are in
+/// world units so they move with the pan and zoom.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GuideStyle {
+    /// The thickness of the rulers, in viewport pixels.
+    pub ruler_size: f32,
+    pub ruler_color: PremulColor<Srgb>,
+    pub tick_color: PremulColor<Srgb>,
+    pub guide_color: PremulColor<Srgb>,
+    /// The color of the guide being dragged.
+    pub active_color: PremulColor<Srgb>,
+    /// The width of the guides, in viewport pixels.
+    pub guide_width: f32,
+    /// How close a position has to be to a guide to snap to it, and the cursor to grab it, in
+    /// viewport pixels.
+    pub snap_distance: f32,
+}
+
+impl Default for GuideStyle {
+    fn default() -> Self {
+        Self {
+            ruler_size: 18.,
+            ruler_color: PremulColor::new([0.08, 0.08, 0.08, 0.85]),
+            tick_color: PremulColor::new([0.7, 0.7, 0.7, 0.9]),
+            guide_color: PremulColor::new([0., 0.6, 0.9, 0.9]),
+            active_color: PremulColor::new([1., 0.4, 0.1, 0.9]),
+            guide_width: 1.,
+            snap_distance: 6.,
+        }
+    }
+}
+
+impl GuideStyle {
+    /// The ruler under the viewport point, which is also the axis of the guide it pulls out. The
+    /// corner where the rulers meet belongs to neither.
+    pub fn ruler_at(&self, viewport_point: Point) -> Option<GuideAxis> {
+        let in_top = viewport_point.y >= 0. && viewport_point.y < self.ruler_size;
+        let in_left = viewport_point.x >= 0. && viewport_point.x < self.ruler_size;
+        match (in_top, in_left) {
+            (true, false) => Some(GuideAxis::Horizontal),
+            (false, true) => Some(GuideAxis::Vertical),
+            _ => None,
+        }
+    }
+
+    /// The index of the guide closest to the viewport point, if it is close enough to grab.
+    pub fn guide_at(
+        &self,
+        guides: &[Guide],
+        projection: &Projection,
+        viewport_point: Point,
+    ) -> Option<usize> {
+        guides
+            .iter()
+            .enumerate()
+            .map(|(i, guide)| (i, guide.distance(projection, viewport_point)))
+            .filter(|(_, distance)| *distance <= self.snap_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    /// Moves a viewport point onto the nearest horizontal and vertical guides within the snap
+    /// distance, each axis snaps on its own.
+    pub fn snap(&self, guides: &[Guide], projection: &Projection, viewport_point: Point) -> Point {
+        let nearest = |axis: GuideAxis| {
+            guides
+                .iter()
+                .filter(|guide| guide.axis == axis)
+                .map(|guide| (guide, guide.distance(projection, viewport_point)))
+                .filter(|(_, distance)| *distance <= self.snap_distance)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(guide, _)| guide.position)
+        };
+        let mut world = projection.viewport_to_world(viewport_point);
+        match (nearest(GuideAxis::Vertical), nearest(GuideAxis::Horizontal)) {
+            (None, None) => return viewport_point,
+            (x, y) => {
+                world.x = x.unwrap_or(world.x);
+                world.y = y.unwrap_or(world.y);
+            }
+        }
+        projection.world_to_viewport(world)
+    }
+
+    /// The guides across the viewport, with the rulers on top when `rulers` is set. The
+    /// `dragging` guide is drawn in the active color.
+    pub fn mesh(
+        &self,
+        projection: &Projection,
+        guides: &[Guide],
+        dragging: Option<Guide>,
+        rulers: bool,
+    ) -> Mesh<Vertex> {
+        let viewport = projection.get_viewport();
+        let mut mesh = Mesh::empty();
+        let half_width = self.guide_width / 2.;
+        let mut push_guide = |guide: &Guide, color: PremulColor<Srgb>| {
+            let at = projection.world_to_viewport(Point::new(guide.position, guide.position));
+            let area = match guide.axis {
+                GuideAxis::Horizontal => Box2D::new(
+                    Point::new(0., at.y - half_width),
+                    Point::new(viewport.width, at.y + half_width),
+                ),
+                GuideAxis::Vertical => Box2D::new(
+                    Point::new(at.x - half_width, 0.),
+                    Point::new(at.x + half_width, viewport.height),
+                ),
+            };
+            mesh.append(&Mesh::new_color_quad(
+                area,
+                VertexKind::ColorViewport(color),
+            ));
+        };
+        for guide in guides {
+            push_guide(guide, self.guide_color);
+        }
+        if let Some(guide) = &dragging {
+            push_guide(guide, self.active_color);
+        }
+        if rulers {
+            mesh.append(&self.ruler_mesh(projection));
+        }
+        mesh
+    }
+
+    /// The two ruler strips with ticks at a round world spacing that suits the zoom.
+    fn ruler_mesh(&self, projection: &Projection) -> Mesh<Vertex> {
+        let viewport = projection.get_viewport();
+        let scale = projection.scale();
+        let ruler = VertexKind::ColorViewport(self.ruler_color);
+        let tick = VertexKind::ColorViewport(self.tick_color);
+        let mut mesh = Mesh::new_color_quad(
+            Box2D::new(Point::origin(), Point::new(viewport.width, self.ruler_size)),
+            ruler,
+        );
+        mesh.append(&Mesh::new_color_quad(
+            Box2D::new(
+                Point::new(0., self.ruler_size),
+                Point::new(self.ruler_size, viewport.height),
+            ),
+            ruler,
+        ));
+        if !(scale > 0.) {
+            return mesh;
+        }
+
+        // The smallest of 1, 2 and 5 times a power of ten that keeps the ticks apart.
+        let min_step = MIN_TICK_SPACING / scale;
+        let magnitude = 10f32.powf(min_step.log10().floor());
+        let step = [1., 2., 5., 10.]
+            .into_iter()
+            .map(|factor| factor * magnitude)
+            .find(|step| *step >= min_step)
+            .unwrap_or(10. * magnitude);
+
+        let top_left = projection.viewport_to_world(Point::origin());
+        let bottom_right =
+            projection.viewport_to_world(Point::new(viewport.width, viewport.height));
+        // Every fifth tick is a major tick that reaches across most of the ruler.
+        let length = |i: i64| {
+            if i.rem_euclid(5) == 0 {
+                self.ruler_size * 0.6
+            } else {
+                self.ruler_size * 0.25
+            }
+        };
+        let first = (top_left.x.min(bottom_right.x) / step).floor() as i64;
+        let last = (top_left.x.max(bottom_right.x) / step).ceil() as i64;
+        for i in first..=last {
+            let x = projection
+                .world_to_viewport(Point::new(i as f32 * step, 0.))
+                .x;
+            if x < self.ruler_size {
+                continue;
+            }
+            mesh.append(&Mesh::new_color_quad(
+                Box2D::new(
+                    Point::new(x, self.ruler_size - length(i)),
+                    Point::new(x + 1., self.ruler_size),
+                ),
+                tick,
+            ));
+        }
+        let first = (top_left.y.min(bottom_right.y) / step).floor() as i64;
+        let last = (top_left.y.max(bottom_right.y) / step).ceil() as i64;
+        for i in first..=last {
+            let y = projection
+                .world_to_viewport(Point::new(0., i as f32 * step))
+                .y;
+            if y < self.ruler_size {
+                continue;
+            }
+            mesh.append(&Mesh::new_color_quad(
+                Box2D::new(
+                    Point::new(self.ruler_size - length(i), y),
+                    Point::new(self.ruler_size, y + 1.),
+                ),
+                tick,
+            ));
+        }
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon::math::Size;
+
+    use super::*;
+
+    const GUIDES: [Guide; 2] = [
+        Guide {
+            axis: GuideAxis::Vertical,
+            position: 50.,
+        },
+        Guide {
+            axis: GuideAxis::Horizontal,
+            position: 30.,
+        },
+    ];
+
+    #[test]
+    fn each_axis_snaps_on_its_own() {
+        let style = GuideStyle::default();
+        let projection = Projection::new(Size::new(200., 200.));
+        let snap = |x, y| style.snap(&GUIDES, &projection, Point::new(x, y));
+
+        assert_eq!(snap(53., 80.), Point::new(50., 80.));
+        assert_eq!(snap(80., 25.), Point::new(80., 30.));
+        assert_eq!(snap(47., 34.), Point::new(50., 30.));
+        assert_eq!(snap(80., 80.), Point::new(80., 80.));
+    }
+
+    #[test]
+    fn the_snap_distance_is_in_viewport_pixels() {
+        let style = GuideStyle::default();
+        let mut projection = Projection::new(Size::new(200., 200.));
+        projection.zoom_at(Point::origin(), 2.);
+
+        // The vertical guide is shown at x = 100.
+        let near = Point::new(104., 150.);
+        assert_eq!(
+            style.snap(&GUIDES, &projection, near),
+            Point::new(100., 150.)
+        );
+        assert_eq!(style.guide_at(&GUIDES, &projection, near), Some(0));
+        // Only 4 world units away from the guide, but 8 pixels.
+        let far = Point::new(108., 150.);
+        assert_eq!(style.snap(&GUIDES, &projection, far), far);
+        assert_eq!(style.guide_at(&GUIDES, &projection, far), None);
+    }
+
+    #[test]
+    fn the_rulers_pull_out_guides_along_their_axis() {
+        let style = GuideStyle::default();
+        assert_eq!(
+            style.ruler_at(Point::new(100., 5.)),
+            Some(GuideAxis::Horizontal)
+        );
+        assert_eq!(
+            style.ruler_at(Point::new(5., 100.)),
+            Some(GuideAxis::Vertical)
+        );
+        assert_eq!(style.ruler_at(Point::new(5., 5.)), None);
+        assert_eq!(style.ruler_at(Point::new(100., 100.)), None);
+    }
+}
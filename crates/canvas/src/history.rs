@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use euclid::default::Vector2D;
+use graphics::primitives::FlipAxis;
+use graphics::{CanvasCoordinates, Drawable, Primitive, Systems, Vertex};
+
+use crate::scene::{LayerId, NodeId};
+
+/// A change to the scene that can be undone.
+#[derive(Debug)]
+pub enum HistoryEntry {
+    /// The node was added to the scene, undoing removes it again.
+    Added(NodeId),
+    /// The node was removed from the scene, undoing puts it back where it was.
+    ///
+    /// The entry owns the primitive, including any atlas allocations it holds, so dropping the
+    /// entry releases them.
+    Removed {
+        id: NodeId,
+        node: Primitive<CanvasCoordinates>,
+        layer: LayerId,
+        /// The index of the node in the scene ordering.
+        index: usize,
+        metadata: Option<serde_json::Value>,
+    },
+    /// The node was swapped for another primitive, undoing puts the original `node` back.
+    Replaced {
+        id: NodeId,
+        node: Primitive<CanvasCoordinates>,
+    },
+    /// The nodes were moved by the offset, undoing moves them back.
+    Moved {
+        ids: Vec<NodeId>,
+        offset: Vector2D<f32>,
+    },
+    /// The nodes were mirrored about the line at `at`, undoing mirrors them again.
+    Flipped {
+        ids: Vec<NodeId>,
+        axis: FlipAxis,
+        at: f32,
+        /// Whether their text was mirrored as well, see [`Primitive::mirror`].
+        mirror_text: bool,
+    },
+    /// The node was rotated, undoing turns it back to `rotation`.
+    Rotated { id: NodeId, rotation: f32 },
+    /// Several changes made at once, undoing reverts them from the last to the first.
+    Batch(Vec<HistoryEntry>),
+}
+
+impl HistoryEntry {
+    /// An estimate of the memory held by the entry, in bytes.
+    fn approximate_size(&mut self, systems: &mut Systems) -> usize {
+        let size = std::mem::size_of::<Self>();
+        match self {
+            HistoryEntry::Added(_) | HistoryEntry::Rotated { .. } => size,
+            HistoryEntry::Moved { ids, .. } | HistoryEntry::Flipped { ids, .. } => {
+                size + ids.len() * std::mem::size_of::<NodeId>()
+            }
+            HistoryEntry::Batch(entries) => {
+                size + entries
+                    .iter_mut()
+                    .map(|entry| entry.approximate_size(systems))
+                    .sum::<usize>()
+            }
+            HistoryEntry::Removed { node, .. } | HistoryEntry::Replaced { node, .. } => {
+                let mesh = node.render(systems);
+                size + mesh.vertices.len() * std::mem::size_of::<Vertex>()
+                    + mesh.indices.len() * std::mem::size_of::<u32>()
+            }
+        }
+    }
+}
+
+/// Bounds on the undo history, the oldest entries are evicted once either is exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryLimits {
+    pub max_entries: usize,
+    /// The estimated memory the history may hold, in bytes.
+    pub max_bytes: usize,
+}
+
+impl Default for HistoryLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 256,
+            max_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// The undo stack of a canvas, bounded by [`HistoryLimits`].
+#[derive(Debug, Default)]
+pub struct History {
+    /// The entries along with their estimated size, oldest first.
+    entries: VecDeque<(HistoryEntry, usize)>,
+    bytes: usize,
+    limits: HistoryLimits,
+}
+
+impl History {
+    pub fn new(limits: HistoryLimits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, systems: &mut Systems, mut entry: HistoryEntry) {
+        let size = entry.approximate_size(systems);
+        self.bytes += size;
+        self.entries.push_back((entry, size));
+        self.evict();
+    }
+
+    /// Takes the most recent entry.
+    pub fn pop(&mut self) -> Option<HistoryEntry> {
+        let (entry, size) = self.entries.pop_back()?;
+        self.bytes -= size;
+        Some(entry)
+    }
+
+    /// Adds the entry to the most recent one, turning it into a batch, so that both are undone
+    /// at once.
+    pub fn append(&mut self, systems: &mut Systems, mut entry: HistoryEntry) {
+        let Some((last, last_size)) = self.entries.pop_back() else {
+            return self.push(systems, entry);
+        };
+        let size = entry.approximate_size(systems);
+        let batch = match last {
+            HistoryEntry::Batch(mut entries) => {
+                entries.push(entry);
+                entries
+            }
+            last => vec![last, entry],
+        };
+        self.bytes += size;
+        self.entries
+            .push_back((HistoryEntry::Batch(batch), last_size + size));
+        self.evict();
+    }
+
+    /// The most recent entry, which can be extended instead of pushing a new one.
+    pub fn last_mut(&mut self) -> Option<&mut HistoryEntry> {
+        self.entries.back_mut().map(|(entry, _)| entry)
+    }
+
+    pub fn limits(&self) -> HistoryLimits {
+        self.limits
+    }
+    /// Changes the limits, evicting entries that no longer fit.
+    pub fn set_limits(&mut self, limits: HistoryLimits) {
+        self.limits = limits;
+        self.evict();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// The estimated memory held by the history, in bytes.
+    pub fn approximate_size(&self) -> usize {
+        self.bytes
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > self.limits.max_entries || self.bytes > self.limits.max_bytes {
+            let Some((_, size)) = self.entries.pop_front() else {
+                break;
+            };
+            self.bytes -= size;
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Turns images pasted from the clipboard into svgs embedding them, which is how the canvas draws
+//! raster images.
+
+use std::ops::Range;
+
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use euclid::default::Size2D;
+use graphics::png;
+use graphics::systems::DecodedImage;
+use tracing::warn;
+
+use crate::export;
+
+/// The widest and tallest pasted image that is decoded, past this it is rejected instead of being
+/// scaled down.
+const MAX_PASTED_DIMENSION: u32 = 16384;
+
+/// A pasted image, ready to be placed on the canvas.
+pub struct PastedImage {
+    /// The size of the image as it was pasted, before any scaling down.
+    pub size: Size2D<u32>,
+    /// An svg showing the image over `size`.
+    pub svg: Vec<u8>,
+}
+
+/// Decodes a pasted png, scaling it down when it is wider or taller than `max_dimension`, the
+/// largest image that fits in the atlas.
+pub fn pasted_png(bytes: &[u8], max_dimension: u32) -> Result<PastedImage> {
+    let image = png::decode_with_limit(bytes, MAX_PASTED_DIMENSION)?;
+    let size = Size2D::new(image.width, image.height);
+    // Svgs decode their images again when they are drawn, which only takes images up to this.
+    let max_dimension = max_dimension.min(png::MAX_DIMENSION);
+
+    let encoded = if size.width.max(size.height) > max_dimension {
+        let scaled = downscale(&image, max_dimension);
+        warn!(
+            "the pasted image is {}x{}, larger than the atlas allows, scaling it down to {}x{}",
+            size.width, size.height, scaled.width, scaled.height
+        );
+        export::png::encode(
+            Size2D::new(scaled.width, scaled.height),
+            &scaled.pixels,
+            None,
+        )
+    } else {
+        bytes.to_vec()
+    };
+    Ok(PastedImage {
+        size,
+        svg: embed(size, &encoded),
+    })
+}
+
+/// An svg of `size` showing the png stretched over all of it.
+fn embed(size: Size2D<u32>, png: &[u8]) -> Vec<u8> {
+    let (width, height) = (size.width, size.height);
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" "#,
+            r#"width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#,
+            r#"<image width="{width}" height="{height}" preserveAspectRatio="none" "#,
+            r#"xlink:href="data:image/png;base64,{data}"/></svg>"#,
+        ),
+        width = width,
+        height = height,
+        data = STANDARD.encode(png),
+    )
+    .into_bytes()
+}
+
+/// Scales an image down so that its longer side is `max_dimension`, averaging the pixels each
+/// pixel of the result covers.
+fn downscale(image: &DecodedImage, max_dimension: u32) -> DecodedImage {
+    let scale = f64::from(max_dimension) / f64::from(image.width.max(image.height));
+    let scaled = |len: u32| ((f64::from(len) * scale).round() as u32).clamp(1, max_dimension);
+    let (width, height) = (scaled(image.width), scaled(image.height));
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        let rows = covered(y, height, image.height);
+        for x in 0..width {
+            let columns = covered(x, width, image.width);
+            let mut sum = [0u64; 4];
+            for row in rows.clone() {
+                for column in columns.clone() {
+                    let index = (row as usize * image.width as usize + column as usize) * 4;
+                    let [r, g, b, a] = [0, 1, 2, 3].map(|c| u64::from(image.pixels[index + c]));
+                    // The colors are weighted by their alpha, so that the color of transparent
+                    // pixels does not bleed into the edges of what is around them.
+                    sum[0] += r * a;
+                    sum[1] += g * a;
+                    sum[2] += b * a;
+                    sum[3] += a;
+                }
+            }
+            let count = (rows.len() * columns.len()) as u64;
+            let alpha = sum[3];
+            let color = |channel: usize| (sum[channel] + alpha / 2).checked_div(alpha).unwrap_or(0);
+            pixels.extend(
+                [color(0), color(1), color(2), (alpha + count / 2) / count].map(|c| c as u8),
+            );
+        }
+    }
+
+    DecodedImage {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// The pixels of a side `len` long that pixel `index` covers once the side is `scaled_len` long.
+fn covered(index: u32, scaled_len: u32, len: u32) -> Range<u32> {
+    let (index, scaled_len, len) = (u64::from(index), u64::from(scaled_len), u64::from(len));
+    let start = index * len / scaled_len;
+    let end = ((index + 1) * len).div_ceil(scaled_len);
+    start as u32..end.max(start + 1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use graphics::primitives::{Svg, SvgOptions};
+    use graphics::CanvasCoordinates;
+
+    use super::*;
+
+    fn encoded(width: u32, height: u32, pixel: impl Fn(u32, u32) -> [u8; 4]) -> Vec<u8> {
+        let pixels: Vec<u8> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| pixel(x, y))
+            .collect();
+        export::png::encode(Size2D::new(width, height), &pixels, None)
+    }
+
+    /// The png embedded in a pasted svg.
+    fn embedded(svg: &[u8]) -> Vec<u8> {
+        let svg = std::str::from_utf8(svg).unwrap();
+        let (_, data) = svg.split_once("base64,").unwrap();
+        let (data, _) = data.split_once('"').unwrap();
+        STANDARD.decode(data).unwrap()
+    }
+
+    #[test]
+    fn small_images_are_embedded_unchanged() {
+        let bytes = encoded(3, 2, |x, y| [x as u8 * 80, y as u8 * 80, 0, 255]);
+        let pasted = pasted_png(&bytes, 8192).unwrap();
+        assert_eq!(pasted.size, Size2D::new(3, 2));
+        assert_eq!(embedded(&pasted.svg), bytes);
+
+        let svg = Svg::<CanvasCoordinates>::new(
+            Default::default(),
+            Size2D::new(3., 2.),
+            pasted.svg,
+            SvgOptions::default(),
+        );
+        assert_eq!(svg.intrinsic_size(), Some(Size2D::new(3., 2.)));
+    }
+
+    #[test]
+    fn large_images_are_scaled_down_to_the_limit() {
+        let bytes = encoded(40, 10, |x, _| {
+            if x < 20 {
+                [255, 0, 0, 255]
+            } else {
+                [0, 0, 255, 255]
+            }
+        });
+        let pasted = pasted_png(&bytes, 8).unwrap();
+        // The image keeps the size it was pasted at, only its pixels are scaled down.
+        assert_eq!(pasted.size, Size2D::new(40, 10));
+
+        let scaled = png::decode(&embedded(&pasted.svg)).unwrap();
+        assert_eq!((scaled.width, scaled.height), (8, 2));
+        assert_eq!(&scaled.pixels[..4], &[255, 0, 0, 255]);
+        assert_eq!(&scaled.pixels[7 * 4..8 * 4], &[0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn transparent_pixels_do_not_darken_the_scaled_image() {
+        let image = DecodedImage {
+            width: 2,
+            height: 1,
+            pixels: vec![255, 255, 255, 255, 0, 0, 0, 0],
+        };
+        let scaled = downscale(&image, 1);
+        assert_eq!(scaled.pixels, vec![255, 255, 255, 128]);
+    }
+
+    #[test]
+    fn images_that_are_not_pngs_are_rejected() {
+        assert!(pasted_png(b"GIF89a", 8192).is_err());
+    }
+}
@@ -1,9 +1,17 @@
 #![feature(const_trait_impl)]
 
+pub mod align;
 pub mod canvas;
+pub mod export;
+pub mod grid;
+pub mod guides;
+pub mod history;
+pub mod import;
 pub mod pipeline;
 pub mod projection;
+pub mod recorder;
 pub mod scene;
+pub mod session;
 pub mod tools;
 pub mod view;
 
@@ -14,9 +22,9 @@ use std::time::Duration;
 use lyon::math::Point;
 use serde::{Deserialize, Serialize};
 
-use graphics::{Drawable, Vertex};
+use graphics::{CanvasCoordinates, Drawable, Primitive, Vertex};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum ClickResult {
     Handled,
     Unhandled,
@@ -28,6 +36,14 @@ pub trait Node: Drawable<Vertex> {
     fn handle_drag(&mut self, position: Point);
 }
 
+/// None of the current primitives are interactive, so clicks fall through to the tool.
+impl Node for Primitive<CanvasCoordinates> {
+    fn handle_click(&mut self, _position: Point) -> ClickResult {
+        ClickResult::Unhandled
+    }
+    fn handle_drag(&mut self, _position: Point) {}
+}
+
 pub trait RedrawRequest {
     fn request_redraw(&self);
     fn request_redraw_duration(&self, duration: Duration);
@@ -1,4 +1,10 @@
+use std::f32::consts::TAU;
+
+use bytemuck::{Pod, Zeroable};
+use graphics::BlendMode;
+use lyon::math::Vector;
 use renderer::State;
+use serde::{Deserialize, Serialize};
 use wgpu::{
     util::DeviceExt, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
     BindingResource, Buffer, Device, FilterMode, Sampler, SamplerDescriptor, TextureView,
@@ -9,20 +15,35 @@ use crate::projection::Projection;
 #[derive(Debug)]
 pub struct DrawPipeline {
     pub render_pipeline: wgpu::RenderPipeline,
+    /// Draws with a destination-out blend, removing the alpha of whatever was drawn below.
+    pub erase_pipeline: wgpu::RenderPipeline,
+    /// Draws with a destination-over blend, below whatever was drawn before it. The colors are
+    /// taken as premultiplied.
+    pub behind_pipeline: wgpu::RenderPipeline,
+    /// Composites the dilated silhouette of a [`ContrastTarget`] below whatever was drawn before
+    /// it.
+    pub contrast_pipeline: wgpu::RenderPipeline,
     pub bind_group_layouts: Layouts,
     pub sampler: wgpu::Sampler,
+    /// Samples the silhouette between pixels, as the outline offsets are fractional.
+    pub contrast_sampler: wgpu::Sampler,
 }
 
 #[derive(Debug)]
 pub struct Layouts {
     pub projection: wgpu::BindGroupLayout,
     pub texture_atlas: wgpu::BindGroupLayout,
+    pub contrast: wgpu::BindGroupLayout,
 }
 
 #[derive(Debug)]
 pub struct Binds {
     pub projection: ProjectionBind,
     pub texture_atlases: wgpu::BindGroup,
+    /// Draws the scene as its silhouette for the contrast outline, `None` while it is disabled.
+    pub contrast: Option<ProjectionBind>,
+    /// Where the silhouette is drawn, created for the size of the frame.
+    pub contrast_target: Option<ContrastTarget>,
 }
 
 impl DrawPipeline {
@@ -39,8 +60,16 @@ impl DrawPipeline {
             ..Default::default()
         });
 
+        let contrast_sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("contrast sampler"),
+            min_filter: FilterMode::Linear,
+            mag_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
         let projection_layout = Layouts::projection_layout(device);
         let texture_atlas_layout = Layouts::texture_atlas_layout(device);
+        let contrast_layout = Layouts::contrast_layout(device);
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -49,27 +78,80 @@ impl DrawPipeline {
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Canvas Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
+        let render_pipeline = Self::create_pipeline(
+            device,
+            "Canvas Render Pipeline",
+            &render_pipeline_layout,
+            &shader,
+            texture_format,
+            wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+        );
+        let erase_component = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Zero,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let erase_pipeline = Self::create_pipeline(
+            device,
+            "Canvas Erase Pipeline",
+            &render_pipeline_layout,
+            &shader,
+            texture_format,
+            wgpu::BlendState {
+                color: erase_component,
+                alpha: erase_component,
+            },
+        );
+
+        let behind_component = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::OneMinusDstAlpha,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let behind_pipeline = Self::create_pipeline(
+            device,
+            "Canvas Behind Pipeline",
+            &render_pipeline_layout,
+            &shader,
+            texture_format,
+            wgpu::BlendState {
+                color: behind_component,
+                alpha: behind_component,
+            },
+        );
+
+        let contrast_shader =
+            device.create_shader_module(wgpu::include_wgsl!("shaders/contrast.wgsl"));
+        let contrast_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("contrast pipeline layout"),
+                bind_group_layouts: &[&contrast_layout],
+                push_constant_ranges: &[],
+            });
+        let contrast_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Canvas Contrast Pipeline"),
+            layout: Some(&contrast_pipeline_layout),
             vertex: wgpu::VertexState {
-                module: &shader,
+                module: &contrast_shader,
                 entry_point: Some("vs_main"),
-                buffers: &[crate::Vertex::buffer_layout()],
+                buffers: &[],
                 compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader,
+                module: &contrast_shader,
                 entry_point: Some("fs_main"),
                 targets: &[Some(wgpu::ColorTargetState {
                     format: texture_format,
                     blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::SrcAlpha,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                        alpha: wgpu::BlendComponent::OVER,
+                        color: behind_component,
+                        alpha: behind_component,
                     }),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -84,19 +166,69 @@ impl DrawPipeline {
 
         Self {
             render_pipeline,
+            erase_pipeline,
+            behind_pipeline,
+            contrast_pipeline,
             bind_group_layouts: Layouts {
                 projection: projection_layout,
                 texture_atlas: texture_atlas_layout,
+                contrast: contrast_layout,
             },
             sampler,
+            contrast_sampler,
         }
     }
 }
+impl DrawPipeline {
+    /// The pipeline to draw primitives with the given blend mode.
+    pub const fn for_blend_mode(&self, blend_mode: BlendMode) -> &wgpu::RenderPipeline {
+        match blend_mode {
+            BlendMode::Normal => &self.render_pipeline,
+            BlendMode::Erase => &self.erase_pipeline,
+        }
+    }
+
+    fn create_pipeline(
+        device: &Device,
+        label: &str,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        texture_format: wgpu::TextureFormat,
+        blend: wgpu::BlendState,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                buffers: &[crate::Vertex::buffer_layout()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: texture_format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    }
+}
 impl Layouts {
     pub fn new(device: &Device) -> Self {
         Self {
             projection: Self::texture_atlas_layout(device),
             texture_atlas: Self::texture_atlas_layout(device),
+            contrast: Self::contrast_layout(device),
         }
     }
 
@@ -123,6 +255,16 @@ impl Layouts {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
             label: Some("projection bind group layout"),
         })
@@ -160,6 +302,39 @@ impl Layouts {
             label: Some("texture atlas bind group layout"),
         })
     }
+    pub fn contrast_layout(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("contrast bind group layout"),
+        })
+    }
 }
 impl Layouts {
     pub fn new_projection_group(
@@ -167,6 +342,7 @@ impl Layouts {
         device: &Device,
         world_projection: &Buffer,
         viewport_projection: &Buffer,
+        color_correction: &Buffer,
     ) -> BindGroup {
         device.create_bind_group(&BindGroupDescriptor {
             layout: &self.projection,
@@ -179,6 +355,10 @@ impl Layouts {
                     binding: 1,
                     resource: viewport_projection.as_entire_binding(),
                 },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: color_correction.as_entire_binding(),
+                },
             ],
             label: Some("projection bind group"),
         })
@@ -211,17 +391,205 @@ impl Layouts {
     }
 }
 
+/// A correction applied to every color drawn on an output, to match the overlay across displays
+/// with different color profiles. The default leaves colors untouched, and only gives glyph edges
+/// the usual text contrast.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColorCorrection {
+    /// Mixes the rgb channels, as rows of the matrix.
+    pub matrix: [[f32; 3]; 3],
+    /// The exponent the mixed channels are raised to.
+    pub gamma: f32,
+    /// Tunes the anti-aliased edges of glyphs, their coverage is raised to one over this before
+    /// it is used as alpha. Above one makes text heavier, below one thinner.
+    #[serde(default = "ColorCorrection::default_text_gamma")]
+    pub text_gamma: f32,
+}
+
+impl Default for ColorCorrection {
+    fn default() -> Self {
+        Self {
+            matrix: [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]],
+            gamma: 1.,
+            text_gamma: Self::DEFAULT_TEXT_GAMMA,
+        }
+    }
+}
+
+impl ColorCorrection {
+    /// Close to the text contrast of most desktop renderers, which keeps thin strokes from
+    /// washing out over a transparent background.
+    pub const DEFAULT_TEXT_GAMMA: f32 = 1.2;
+
+    const fn default_text_gamma() -> f32 {
+        Self::DEFAULT_TEXT_GAMMA
+    }
+
+    /// Draws every color black while keeping its alpha, leaving only the shape of the content.
+    pub const SILHOUETTE: Self = Self {
+        matrix: [[0.; 3]; 3],
+        gamma: 1.,
+        text_gamma: Self::DEFAULT_TEXT_GAMMA,
+    };
+}
+
+/// How many samples of the scene silhouette make up the contrast outline, which has to match the
+/// array in `shaders/contrast.wgsl`.
+const CONTRAST_SAMPLES: usize = 8;
+
+/// A dark outline behind all of the canvas content, so that it stands out over both light and
+/// dark backgrounds.
+///
+/// The silhouette of the scene is drawn once offscreen, and then dilated below the scene by
+/// keeping the highest alpha of the samples around a circle. Taking the highest alpha instead of
+/// blending the samples keeps translucent content from building up a darker outline.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContrastOutline {
+    /// How far the outline reaches past the content, in viewport pixels.
+    pub radius: f32,
+}
+
+impl Default for ContrastOutline {
+    fn default() -> Self {
+        Self { radius: 1.5 }
+    }
+}
+
+impl ContrastOutline {
+    /// The viewport offsets the silhouette is drawn at.
+    pub fn offsets(&self) -> [Vector; CONTRAST_SAMPLES] {
+        std::array::from_fn(|i| {
+            let angle = i as f32 / CONTRAST_SAMPLES as f32 * TAU;
+            Vector::new(angle.cos(), angle.sin()) * self.radius
+        })
+    }
+}
+
+/// The layout of the outline in `shaders/contrast.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ContrastUniform {
+    offsets: [[f32; 4]; CONTRAST_SAMPLES],
+    texel_size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+/// The offscreen texture the silhouette of the scene is drawn into, and the bind group to
+/// composite it as the [`ContrastOutline`].
+#[derive(Debug)]
+pub struct ContrastTarget {
+    texture: wgpu::Texture,
+    pub view: TextureView,
+    pub bind_group: BindGroup,
+}
+
+impl ContrastTarget {
+    pub fn new(
+        state: &State,
+        pipeline: &DrawPipeline,
+        outline: ContrastOutline,
+        size: wgpu::Extent3d,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Contrast Silhouette Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let uniform = ContrastUniform {
+            offsets: outline.offsets().map(|offset| [offset.x, offset.y, 0., 0.]),
+            texel_size: [1. / size.width as f32, 1. / size.height as f32],
+            _padding: [0.; 2],
+        };
+        let uniform = state
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Contrast Uniform Buffer"),
+                contents: bytemuck::bytes_of(&uniform),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let bind_group = state.device.create_bind_group(&BindGroupDescriptor {
+            layout: &pipeline.bind_group_layouts.contrast,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&pipeline.contrast_sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: uniform.as_entire_binding(),
+                },
+            ],
+            label: Some("contrast bind group"),
+        });
+        Self {
+            texture,
+            view,
+            bind_group,
+        }
+    }
+
+    /// Whether the target can be drawn into for a frame of the given size and format.
+    pub fn fits(&self, size: wgpu::Extent3d, format: wgpu::TextureFormat) -> bool {
+        self.texture.size() == size && self.texture.format() == format
+    }
+}
+
+/// The layout of [`ColorCorrection`] in the shader, where each column of a `mat3x3` is padded to
+/// 16 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ColorCorrectionUniform {
+    columns: [[f32; 4]; 3],
+    gamma: f32,
+    text_gamma: f32,
+    _padding: [f32; 2],
+}
+
+impl From<ColorCorrection> for ColorCorrectionUniform {
+    fn from(value: ColorCorrection) -> Self {
+        let m = value.matrix;
+        Self {
+            columns: std::array::from_fn(|col| [m[0][col], m[1][col], m[2][col], 0.]),
+            gamma: value.gamma,
+            // A gamma of zero or less would blow the coverage up, so it falls back to linear.
+            text_gamma: if value.text_gamma > 0. {
+                value.text_gamma
+            } else {
+                1.
+            },
+            _padding: [0.; 2],
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ProjectionBind {
     world_projection: Buffer,
     viewport_projection: Buffer,
+    color_correction: Buffer,
     pub bind_group: BindGroup,
 }
 pub struct TextureAtlasBind {
     pub bind_group: BindGroup,
 }
 impl ProjectionBind {
-    pub fn new(state: &State, layout: &Layouts, projection: &Projection) -> Self {
+    pub fn new(
+        state: &State,
+        layout: &Layouts,
+        projection: &Projection,
+        color_correction: ColorCorrection,
+    ) -> Self {
         let world_projection = state
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -237,15 +605,35 @@ impl ProjectionBind {
                     contents: &bytemuck::cast_slice(&projection.viewport_to_uv().to_arrays()),
                     usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 });
-        let bind_group =
-            layout.new_projection_group(&state.device, &world_projection, &viewport_projection);
+        let color_correction = state
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Color Correction Uniform Buffer"),
+                contents: bytemuck::bytes_of(&ColorCorrectionUniform::from(color_correction)),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let bind_group = layout.new_projection_group(
+            &state.device,
+            &world_projection,
+            &viewport_projection,
+            &color_correction,
+        );
         ProjectionBind {
             world_projection,
             viewport_projection,
+            color_correction,
             bind_group,
         }
     }
 
+    pub fn update_color_correction(&self, state: &State, color_correction: ColorCorrection) {
+        state.queue.write_buffer(
+            &self.color_correction,
+            0,
+            bytemuck::bytes_of(&ColorCorrectionUniform::from(color_correction)),
+        );
+    }
+
     pub fn update_world(&self, state: &State, projection: &Projection) {
         state.queue.write_buffer(
             &self.world_projection,
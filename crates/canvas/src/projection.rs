@@ -1,15 +1,53 @@
+use std::time::{Duration, Instant};
+
 use lyon::{
     geom::euclid::default::Transform3D,
-    math::{Point, Size, Vector},
+    math::{Box2D, Point, Size, Vector},
 };
 use serde::{Deserialize, Serialize};
 
+/// The direction world y coordinates increase in on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum YDirection {
+    /// Y increases downwards, like screen coordinates. This is the default.
+    #[default]
+    Down,
+    /// Y increases upwards, like most maths and cad coordinate systems.
+    ///
+    /// Primitives still lay out their content in y-down order, so text and svgs appear mirrored
+    /// in a y-up world: their anchor, the top left corner, is shown at the bottom left and they
+    /// read upside down. This is intended, as it keeps the primitives the same in either world.
+    /// Mirroring them vertically with [`graphics::Primitive::mirror`] makes them read the right
+    /// way. The gui is unaffected as it is drawn in viewport coordinates.
+    Up,
+}
+
+/// An eased transition of the pan and zoom between two transforms.
+#[derive(Clone, Copy, Debug)]
+struct TransformAnimation {
+    from: Transform3D<f32>,
+    to: Transform3D<f32>,
+    start: Instant,
+    duration: Duration,
+}
+
+/// Maps world coordinates to viewport pixels.
+///
+/// The mapping is made of two steps: the axes, which orient the y axis and place the world
+/// origin, followed by the pan and zoom. The pan and zoom is a uniform scale followed by a
+/// translation in viewport pixels, so a world point `p` (after the axes) is shown at
+/// `p * scale + translation`. The setters below all work on this second step.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Projection {
     needs_rebinding: bool,
     /// Matrix taking world coords → viewport coords
     world_to_viewport: Transform3D<f32>,
     viewport: Size,
+    y_direction: YDirection,
+    /// The viewport position the world origin is shown at before any panning or zooming.
+    origin: Point,
+    #[serde(skip)]
+    animation: Option<TransformAnimation>,
 }
 
 impl Projection {
@@ -18,9 +56,51 @@ impl Projection {
             needs_rebinding: true,
             world_to_viewport: Transform3D::identity(),
             viewport,
+            y_direction: YDirection::Down,
+            origin: Point::origin(),
+            animation: None,
+        }
+    }
+
+    /// Creates a projection with the given axes, the origin being in viewport pixels.
+    pub fn with_axes(viewport: Size, y_direction: YDirection, origin: Point) -> Self {
+        Self {
+            y_direction,
+            origin,
+            ..Self::new(viewport)
         }
     }
 
+    pub fn y_direction(&self) -> YDirection {
+        self.y_direction
+    }
+    pub fn set_y_direction(&mut self, y_direction: YDirection) {
+        self.y_direction = y_direction;
+        self.needs_rebinding = true;
+    }
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+    pub fn set_origin(&mut self, origin: Point) {
+        self.origin = origin;
+        self.needs_rebinding = true;
+    }
+
+    /// The matrix taking world coords to viewport coords before any panning or zooming, which
+    /// places the origin and orients the y axis.
+    fn axes(&self) -> Transform3D<f32> {
+        let y = match self.y_direction {
+            YDirection::Down => 1.,
+            YDirection::Up => -1.,
+        };
+        Transform3D::scale(1., y, 1.).then_translate(self.origin.to_vector().to_3d())
+    }
+
+    /// The full world → viewport matrix, including the axes.
+    fn full_world_to_viewport(&self) -> Transform3D<f32> {
+        self.axes().then(&self.world_to_viewport)
+    }
+
     pub fn needs_rebinding(&self) -> bool {
         self.needs_rebinding
     }
@@ -34,6 +114,7 @@ impl Projection {
     pub fn set_viewport(&mut self, new_size: Size) {
         self.viewport = new_size;
         self.world_to_viewport = Transform3D::identity();
+        self.animation = None;
         self.needs_rebinding = true;
     }
 
@@ -42,6 +123,11 @@ impl Projection {
         let t = Transform3D::translation(delta.x, delta.y, 0.0);
         // world → viewport = (old_world_to_viewport) ∘ (translate_world)
         self.world_to_viewport = self.world_to_viewport.then(&t);
+        // Panning mid-animation moves where the animation ends up as well, so they don't fight.
+        if let Some(animation) = &mut self.animation {
+            animation.from = animation.from.then(&t);
+            animation.to = animation.to.then(&t);
+        }
         self.needs_rebinding = true;
     }
 
@@ -49,6 +135,22 @@ impl Projection {
     /// `focus` is in viewport pixels.
     /// FIXME: doesn't properly zoom around focus
     pub fn zoom_at(&mut self, focus: Point, factor: f32) {
+        // apply that *before* your existing world→viewport
+        self.world_to_viewport = self.world_to_viewport.then(&Self::zoom(focus, factor));
+        self.animation = None;
+        self.needs_rebinding = true;
+    }
+
+    /// Like [`Projection::zoom_at`], but eases into the new zoom over `duration`.
+    ///
+    /// Zooming again while an animation is running zooms from where the running animation ends
+    /// up, so repeated zooms accumulate into a single animation.
+    pub fn zoom_at_animated(&mut self, focus: Point, factor: f32, duration: Duration) {
+        let target = self.target_transform().then(&Self::zoom(focus, factor));
+        self.animate_to(target, duration);
+    }
+
+    fn zoom(focus: Point, factor: f32) -> Transform3D<f32> {
         // 1. move focus → origin in world coords
         let to_origin = Transform3D::translation(-focus.x, -focus.y, 0.0);
         // 2. scale
@@ -56,16 +158,145 @@ impl Projection {
         // 3. move back
         let back = Transform3D::translation(focus.x, focus.y, 0.0);
         // zoom = back ∘ scale ∘ to_origin
-        let zoom = to_origin.then(&scale).then(&back);
+        to_origin.then(&scale).then(&back)
+    }
 
-        // apply that *before* your existing world→viewport
-        self.world_to_viewport = self.world_to_viewport.then(&zoom);
+    /// The current pan and zoom, taking world coords (after the axes) to viewport pixels.
+    pub fn current_transform(&self) -> Transform3D<f32> {
+        self.world_to_viewport
+    }
+    /// Replaces the pan and zoom, cancelling any running animation.
+    pub fn set_transform(&mut self, transform: Transform3D<f32>) {
+        self.world_to_viewport = transform;
+        self.animation = None;
         self.needs_rebinding = true;
     }
 
+    /// The zoom factor, one being unzoomed.
+    pub fn scale(&self) -> f32 {
+        self.world_to_viewport.m11
+    }
+    /// Sets the zoom factor, keeping the point at the center of the viewport in place.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.set_transform(self.with_scale(self.world_to_viewport, scale));
+    }
+    /// Like [`Projection::set_scale`], but eases into the new zoom over `duration`.
+    pub fn set_scale_animated(&mut self, scale: f32, duration: Duration) {
+        let target = self.with_scale(self.target_transform(), scale);
+        self.animate_to(target, duration);
+    }
+
+    /// The viewport position, in pixels, that the world origin is moved to by the pan.
+    pub fn translation(&self) -> Vector {
+        Vector::new(self.world_to_viewport.m41, self.world_to_viewport.m42)
+    }
+    /// Sets the pan in viewport pixels, keeping the zoom.
+    pub fn set_translation(&mut self, translation: Vector) {
+        self.set_transform(Self::with_translation(self.world_to_viewport, translation));
+    }
+    /// Like [`Projection::set_translation`], but eases into the new pan over `duration`.
+    pub fn set_translation_animated(&mut self, translation: Vector, duration: Duration) {
+        let target = Self::with_translation(self.target_transform(), translation);
+        self.animate_to(target, duration);
+    }
+
+    /// `transform` zoomed to `scale` about the center of the viewport.
+    fn with_scale(&self, transform: Transform3D<f32>, scale: f32) -> Transform3D<f32> {
+        let current = transform.m11;
+        if scale <= 0. || current == 0. {
+            return transform;
+        }
+        let center = (self.viewport / 2.).to_vector().to_point();
+        transform.then(&Self::zoom(center, scale / current))
+    }
+
+    fn with_translation(transform: Transform3D<f32>, translation: Vector) -> Transform3D<f32> {
+        Transform3D {
+            m41: translation.x,
+            m42: translation.y,
+            ..transform
+        }
+    }
+
     pub fn reset_zoom(&mut self) {
         self.world_to_viewport = Transform3D::identity();
+        self.animation = None;
+        self.needs_rebinding = true;
+    }
+    /// Like [`Projection::reset_zoom`], but eases back over `duration`.
+    pub fn reset_zoom_animated(&mut self, duration: Duration) {
+        self.animate_to(Transform3D::identity(), duration);
+    }
+
+    /// The pan and zoom that fits the world `bounds` into the viewport, leaving `margin` viewport
+    /// pixels around them. `None` when the bounds have no size to fit.
+    pub fn fit_transform(&self, bounds: Box2D, margin: f32) -> Option<Transform3D<f32>> {
+        let axes = self.axes();
+        let bounds = Box2D::from_points([
+            axes.transform_point2d(bounds.min)?,
+            axes.transform_point2d(bounds.max)?,
+        ]);
+        let size = bounds.size();
+        let available = Size::new(
+            (self.viewport.width - 2. * margin).max(1.),
+            (self.viewport.height - 2. * margin).max(1.),
+        );
+        let scale = (available.width / size.width).min(available.height / size.height);
+        if !scale.is_finite() || scale <= 0. {
+            return None;
+        }
+        let translation = (self.viewport / 2.).to_vector() - bounds.center().to_vector() * scale;
+        Some(Transform3D::scale(scale, scale, 1.).then_translate(translation.to_3d()))
+    }
+
+    /// Eases the pan and zoom from the current transform to `target`, a zero duration jumps
+    /// there immediately. The animation is advanced by [`Projection::step_animation`].
+    pub fn animate_to(&mut self, target: Transform3D<f32>, duration: Duration) {
+        if duration.is_zero() {
+            self.world_to_viewport = target;
+            self.animation = None;
+            self.needs_rebinding = true;
+            return;
+        }
+        self.animation = Some(TransformAnimation {
+            from: self.world_to_viewport,
+            to: target,
+            start: Instant::now(),
+            duration,
+        });
+    }
+
+    /// The pan and zoom the projection settles at, once any running animation finishes.
+    pub fn target_transform(&self) -> Transform3D<f32> {
+        self.animation
+            .map_or(self.world_to_viewport, |animation| animation.to)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Advances the running animation to `now`, returning whether it is still running.
+    pub fn step_animation(&mut self, now: Instant) -> bool {
+        let Some(animation) = self.animation else {
+            return false;
+        };
+        let elapsed = now.saturating_duration_since(animation.start);
+        let t = (elapsed.as_secs_f32() / animation.duration.as_secs_f32()).min(1.);
+        // Ease out, so the zoom reacts immediately and settles gently.
+        let eased = 1. - (1. - t).powi(3);
+
+        // Zooming and panning keep the matrix affine, and lerping every entry by the same amount
+        // keeps the zoom focus in place throughout.
+        let (from, to) = (animation.from.to_array(), animation.to.to_array());
+        self.world_to_viewport =
+            Transform3D::from_array(std::array::from_fn(|i| from[i] + (to[i] - from[i]) * eased));
         self.needs_rebinding = true;
+
+        if t >= 1. {
+            self.animation = None;
+        }
+        self.animation.is_some()
     }
 
     /// Build a full world→UV (NDC) matrix: world → viewport → ortho → NDC
@@ -79,7 +310,7 @@ impl Projection {
             1.0,
         );
         // world→uv = (world→viewport) ∘ ortho
-        self.world_to_viewport.then(&ortho)
+        self.full_world_to_viewport().then(&ortho)
     }
 
     /// Just the viewport→UV step (no pan/zoom).
@@ -94,10 +325,17 @@ impl Projection {
         )
     }
 
+    /// Map a point in world coords to viewport‐pixel space.
+    pub fn world_to_viewport(&self, p: Point) -> Point {
+        self.full_world_to_viewport()
+            .transform_point2d(p)
+            .unwrap_or(p)
+    }
+
     /// Map a point in viewport‐pixel space back to **world** coords.
     pub fn viewport_to_world(&self, p: Point) -> Point {
         // invert world→viewport, drop Z
-        let inv = self.world_to_viewport.inverse().unwrap();
+        let inv = self.full_world_to_viewport().inverse().unwrap();
         // FIXME: returns None when zoomed out alot
         inv.transform_point3d(p.to_3d()).unwrap().to_2d()
     }
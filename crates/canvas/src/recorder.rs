@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+
+use euclid::default::Point2D;
+use graphics::{CanvasCoordinates, Drawable, Primitive};
+
+use crate::tools::ToolMessage;
+
+/// Remembers the last committed primitive, and optionally records a macro of tool messages that
+/// can be replayed elsewhere on the canvas.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    last_commit: Option<Primitive<CanvasCoordinates>>,
+
+    recording: Option<Macro>,
+    recorded: Option<Macro>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Macro {
+    /// The world position the macro was recorded from, replays are offset relative to this.
+    origin: Point2D<f32>,
+    messages: Vec<ToolMessage>,
+    /// When each message was recorded, relative to the start of the recording.
+    times: Vec<Duration>,
+    started: Instant,
+}
+
+impl Macro {
+    /// The time from the start of the recording to its last message.
+    pub fn duration(&self) -> Duration {
+        self.times.last().copied().unwrap_or_default()
+    }
+
+    /// The amount of messages shown by each frame of a playback at `fps`, following the timing
+    /// of the recording. The first frame is at the start of the recording and the last one
+    /// shows every message.
+    pub fn frame_steps(&self, fps: f32) -> Vec<usize> {
+        if fps <= 0. {
+            return vec![self.messages.len()];
+        }
+        let frames = (self.duration().as_secs_f32() * fps).ceil() as usize + 1;
+        let mut applied = 0;
+        let mut steps = (0..frames)
+            .map(|frame| {
+                let at = Duration::from_secs_f32(frame as f32 / fps);
+                while applied < self.times.len() && self.times[applied] <= at {
+                    applied += 1;
+                }
+                applied
+            })
+            .collect::<Vec<_>>();
+        // Rounding can leave the last frame just short of the last message.
+        if let Some(last) = steps.last_mut() {
+            *last = self.messages.len();
+        }
+        steps
+    }
+
+    /// The recorded messages, oldest first.
+    pub fn messages(&self) -> &[ToolMessage] {
+        &self.messages
+    }
+}
+
+impl Recorder {
+    /// Records a message that was handled by the view.
+    pub fn record(&mut self, message: &ToolMessage) {
+        if let ToolMessage::Commit(primitive)
+        | ToolMessage::ReplaceCommit(primitive)
+        | ToolMessage::AppendCommit(primitive) = message
+        {
+            self.last_commit = Some(primitive.clone());
+        }
+        if let Some(recording) = &mut self.recording
+            && Self::is_replayable(message)
+        {
+            recording.messages.push(message.clone());
+            recording.times.push(recording.started.elapsed());
+        }
+    }
+
+    /// Returns a copy of the last committed primitive, centered on the given world position.
+    pub fn repeat_last(&self, position: Point2D<f32>) -> Option<Primitive<CanvasCoordinates>> {
+        let mut primitive = self.last_commit.clone()?;
+        let center = primitive.bounding_box().center();
+        primitive.translate(position - center);
+        Some(primitive)
+    }
+
+    pub const fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Starts recording a macro from the given world position, or finishes the current one.
+    pub fn toggle_recording(&mut self, position: Point2D<f32>) {
+        match self.recording.take() {
+            Some(recording) => {
+                tracing::info!("recorded macro with {} messages", recording.messages.len());
+                self.recorded = Some(recording);
+            }
+            None => {
+                self.recording = Some(Macro {
+                    origin: position,
+                    messages: vec![],
+                    times: vec![],
+                    started: Instant::now(),
+                });
+            }
+        }
+    }
+
+    /// The last macro that finished recording.
+    pub fn recorded(&self) -> Option<&Macro> {
+        self.recorded.as_ref()
+    }
+
+    /// Returns the messages of the recorded macro, offset so that it starts at the given world
+    /// position.
+    ///
+    /// Nothing is replayed while a macro is being recorded.
+    pub fn replay(&self, position: Point2D<f32>) -> Vec<ToolMessage> {
+        let Some(recorded) = self.recorded.as_ref().filter(|_| !self.is_recording()) else {
+            return vec![];
+        };
+        let offset = position - recorded.origin;
+        recorded
+            .messages
+            .iter()
+            .cloned()
+            .map(|message| match message {
+                ToolMessage::Commit(mut primitive) => {
+                    primitive.translate(offset);
+                    ToolMessage::Commit(primitive)
+                }
+                ToolMessage::ReplaceCommit(mut primitive) => {
+                    primitive.translate(offset);
+                    ToolMessage::ReplaceCommit(primitive)
+                }
+                ToolMessage::AppendCommit(mut primitive) => {
+                    primitive.translate(offset);
+                    ToolMessage::AppendCommit(primitive)
+                }
+                ToolMessage::Erase(point) => ToolMessage::Erase(point + offset),
+                message => message,
+            })
+            .collect()
+    }
+
+    /// Whether the message changes the canvas and so is worth replaying, as opposed to messages
+    /// that only affect the scratch buffer or cursor.
+    const fn is_replayable(message: &ToolMessage) -> bool {
+        matches!(
+            message,
+            ToolMessage::Commit(_)
+                | ToolMessage::ReplaceCommit(_)
+                | ToolMessage::AppendCommit(_)
+                | ToolMessage::Erase(_)
+                | ToolMessage::ZoomIn(_)
+                | ToolMessage::ZoomOut(_)
+                | ToolMessage::ResetZoom
+        )
+    }
+}
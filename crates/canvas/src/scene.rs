@@ -1,22 +1,94 @@
 use anyhow::Result;
 use euclid::default::Point2D;
+use graphics::BlendMode;
 use graphics::Drawable;
 use graphics::Primitive;
 use graphics::Systems;
 use graphics::Vertex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::OnceLock;
 
-use graphics::{CanvasCoordinates, Mesh};
+use graphics::{CanvasCoordinates, Mesh, MeshBatcher};
+
+use crate::guides::Guide;
 
 type C = CanvasCoordinates;
+
+/// The unique id of a node within a [`Scene`].
+pub type NodeId = u32;
+/// The unique id of a layer within a [`Scene`].
+pub type LayerId = u32;
+
+/// A run of consecutive indices in the tessellated scene that share a blend mode, each batch is
+/// drawn with the pipeline matching its blend mode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DrawBatch {
+    pub blend_mode: BlendMode,
+    pub indices: Range<u32>,
+}
+
+/// A named group of nodes that can be hidden or locked together.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Layer {
+    pub id: LayerId,
+    pub name: String,
+    /// Hidden layers are neither rendered nor hit-tested.
+    pub visible: bool,
+    /// Locked layers are rendered, but their nodes can not be selected or edited.
+    pub locked: bool,
+}
+
+impl Layer {
+    pub fn new(id: LayerId, name: String) -> Self {
+        Self {
+            id,
+            name,
+            visible: true,
+            locked: false,
+        }
+    }
+}
+
 /// A trait that describes a scene containing shapes, with serialization support.
 /// The scene is responsible for shape management, layer ordering, and converting to/from a serializable state.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize)]
 pub struct Scene {
     nodes: HashMap<u32, Primitive<C>>,
     ordering: Vec<u32>,
     pub next_node_id: u32,
+
+    /// The layers of the scene, bottom-most first. There is always at least one layer.
+    #[serde(default = "Scene::default_layers")]
+    layers: Vec<Layer>,
+    /// The layer each node belongs to, nodes missing from here belong to the bottom-most layer.
+    #[serde(default)]
+    node_layers: HashMap<NodeId, LayerId>,
+    /// The layer new nodes are added to.
+    #[serde(default)]
+    active_layer: LayerId,
+    #[serde(default = "Scene::default_next_layer_id")]
+    next_layer_id: LayerId,
+    /// User data attached to nodes, which is saved with the scene but never rendered.
+    ///
+    /// Only tagged nodes have an entry, so untagged scenes serialize exactly as before.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    node_metadata: HashMap<NodeId, serde_json::Value>,
+    /// The alignment guides, which are saved with the scene but drawn by the view.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    guides: Vec<Guide>,
+
+    /// The ids of every visible node in draw order, built on first use after the nodes, their
+    /// ordering or the layers change, as every hit-test and tessellation goes through it.
+    #[serde(skip)]
+    draw_order: OnceLock<Vec<NodeId>>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new(HashMap::new(), vec![])
+    }
 }
 // TODO: remove allow(unused)
 #[allow(unused)]
@@ -26,25 +98,73 @@ impl Scene {
             nodes,
             ordering,
             next_node_id: 0,
+
+            layers: Self::default_layers(),
+            node_layers: HashMap::new(),
+            active_layer: 0,
+            next_layer_id: Self::default_next_layer_id(),
+            node_metadata: HashMap::new(),
+            guides: vec![],
+
+            draw_order: OnceLock::new(),
         }
     }
-    /// Add a new shape to the scene; returns its unique ID.
-    pub fn add_node(&mut self, node: Primitive<C>) {
-        self.nodes.insert(self.next_node_id, node);
-        if !self.ordering.contains(&self.next_node_id) {
-            self.ordering.push(self.next_node_id);
+    fn default_layers() -> Vec<Layer> {
+        vec![Layer::new(0, "Layer 1".to_string())]
+    }
+    const fn default_next_layer_id() -> LayerId {
+        1
+    }
+
+    /// Add a new shape to the active layer of the scene; returns its unique ID.
+    pub fn add_node(&mut self, node: Primitive<C>) -> NodeId {
+        let id = self.next_node_id;
+        self.nodes.insert(id, node);
+        self.node_layers.insert(id, self.active_layer);
+        if !self.ordering.contains(&id) {
+            self.ordering.push(id);
         } else {
             unreachable!("next_node_id was not incremented properly!");
         }
         self.next_node_id += 1;
+        _ = self.draw_order.take();
+        id
     }
 
-    /// Remove a shape by its ID.
-    pub fn remove_node(&mut self, id: u32) {
-        _ = self.nodes.remove(&id);
-        if let Some(position) = self.ordering.iter().position(|x| *x == id) {
+    /// Remove a shape by its ID, returning it.
+    ///
+    /// The metadata of the node is dropped, see [`Scene::take_metadata`] to keep it.
+    pub fn remove_node(&mut self, id: u32) -> Option<Primitive<C>> {
+        _ = self.node_layers.remove(&id);
+        _ = self.node_metadata.remove(&id);
+        if let Some(position) = self.ordering_index(id) {
             _ = self.ordering.remove(position);
         };
+        _ = self.draw_order.take();
+        self.nodes.remove(&id)
+    }
+
+    /// Puts a previously removed node back under its old id, at the given index of the ordering.
+    pub fn restore_node(&mut self, id: NodeId, node: Primitive<C>, layer: LayerId, index: usize) {
+        self.nodes.insert(id, node);
+        self.node_layers.insert(id, layer);
+        if !self.ordering.contains(&id) {
+            self.ordering.insert(index.min(self.ordering.len()), id);
+        }
+        _ = self.draw_order.take();
+    }
+
+    /// The index of the node in the ordering of the scene.
+    pub fn ordering_index(&self, id: NodeId) -> Option<usize> {
+        self.ordering.iter().position(|x| *x == id)
+    }
+
+    /// The amount of nodes in the scene, including those on hidden layers.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
     }
 
     /// Update a shape's color.
@@ -54,29 +174,174 @@ impl Scene {
     pub fn get_node_mut(&mut self, id: u32) -> Option<&mut Primitive<C>> {
         self.nodes.get_mut(&id)
     }
+
+    /// Iterate over all nodes in render (layer) order, bottom-most first, including the nodes on
+    /// hidden layers.
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Primitive<C>)> {
+        self.layers
+            .iter()
+            .flat_map(|layer| self.layer_nodes(layer.id))
+            .filter_map(|id| self.nodes.get(&id).map(|node| (id, node)))
+    }
+    /// Iterate over the nodes on visible layers in render order, see [`Scene::draw_order`].
+    pub fn visible_nodes(&self) -> impl Iterator<Item = (NodeId, &Primitive<C>)> {
+        self.draw_order()
+            .iter()
+            .filter_map(|id| self.nodes.get(id).map(|node| (*id, node)))
+    }
+    /// Iterate over all visible nodes that carry metadata, in render order.
+    pub fn tagged_nodes(
+        &self,
+    ) -> impl Iterator<Item = (NodeId, &Primitive<C>, &serde_json::Value)> {
+        self.visible_nodes()
+            .filter_map(|(id, node)| self.node_metadata.get(&id).map(|data| (id, node, data)))
+    }
+
+    pub fn metadata(&self, id: NodeId) -> Option<&serde_json::Value> {
+        self.node_metadata.get(&id)
+    }
+    /// Attaches metadata to an existing node, or clears it with `None`, returning the previous
+    /// metadata.
+    pub fn set_metadata(
+        &mut self,
+        id: NodeId,
+        metadata: Option<serde_json::Value>,
+    ) -> Option<serde_json::Value> {
+        match metadata {
+            Some(metadata) if self.nodes.contains_key(&id) => {
+                self.node_metadata.insert(id, metadata)
+            }
+            Some(_) => None,
+            None => self.node_metadata.remove(&id),
+        }
+    }
+    pub fn take_metadata(&mut self, id: NodeId) -> Option<serde_json::Value> {
+        self.node_metadata.remove(&id)
+    }
+
+    pub fn guides(&self) -> &[Guide] {
+        &self.guides
+    }
+    /// Adds a guide, returning its index.
+    pub fn add_guide(&mut self, guide: Guide) -> usize {
+        self.guides.push(guide);
+        self.guides.len() - 1
+    }
+    /// Replaces the guide at the index, returning whether it existed.
+    pub fn set_guide(&mut self, index: usize, guide: Guide) -> bool {
+        match self.guides.get_mut(index) {
+            Some(existing) => {
+                *existing = guide;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Removes the guide at the index, the guides after it shift down by one.
+    pub fn remove_guide(&mut self, index: usize) -> Option<Guide> {
+        (index < self.guides.len()).then(|| self.guides.remove(index))
+    }
+    pub fn clear_guides(&mut self) {
+        self.guides.clear();
+    }
+
     pub fn get_node_at_position<'a>(
         &'a mut self,
         point: Point2D<f32>,
     ) -> Option<&'a mut Primitive<C>> {
-        for id in self.ordering.iter().rev() {
-            if let Some(node) = self.nodes.get(id)
-                && node.bounding_box().contains(point)
-            {
-                return self.get_node_mut(*id);
-            }
-        }
-        None
+        let id = self.get_node_id_at_position(point)?;
+        self.get_node_mut(id)
     }
 
+    /// Returns the top-most node at the given position, see [`Self::nodes_at_position`].
     pub fn get_node_id_at_position<'a>(&'a mut self, point: Point2D<f32>) -> Option<u32> {
-        for id in self.ordering.iter().rev() {
-            if let Some(node) = self.nodes.get(id)
-                && node.bounding_box().contains(point)
-            {
-                return Some(*id);
-            }
+        self.nodes_at_position(point).first().copied()
+    }
+
+    /// Every node at the given position from top to bottom, ignoring nodes on hidden or locked
+    /// layers and eraser strokes, which have nothing visible to pick.
+    pub fn nodes_at_position(&self, point: Point2D<f32>) -> Vec<NodeId> {
+        self.draw_order()
+            .iter()
+            .rev()
+            .copied()
+            .filter(|id| {
+                self.is_node_editable(*id)
+                    && self.nodes.get(id).is_some_and(|node| {
+                        node.blend_mode() != BlendMode::Erase && node.bounding_box().contains(point)
+                    })
+            })
+            .collect()
+    }
+
+    /// The ids of every visible node, ordered by layer and then by their ordering within it.
+    pub fn draw_order(&self) -> &[NodeId] {
+        self.draw_order.get_or_init(|| {
+            self.layers
+                .iter()
+                .filter(|layer| layer.visible)
+                .flat_map(|layer| self.layer_nodes(layer.id))
+                .collect()
+        })
+    }
+    /// The ids of the nodes on the layer, in their ordering.
+    fn layer_nodes(&self, layer: LayerId) -> impl Iterator<Item = NodeId> + '_ {
+        self.ordering
+            .iter()
+            .copied()
+            .filter(move |id| self.layer_of(*id) == layer)
+    }
+
+    /// The layers of the scene, bottom-most first.
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+    pub fn layer(&self, id: LayerId) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.id == id)
+    }
+    fn layer_mut(&mut self, id: LayerId) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.id == id)
+    }
+    pub const fn active_layer(&self) -> LayerId {
+        self.active_layer
+    }
+    /// Sets the layer new nodes are added to, ignoring unknown layers.
+    pub fn set_active_layer(&mut self, id: LayerId) {
+        if self.layer(id).is_some() {
+            self.active_layer = id;
+        }
+    }
+    /// Adds a new layer on top of the others and makes it the active layer.
+    pub fn add_layer(&mut self, name: String) -> LayerId {
+        let id = self.next_layer_id;
+        self.next_layer_id += 1;
+        self.layers.push(Layer::new(id, name));
+        self.active_layer = id;
+        _ = self.draw_order.take();
+        id
+    }
+    pub fn set_layer_visible(&mut self, id: LayerId, visible: bool) {
+        if let Some(layer) = self.layer_mut(id) {
+            layer.visible = visible;
+        }
+        _ = self.draw_order.take();
+    }
+    pub fn set_layer_locked(&mut self, id: LayerId, locked: bool) {
+        if let Some(layer) = self.layer_mut(id) {
+            layer.locked = locked;
         }
-        None
+    }
+    /// The layer the node belongs to.
+    pub fn layer_of(&self, id: NodeId) -> LayerId {
+        self.node_layers
+            .get(&id)
+            .copied()
+            .unwrap_or(self.layers[0].id)
+    }
+    /// Whether the node is on a visible and unlocked layer.
+    pub fn is_node_editable(&self, id: NodeId) -> bool {
+        self.layer(self.layer_of(id))
+            .is_some_and(|layer| layer.visible && !layer.locked)
     }
 
     /// Reorder a shape to a new z-index.
@@ -91,40 +356,63 @@ impl Scene {
                 target_layer
             };
             self.ordering.insert(new_layer, id);
+            _ = self.draw_order.take();
         }
     }
 
-    /// Get shapes in render (layer) order.
-    pub fn tessellate(&mut self, systems: &mut Systems) -> Mesh<Vertex> {
-        let keys = self.ordering.clone();
+    /// Get the visible shapes in render (layer) order, along with the batches to draw them in.
+    pub fn tessellate(&mut self, systems: &mut Systems) -> (Mesh<Vertex>, Vec<DrawBatch>) {
+        let keys = self.draw_order().to_vec();
+        self.tessellate_keys(systems, keys)
+    }
 
-        let (vertex_count, index_count) = keys
-            .iter()
-            .filter_map(|key| {
-                self.nodes.get_mut(key).map(|node| {
-                    let mesh = node.render(systems);
-                    (mesh.vertices.len(), mesh.indices.len())
-                })
-            })
-            .fold((0, 0), |(va, ia), (vc, ic)| (va + vc, ia + ic));
+    /// Like [`Scene::tessellate`], but only includes the given nodes, still in render order.
+    pub fn tessellate_only(
+        &mut self,
+        systems: &mut Systems,
+        ids: &[NodeId],
+    ) -> (Mesh<Vertex>, Vec<DrawBatch>) {
+        let mut keys = self.draw_order().to_vec();
+        keys.retain(|id| ids.contains(id));
+        self.tessellate_keys(systems, keys)
+    }
 
-        let mut result = Mesh {
-            vertices: Vec::with_capacity(vertex_count),
-            indices: Vec::with_capacity(index_count),
-        };
+    /// Tessellates the given nodes in the given order, skipping ids that aren't in the scene.
+    pub fn tessellate_keys(
+        &mut self,
+        systems: &mut Systems,
+        keys: Vec<NodeId>,
+    ) -> (Mesh<Vertex>, Vec<DrawBatch>) {
+        let mut batcher = MeshBatcher::new();
+        for key in &keys {
+            if let Some(node) = self.nodes.get_mut(key) {
+                batcher.reserve(node.render(systems));
+            }
+        }
 
+        let mut batches: Vec<DrawBatch> = vec![];
         for key in keys {
             if let Some(node) = self.nodes.get_mut(&key) {
-                result.append(node.render(systems));
+                let blend_mode = node.blend_mode();
+                let indices = batcher.push(node.render(systems));
+                match batches.last_mut() {
+                    Some(batch) if batch.blend_mode == blend_mode => {
+                        batch.indices.end = indices.end
+                    }
+                    _ => batches.push(DrawBatch {
+                        blend_mode,
+                        indices,
+                    }),
+                }
             }
         }
 
-        result
+        (batcher.finish(), batches)
     }
 
     /// Serialize the scene.
     pub fn serialize(&self) -> Result<String> {
-        todo!()
+        Ok(serde_json::to_string(self)?)
     }
 
     /// Deserialize a scene.
@@ -132,6 +420,62 @@ impl Scene {
     where
         Self: Sized,
     {
-        todo!()
+        Ok(serde_json::from_str(serialized)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::default::Size2D;
+    use graphics::primitives::{Rectangle, RectangleOptions};
+
+    use super::*;
+
+    fn rectangle(x: f32) -> Primitive<C> {
+        Primitive::Rectangle(Rectangle::new(
+            Point2D::new(x, 0.),
+            Size2D::new(10., 10.),
+            RectangleOptions::DEFAULT,
+        ))
+    }
+
+    #[test]
+    fn hidden_layers_are_only_left_out_of_the_visible_nodes() {
+        let mut scene = Scene::default();
+        let bottom = scene.add_node(rectangle(0.));
+        let layer = scene.add_layer("Layer 2".to_string());
+        let top = scene.add_node(rectangle(20.));
+        scene.set_layer_visible(layer, false);
+
+        let ids = |nodes: Vec<(NodeId, &Primitive<C>)>| {
+            nodes.into_iter().map(|(id, _)| id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(scene.nodes().collect()), vec![bottom, top]);
+        assert_eq!(ids(scene.visible_nodes().collect()), vec![bottom]);
+        assert!(scene.nodes_at_position(Point2D::new(25., 5.)).is_empty());
+    }
+
+    #[test]
+    fn the_draw_order_follows_changes_to_the_scene() {
+        let mut scene = Scene::default();
+        let first = scene.add_node(rectangle(0.));
+        let layer = scene.add_layer("Layer 2".to_string());
+        let second = scene.add_node(rectangle(0.));
+        assert_eq!(scene.draw_order(), &[first, second]);
+
+        scene.set_layer_visible(layer, false);
+        assert_eq!(scene.draw_order(), &[first]);
+        scene.set_layer_visible(layer, true);
+
+        let node = scene.remove_node(first).unwrap();
+        assert_eq!(scene.draw_order(), &[second]);
+        scene.restore_node(first, node, 0, 0);
+        assert_eq!(
+            scene.nodes_at_position(Point2D::new(5., 5.)),
+            vec![second, first]
+        );
+
+        let scene = Scene::deserialize(&scene.serialize().unwrap()).unwrap();
+        assert_eq!(scene.draw_order(), &[first, second]);
     }
 }
@@ -0,0 +1,213 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{ensure, Context, Result};
+
+use crate::scene::Scene;
+
+const EXTENSION: &str = "json";
+
+/// A session file in the session directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// The file name without its extension.
+    pub name: String,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+}
+
+/// Saves scenes to and loads them from a directory of session files.
+///
+/// The manager remembers the session that is open, and the canvas revision it was saved or
+/// loaded at, so the canvas counts as dirty once its revision moves past that.
+#[derive(Clone, Debug)]
+pub struct SessionManager {
+    directory: PathBuf,
+    current: Option<PathBuf>,
+    saved_revision: u64,
+}
+
+impl SessionManager {
+    pub fn new(directory: PathBuf) -> Self {
+        Self {
+            directory,
+            current: None,
+            saved_revision: 0,
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+    /// The file of the open session, `None` until the canvas is saved or a session is loaded.
+    pub fn current(&self) -> Option<&Path> {
+        self.current.as_deref()
+    }
+
+    /// Whether the canvas at `revision` has changes that are not in the open session.
+    pub fn is_dirty(&self, revision: u64) -> bool {
+        revision != self.saved_revision
+    }
+    /// Treats the canvas at `revision` as saved.
+    pub fn mark_saved(&mut self, revision: u64) {
+        self.saved_revision = revision;
+    }
+
+    /// Every session in the directory, most recently modified first. A directory that does not
+    /// exist yet has no sessions.
+    pub fn list(&self) -> Result<Vec<SessionInfo>> {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("failed to read the session directory {:?}", self.directory)
+                });
+            }
+        };
+        let mut sessions = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension() != Some(OsStr::new(EXTENSION)) {
+                    return None;
+                }
+                Some(SessionInfo {
+                    name: path.file_stem()?.to_string_lossy().into_owned(),
+                    modified: path.metadata().and_then(|meta| meta.modified()).ok()?,
+                    path,
+                })
+            })
+            .collect::<Vec<_>>();
+        sessions.sort_by(|a, b| b.modified.cmp(&a.modified));
+        Ok(sessions)
+    }
+
+    /// The file a session with the given name is saved to.
+    pub fn path_for(&self, name: &str) -> PathBuf {
+        self.directory.join(format!("{name}.{EXTENSION}"))
+    }
+
+    /// A session name from the current time, so unnamed sessions sort by when they were made.
+    pub fn timestamped_name() -> String {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        format!("session-{seconds}")
+    }
+
+    /// Saves the scene to the open session, or to a new timestamped one if none is open.
+    pub fn save(&mut self, scene: &Scene, revision: u64) -> Result<PathBuf> {
+        let path = match &self.current {
+            Some(path) => path.clone(),
+            None => self.path_for(&Self::timestamped_name()),
+        };
+        self.save_to(scene, revision, path)
+    }
+
+    /// Saves the scene as a session with the given name, which becomes the open session.
+    pub fn save_as(&mut self, scene: &Scene, revision: u64, name: &str) -> Result<PathBuf> {
+        ensure!(
+            !name.is_empty() && !name.contains(['/', '\\']) && name != "." && name != "..",
+            "{name:?} is not a valid session name"
+        );
+        self.save_to(scene, revision, self.path_for(name))
+    }
+
+    fn save_to(&mut self, scene: &Scene, revision: u64, path: PathBuf) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.directory).with_context(|| {
+            format!(
+                "failed to create the session directory {:?}",
+                self.directory
+            )
+        })?;
+        // Writing next to the session and renaming keeps the old file intact if saving fails.
+        let temporary = path.with_extension(format!("{EXTENSION}.tmp"));
+        std::fs::write(&temporary, scene.serialize()?)
+            .with_context(|| format!("failed to write the session {temporary:?}"))?;
+        std::fs::rename(&temporary, &path)
+            .with_context(|| format!("failed to move the session into place at {path:?}"))?;
+        self.current = Some(path.clone());
+        self.saved_revision = revision;
+        Ok(path)
+    }
+
+    /// Reads the scene of a session, which becomes the open session. The caller should replace
+    /// the canvas with it and then [`SessionManager::mark_saved`] the new revision.
+    pub fn load(&mut self, path: &Path) -> Result<Scene> {
+        let serialized = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read the session {path:?}"))?;
+        let scene = Scene::deserialize(&serialized)
+            .with_context(|| format!("failed to parse the session {path:?}"))?;
+        self.current = Some(path.to_path_buf());
+        Ok(scene)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use euclid::default::{Point2D, Size2D};
+    use graphics::primitives::{Rectangle, RectangleOptions};
+    use graphics::Primitive;
+
+    use super::*;
+
+    fn scene() -> Scene {
+        let mut scene = Scene::default();
+        scene.add_node(Primitive::Rectangle(Rectangle::new(
+            Point2D::zero(),
+            Size2D::new(40., 30.),
+            RectangleOptions::DEFAULT,
+        )));
+        scene
+    }
+
+    /// Backdates a session so the order of the listing doesn't depend on the clock.
+    fn set_modified(path: &Path, seconds: u64) {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    #[test]
+    fn sessions_are_saved_listed_and_loaded() {
+        let directory = std::env::temp_dir().join(format!("draw-sessions-{}", std::process::id()));
+        let mut sessions = SessionManager::new(directory.clone());
+        assert!(sessions.list().unwrap().is_empty());
+        assert!(sessions.current().is_none());
+
+        let first = sessions.save_as(&scene(), 1, "first").unwrap();
+        assert_eq!(first, directory.join("first.json"));
+        assert_eq!(sessions.current(), Some(first.as_path()));
+        assert!(!sessions.is_dirty(1));
+        assert!(sessions.is_dirty(2));
+        // Saving again overwrites the open session.
+        assert_eq!(sessions.save(&Scene::default(), 2).unwrap(), first);
+        set_modified(&first, 1_000);
+
+        let second = sessions.save_as(&scene(), 3, "second").unwrap();
+        set_modified(&second, 2_000);
+        std::fs::write(directory.join("notes.txt"), "not a session").unwrap();
+        assert!(sessions.save_as(&scene(), 3, "../outside").is_err());
+
+        let listed = sessions.list().unwrap();
+        let names = listed
+            .iter()
+            .map(|info| info.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["second", "first"]);
+
+        let mut reopened = SessionManager::new(directory.clone());
+        assert_eq!(reopened.load(&second).unwrap().len(), 1);
+        assert_eq!(reopened.load(&first).unwrap().len(), 0);
+        assert_eq!(reopened.current(), Some(first.as_path()));
+        assert!(reopened.load(&directory.join("missing.json")).is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
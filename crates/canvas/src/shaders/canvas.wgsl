@@ -1,9 +1,16 @@
 @group(0) @binding(0) var<uniform> world_projection: mat4x4<f32>;
 @group(0) @binding(1) var<uniform> viewport_projection: mat4x4<f32>;
+@group(0) @binding(2) var<uniform> color_correction: ColorCorrection;
 @group(1) @binding(0) var mask_atlas: texture_2d_array<f32>;
 @group(1) @binding(1) var color_atlas: texture_2d_array<f32>;
 @group(1) @binding(2) var tex_sampler: sampler;
 
+struct ColorCorrection {
+    matrix: mat3x3<f32>,
+    gamma: f32,
+    text_gamma: f32,
+};
+
 struct VertexInput {
     @location(0) position: vec2<f32>,
     @location(1) color: vec4<f32>,
@@ -36,12 +43,23 @@ fn vs_main(input: VertexInput) -> VertexOutput {
     return out;
 }
 
+// The channels are mixed by the matrix first, and then raised to the gamma.
+fn correct(color: vec4<f32>) -> vec4<f32> {
+    let rgb = clamp(color_correction.matrix * color.rgb, vec3(0.), vec3(1.));
+    return vec4<f32>(pow(rgb, vec3(color_correction.gamma)), color.a);
+}
+
+// Reshapes the anti-aliased edge of a glyph, a text gamma above one makes it heavier.
+fn glyph_coverage(coverage: f32) -> f32 {
+    return pow(clamp(coverage, 0., 1.), 1. / color_correction.text_gamma);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     switch in.kind {
-        case 0u, 3u: {return in.color;}
-        case 1u, 4u: { return vec4<f32>(in.color.rgb, in.color.a * textureSampleLevel(mask_atlas, tex_sampler, in.tex_coords, in.texture, 0.).x) ;}
-        case 2u, 5u: {return textureSample(color_atlas, tex_sampler, in.tex_coords, in.texture);}
+        case 0u, 3u: {return correct(in.color);}
+        case 1u, 4u: { return correct(vec4<f32>(in.color.rgb, in.color.a * glyph_coverage(textureSampleLevel(mask_atlas, tex_sampler, in.tex_coords, in.texture, 0.).x))) ;}
+        case 2u, 5u: {return correct(textureSample(color_atlas, tex_sampler, in.tex_coords, in.texture));}
         default: {return vec4<f32>(0.);}
     }
 }
@@ -0,0 +1,36 @@
+@group(0) @binding(0) var silhouette: texture_2d<f32>;
+@group(0) @binding(1) var silhouette_sampler: sampler;
+@group(0) @binding(2) var<uniform> outline: Outline;
+
+struct Outline {
+    // The offsets in pixels, only xy is used so that the array keeps the uniform alignment.
+    offsets: array<vec4<f32>, 8>,
+    texel_size: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// A single triangle covering the whole viewport.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2. - 1., 1. - uv.y * 2., 0., 1.);
+    out.uv = uv;
+    return out;
+}
+
+// Dilates the silhouette by keeping the highest alpha around the pixel, so overlapping content
+// never makes the outline darker than its most opaque part.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var alpha = 0.;
+    for (var i = 0; i < 8; i++) {
+        let uv = in.uv + outline.offsets[i].xy * outline.texel_size;
+        alpha = max(alpha, textureSampleLevel(silhouette, silhouette_sampler, uv, 0.).a);
+    }
+    return vec4<f32>(0., 0., 0., alpha);
+}
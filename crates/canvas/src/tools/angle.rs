@@ -0,0 +1,107 @@
+use euclid::default::Point2D;
+use graphics::primitives::{Angle, AngleOptions};
+use graphics::{CanvasCoordinates, Drawable, Primitive, Systems};
+use input::{
+    Key, KeyboardEvent, KeyboardEventKind, Modifiers, MouseButton, MouseEvent, MouseEventKind,
+    SpecialKey,
+};
+
+use crate::tools::{Tool, ToolMessage};
+
+/// Measures the angle between two rays, placed with three clicks: the vertex, a point on the
+/// first ray and a point on the second ray.
+///
+/// The point being placed follows the cursor, and holding Alt measures the reflex angle on the
+/// other side of the rays.
+#[derive(Clone, Debug, Default)]
+pub struct AngleTool {
+    current: Option<Angle<CanvasCoordinates>>,
+    /// Whether the first ray has been placed, after which only the second ray follows the cursor.
+    placed_start: bool,
+    pub options: AngleOptions,
+}
+
+impl AngleTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&mut self, position: Point2D<f32>, modifiers: Modifiers) {
+        let Some(angle) = self.current.as_mut() else {
+            return;
+        };
+        if !self.placed_start {
+            angle.set_start(position);
+        }
+        angle.set_end(position);
+        angle.set_reflex(modifiers.intersects(Modifiers::ALT));
+    }
+
+    fn cancel(&mut self) -> Vec<ToolMessage> {
+        self.current = None;
+        self.placed_start = false;
+        vec![ToolMessage::ReleaseFocus, ToolMessage::ClearScratch]
+    }
+}
+
+impl Tool for AngleTool {
+    fn mouse_event(
+        &mut self,
+        systems: &mut Systems,
+        event: MouseEvent,
+        modifiers: Modifiers,
+    ) -> Vec<ToolMessage> {
+        match event.kind {
+            MouseEventKind::Leave if self.current.is_some() => self.cancel(),
+            MouseEventKind::Motion { .. } => {
+                self.update(event.position, modifiers);
+                match self.current.as_mut() {
+                    Some(angle) => vec![ToolMessage::Scratch(angle.render(systems).clone())],
+                    None => vec![],
+                }
+            }
+            MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                let Some(angle) = self.current.as_mut() else {
+                    let angle = self.current.insert(Angle::new(
+                        event.position,
+                        event.position,
+                        event.position,
+                        self.options.clone(),
+                    ));
+                    return vec![
+                        ToolMessage::SetFocus,
+                        ToolMessage::Scratch(angle.render(systems).clone()),
+                    ];
+                };
+                if self.placed_start {
+                    angle.set_end(event.position);
+                    angle.set_reflex(modifiers.intersects(Modifiers::ALT));
+                    let angle = self.current.take().unwrap();
+                    self.placed_start = false;
+                    return vec![
+                        ToolMessage::ReleaseFocus,
+                        ToolMessage::Commit(Primitive::Angle(angle)),
+                    ];
+                }
+                // The first ray needs a direction, so a click on the vertex is ignored.
+                if angle.vertex() != event.position {
+                    self.placed_start = true;
+                }
+                vec![]
+            }
+            MouseEventKind::Press { button, .. } if button == MouseButton::Right => self.cancel(),
+            _ => vec![],
+        }
+    }
+
+    fn keyboard_event(&mut self, _systems: &mut Systems, event: KeyboardEvent) -> Vec<ToolMessage> {
+        match event.kind {
+            KeyboardEventKind::Press(Key::SpecialKey(SpecialKey::Escape))
+                if self.current.is_some() =>
+            {
+                self.cancel()
+            }
+            _ => vec![],
+        }
+    }
+}
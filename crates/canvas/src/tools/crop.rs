@@ -0,0 +1,72 @@
+use euclid::default::{Box2D, Point2D};
+use graphics::Systems;
+use input::{Modifiers, MouseButton, MouseEvent, MouseEventKind};
+
+use crate::tools::{Tool, ToolMessage};
+
+/// Drags out the region of the canvas that is exported.
+///
+/// Holding Ctrl while pressing crops to the bounds of the visible content instead, and a right
+/// click clears the crop so the whole content is exported again.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CropTool {
+    origin: Option<Point2D<f32>>,
+}
+
+impl CropTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Tool for CropTool {
+    fn mouse_event(
+        &mut self,
+        _systems: &mut Systems,
+        event: MouseEvent,
+        modifiers: Modifiers,
+    ) -> Vec<ToolMessage> {
+        match event.kind {
+            MouseEventKind::Leave => {
+                if self.origin.take().is_some() {
+                    vec![ToolMessage::ReleaseFocus]
+                } else {
+                    vec![]
+                }
+            }
+            MouseEventKind::Motion { .. } => match self.origin {
+                Some(origin) => vec![ToolMessage::Crop(Box2D::from_points([
+                    origin,
+                    event.position,
+                ]))],
+                None => vec![],
+            },
+            MouseEventKind::Press { button, .. } => match button {
+                MouseButton::Left if modifiers.contains(Modifiers::CTRL) => {
+                    vec![ToolMessage::CropToContent]
+                }
+                MouseButton::Left => {
+                    self.origin = Some(event.position);
+                    vec![ToolMessage::SetFocus]
+                }
+                MouseButton::Right => {
+                    self.origin = None;
+                    vec![ToolMessage::ReleaseFocus, ToolMessage::ClearCrop]
+                }
+                _ => vec![],
+            },
+            MouseEventKind::Release { button, .. } if button == MouseButton::Left => {
+                let Some(origin) = self.origin.take() else {
+                    return vec![];
+                };
+                let crop = Box2D::from_points([origin, event.position]);
+                if crop.is_empty() {
+                    vec![ToolMessage::ReleaseFocus, ToolMessage::ClearCrop]
+                } else {
+                    vec![ToolMessage::ReleaseFocus, ToolMessage::Crop(crop)]
+                }
+            }
+            _ => vec![],
+        }
+    }
+}
@@ -1,20 +1,45 @@
-use euclid::default::Vector2D;
+use euclid::default::{Point2D, Vector2D};
 use graphics::{
     primitives::{Ellipse, EllipseOptions},
     Drawable, Systems,
 };
 
-use crate::tools::{Tool, ToolMessage};
+use crate::tools::{drag_bounds, Tool, ToolMessage};
 use graphics::{CanvasCoordinates, Primitive};
 use input::{KeyboardEvent, KeyboardEventKind, Modifiers, MouseButton, MouseEvent, MouseEventKind};
 
 #[derive(Clone, Debug, Default)]
 pub struct EllipseTool {
     drag: Option<Primitive<CanvasCoordinates>>,
+    /// The press position of the current drag.
+    start: Point2D<f32>,
+    /// The last pointer position of the current drag, so modifier changes can resize it.
+    position: Point2D<f32>,
 }
 impl EllipseTool {
     pub fn new() -> Self {
-        Self { drag: None }
+        Self::default()
+    }
+
+    /// Resizes the drag to the current pointer position, returning the new radius.
+    fn resize(&mut self, modifiers: Modifiers) -> Option<Vector2D<f32>> {
+        let bounds = drag_bounds(self.start, self.position, modifiers);
+        match self.drag.as_mut()? {
+            Primitive::Ellipse(elem) => {
+                elem.set_bounds(bounds);
+                Some(elem.radius().abs())
+            }
+            _ => unreachable!("ellipse tool's drag can only be a ellipse"),
+        }
+    }
+
+    /// Resizes the drag and previews it.
+    fn preview(&mut self, systems: &mut Systems, modifiers: Modifiers) -> Vec<ToolMessage> {
+        _ = self.resize(modifiers);
+        match &mut self.drag {
+            Some(drag) => vec![ToolMessage::Scratch(drag.render(systems).clone())],
+            None => vec![],
+        }
     }
 }
 
@@ -35,23 +60,13 @@ impl Tool for EllipseTool {
                 }
             }
             MouseEventKind::Motion { .. } => {
-                let Some(drag) = self.drag.as_mut() else {
-                    return vec![];
-                };
-                match drag {
-                    Primitive::Ellipse(elem) => {
-                        if modifiers.intersects(Modifiers::SHIFT) {
-                            elem.resize_to_point_square(event.position);
-                        } else {
-                            elem.resize_to_point(event.position);
-                        }
-                    }
-                    _ => unreachable!("ellipse tool's drag can only be a ellipse"),
-                }
-                vec![ToolMessage::Scratch(drag.render(systems).clone())]
+                self.position = event.position;
+                self.preview(systems, modifiers)
             }
             MouseEventKind::Press { button, .. } => match button {
                 MouseButton::Left => {
+                    self.start = event.position;
+                    self.position = event.position;
                     let drag = self.drag.insert(Primitive::Ellipse(Ellipse::new(
                         event.position,
                         Vector2D::zero(),
@@ -70,21 +85,13 @@ impl Tool for EllipseTool {
             },
             MouseEventKind::Release { button, .. } => match button {
                 MouseButton::Left => {
-                    let Some(mut drag) = self.drag.take() else {
+                    self.position = event.position;
+                    let Some(radius) = self.resize(modifiers) else {
+                        return vec![];
+                    };
+                    let Some(drag) = self.drag.take() else {
                         return vec![];
                     };
-                    let radius;
-                    match &mut drag {
-                        Primitive::Ellipse(elem) => {
-                            if modifiers.intersects(Modifiers::SHIFT) {
-                                elem.resize_to_point_square(event.position);
-                            } else {
-                                elem.resize_to_point(event.position);
-                            }
-                            radius = elem.radius().abs();
-                        }
-                        _ => unreachable!("ellipse tool's drag can only be a ellipse"),
-                    }
                     if radius.x == 0. || radius.y == 0. {
                         vec![ToolMessage::ReleaseFocus]
                     } else {
@@ -98,22 +105,7 @@ impl Tool for EllipseTool {
     }
     fn keyboard_event(&mut self, systems: &mut Systems, event: KeyboardEvent) -> Vec<ToolMessage> {
         match event.kind {
-            KeyboardEventKind::ModifiersChanged => {
-                let Some(drag) = self.drag.as_mut() else {
-                    return vec![];
-                };
-                match drag {
-                    Primitive::Ellipse(elem) => {
-                        if event.modifiers.intersects(Modifiers::SHIFT) {
-                            elem.resize_square();
-                            vec![ToolMessage::Scratch(drag.render(systems).clone())]
-                        } else {
-                            vec![]
-                        }
-                    }
-                    _ => unreachable!("ellipse tool's drag can only be a ellipse"),
-                }
-            }
+            KeyboardEventKind::ModifiersChanged => self.preview(systems, event.modifiers),
             _ => vec![],
         }
     }
@@ -1,26 +1,117 @@
 use crate::tools::{Tool, ToolMessage};
-use graphics::Systems;
-use input::{Modifiers, MouseButton, MouseEvent, MouseEventKind};
+use color::PremulColor;
+use graphics::primitives::{Pen, PenOptions};
+use graphics::{BlendMode, CanvasCoordinates, Drawable, Primitive, Systems};
+use input::{Key, Modifiers, MouseButton, MouseEvent, MouseEventKind};
 
-#[derive(Clone, Debug, Copy, Default, PartialEq)]
-pub struct EraserTool;
+const BRUSH_WIDTH: f32 = 16.;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EraserMode {
+    /// Removes the whole primitive under the cursor.
+    #[default]
+    Delete,
+    /// Paints transparency over the scene, like a pen stroke that erases.
+    Brush,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EraserTool {
+    pub mode: EraserMode,
+    drag: Option<Primitive<CanvasCoordinates>>,
+}
 
 impl EraserTool {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    fn brush_options() -> PenOptions {
+        PenOptions {
+            color: PremulColor::WHITE,
+            blend_mode: BlendMode::Erase,
+            width: BRUSH_WIDTH,
+            ..Default::default()
+        }
+    }
+
+    fn brush_mouse_event(&mut self, systems: &mut Systems, event: MouseEvent) -> Vec<ToolMessage> {
+        match event.kind {
+            MouseEventKind::Enter => {
+                self.drag = None;
+                vec![]
+            }
+            MouseEventKind::Leave => {
+                if self.drag.take().is_some() {
+                    vec![ToolMessage::ReleaseFocus, ToolMessage::ClearScratch]
+                } else {
+                    vec![]
+                }
+            }
+            MouseEventKind::Motion { .. } => match &mut self.drag {
+                Some(Primitive::Pen(pen)) => {
+                    pen.handle_drag(event.position);
+                    vec![ToolMessage::EraseScratch(pen.render(systems).clone())]
+                }
+                _ => vec![],
+            },
+            MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                let mut pen = Pen::new(event.position, Self::brush_options());
+                let mesh = pen.render(systems).clone();
+                self.drag = Some(Primitive::Pen(pen));
+                vec![ToolMessage::SetFocus, ToolMessage::EraseScratch(mesh)]
+            }
+            MouseEventKind::Release { button, .. } if button == MouseButton::Left => {
+                match self.drag.take() {
+                    Some(Primitive::Pen(mut pen)) => {
+                        pen.handle_drag(event.position);
+                        vec![
+                            ToolMessage::ReleaseFocus,
+                            ToolMessage::Commit(Primitive::Pen(pen)),
+                        ]
+                    }
+                    _ => vec![],
+                }
+            }
+            _ => vec![],
+        }
     }
 }
 
 impl Tool for EraserTool {
     fn mouse_event(
         &mut self,
-        _systems: &mut Systems,
+        systems: &mut Systems,
         event: MouseEvent,
         _modifiers: Modifiers,
+    ) -> Vec<ToolMessage> {
+        match self.mode {
+            EraserMode::Delete => match event.kind {
+                MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                    vec![ToolMessage::Erase(event.position)]
+                }
+                _ => vec![],
+            },
+            EraserMode::Brush => self.brush_mouse_event(systems, event),
+        }
+    }
+
+    fn keyboard_event(
+        &mut self,
+        _systems: &mut Systems,
+        event: input::KeyboardEvent,
     ) -> Vec<ToolMessage> {
         match event.kind {
-            MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
-                vec![ToolMessage::Erase(event.position)]
+            input::KeyboardEventKind::Press(Key::SpecialKey(input::SpecialKey::Tab)) => {
+                self.mode = match self.mode {
+                    EraserMode::Delete => EraserMode::Brush,
+                    EraserMode::Brush => EraserMode::Delete,
+                };
+                if self.drag.take().is_some() {
+                    vec![ToolMessage::ReleaseFocus, ToolMessage::ClearScratch]
+                } else {
+                    vec![]
+                }
             }
             _ => vec![],
         }
@@ -50,4 +50,8 @@ impl Tool for GrabTool {
             _ => vec![],
         }
     }
+
+    fn cursor(&self, _modifiers: Modifiers) -> Option<input::CursorIcon> {
+        self.grab.map(|_| input::CursorIcon::Grabbing)
+    }
 }
@@ -1,6 +1,7 @@
 use crate::tools::{Tool, ToolMessage};
 use color::{PremulColor, Srgb};
 use input::{Key, Modifiers, MouseButton, MouseEvent, MouseEventKind};
+use lyon::math::Point;
 
 use graphics::primitives::{Line, LineOptions};
 use graphics::{CanvasCoordinates, Drawable, Primitive, Systems};
@@ -11,6 +12,12 @@ const SNAP_RADIANS: f32 = std::f32::consts::FRAC_PI_8 / 2.;
 pub struct LineTool {
     drag: Option<Primitive<CanvasCoordinates>>,
     pub color: PremulColor<Srgb>,
+    /// Where the last line committed while holding Shift ended, further Shift-clicks continue
+    /// from here until Shift is let go.
+    ///
+    /// Only lines drawn with Shift count, so that holding Shift for the first line of a chain
+    /// still just snaps its angle.
+    last_end: Option<Point>,
 }
 impl LineTool {
     pub fn new() -> Self {
@@ -22,6 +29,7 @@ impl Default for LineTool {
         Self {
             drag: None,
             color: PremulColor::new([1., 1., 1., 1.]),
+            last_end: None,
         }
     }
 }
@@ -60,19 +68,29 @@ impl Tool for LineTool {
             }
             MouseEventKind::Press { button, .. } => match button {
                 MouseButton::Left => {
-                    let drag = self.drag.insert(Primitive::Line(Line::new(
-                        event.position,
-                        event.position,
+                    // Shift-clicking joins the new line onto the end of the last one.
+                    let origin = self
+                        .last_end
+                        .filter(|_| modifiers.intersects(Modifiers::SHIFT))
+                        .unwrap_or(event.position);
+                    let mut line = Line::new(
+                        origin,
+                        origin,
                         LineOptions {
                             color: self.color,
                             ..Default::default()
                         },
-                    )));
+                    );
+                    if origin != event.position {
+                        line.set_destination_snap(event.position, SNAP_RADIANS);
+                    }
+                    let drag = self.drag.insert(Primitive::Line(line));
                     let mesh = drag.render(systems).clone();
                     vec![ToolMessage::SetFocus, ToolMessage::Scratch(mesh)]
                 }
                 MouseButton::Right => {
                     self.drag = None;
+                    self.last_end = None;
                     vec![ToolMessage::ClearScratch]
                 }
                 _ => vec![],
@@ -91,6 +109,9 @@ impl Tool for LineTool {
                                 elem.set_destination(event.position);
                             }
                             is_empty = elem.is_empty();
+                            if !is_empty && modifiers.intersects(Modifiers::SHIFT) {
+                                self.last_end = Some(elem.destination());
+                            }
                         }
                         _ => unreachable!("line tool's drag can only be a line"),
                     }
@@ -122,6 +143,13 @@ impl Tool for LineTool {
                 }
                 vec![]
             }
+            input::KeyboardEventKind::ModifiersChanged => {
+                // Letting go of Shift ends the chain, the next line starts at the pointer again.
+                if !event.modifiers.intersects(Modifiers::SHIFT) {
+                    self.last_end = None;
+                }
+                vec![]
+            }
             _ => vec![],
         }
     }
@@ -1,98 +1,162 @@
+use std::collections::{HashMap, HashSet};
+
 use color::{PremulColor, Srgb};
-use euclid::default::Point2D;
+use euclid::default::{Box2D, Point2D, Vector2D};
 use graphics::{CanvasCoordinates, Mesh, Primitive, Systems, Vertex};
 
 use gui::reexports::taffy::NodeId;
 use input::{CursorIcon, KeyboardEvent, Modifiers, MouseEvent};
+use strum::IntoEnumIterator;
 
 use crate::projection::Projection;
 
+mod angle;
 mod arrow;
+mod crop;
 mod ellipse;
 mod eraser;
 mod grab;
 mod highlighter;
 mod line;
+mod note;
 mod pen;
 mod rectangle;
+mod rotate;
 mod select;
+mod stamp;
 mod text;
 mod zoom;
 
+pub use rotate::{snap_rotation, ROTATION_SNAP};
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, strum::EnumIter)]
 pub enum ToolKind {
     Grab,
     Select,
+    Rotate,
     Pen,
     Line,
     Arrow,
+    Angle,
     #[default]
     Rectangle,
     Ellipse,
     Text,
+    Note,
     Highlighter,
+    Stamp,
     Eraser,
     Zoom,
+    Crop,
 }
 #[derive(Clone, Debug, Default)]
 pub struct Tools {
     pub grab: grab::GrabTool,
     pub select: select::SelectTool,
+    pub rotate: rotate::RotateTool,
     pub pen: pen::PenTool,
     pub line: line::LineTool,
     pub arrow: arrow::ArrowTool,
+    pub angle: angle::AngleTool,
     pub rectangle: rectangle::RectangleTool,
     pub ellipse: ellipse::EllipseTool,
     pub text: text::TextTool,
+    pub note: note::NoteTool,
     pub highlighter: highlighter::HighlighterTool,
+    pub stamp: stamp::StampTool,
     pub eraser: eraser::EraserTool,
     pub zoom: zoom::ZoomTool,
+    pub crop: crop::CropTool,
+}
+
+impl Tools {
+    /// Passes the snapping toggle on to every tool that snaps.
+    pub fn set_snapping(&mut self, snapping: Snapping) {
+        self.text.snapping = snapping;
+    }
+
+    /// Passes the current color on to every tool that draws with it.
+    pub fn set_color(&mut self, color: PremulColor<Srgb>) {
+        self.pen.color = color;
+        self.stamp.color = color;
+    }
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct ToolNodeMap {
-    grab: NodeId,
-    select: NodeId,
-    pen: NodeId,
-    line: NodeId,
-    arrow: NodeId,
-    rectangle: NodeId,
-    ellipse: NodeId,
-    text: NodeId,
-    highlighter: NodeId,
-    eraser: NodeId,
-    zoom: NodeId,
+/// The single toggle for every kind of snapping, such as text snapping to existing text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Snapping {
+    pub enabled: bool,
 }
+
+impl Default for Snapping {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+impl Snapping {
+    /// Whether to snap for an event, holding Ctrl inverts the toggle while it is held.
+    pub fn is_active(self, modifiers: Modifiers) -> bool {
+        self.enabled != modifiers.contains(Modifiers::CTRL)
+    }
+}
+
+/// The toolbar button of every enabled tool.
+#[derive(Clone, Debug, Default)]
+pub struct ToolNodeMap(HashMap<ToolKind, NodeId>);
 impl ToolNodeMap {
-    pub const fn zero() -> Self {
-        Self {
-            grab: NodeId::new(0),
-            select: NodeId::new(0),
-            pen: NodeId::new(0),
-            line: NodeId::new(0),
-            arrow: NodeId::new(0),
-            rectangle: NodeId::new(0),
-            ellipse: NodeId::new(0),
-            text: NodeId::new(0),
-            highlighter: NodeId::new(0),
-            eraser: NodeId::new(0),
-            zoom: NodeId::new(0),
-        }
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn set(&mut self, kind: ToolKind, node_id: NodeId) {
+        self.0.insert(kind, node_id);
+    }
+    pub fn get(&self, kind: ToolKind) -> Option<NodeId> {
+        self.0.get(&kind).copied()
+    }
+}
+
+/// The tools a view offers, disabled tools are left out of the toolbar and the command palette
+/// and can not be selected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnabledTools(HashSet<ToolKind>);
+
+impl Default for EnabledTools {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl EnabledTools {
+    pub fn all() -> Self {
+        Self(ToolKind::iter().collect())
+    }
+    /// Enables only the given tools.
+    pub fn only(tools: impl IntoIterator<Item = ToolKind>) -> Self {
+        Self(tools.into_iter().collect())
+    }
+    pub fn disable(&mut self, tool: ToolKind) {
+        self.0.remove(&tool);
+    }
+    pub fn enable(&mut self, tool: ToolKind) {
+        self.0.insert(tool);
     }
-    pub const fn set(&mut self, kind: ToolKind, node_id: NodeId) {
-        match kind {
-            ToolKind::Grab => self.grab = node_id,
-            ToolKind::Select => self.select = node_id,
-            ToolKind::Pen => self.pen = node_id,
-            ToolKind::Line => self.line = node_id,
-            ToolKind::Arrow => self.arrow = node_id,
-            ToolKind::Rectangle => self.rectangle = node_id,
-            ToolKind::Ellipse => self.ellipse = node_id,
-            ToolKind::Text => self.text = node_id,
-            ToolKind::Highlighter => self.highlighter = node_id,
-            ToolKind::Eraser => self.eraser = node_id,
-            ToolKind::Zoom => self.zoom = node_id,
+    pub fn is_enabled(&self, tool: ToolKind) -> bool {
+        self.0.contains(&tool)
+    }
+    /// The enabled tools, in toolbar order.
+    pub fn iter(&self) -> impl Iterator<Item = ToolKind> + '_ {
+        ToolKind::iter().filter(|tool| self.is_enabled(*tool))
+    }
+    /// The tool that is selected when the view is created, the default tool if it is enabled and
+    /// otherwise the first enabled one.
+    ///
+    /// Panning is always possible, so it is used when every tool is disabled.
+    pub fn default_tool(&self) -> ToolKind {
+        if self.is_enabled(ToolKind::default()) {
+            return ToolKind::default();
         }
+        self.iter().next().unwrap_or(ToolKind::Grab)
     }
 }
 
@@ -101,43 +165,140 @@ impl ToolKind {
         match self {
             ToolKind::Grab => include_bytes!("../../../../resources/grab.svg"),
             ToolKind::Select => include_bytes!("../../../../resources/select.svg"),
+            ToolKind::Rotate => include_bytes!("../../../../resources/rotate.svg"),
             ToolKind::Pen => include_bytes!("../../../../resources/pen.svg"),
             ToolKind::Line => include_bytes!("../../../../resources/line.svg"),
             ToolKind::Arrow => include_bytes!("../../../../resources/arrow.svg"),
+            ToolKind::Angle => include_bytes!("../../../../resources/angle.svg"),
             ToolKind::Rectangle => include_bytes!("../../../../resources/rectangle.svg"),
             ToolKind::Ellipse => include_bytes!("../../../../resources/ellipse.svg"),
             ToolKind::Text => include_bytes!("../../../../resources/text.svg"),
+            ToolKind::Note => include_bytes!("../../../../resources/note.svg"),
             ToolKind::Highlighter => include_bytes!("../../../../resources/highlighter.svg"),
+            ToolKind::Stamp => include_bytes!("../../../../resources/stamp.svg"),
             ToolKind::Eraser => include_bytes!("../../../../resources/eraser.svg"),
             ToolKind::Zoom => include_bytes!("../../../../resources/zoom.svg"),
+            ToolKind::Crop => include_bytes!("../../../../resources/crop.svg"),
         }
     }
     pub const fn default_cursor(&self) -> CursorIcon {
         match self {
             ToolKind::Grab => CursorIcon::Grab,
             ToolKind::Select => CursorIcon::Default,
+            ToolKind::Rotate => CursorIcon::Grab,
             ToolKind::Eraser => CursorIcon::NotAllowed,
             ToolKind::Zoom => CursorIcon::ZoomIn,
-            ToolKind::Text => CursorIcon::Text,
+            ToolKind::Text | ToolKind::Note => CursorIcon::Text,
             _ => CursorIcon::Crosshair,
         }
     }
 
-    pub const fn get_node_id(&self, node_map: &ToolNodeMap) -> NodeId {
+    /// A short hint on how to use the tool, for the status line. Holding a modifier the tool
+    /// reacts to switches to a hint for that modifier.
+    pub fn hint(&self, modifiers: Modifiers) -> &'static str {
+        let shift = modifiers.intersects(Modifiers::SHIFT);
+        let alt = modifiers.intersects(Modifiers::ALT);
+        let ctrl = modifiers.intersects(Modifiers::CTRL);
         match self {
-            ToolKind::Grab => node_map.grab,
-            ToolKind::Select => node_map.select,
-            ToolKind::Pen => node_map.pen,
-            ToolKind::Line => node_map.line,
-            ToolKind::Arrow => node_map.arrow,
-            ToolKind::Rectangle => node_map.rectangle,
-            ToolKind::Ellipse => node_map.ellipse,
-            ToolKind::Text => node_map.text,
-            ToolKind::Highlighter => node_map.highlighter,
-            ToolKind::Eraser => node_map.eraser,
-            ToolKind::Zoom => node_map.zoom,
+            ToolKind::Grab => "Drag to pan the canvas",
+            ToolKind::Select if alt => "Click to select the node below the top-most one",
+            ToolKind::Select => {
+                "Click to select, click again to cycle through overlapping nodes; Alt to start \
+                 below the top"
+            }
+            ToolKind::Rotate if shift => "Rotating in steps of 15°",
+            ToolKind::Rotate => {
+                "Drag an svg to rotate it, other shapes can't be rotated yet; hold Shift to snap"
+            }
+            ToolKind::Pen if shift => "Click to continue straight from the end of the last stroke",
+            ToolKind::Pen => {
+                "Drag to draw freehand, Shift+click to continue the last stroke; Tab swaps black \
+                 and white"
+            }
+            ToolKind::Line if shift => {
+                "Snapping the angle; keep holding Shift to continue from the end of each line"
+            }
+            ToolKind::Arrow if shift => "Snapping the angle",
+            ToolKind::Line => {
+                "Drag to draw a line; hold Shift to snap the angle or continue the last line"
+            }
+            ToolKind::Arrow => "Drag to draw an arrow; hold Shift to snap the angle",
+            ToolKind::Angle if alt => "Measuring the reflex angle",
+            ToolKind::Angle => "Click the vertex, then the two rays; hold Alt for the reflex angle",
+            ToolKind::Rectangle | ToolKind::Ellipse if shift && alt => {
+                "Drawing a square from the center"
+            }
+            ToolKind::Rectangle | ToolKind::Ellipse if shift => {
+                "Drawing a square; hold Alt to draw from the center"
+            }
+            ToolKind::Rectangle | ToolKind::Ellipse if alt => {
+                "Drawing from the center; hold Shift for a square"
+            }
+            ToolKind::Rectangle => {
+                "Drag to draw a rectangle; hold Shift for a square, Alt to draw from the center"
+            }
+            ToolKind::Ellipse => {
+                "Drag to draw an ellipse; hold Shift for a circle, Alt to draw from the center"
+            }
+            ToolKind::Text => {
+                "Click to place or edit text, Ctrl+Enter to finish, Escape to discard the changes; \
+                 Tab swaps black and white"
+            }
+            ToolKind::Note => "Click to place a note, Enter to finish, Escape to discard it",
+            ToolKind::Highlighter => "Drag to highlight",
+            ToolKind::Stamp => "Click to stamp, drag to stamp along the way",
+            ToolKind::Eraser => "Drag over nodes to erase them",
+            ToolKind::Zoom if alt => "Click to zoom out",
+            ToolKind::Zoom => "Click to zoom in; hold Alt to zoom out",
+            ToolKind::Crop if ctrl => "Click to crop to the content",
+            ToolKind::Crop => "Drag the export region; right click to clear, Ctrl+click to fit",
         }
     }
+
+    /// The cursor the tool currently wants, which unlike [`ToolKind::default_cursor`] follows
+    /// the state of the tool.
+    pub fn cursor(&self, tools: &Tools, modifiers: Modifiers) -> CursorIcon {
+        let cursor = match self {
+            ToolKind::Grab => tools.grab.cursor(modifiers),
+            ToolKind::Select => tools.select.cursor(modifiers),
+            ToolKind::Rotate => tools.rotate.cursor(modifiers),
+            ToolKind::Pen => tools.pen.cursor(modifiers),
+            ToolKind::Line => tools.line.cursor(modifiers),
+            ToolKind::Arrow => tools.arrow.cursor(modifiers),
+            ToolKind::Angle => tools.angle.cursor(modifiers),
+            ToolKind::Rectangle => tools.rectangle.cursor(modifiers),
+            ToolKind::Ellipse => tools.ellipse.cursor(modifiers),
+            ToolKind::Text => tools.text.cursor(modifiers),
+            ToolKind::Note => tools.note.cursor(modifiers),
+            ToolKind::Highlighter => tools.highlighter.cursor(modifiers),
+            ToolKind::Stamp => tools.stamp.cursor(modifiers),
+            ToolKind::Eraser => tools.eraser.cursor(modifiers),
+            ToolKind::Zoom => tools.zoom.cursor(modifiers),
+            ToolKind::Crop => tools.crop.cursor(modifiers),
+        };
+        cursor.unwrap_or(self.default_cursor())
+    }
+
+    /// Whether dragging with the tool near the edge of the viewport pans the canvas, only the
+    /// tools that draw do, panning while moving a selection would move it along with the canvas.
+    pub const fn auto_pans(&self) -> bool {
+        matches!(
+            self,
+            ToolKind::Pen
+                | ToolKind::Line
+                | ToolKind::Arrow
+                | ToolKind::Angle
+                | ToolKind::Rectangle
+                | ToolKind::Ellipse
+                | ToolKind::Highlighter
+                | ToolKind::Eraser
+        )
+    }
+
+    /// The toolbar button of the tool, `None` when the tool is disabled.
+    pub fn get_node_id(&self, node_map: &ToolNodeMap) -> Option<NodeId> {
+        node_map.get(*self)
+    }
     pub fn mouse_event(
         &self,
         systems: &mut Systems,
@@ -153,16 +314,21 @@ impl ToolKind {
         match self {
             ToolKind::Grab => tools.grab.mouse_event(systems, event, modifiers),
             ToolKind::Select => tools.select.mouse_event(systems, event, modifiers),
+            ToolKind::Rotate => tools.rotate.mouse_event(systems, event, modifiers),
             ToolKind::Pen => tools.pen.mouse_event(systems, event, modifiers),
             ToolKind::Line => tools.line.mouse_event(systems, event, modifiers),
             ToolKind::Arrow => tools.arrow.mouse_event(systems, event, modifiers),
+            ToolKind::Angle => tools.angle.mouse_event(systems, event, modifiers),
             ToolKind::Rectangle => tools.rectangle.mouse_event(systems, event, modifiers),
             ToolKind::Ellipse => tools.ellipse.mouse_event(systems, event, modifiers),
             ToolKind::Text => tools.text.mouse_event(systems, event, modifiers),
+            ToolKind::Note => tools.note.mouse_event(systems, event, modifiers),
             ToolKind::Highlighter => tools.highlighter.mouse_event(systems, event, modifiers),
+            ToolKind::Stamp => tools.stamp.mouse_event(systems, event, modifiers),
 
             ToolKind::Eraser => tools.eraser.mouse_event(systems, event, modifiers),
             ToolKind::Zoom => tools.zoom.mouse_event(systems, event, modifiers),
+            ToolKind::Crop => tools.crop.mouse_event(systems, event, modifiers),
         }
     }
 
@@ -175,19 +341,45 @@ impl ToolKind {
         match self {
             ToolKind::Grab => tools.grab.keyboard_event(systems, event),
             ToolKind::Select => tools.select.keyboard_event(systems, event),
+            ToolKind::Rotate => tools.rotate.keyboard_event(systems, event),
             ToolKind::Pen => tools.pen.keyboard_event(systems, event),
             ToolKind::Line => tools.line.keyboard_event(systems, event),
             ToolKind::Arrow => tools.arrow.keyboard_event(systems, event),
+            ToolKind::Angle => tools.angle.keyboard_event(systems, event),
             ToolKind::Rectangle => tools.rectangle.keyboard_event(systems, event),
             ToolKind::Ellipse => tools.ellipse.keyboard_event(systems, event),
             ToolKind::Text => tools.text.keyboard_event(systems, event),
+            ToolKind::Note => tools.note.keyboard_event(systems, event),
             ToolKind::Highlighter => tools.highlighter.keyboard_event(systems, event),
+            ToolKind::Stamp => tools.stamp.keyboard_event(systems, event),
             ToolKind::Eraser => tools.eraser.keyboard_event(systems, event),
             ToolKind::Zoom => tools.zoom.keyboard_event(systems, event),
+            ToolKind::Crop => tools.crop.keyboard_event(systems, event),
         }
     }
 }
 
+/// The area a shape tool covers when dragged from `start` to `position`.
+///
+/// Shapes are drawn from corner to corner, or outwards from `start` as their center while Alt
+/// is held. Holding Shift keeps the shape square.
+pub fn drag_bounds(
+    start: Point2D<f32>,
+    position: Point2D<f32>,
+    modifiers: Modifiers,
+) -> Box2D<f32> {
+    let mut delta = position - start;
+    if modifiers.intersects(Modifiers::SHIFT) {
+        let length = delta.x.abs().min(delta.y.abs());
+        delta = Vector2D::new(length * delta.x.signum(), length * delta.y.signum());
+    }
+    if modifiers.intersects(Modifiers::ALT) {
+        Box2D::from_points([start - delta, start + delta])
+    } else {
+        Box2D::from_points([start, start + delta])
+    }
+}
+
 pub trait Tool {
     fn mouse_event(
         &mut self,
@@ -200,24 +392,112 @@ pub trait Tool {
     fn keyboard_event(&mut self, systems: &mut Systems, event: KeyboardEvent) -> Vec<ToolMessage> {
         vec![]
     }
+
+    /// The cursor for the current state of the tool and the held modifiers, `None` uses the
+    /// default cursor of the tool kind.
+    #[allow(unused_variables)]
+    fn cursor(&self, modifiers: Modifiers) -> Option<CursorIcon> {
+        None
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum ToolMessage {
     CursorIcon(CursorIcon),
     Commit(Primitive<CanvasCoordinates>),
+    /// Replaces the node committed just before in the same batch, such as a stroke that was
+    /// recognized as a shape, so undoing brings the original back.
+    ReplaceCommit(Primitive<CanvasCoordinates>),
+    /// Commits a node into the same undoable change as the node committed before it, such as
+    /// the stamps of a single drag.
+    AppendCommit(Primitive<CanvasCoordinates>),
     Scratch(Mesh<Vertex>),
+    /// Like [`ToolMessage::Scratch`], but the preview erases the content below it.
+    EraseScratch(Mesh<Vertex>),
     ClearScratch,
     ChangePrimaryColor(PremulColor<Srgb>),
 
+    /// Edits the top-most text at the position, if there is one, in place of the new text the
+    /// text tool just started.
+    EditText(Point2D<f32>),
+    /// Replaces the text being edited and stops editing it, `None` removes the text.
+    CommitEdit(Option<Primitive<CanvasCoordinates>>),
+    /// Stops editing the text, leaving it as it was before the edit.
+    CancelEdit,
+
     SetFocus,
     ReleaseFocus,
 
-    Select(Point2D<f32>),
+    /// Selects a node at the position, the depth counting down from the top-most node and
+    /// wrapping around once it passes the bottom one.
+    Select(Point2D<f32>, usize),
+    /// The pointer moved while the select tool was held down.
+    SelectDrag(Point2D<f32>),
     GrabMove(Point2D<f32>, Point2D<f32>),
+    /// Starts rotating the top-most rotatable node at the position.
+    Rotate(Point2D<f32>),
+    /// The pointer moved while rotating, along with whether the rotation snaps.
+    RotateDrag(Point2D<f32>, bool),
+    /// The pointer moved without rotating, to show whether the node under it can be rotated.
+    RotateHover(Point2D<f32>),
     Erase(Point2D<f32>),
 
     ZoomIn(Point2D<f32>),
     ZoomOut(Point2D<f32>),
     ResetZoom,
+
+    /// Sets the world region that is exported.
+    Crop(Box2D<f32>),
+    /// Sets the export region to the bounds of the visible content.
+    CropToContent,
+    ClearCrop,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(position: (f32, f32), modifiers: Modifiers) -> Box2D<f32> {
+        drag_bounds(
+            Point2D::new(10., 10.),
+            Point2D::new(position.0, position.1),
+            modifiers,
+        )
+    }
+
+    #[test]
+    fn shapes_are_dragged_from_corner_to_corner() {
+        let expected = Box2D::new(Point2D::new(10., 10.), Point2D::new(40., 30.));
+        assert_eq!(bounds((40., 30.), Modifiers::empty()), expected);
+        // Dragging up and to the left gives the same box the other way around.
+        assert_eq!(
+            bounds((-20., -10.), Modifiers::empty()),
+            Box2D::new(Point2D::new(-20., -10.), Point2D::new(10., 10.))
+        );
+    }
+
+    #[test]
+    fn shift_keeps_the_shape_square() {
+        assert_eq!(
+            bounds((40., 30.), Modifiers::SHIFT),
+            Box2D::new(Point2D::new(10., 10.), Point2D::new(30., 30.))
+        );
+        // The square keeps to the side of the start the cursor is on.
+        assert_eq!(
+            bounds((-20., 30.), Modifiers::SHIFT),
+            Box2D::new(Point2D::new(-10., 10.), Point2D::new(10., 30.))
+        );
+    }
+
+    #[test]
+    fn alt_draws_from_the_center() {
+        assert_eq!(
+            bounds((40., 30.), Modifiers::ALT),
+            Box2D::new(Point2D::new(-20., -10.), Point2D::new(40., 30.))
+        );
+        assert_eq!(
+            bounds((40., 30.), Modifiers::SHIFT | Modifiers::ALT),
+            Box2D::new(Point2D::new(-10., -10.), Point2D::new(30., 30.))
+        );
+    }
 }
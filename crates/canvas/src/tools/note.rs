@@ -0,0 +1,98 @@
+use graphics::primitives::{Note, NoteOptions};
+use graphics::{CanvasCoordinates, Drawable, Primitive, Systems};
+use input::{
+    Key, KeyboardEvent, KeyboardEventKind, Modifiers, MouseButton, MouseEvent, MouseEventKind,
+    SpecialKey,
+};
+
+use crate::tools::{Tool, ToolMessage};
+
+/// Places sticky notes, text on a background that grows as the text is typed.
+///
+/// Clicking places a note at the cursor, which is committed with Enter, by clicking somewhere else
+/// or when the cursor leaves the canvas. Notes that are empty when committed are discarded.
+#[derive(Clone, Debug, Default)]
+pub struct NoteTool {
+    current: Option<Note<CanvasCoordinates>>,
+    pub options: NoteOptions,
+}
+
+impl NoteTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Commits the note being typed, or drops it if it has no text.
+    fn finish(&mut self) -> Vec<ToolMessage> {
+        match self.current.take() {
+            Some(note) if !note.text().content().trim().is_empty() => {
+                vec![ToolMessage::Commit(Primitive::Note(note))]
+            }
+            _ => vec![ToolMessage::ClearScratch],
+        }
+    }
+}
+
+impl Tool for NoteTool {
+    fn mouse_event(
+        &mut self,
+        systems: &mut Systems,
+        event: MouseEvent,
+        _modifiers: Modifiers,
+    ) -> Vec<ToolMessage> {
+        match event.kind {
+            MouseEventKind::Enter | MouseEventKind::Leave => {
+                let mut messages = vec![ToolMessage::ReleaseFocus];
+                messages.extend(self.finish());
+                messages
+            }
+            MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                // Clicking again while editing commits the note and starts a new one.
+                let mut messages = self.finish();
+                let note = self.current.insert(Note::new(
+                    String::new(),
+                    event.position,
+                    self.options.clone(),
+                ));
+                messages.extend([
+                    ToolMessage::SetFocus,
+                    ToolMessage::Scratch(note.render(systems).clone()),
+                ]);
+                messages
+            }
+            _ => vec![],
+        }
+    }
+
+    fn keyboard_event(&mut self, systems: &mut Systems, event: KeyboardEvent) -> Vec<ToolMessage> {
+        let KeyboardEventKind::Press(key) = event.kind else {
+            return vec![];
+        };
+        let Some(note) = self.current.as_mut() else {
+            return vec![];
+        };
+        match key {
+            Key::SpecialKey(SpecialKey::Enter) => {
+                let mut messages = vec![ToolMessage::ReleaseFocus];
+                messages.extend(self.finish());
+                return messages;
+            }
+            Key::SpecialKey(SpecialKey::Escape) => {
+                self.current = None;
+                return vec![ToolMessage::ReleaseFocus, ToolMessage::ClearScratch];
+            }
+            Key::SpecialKey(SpecialKey::Backspace | SpecialKey::Delete) => {
+                let content = note.text().content();
+                if let Some((index, _)) = content.char_indices().next_back() {
+                    note.text_mut().delete_range(index..content.len());
+                }
+            }
+            Key::Character(str) => {
+                let len = note.text().len();
+                note.text_mut().insert_at(len, str.as_str());
+            }
+            _ => return vec![],
+        }
+        vec![ToolMessage::Scratch(note.render(systems).clone())]
+    }
+}
@@ -1,24 +1,74 @@
 use crate::tools::{Tool, ToolMessage};
 use color::{PremulColor, Srgb};
-use graphics::primitives::{Pen, PenOptions};
+use graphics::primitives::{
+    Line, LineOptions, Pen, PenOptions, RecognizedShape, Rectangle, RectangleOptions,
+};
 use graphics::{CanvasCoordinates, Drawable, Primitive, Systems};
 use input::{Key, Modifiers, MouseButton, MouseEvent, MouseEventKind};
+use lyon::math::Point;
 
 #[derive(Clone, Debug)]
 pub struct PenTool {
     drag: Option<Primitive<CanvasCoordinates>>,
     pub color: PremulColor<Srgb>,
+    /// Replaces strokes that look like a line, rectangle, ellipse or arrow with the clean shape.
+    pub recognize_shapes: bool,
+    /// The confidence from 0 to 1 a stroke needs before it is replaced, so ambiguous strokes
+    /// stay freehand.
+    pub recognition_threshold: f32,
+    /// Where the last committed stroke ended, a Shift-click continues from here.
+    last_end: Option<Point>,
 }
 impl PenTool {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// The clean shape to replace a finished stroke with, if it is confidently recognized.
+    fn recognize(&self, pen: &Pen<CanvasCoordinates>) -> Option<Primitive<CanvasCoordinates>> {
+        if !self.recognize_shapes {
+            return None;
+        }
+        let recognition = pen
+            .recognize()
+            .filter(|recognition| recognition.confidence >= self.recognition_threshold)?;
+        let options = pen.options();
+        Some(match recognition.shape {
+            RecognizedShape::Line { from, to } => Primitive::Line(Line::new(
+                from,
+                to,
+                LineOptions {
+                    color: options.color,
+                    width: options.width,
+                    line_cap: options.line_cap,
+                    line_join: options.line_join,
+                    miter_limit: options.miter_limit,
+                    ..Default::default()
+                },
+            )),
+            RecognizedShape::Rectangle(rect) => Primitive::Rectangle(Rectangle::new(
+                rect.min,
+                rect.size(),
+                RectangleOptions {
+                    color: PremulColor::TRANSPARENT.into(),
+                    stroke_color: options.color.into(),
+                    stroke_width: options.width,
+                    ..RectangleOptions::DEFAULT
+                },
+            )),
+            // Ellipses are only filled, and there is no arrow primitive, so both stay strokes.
+            shape => Primitive::Pen(Pen::from_points(shape.outline(), options.clone())),
+        })
+    }
 }
 impl Default for PenTool {
     fn default() -> Self {
         Self {
             drag: Default::default(),
             color: PremulColor::new([1., 1., 1., 1.]),
+            recognize_shapes: false,
+            recognition_threshold: 0.6,
+            last_end: None,
         }
     }
 }
@@ -28,7 +78,7 @@ impl Tool for PenTool {
         &mut self,
         systems: &mut Systems,
         event: MouseEvent,
-        _modifiers: Modifiers,
+        modifiers: Modifiers,
     ) -> Vec<ToolMessage> {
         match event.kind {
             MouseEventKind::Enter => {
@@ -46,7 +96,7 @@ impl Tool for PenTool {
             MouseEventKind::Motion { .. } => {
                 if let Some(mut drag) = self.drag.take() {
                     match &mut drag {
-                        Primitive::Pen(elem) => elem.handle_drag(event.position),
+                        Primitive::Pen(elem) => elem.push_point(event.position, event.pressure),
                         _ => unreachable!(),
                     }
                     self.drag = Some(drag);
@@ -61,13 +111,23 @@ impl Tool for PenTool {
             }
             MouseEventKind::Press { button, .. } => {
                 if button == MouseButton::Left {
-                    self.drag = Some(Primitive::Pen(Pen::new(
-                        event.position,
+                    // Shift-clicking starts with a straight segment from the end of the last
+                    // stroke, the rest of the drag is drawn freehand as usual.
+                    let start = self
+                        .last_end
+                        .filter(|_| modifiers.intersects(Modifiers::SHIFT));
+                    let mut pen = Pen::new(
+                        start.unwrap_or(event.position),
                         PenOptions {
                             color: self.color,
                             ..Default::default()
                         },
-                    )));
+                    )
+                    .with_pressure(event.pressure);
+                    if start.is_some() {
+                        pen.push_point(event.position, event.pressure);
+                    }
+                    self.drag = Some(Primitive::Pen(pen));
                     if let Some(drag) = &mut self.drag {
                         vec![
                             ToolMessage::SetFocus,
@@ -83,11 +143,18 @@ impl Tool for PenTool {
             MouseEventKind::Release { button, .. } => {
                 if button == MouseButton::Left {
                     if let Some(mut drag) = self.drag.take() {
-                        match &mut drag {
-                            Primitive::Pen(elem) => elem.handle_drag(event.position),
+                        let shape = match &mut drag {
+                            Primitive::Pen(elem) => {
+                                elem.push_point(event.position, event.pressure);
+                                self.last_end = elem.points().last().copied();
+                                self.recognize(elem)
+                            }
                             _ => unreachable!(),
-                        }
-                        vec![ToolMessage::ReleaseFocus, ToolMessage::Commit(drag)]
+                        };
+                        let mut messages =
+                            vec![ToolMessage::ReleaseFocus, ToolMessage::Commit(drag)];
+                        messages.extend(shape.map(ToolMessage::ReplaceCommit));
+                        messages
                     } else {
                         vec![]
                     }
@@ -111,6 +178,7 @@ impl Tool for PenTool {
                     } else {
                         self.color = PremulColor::WHITE
                     };
+                    let mut messages = vec![ToolMessage::ChangePrimaryColor(self.color)];
                     if let Some(drag) = &mut self.drag {
                         match drag {
                             Primitive::Pen(pen) => pen.update_options(PenOptions {
@@ -119,10 +187,9 @@ impl Tool for PenTool {
                             }),
                             _ => unreachable!(),
                         };
-                        vec![ToolMessage::Scratch(drag.render(systems).clone())]
-                    } else {
-                        vec![]
+                        messages.push(ToolMessage::Scratch(drag.render(systems).clone()));
                     }
+                    messages
                 } else {
                     vec![]
                 }
@@ -1,21 +1,46 @@
 use color::PremulColor;
-use euclid::Size2D;
+use euclid::default::{Point2D, Size2D};
 use graphics::{
     primitives::{Rectangle, RectangleOptions},
     Drawable, Systems,
 };
 
-use crate::tools::{Tool, ToolMessage};
+use crate::tools::{drag_bounds, Tool, ToolMessage};
 use graphics::{CanvasCoordinates, Primitive};
-use input::{Modifiers, MouseButton, MouseEvent, MouseEventKind};
+use input::{KeyboardEvent, KeyboardEventKind, Modifiers, MouseButton, MouseEvent, MouseEventKind};
 
 #[derive(Clone, Debug, Default)]
 pub struct RectangleTool {
     drag: Option<Primitive<CanvasCoordinates>>,
+    /// The press position of the current drag.
+    start: Point2D<f32>,
+    /// The last pointer position of the current drag, so modifier changes can resize it.
+    position: Point2D<f32>,
 }
 impl RectangleTool {
     pub fn new() -> Self {
-        Self { drag: None }
+        Self::default()
+    }
+
+    /// Resizes the drag to the current pointer position, returning the new size.
+    fn resize(&mut self, modifiers: Modifiers) -> Option<Size2D<f32>> {
+        let bounds = drag_bounds(self.start, self.position, modifiers);
+        match self.drag.as_mut()? {
+            Primitive::Rectangle(elem) => {
+                elem.update_area(bounds.min, bounds.size());
+                Some(elem.size().abs())
+            }
+            _ => unreachable!("rectangle tool's drag can only be a rectangle"),
+        }
+    }
+
+    /// Resizes the drag and previews it.
+    fn preview(&mut self, systems: &mut Systems, modifiers: Modifiers) -> Vec<ToolMessage> {
+        _ = self.resize(modifiers);
+        match &mut self.drag {
+            Some(drag) => vec![ToolMessage::Scratch(drag.render(systems).clone())],
+            None => vec![],
+        }
     }
 }
 
@@ -36,23 +61,13 @@ impl Tool for RectangleTool {
                 }
             }
             MouseEventKind::Motion { .. } => {
-                let Some(drag) = self.drag.as_mut() else {
-                    return vec![];
-                };
-                match drag {
-                    Primitive::Rectangle(elem) => {
-                        if modifiers.intersects(Modifiers::SHIFT) {
-                            elem.resize_to_point_square(event.position);
-                        } else {
-                            elem.resize_to_point(event.position);
-                        }
-                    }
-                    _ => unreachable!("rectangle tool's drag can only be a rectangle"),
-                }
-                vec![ToolMessage::Scratch(drag.render(systems).clone())]
+                self.position = event.position;
+                self.preview(systems, modifiers)
             }
             MouseEventKind::Press { button, .. } => match button {
                 MouseButton::Left => {
+                    self.start = event.position;
+                    self.position = event.position;
                     let drag = self.drag.insert(Primitive::Rectangle(Rectangle::new(
                         event.position,
                         Size2D::zero(),
@@ -76,21 +91,13 @@ impl Tool for RectangleTool {
             },
             MouseEventKind::Release { button, .. } => match button {
                 MouseButton::Left => {
-                    let Some(mut drag) = self.drag.take() else {
+                    self.position = event.position;
+                    let Some(size) = self.resize(modifiers) else {
+                        return vec![];
+                    };
+                    let Some(drag) = self.drag.take() else {
                         return vec![];
                     };
-                    let size;
-                    match &mut drag {
-                        Primitive::Rectangle(elem) => {
-                            if modifiers.intersects(Modifiers::SHIFT) {
-                                elem.resize_to_point_square(event.position);
-                            } else {
-                                elem.resize_to_point(event.position);
-                            }
-                            size = elem.size().abs();
-                        }
-                        _ => unreachable!("rectangle tool's drag can only be a rectangle"),
-                    }
                     if size.is_empty() {
                         vec![ToolMessage::ReleaseFocus]
                     } else {
@@ -102,4 +109,10 @@ impl Tool for RectangleTool {
             _ => vec![],
         }
     }
+    fn keyboard_event(&mut self, systems: &mut Systems, event: KeyboardEvent) -> Vec<ToolMessage> {
+        match event.kind {
+            KeyboardEventKind::ModifiersChanged => self.preview(systems, event.modifiers),
+            _ => vec![],
+        }
+    }
 }
@@ -0,0 +1,88 @@
+use std::f32::consts::PI;
+
+use graphics::Systems;
+use input::{Modifiers, MouseButton, MouseEvent, MouseEventKind};
+
+use crate::tools::{Tool, ToolMessage};
+
+/// The step rotations snap to while Shift is held.
+pub const ROTATION_SNAP: f32 = PI / 12.;
+
+/// The rotation of a node that started at `start` and was turned by `delta`, in radians.
+///
+/// Snapping rounds the turn rather than the result, so a node that was already rotated keeps
+/// its offset from the absolute angles.
+pub fn snap_rotation(start: f32, delta: f32, snap: bool) -> f32 {
+    if snap {
+        start + (delta / ROTATION_SNAP).round() * ROTATION_SNAP
+    } else {
+        start + delta
+    }
+}
+
+/// Rotates the node under the cursor about its center by dragging around it.
+///
+/// Holding Shift snaps the rotation to 15° steps, and the angle is shown next to the cursor
+/// while dragging. Only svgs can be rotated, hovering any other node shows a not allowed cursor.
+#[derive(Clone, Debug, Copy, Default, PartialEq)]
+pub struct RotateTool {
+    dragging: bool,
+}
+
+impl RotateTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Tool for RotateTool {
+    fn mouse_event(
+        &mut self,
+        _systems: &mut Systems,
+        event: MouseEvent,
+        modifiers: Modifiers,
+    ) -> Vec<ToolMessage> {
+        match event.kind {
+            MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                self.dragging = true;
+                vec![ToolMessage::SetFocus, ToolMessage::Rotate(event.position)]
+            }
+            MouseEventKind::Motion { .. } if self.dragging => {
+                vec![ToolMessage::RotateDrag(
+                    event.position,
+                    modifiers.intersects(Modifiers::SHIFT),
+                )]
+            }
+            MouseEventKind::Motion { .. } => vec![ToolMessage::RotateHover(event.position)],
+            MouseEventKind::Release { button, .. } if button == MouseButton::Left => {
+                self.dragging = false;
+                vec![ToolMessage::ReleaseFocus, ToolMessage::ClearScratch]
+            }
+            MouseEventKind::Leave if self.dragging => {
+                self.dragging = false;
+                vec![ToolMessage::ReleaseFocus, ToolMessage::ClearScratch]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapping_rounds_the_turn() {
+        assert_eq!(snap_rotation(0., 0.3, false), 0.3);
+        assert_eq!(snap_rotation(0., 0.3, true), ROTATION_SNAP);
+        assert_eq!(snap_rotation(0., 0.1, true), 0.);
+        assert_eq!(snap_rotation(0., -0.2, true), -ROTATION_SNAP);
+    }
+
+    #[test]
+    fn snapping_keeps_the_offset_of_a_rotated_node() {
+        let start = 0.1;
+        let rotation = snap_rotation(start, ROTATION_SNAP * 2. + 0.05, true);
+        assert!((rotation - (start + ROTATION_SNAP * 2.)).abs() < 1e-6);
+    }
+}
@@ -4,15 +4,32 @@ use graphics::Systems;
 use crate::tools::{Tool, ToolMessage};
 use input::{Modifiers, MouseButton, MouseEvent, MouseEventKind};
 
+/// How far apart, in world units, two clicks can be to count as the same location.
+const CYCLE_DISTANCE: f32 = 4.;
+
+/// Selects the node under the cursor.
+///
+/// Repeated clicks at the same location cycle through the overlapping nodes from top to bottom,
+/// and holding Alt starts with the node below the top-most one.
 #[derive(Clone, Debug, Copy, Default, PartialEq)]
 pub struct SelectTool {
     pub initial_point: Option<Point2D<f32>>,
+    /// The location of the last click along with the depth it selected.
+    last_click: Option<(Point2D<f32>, usize)>,
 }
 impl SelectTool {
     pub fn new() -> Self {
-        Self {
-            initial_point: None,
-        }
+        Self::default()
+    }
+
+    fn depth(&mut self, position: Point2D<f32>, modifiers: Modifiers) -> usize {
+        let depth = match self.last_click {
+            Some((point, depth)) if (point - position).length() <= CYCLE_DISTANCE => depth + 1,
+            _ if modifiers.contains(Modifiers::ALT) => 1,
+            _ => 0,
+        };
+        self.last_click = Some((position, depth));
+        depth
     }
 }
 
@@ -21,22 +38,23 @@ impl Tool for SelectTool {
         &mut self,
         _systems: &mut Systems,
         event: MouseEvent,
-        _active_modifiers: Modifiers,
+        active_modifiers: Modifiers,
     ) -> Vec<ToolMessage> {
         match event.kind {
             MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
                 self.initial_point = Some(event.position);
-                vec![ToolMessage::SetFocus]
+                let depth = self.depth(event.position, active_modifiers);
+                vec![
+                    ToolMessage::SetFocus,
+                    ToolMessage::Select(event.position, depth),
+                ]
+            }
+            MouseEventKind::Motion { .. } if self.initial_point.is_some() => {
+                vec![ToolMessage::SelectDrag(event.position)]
             }
             MouseEventKind::Release { button, .. } if button == MouseButton::Left => {
-                if let Some(initial_point) = self.initial_point.take() {
-                    vec![
-                        ToolMessage::ReleaseFocus,
-                        ToolMessage::Select(initial_point),
-                    ]
-                } else {
-                    vec![ToolMessage::ReleaseFocus]
-                }
+                self.initial_point = None;
+                vec![ToolMessage::ReleaseFocus]
             }
             MouseEventKind::Enter | MouseEventKind::Leave => {
                 self.initial_point = None;
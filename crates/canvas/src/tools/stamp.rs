@@ -0,0 +1,193 @@
+use std::f32::consts::PI;
+
+use color::{PremulColor, Srgb};
+use euclid::default::{Point2D, Size2D, Vector2D};
+use graphics::primitives::{ImageFit, Svg, SvgOptions};
+use graphics::{CanvasCoordinates, Primitive, Systems};
+use input::{Modifiers, MouseButton, MouseEvent, MouseEventKind};
+
+use crate::tools::{Tool, ToolMessage};
+
+/// Places copies of an svg at the cursor, one per click or spaced out along a drag.
+///
+/// Each stamp is committed as its own svg, with a random rotation and size within the jitter,
+/// and the stamps of a single drag are undone at once.
+#[derive(Clone, Debug)]
+pub struct StampTool {
+    /// The svg that is stamped, parsed once so that stamping only clones it.
+    template: Svg<CanvasCoordinates>,
+    /// The length of the longer side of a stamp, before the scale jitter.
+    pub size: f32,
+    /// The distance between stamps placed along a drag, zero only stamps where the drag starts.
+    pub spacing: f32,
+    /// The largest rotation either way of a stamp, in radians.
+    pub rotation_jitter: f32,
+    /// The largest change in size of a stamp, as a fraction of `size`.
+    pub scale_jitter: f32,
+    /// The current color, which the stamps are drawn in when `tint` is set.
+    pub color: PremulColor<Srgb>,
+    /// Whether to draw the whole stamp in the current color instead of the colors of the svg.
+    pub tint: bool,
+
+    /// Where the last stamp of the current drag was placed.
+    last: Option<Point2D<f32>>,
+    /// The state of the generator the jitter is drawn from.
+    seed: u32,
+}
+
+impl Default for StampTool {
+    fn default() -> Self {
+        Self {
+            template: template(include_bytes!("../../../../resources/star.svg").to_vec()),
+            size: 32.,
+            spacing: 48.,
+            rotation_jitter: PI / 12.,
+            scale_jitter: 0.2,
+            color: PremulColor::WHITE,
+            tint: true,
+
+            last: None,
+            seed: 0x9e37_79b9,
+        }
+    }
+}
+
+impl StampTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Changes the svg that is stamped.
+    pub fn set_source(&mut self, source: Vec<u8>) {
+        self.template = template(source);
+    }
+
+    /// A pseudo-random number in `-1..=1`, the jitter does not need to be any better than this.
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed as f32 / u32::MAX as f32) * 2. - 1.
+    }
+
+    /// A stamp centered on `position`.
+    fn stamp(&mut self, position: Point2D<f32>) -> Primitive<CanvasCoordinates> {
+        let size = self.size * (1. + self.scale_jitter * self.random()).max(0.);
+        let rotation = self.rotation_jitter * self.random();
+        let tint = self.tint.then_some(self.color);
+        let mut svg = self.template.clone();
+        svg.update_options(SvgOptions {
+            fill_color: tint,
+            stroke_color: tint,
+            ..STAMP_OPTIONS
+        });
+        // The stamp keeps the aspect of the svg, with its longer side being `size`.
+        let size = match svg.intrinsic_size() {
+            Some(intrinsic) if intrinsic.width > 0. && intrinsic.height > 0. => {
+                intrinsic * (size / intrinsic.width.max(intrinsic.height))
+            }
+            _ => Size2D::splat(size),
+        };
+        svg.update_rect(position - size.to_vector() / 2., size);
+        svg.set_rotation(rotation);
+        Primitive::Svg(svg)
+    }
+
+    /// Stamps every `spacing` along the straight line from the last stamp to `position`.
+    fn stamp_towards(&mut self, position: Point2D<f32>) -> Vec<ToolMessage> {
+        let Some(mut last) = self.last else {
+            return vec![];
+        };
+        if self.spacing <= 0. {
+            return vec![];
+        }
+        let mut messages = vec![];
+        let delta: Vector2D<f32> = position - last;
+        let mut remaining = delta.length();
+        while remaining >= self.spacing {
+            last += delta / delta.length() * self.spacing;
+            remaining -= self.spacing;
+            messages.push(ToolMessage::AppendCommit(self.stamp(last)));
+        }
+        self.last = Some(last);
+        messages
+    }
+}
+
+const STAMP_OPTIONS: SvgOptions = SvgOptions {
+    fill_color: None,
+    stroke_color: None,
+    fit: ImageFit::Fit,
+    lock_aspect: true,
+};
+
+fn template(source: Vec<u8>) -> Svg<CanvasCoordinates> {
+    Svg::new(Point2D::zero(), Size2D::zero(), source, STAMP_OPTIONS)
+}
+
+impl Tool for StampTool {
+    fn mouse_event(
+        &mut self,
+        _systems: &mut Systems,
+        event: MouseEvent,
+        _modifiers: Modifiers,
+    ) -> Vec<ToolMessage> {
+        match event.kind {
+            MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                self.last = Some(event.position);
+                vec![
+                    ToolMessage::SetFocus,
+                    ToolMessage::Commit(self.stamp(event.position)),
+                ]
+            }
+            MouseEventKind::Motion { .. } => self.stamp_towards(event.position),
+            MouseEventKind::Release { button, .. } if button == MouseButton::Left => {
+                self.last = None;
+                vec![ToolMessage::ReleaseFocus]
+            }
+            MouseEventKind::Leave if self.last.is_some() => {
+                self.last = None;
+                vec![ToolMessage::ReleaseFocus]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamps_take_the_current_color() {
+        let mut tool = StampTool::new();
+        let color = PremulColor::new([1., 0., 0., 1.]);
+        tool.color = color;
+        let Primitive::Svg(svg) = tool.stamp(Point2D::origin()) else {
+            panic!("stamps are svgs");
+        };
+        assert_eq!(svg.options().fill_color, Some(color));
+        assert_eq!(svg.options().stroke_color, Some(color));
+
+        tool.tint = false;
+        let Primitive::Svg(svg) = tool.stamp(Point2D::origin()) else {
+            panic!("stamps are svgs");
+        };
+        assert_eq!(svg.options().fill_color, None);
+    }
+
+    #[test]
+    fn stamps_along_a_drag_join_the_first_one() {
+        let mut tool = StampTool {
+            spacing: 10.,
+            ..StampTool::new()
+        };
+        tool.last = Some(Point2D::origin());
+        let messages = tool.stamp_towards(Point2D::new(25., 0.));
+        assert_eq!(messages.len(), 2);
+        assert!(messages
+            .iter()
+            .all(|message| matches!(message, ToolMessage::AppendCommit(_))));
+        assert_eq!(tool.last, Some(Point2D::new(20., 0.)));
+    }
+}
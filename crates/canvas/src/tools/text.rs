@@ -1,18 +1,48 @@
 use color::{PremulColor, Srgb};
-use euclid::default::{Box2D, Size2D};
+use euclid::default::{Box2D, Point2D, Size2D};
 use graphics::primitives::{Text, TextOptions};
-use graphics::{CanvasCoordinates, Drawable, Primitive, Systems};
+use graphics::{CanvasCoordinates, Drawable, Mesh, Primitive, Systems, Vertex, VertexKind};
 
-use crate::tools::{Tool, ToolMessage};
+use crate::tools::{Snapping, Tool, ToolMessage};
 use input::{
     Key, KeyboardEvent, KeyboardEventKind, Modifiers, MouseButton, MouseEvent, MouseEventKind,
     SpecialKey,
 };
 
+/// How close, in world units, the new text has to be to an existing edge or baseline to snap.
+const SNAP_DISTANCE: f32 = 8.;
+const GUIDE_WIDTH: f32 = 1.;
+/// How far the baseline guide extends past the cursor.
+const GUIDE_OVERHANG: f32 = 200.;
+const GUIDE_COLOR: PremulColor<Srgb> = PremulColor::new([0.3, 0.6, 1., 0.8]);
+
+/// Places and types text.
+///
+/// Clicking on existing text edits it again. Enter starts a new line. Ctrl+Enter, or clicking
+/// somewhere else, commits the text, and Escape discards it, or the changes to text that is
+/// edited again. Text that is empty when committed is discarded too.
 #[derive(Clone, Debug)]
 pub struct TextTool {
     current: Option<Primitive<CanvasCoordinates>>,
+    /// Whether `current` is existing text being edited again rather than new text.
+    editing: bool,
     pub color: PremulColor<Srgb>,
+    /// The left edge and first baseline of the existing text, kept up to date by the view.
+    pub anchors: Vec<Point2D<f32>>,
+    pub snapping: Snapping,
+    /// The font size and first baseline offset of new text, laying out text on every motion to
+    /// measure it again is slow.
+    baseline_offset: Option<(f32, f32)>,
+}
+
+/// Where new text would be placed, along with the anchors it snapped to.
+#[derive(Clone, Copy, Debug)]
+struct Placement {
+    /// The top-left of the text area.
+    origin: Point2D<f32>,
+    baseline: f32,
+    snapped_left: Option<Point2D<f32>>,
+    snapped_baseline: Option<Point2D<f32>>,
 }
 
 impl TextTool {
@@ -25,55 +55,195 @@ impl Default for TextTool {
     fn default() -> Self {
         Self {
             current: None,
+            editing: false,
             color: PremulColor::new([1., 1., 1., 1.]),
+            anchors: vec![],
+            snapping: Snapping::default(),
+            baseline_offset: None,
         }
     }
 }
 
+impl TextTool {
+    fn text_options(&self) -> TextOptions {
+        TextOptions {
+            fill: Some(self.color),
+            ..Default::default()
+        }
+    }
+
+    fn new_text(&self, origin: Point2D<f32>) -> Text<CanvasCoordinates> {
+        Text::new(
+            String::new(),
+            self.text_options(),
+            Box2D::from_origin_and_size(origin, Size2D::new(f32::MAX, f32::MAX)),
+        )
+    }
+
+    /// The distance from the top of new text to its first baseline, only measured again once the
+    /// font size changes.
+    fn baseline_offset(&mut self, systems: &mut Systems) -> f32 {
+        let font_size = self.text_options().font_size;
+        match self.baseline_offset {
+            Some((size, offset)) if size == font_size => offset,
+            _ => {
+                let offset = self.new_text(Point2D::zero()).first_baseline(systems);
+                self.baseline_offset = Some((font_size, offset));
+                offset
+            }
+        }
+    }
+
+    /// Snaps the left edge and baseline of text placed at the cursor to the nearest anchors.
+    fn placement(&self, position: Point2D<f32>, baseline_offset: f32, snap: bool) -> Placement {
+        let snapped_left = self
+            .anchors
+            .iter()
+            .copied()
+            .filter(|anchor| snap && (anchor.x - position.x).abs() <= SNAP_DISTANCE)
+            .min_by(|a, b| {
+                (a.x - position.x)
+                    .abs()
+                    .total_cmp(&(b.x - position.x).abs())
+            });
+        let mut baseline = position.y + baseline_offset;
+        let snapped_baseline = self
+            .anchors
+            .iter()
+            .copied()
+            .filter(|anchor| snap && (anchor.y - baseline).abs() <= SNAP_DISTANCE)
+            .min_by(|a, b| (a.y - baseline).abs().total_cmp(&(b.y - baseline).abs()));
+        if let Some(anchor) = snapped_baseline {
+            baseline = anchor.y;
+        }
+        let left = snapped_left.map_or(position.x, |anchor| anchor.x);
+        Placement {
+            origin: Point2D::new(left, baseline - baseline_offset),
+            baseline,
+            snapped_left,
+            snapped_baseline,
+        }
+    }
+
+    /// Edits existing text in place of the new text that was just started, returning the preview
+    /// of it.
+    pub fn edit(&mut self, systems: &mut Systems, text: Text<CanvasCoordinates>) -> Mesh<Vertex> {
+        self.editing = true;
+        self.current
+            .insert(Primitive::Text(text))
+            .render(systems)
+            .clone()
+    }
+
+    /// Commits the text being typed, or drops it if there is nothing visible to commit.
+    fn finish(&mut self) -> Vec<ToolMessage> {
+        let editing = std::mem::take(&mut self.editing);
+        match self.current.take() {
+            Some(Primitive::Text(text)) if !text.content().trim().is_empty() => {
+                let text = Primitive::Text(text);
+                if editing {
+                    vec![ToolMessage::CommitEdit(Some(text))]
+                } else {
+                    vec![ToolMessage::Commit(text)]
+                }
+            }
+            _ if editing => vec![ToolMessage::CommitEdit(None)],
+            _ => vec![ToolMessage::ClearScratch],
+        }
+    }
+
+    /// Discards the text being typed, text that was edited again goes back to what it was.
+    fn cancel(&mut self) -> Vec<ToolMessage> {
+        self.current = None;
+        let mut messages = vec![ToolMessage::ReleaseFocus, ToolMessage::ClearScratch];
+        if std::mem::take(&mut self.editing) {
+            messages.push(ToolMessage::CancelEdit);
+        }
+        messages
+    }
+
+    /// Draws the baseline the text would be placed on, along with guides to any snapped anchors.
+    fn guide_mesh(placement: &Placement) -> Mesh<Vertex> {
+        let kind = VertexKind::Color(GUIDE_COLOR);
+        let origin = placement.origin;
+        let (mut start, mut end) = (origin.x, origin.x + GUIDE_OVERHANG);
+        if let Some(anchor) = placement.snapped_baseline {
+            start = start.min(anchor.x);
+            end = end.max(anchor.x);
+        }
+        let mut mesh = Mesh::new_color_quad(
+            Box2D::new(
+                Point2D::new(start, placement.baseline),
+                Point2D::new(end, placement.baseline + GUIDE_WIDTH),
+            ),
+            kind,
+        );
+        if let Some(anchor) = placement.snapped_left {
+            let (top, bottom) = if anchor.y < placement.baseline {
+                (anchor.y, placement.baseline)
+            } else {
+                (placement.baseline, anchor.y)
+            };
+            mesh.append(&Mesh::new_color_quad(
+                Box2D::new(
+                    Point2D::new(origin.x, top),
+                    Point2D::new(origin.x + GUIDE_WIDTH, bottom),
+                ),
+                kind,
+            ));
+        }
+        mesh
+    }
+}
+
 impl Tool for TextTool {
     fn mouse_event(
         &mut self,
         systems: &mut Systems,
         event: MouseEvent,
-        _modifiers: Modifiers,
+        modifiers: Modifiers,
     ) -> Vec<ToolMessage> {
+        let snap = self.snapping.is_active(modifiers);
         match event.kind {
-            MouseEventKind::Enter | MouseEventKind::Leave => {
-                // self.editor = None;
-                self.current = None;
-                vec![ToolMessage::ReleaseFocus, ToolMessage::ClearScratch]
+            MouseEventKind::Enter | MouseEventKind::Leave => self.cancel(),
+            MouseEventKind::Motion { .. } if self.current.is_none() => {
+                let baseline_offset = self.baseline_offset(systems);
+                let placement = self.placement(event.position, baseline_offset, snap);
+                vec![ToolMessage::Scratch(Self::guide_mesh(&placement))]
             }
             MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
-                tracing::info!("adding a text element!!! with color: {:?}", self.color);
-                let current = self.current.get_or_insert(Primitive::Text(Text::new(
-                    String::new(),
-                    TextOptions {
-                        color: self.color,
-                        ..Default::default()
-                    },
-                    Box2D::from_origin_and_size(event.position, Size2D::new(f32::MAX, f32::MAX)),
-                )));
-
-                vec![
+                // Clicking away from the text being typed commits it and starts a new one, unless
+                // there is text to edit under the cursor.
+                let mut messages = self.finish();
+                let baseline_offset = self.baseline_offset(systems);
+                let mut text = self.new_text(event.position);
+                let placement = self.placement(event.position, baseline_offset, snap);
+                text.translate(placement.origin - event.position);
+                let current = self.current.insert(Primitive::Text(text));
+
+                messages.extend([
                     ToolMessage::SetFocus,
                     ToolMessage::Scratch(current.render(systems).clone()),
-                ]
+                    ToolMessage::EditText(event.position),
+                ]);
+                messages
             }
             _ => vec![],
         }
     }
     fn keyboard_event(&mut self, systems: &mut Systems, event: KeyboardEvent) -> Vec<ToolMessage> {
-        tracing::info!("received kb event: {event:?}");
         match event.kind {
             KeyboardEventKind::Press(key) => {
-                if key == Key::SpecialKey(SpecialKey::Enter) {
-                    tracing::info!("finalising text tool");
-                    if let Some(current) = self.current.take() {
-                        return vec![ToolMessage::ReleaseFocus, ToolMessage::Commit(current)];
-                    } else {
-                        return vec![ToolMessage::ReleaseFocus];
-                    }
+                if key == Key::SpecialKey(SpecialKey::Enter)
+                    && event.modifiers.intersects(Modifiers::CTRL)
+                {
+                    let mut messages = vec![ToolMessage::ReleaseFocus];
+                    messages.extend(self.finish());
+                    return messages;
                 };
+                if key == Key::SpecialKey(SpecialKey::Escape) {
+                    return self.cancel();
+                }
 
                 if key == Key::SpecialKey(input::SpecialKey::Tab) {
                     if self.color == PremulColor::WHITE {
@@ -88,26 +258,21 @@ impl Tool for TextTool {
                 };
                 match current {
                     Primitive::Text(text) => {
-                        let mut content = text.content();
+                        let content = text.content();
                         match key {
                             Key::SpecialKey(special_key) => match special_key {
+                                SpecialKey::Enter => text.insert_at(content.len(), "\n"),
                                 SpecialKey::Delete | SpecialKey::Backspace => {
-                                    content.pop();
-                                }
-                                SpecialKey::Left => {
-                                    content =
-                                        "test\nsomething multiline\n with more lines".to_string();
+                                    if let Some((index, _)) = content.char_indices().next_back() {
+                                        text.delete_range(index..content.len());
+                                    }
                                 }
-                                SpecialKey::Right => todo!(),
-                                SpecialKey::Up => todo!(),
-                                SpecialKey::Down => todo!(),
+                                // Text is always typed at its end, there is no cursor to move.
                                 _ => {}
                             },
-                            Key::Character(str) => content.push_str(str.as_str()),
-                            Key::Unknown => {}
+                            Key::Character(str) => text.insert_at(content.len(), str.as_str()),
+                            Key::Unknown { .. } => {}
                         }
-                        tracing::info!("content is now: {content:?}");
-                        text.set_content(content);
                     }
                     _ => unreachable!("text tool should only contain a text primitive"),
                 }
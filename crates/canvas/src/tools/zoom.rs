@@ -1,6 +1,6 @@
 use crate::tools::{Tool, ToolMessage};
 use graphics::Systems;
-use input::{Modifiers, MouseButton, MouseEvent, MouseEventKind};
+use input::{CursorIcon, Modifiers, MouseButton, MouseEvent, MouseEventKind};
 
 #[derive(Clone, Debug, Copy, Default, PartialEq)]
 pub struct ZoomTool {}
@@ -15,10 +15,14 @@ impl Tool for ZoomTool {
         &mut self,
         _systems: &mut Systems,
         event: MouseEvent,
-        _modifiers: Modifiers,
+        modifiers: Modifiers,
     ) -> Vec<ToolMessage> {
         match event.kind {
             MouseEventKind::Press { button, .. } => match button {
+                // Holding Alt swaps the left click to zooming out.
+                MouseButton::Left if modifiers.intersects(Modifiers::ALT) => {
+                    vec![ToolMessage::ZoomOut(event.position)]
+                }
                 MouseButton::Left => vec![ToolMessage::ZoomIn(event.position)],
                 MouseButton::Right => vec![ToolMessage::ZoomOut(event.position)],
                 MouseButton::Middle => vec![ToolMessage::ResetZoom],
@@ -27,4 +31,10 @@ impl Tool for ZoomTool {
             _ => vec![],
         }
     }
+
+    fn cursor(&self, modifiers: Modifiers) -> Option<CursorIcon> {
+        modifiers
+            .intersects(Modifiers::ALT)
+            .then_some(CursorIcon::ZoomOut)
+    }
 }
@@ -0,0 +1,341 @@
+use graphics::primitives::{RectangleOptions, TextOptions};
+use graphics::Rounding;
+
+use gui::modal::{Modal, ModalAction, ModalOptions, ModalResult};
+use gui::prelude::*;
+use gui::tree::UITree;
+use gui::widgets::{ContainerWidget, TextWidget, Widget};
+use input::{Key, KeyboardEvent, KeyboardEventKind, MouseEventKind, SpecialKey};
+
+use crate::tools::{EnabledTools, ToolKind};
+use crate::ui::palette::{PaletteEntry, Shortcuts};
+use crate::ui::styles::colors;
+use crate::ui::Message;
+
+/// The maximum amount of shortcuts shown at once, the rest are reached by scrolling.
+const VISIBLE_ROWS: usize = 12;
+const PANEL_WIDTH: f32 = 460.;
+const KEYS_WIDTH: f32 = 140.;
+/// The width of the panel taken up by its padding, stroke and the gap between the columns.
+const PANEL_INSET: f32 = 2. * 10. + 2. * 3. + 8.;
+const ROW_HEIGHT: f32 = 24.;
+
+/// A key combination and what it does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HelpEntry {
+    pub keys: String,
+    pub description: String,
+}
+
+impl HelpEntry {
+    fn new(keys: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            keys: keys.into(),
+            description: description.into(),
+        }
+    }
+
+    /// Every shortcut of the view, from the bound shortcuts and the modifiers of the enabled
+    /// tools.
+    pub fn all(shortcuts: &Shortcuts, enabled_tools: &EnabledTools) -> Vec<Self> {
+        let palette = PaletteEntry::all(enabled_tools);
+        let mut entries = vec![
+            HelpEntry::new("? / F1", "Show this help"),
+            HelpEntry::new("Ctrl+P", "Open the command palette"),
+        ];
+        entries.extend(shortcuts.0.iter().map(|(key, action)| {
+            let label = palette
+                .iter()
+                .find(|entry| entry.action == *action)
+                .map_or_else(|| format!("{action:?}"), |entry| entry.label.clone());
+            HelpEntry::new(format!("Ctrl+{}", key.to_ascii_uppercase()), label)
+        }));
+        entries.extend([
+            HelpEntry::new("Ctrl+C", "Edit: Copy"),
+            HelpEntry::new("Ctrl+X", "Edit: Cut"),
+            HelpEntry::new("Ctrl+V", "Edit: Paste"),
+            HelpEntry::new("Arrows", "Nudge the selection"),
+            HelpEntry::new("Shift+Arrows", "Nudge the selection further"),
+            HelpEntry::new("Hold Ctrl", "Invert snapping"),
+        ]);
+
+        let shapes = [ToolKind::Rectangle, ToolKind::Ellipse];
+        if shapes.iter().any(|tool| enabled_tools.is_enabled(*tool)) {
+            entries.extend([
+                HelpEntry::new("Hold Shift", "Shapes: Keep square"),
+                HelpEntry::new("Hold Alt", "Shapes: Draw from the center"),
+            ]);
+        }
+        if enabled_tools.is_enabled(ToolKind::Angle) {
+            entries.extend([
+                HelpEntry::new("Hold Alt", "Angle: Measure the reflex angle"),
+                HelpEntry::new("Escape", "Angle: Cancel the measurement"),
+            ]);
+        }
+        if enabled_tools.is_enabled(ToolKind::Pen) {
+            entries.push(HelpEntry::new("Tab", "Pen: Swap black and white"));
+        }
+        if enabled_tools.is_enabled(ToolKind::Text) {
+            entries.extend([
+                HelpEntry::new("Ctrl+Enter", "Text: Finish"),
+                HelpEntry::new("Escape", "Text: Discard the text, or the changes to it"),
+            ]);
+        }
+        if enabled_tools.is_enabled(ToolKind::Note) {
+            entries.extend([
+                HelpEntry::new("Enter", "Note: Finish"),
+                HelpEntry::new("Escape", "Note: Discard the note"),
+            ]);
+        }
+        entries
+    }
+}
+
+/// A modal listing the keyboard shortcuts, toggled with `?` or F1.
+///
+/// The list is rebuilt every time the overlay opens, so it always shows the shortcuts that are
+/// bound at that moment.
+pub struct ShortcutsHelp {
+    modal: Modal,
+    list_node: NodeId,
+    /// The (row, keys, description) nodes of each row in the list.
+    rows: Vec<(NodeId, NodeId, NodeId)>,
+    /// The amount of rows currently attached to the list.
+    attached_rows: usize,
+
+    entries: Vec<HelpEntry>,
+    /// The index of the entry shown in the first row.
+    scroll: usize,
+}
+
+impl ShortcutsHelp {
+    pub fn build(tree: &mut UITree<Widget<Message>>) -> Self {
+        let modal = Modal::build(
+            tree,
+            ModalOptions {
+                title: "Keyboard Shortcuts".to_string(),
+                actions: vec![ModalAction::new("Close", ModalResult::Cancel)],
+                default_result: ModalResult::Cancel,
+                close_on_backdrop: true,
+                panel: RectangleOptions {
+                    color: colors::BACKGROUND.into(),
+                    rounding: Rounding::all(5.),
+                    stroke_width: 3.,
+                    stroke_color: colors::BORDER.into(),
+                    box_sizing: graphics::BoxSizing::ContentBox,
+                },
+                text: Self::text_options(),
+                width: PANEL_WIDTH,
+                ..Default::default()
+            },
+            Message::HelpResult,
+        );
+
+        let list = ContainerWidget::new(false).mouse_handler(|_, ctx| {
+            if let MouseEventKind::Axis { vertical, .. } = ctx.payload().kind {
+                if vertical.discrete != 0 {
+                    ctx.push_messages(vec![Message::HelpScroll(vertical.discrete.signum())]);
+                } else if vertical.absolute != 0. {
+                    ctx.push_messages(vec![Message::HelpScroll(vertical.absolute.signum() as i32)]);
+                }
+            }
+        });
+        let list_node = tree.new_leaf(
+            list.as_widget(),
+            Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                gap: Size::<LengthPercentage>::length(2.),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(modal.content_node(), list_node);
+
+        let rows = (0..VISIBLE_ROWS)
+            .map(|_| {
+                let row_node = tree.new_leaf(
+                    ContainerWidget::new(false).as_widget(),
+                    Style {
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Row,
+                        gap: Size::<LengthPercentage>::length(8.),
+                        ..Style::DEFAULT
+                    },
+                );
+                let keys_node = tree.new_leaf(
+                    TextWidget::new(
+                        String::new(),
+                        TextOptions {
+                            fill: Some(colors::BORDER),
+                            ..Self::text_options()
+                        },
+                    )
+                    .as_widget(),
+                    Style {
+                        size: Size::<Dimension>::from_lengths(KEYS_WIDTH, ROW_HEIGHT),
+                        ..Style::DEFAULT
+                    },
+                );
+                let description_node = tree.new_leaf(
+                    TextWidget::new(String::new(), Self::text_options()).as_widget(),
+                    Style {
+                        size: Size::<Dimension>::from_lengths(
+                            PANEL_WIDTH - KEYS_WIDTH - PANEL_INSET,
+                            ROW_HEIGHT,
+                        ),
+                        ..Style::DEFAULT
+                    },
+                );
+                tree.add_child(row_node, keys_node);
+                tree.add_child(row_node, description_node);
+                (row_node, keys_node, description_node)
+            })
+            .collect();
+
+        Self {
+            modal,
+            list_node,
+            rows,
+            attached_rows: 0,
+
+            entries: vec![],
+            scroll: 0,
+        }
+    }
+
+    pub const fn is_open(&self) -> bool {
+        self.modal.is_open()
+    }
+
+    /// Whether the event is one of the shortcuts that toggle the overlay, `?` or F1.
+    pub fn is_toggle_shortcut(event: &KeyboardEvent) -> bool {
+        match &event.kind {
+            KeyboardEventKind::Press(Key::SpecialKey(SpecialKey::F1)) => true,
+            KeyboardEventKind::Press(Key::Character(text)) => text == "?",
+            _ => false,
+        }
+    }
+
+    pub fn open(&mut self, tree: &mut UITree<Widget<Message>>, entries: Vec<HelpEntry>) {
+        self.entries = entries;
+        self.scroll = 0;
+        self.update_rows(tree);
+        self.modal.open(tree);
+    }
+
+    pub fn close(&mut self, tree: &mut UITree<Widget<Message>>) {
+        self.modal.close(tree);
+    }
+
+    /// Handles a keyboard event while the overlay is open, it takes all keyboard input.
+    pub fn keyboard_event(&mut self, tree: &mut UITree<Widget<Message>>, event: &KeyboardEvent) {
+        if Self::is_toggle_shortcut(event) {
+            self.close(tree);
+            return;
+        }
+        match &event.kind {
+            KeyboardEventKind::Press(Key::SpecialKey(SpecialKey::Up)) => self.scroll(tree, -1),
+            KeyboardEventKind::Press(Key::SpecialKey(SpecialKey::Down)) => self.scroll(tree, 1),
+            KeyboardEventKind::Press(Key::SpecialKey(SpecialKey::PageUp)) => {
+                self.scroll(tree, -(VISIBLE_ROWS as i32))
+            }
+            KeyboardEventKind::Press(Key::SpecialKey(SpecialKey::PageDown)) => {
+                self.scroll(tree, VISIBLE_ROWS as i32)
+            }
+            _ => {
+                self.modal.keyboard_event(tree, event);
+            }
+        }
+    }
+
+    /// Scrolls the visible rows by the given amount of entries.
+    pub fn scroll(&mut self, tree: &mut UITree<Widget<Message>>, delta: i32) {
+        let max_scroll = self.entries.len().saturating_sub(VISIBLE_ROWS);
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(delta as isize)
+            .min(max_scroll);
+        self.update_rows(tree);
+    }
+
+    /// Syncs the attached rows with the entries and the scroll.
+    fn update_rows(&mut self, tree: &mut UITree<Widget<Message>>) {
+        let visible = self
+            .entries
+            .len()
+            .saturating_sub(self.scroll)
+            .min(VISIBLE_ROWS);
+        while self.attached_rows > visible {
+            self.attached_rows -= 1;
+            tree.remove_child(self.list_node, self.rows[self.attached_rows].0);
+        }
+        while self.attached_rows < visible {
+            tree.add_child(self.list_node, self.rows[self.attached_rows].0);
+            self.attached_rows += 1;
+        }
+
+        for (row, (_, keys, description)) in self.rows.iter().take(visible).enumerate() {
+            let entry = &self.entries[self.scroll + row];
+            if let Some(keys) = tree.get_node_mut(*keys).as_text_mut() {
+                keys.update_content(entry.keys.clone());
+            }
+            if let Some(description) = tree.get_node_mut(*description).as_text_mut() {
+                description.update_content(entry.description.clone());
+            }
+        }
+    }
+
+    fn text_options() -> TextOptions {
+        TextOptions {
+            fill: Some(colors::FOREGROUND),
+            font_size: 16.,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::palette::PaletteAction;
+
+    fn descriptions(entries: &[HelpEntry], keys: &str) -> Vec<String> {
+        entries
+            .iter()
+            .filter(|entry| entry.keys == keys)
+            .map(|entry| entry.description.clone())
+            .collect()
+    }
+
+    #[test]
+    fn shortcuts_are_labelled_like_the_palette() {
+        let entries = HelpEntry::all(&Shortcuts::default(), &EnabledTools::all());
+        assert_eq!(descriptions(&entries, "Ctrl+Z"), ["Edit: Undo"]);
+        // Every bound action has a palette entry, so none fall back to their debug name.
+        for (_, action) in &Shortcuts::default().0 {
+            let debug = format!("{action:?}");
+            assert!(entries.iter().all(|entry| entry.description != debug));
+        }
+
+        let custom = Shortcuts(vec![('u', PaletteAction::Undo)]);
+        let entries = HelpEntry::all(&custom, &EnabledTools::all());
+        assert_eq!(descriptions(&entries, "Ctrl+U"), ["Edit: Undo"]);
+        assert!(descriptions(&entries, "Ctrl+Z").is_empty());
+    }
+
+    #[test]
+    fn only_enabled_tools_list_their_modifiers() {
+        let entries = HelpEntry::all(&Shortcuts::default(), &EnabledTools::all());
+        assert_eq!(descriptions(&entries, "Enter"), ["Note: Finish"]);
+        assert_eq!(
+            descriptions(&entries, "Hold Shift"),
+            ["Shapes: Keep square"]
+        );
+
+        let entries = HelpEntry::all(&Shortcuts::default(), &EnabledTools::only([ToolKind::Pen]));
+        assert_eq!(descriptions(&entries, "Tab"), ["Pen: Swap black and white"]);
+        assert!(descriptions(&entries, "Enter").is_empty());
+        assert!(descriptions(&entries, "Hold Shift").is_empty());
+        assert!(descriptions(&entries, "Escape").is_empty());
+    }
+}
@@ -0,0 +1,349 @@
+use color::{PremulColor, Srgb};
+use euclid::default::Point2D;
+use graphics::primitives::{RectangleOptions, TextOptions};
+use graphics::Rounding;
+
+use gui::prelude::*;
+use gui::tree::{UITree, ZIndexProperties};
+use gui::widgets::background::reserve_stroke;
+use gui::widgets::button::{ButtonOptions, FADE_DURATION};
+use gui::widgets::{BackgroundWidget, ButtonWidget, ContainerWidget, TextWidget, Widget};
+use input::{CursorIcon, MouseButton, MouseEventKind};
+
+use crate::scene::{Layer, LayerId};
+use crate::ui::options::grab_fn;
+use crate::ui::styles::{colors, floating_grab};
+use crate::ui::Message;
+
+/// The maximum amount of layers shown at once, the rest are reached by scrolling.
+const VISIBLE_ROWS: usize = 6;
+const LABEL_WIDTH: f32 = 120.;
+const ROW_HEIGHT: f32 = 24.;
+
+#[derive(Clone, Copy, Debug)]
+pub enum LayersMessage {
+    /// Makes the layer shown in the given row the active layer.
+    Select(usize),
+    ToggleVisible(usize),
+    ToggleLocked(usize),
+    Add,
+    Scroll(i32),
+}
+
+impl From<LayersMessage> for Message {
+    fn from(value: LayersMessage) -> Self {
+        Message::Layers(value)
+    }
+}
+
+struct LayerRow {
+    background: NodeId,
+    label: NodeId,
+    visible: NodeId,
+    locked: NodeId,
+}
+
+/// A floating panel listing the layers of the scene, top-most first.
+pub struct LayersPanel {
+    pub grab_area_node: NodeId,
+    list_node: NodeId,
+    rows: Vec<LayerRow>,
+    /// The amount of rows currently attached to the list.
+    attached_rows: usize,
+    /// The layer shown in each attached row.
+    row_layers: Vec<LayerId>,
+    /// The index of the first visible layer, counting from the top-most layer.
+    scroll: usize,
+}
+
+impl LayersPanel {
+    pub fn build(tree: &mut UITree<Widget<Message>>, root: NodeId) -> Self {
+        let grab_area = ContainerWidget::new(true).mouse_handler(grab_fn);
+        let grab_area_node = tree.new_leaf_with_z(
+            grab_area.as_widget(),
+            floating_grab(100., Point2D::new(900., 100.)),
+            ZIndexProperties {
+                z_index: 2,
+                isolate_z: true,
+            },
+        );
+        tree.add_child(root, grab_area_node);
+
+        let background = RectangleOptions {
+            color: colors::BACKGROUND.into(),
+            rounding: Rounding::all(5.),
+            stroke_width: 3.,
+            stroke_color: colors::BORDER.into(),
+            box_sizing: graphics::BoxSizing::ContentBox,
+        };
+        let background_node = tree.new_leaf(
+            BackgroundWidget::new(background).as_widget(),
+            reserve_stroke(
+                &background,
+                Style {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    padding: Rect::<LengthPercentage>::length(10.),
+                    gap: Size::<LengthPercentage>::length(8.),
+                    ..Style::DEFAULT
+                },
+            ),
+        );
+        tree.add_child(grab_area_node, background_node);
+
+        let header_node = tree.new_leaf(
+            ContainerWidget::new(false).as_widget(),
+            Style {
+                display: Display::Flex,
+                align_items: Some(AlignItems::Center),
+                gap: Size::<LengthPercentage>::length(6.),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(background_node, header_node);
+        let title_node = tree.new_leaf(
+            TextWidget::new("Layers".to_string(), Self::text_options()).as_widget(),
+            Style {
+                size: Size::<Dimension>::from_lengths(
+                    LABEL_WIDTH + 2. * (ROW_HEIGHT - 4.) + 6.,
+                    ROW_HEIGHT,
+                ),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(header_node, title_node);
+        let add_node = Self::toggle_button(tree, "+", false, LayersMessage::Add);
+        tree.add_child(header_node, add_node);
+
+        let list = ContainerWidget::new(false).mouse_handler(|_, ctx| {
+            if let MouseEventKind::Axis { vertical, .. } = ctx.payload().kind {
+                if vertical.discrete != 0 {
+                    ctx.push_messages(vec![
+                        LayersMessage::Scroll(vertical.discrete.signum()).into()
+                    ]);
+                } else if vertical.absolute != 0. {
+                    ctx.push_messages(vec![LayersMessage::Scroll(
+                        vertical.absolute.signum() as i32
+                    )
+                    .into()]);
+                }
+            }
+        });
+        let list_node = tree.new_leaf(
+            list.as_widget(),
+            Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                gap: Size::<LengthPercentage>::length(2.),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(background_node, list_node);
+
+        let rows = (0..VISIBLE_ROWS)
+            .map(|row| {
+                let background =
+                    BackgroundWidget::new(Self::row_options(false)).mouse_handler(move |_, ctx| {
+                        match ctx.payload().kind {
+                            MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                                ctx.push_messages(vec![LayersMessage::Select(row).into()]);
+                                ctx.stop_propagation();
+                            }
+                            _ => {}
+                        }
+                    });
+                let background = tree.new_leaf(
+                    background.as_widget(),
+                    Style {
+                        display: Display::Flex,
+                        align_items: Some(AlignItems::Center),
+                        gap: Size::<LengthPercentage>::length(6.),
+                        padding: Rect::<LengthPercentage>::length(2.),
+                        ..Style::DEFAULT
+                    },
+                );
+                let visible =
+                    Self::toggle_button(tree, "V", true, LayersMessage::ToggleVisible(row));
+                let locked =
+                    Self::toggle_button(tree, "L", false, LayersMessage::ToggleLocked(row));
+                let label = tree.new_leaf(
+                    TextWidget::new(String::new(), Self::text_options()).as_widget(),
+                    Style {
+                        size: Size::<Dimension>::from_lengths(LABEL_WIDTH, ROW_HEIGHT - 4.),
+                        ..Style::DEFAULT
+                    },
+                );
+                tree.add_child(background, visible);
+                tree.add_child(background, locked);
+                tree.add_child(background, label);
+                LayerRow {
+                    background,
+                    label,
+                    visible,
+                    locked,
+                }
+            })
+            .collect();
+
+        Self {
+            grab_area_node,
+            list_node,
+            rows,
+            attached_rows: 0,
+            row_layers: vec![],
+            scroll: 0,
+        }
+    }
+
+    /// The layer shown in the given row.
+    pub fn layer_at_row(&self, row: usize) -> Option<LayerId> {
+        self.row_layers.get(row).copied()
+    }
+
+    /// Scrolls the list by the given amount of layers.
+    pub fn scroll(
+        &mut self,
+        tree: &mut UITree<Widget<Message>>,
+        layers: &[Layer],
+        active: LayerId,
+        delta: i32,
+    ) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(delta as isize)
+            .min(layers.len().saturating_sub(VISIBLE_ROWS));
+        self.update(tree, layers, active);
+    }
+
+    /// Syncs the rows with the layers of the scene.
+    pub fn update(
+        &mut self,
+        tree: &mut UITree<Widget<Message>>,
+        layers: &[Layer],
+        active: LayerId,
+    ) {
+        self.scroll = self.scroll.min(layers.len().saturating_sub(VISIBLE_ROWS));
+        let shown = layers
+            .iter()
+            .rev()
+            .skip(self.scroll)
+            .take(VISIBLE_ROWS)
+            .collect::<Vec<_>>();
+
+        while self.attached_rows > shown.len() {
+            self.attached_rows -= 1;
+            tree.remove_child(self.list_node, self.rows[self.attached_rows].background);
+        }
+        while self.attached_rows < shown.len() {
+            tree.add_child(self.list_node, self.rows[self.attached_rows].background);
+            self.attached_rows += 1;
+        }
+
+        for (row, layer) in self.rows.iter().zip(&shown) {
+            if let Some(label) = tree.get_node_mut(row.label).as_text_mut() {
+                label.update_content(layer.name.clone());
+            }
+            if let Some(background) = tree.get_node_mut(row.background).as_background_mut() {
+                background.change_options(Self::row_options(layer.id == active));
+            }
+            if let Some(button) = tree.get_node_mut(row.visible).as_button_mut() {
+                button.set_active(layer.visible);
+            }
+            if let Some(button) = tree.get_node_mut(row.locked).as_button_mut() {
+                button.set_active(layer.locked);
+            }
+        }
+        self.row_layers = shown.iter().map(|layer| layer.id).collect();
+    }
+
+    /// A small square button whose active state shows whether the toggle is set.
+    fn toggle_button(
+        tree: &mut UITree<Widget<Message>>,
+        text: &str,
+        active: bool,
+        message: LayersMessage,
+    ) -> NodeId {
+        let options = |color: PremulColor<Srgb>| RectangleOptions {
+            color: color.into(),
+            rounding: Rounding::all(3.),
+            stroke_width: 1.,
+            stroke_color: colors::BORDER.into(),
+            ..Default::default()
+        };
+        let button = ButtonWidget::new(
+            ButtonOptions {
+                pressed: options(PremulColor::new([0.45, 0.45, 0.45, 1.])),
+                active: options(PremulColor::new([0.35, 0.35, 0.35, 1.])),
+                hovered: options(PremulColor::new([0.2, 0.2, 0.2, 1.])),
+                normal: options(colors::BACKGROUND),
+                disabled: options(PremulColor::new([0.3, 0.14, 0.14, 0.5])),
+            },
+            true,
+            active,
+            FADE_DURATION,
+        )
+        .mouse_handler(move |_, ctx| {
+            match ctx.payload().kind {
+                MouseEventKind::Enter => {
+                    ctx.push_messages(vec![Message::CursorIcon(CursorIcon::Pointer)])
+                }
+                MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                    ctx.push_messages(vec![message.into()])
+                }
+                _ => {}
+            };
+            match ctx.current_phase() {
+                EventPhase::Direct | EventPhase::AtTarget | EventPhase::Bubbling => {
+                    ctx.stop_propagation();
+                }
+                _ => {}
+            }
+        });
+        let button_node = tree.new_leaf(
+            button.as_widget(),
+            Style {
+                display: Display::Flex,
+                justify_content: Some(JustifyContent::Center),
+                align_items: Some(AlignItems::Center),
+                size: Size::<Dimension>::from_lengths(ROW_HEIGHT - 4., ROW_HEIGHT - 4.),
+                ..Style::DEFAULT
+            },
+        );
+        let text_node = tree.new_leaf(
+            TextWidget::new(
+                text.to_string(),
+                TextOptions {
+                    font_size: 12.,
+                    ..Self::text_options()
+                },
+            )
+            .as_widget(),
+            Style {
+                size: Size::<Dimension>::from_lengths(8., 14.),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(button_node, text_node);
+        button_node
+    }
+
+    fn text_options() -> TextOptions {
+        TextOptions {
+            fill: Some(colors::FOREGROUND),
+            font_size: 16.,
+            ..Default::default()
+        }
+    }
+
+    fn row_options(active: bool) -> RectangleOptions {
+        RectangleOptions {
+            color: if active {
+                PremulColor::new([0.25, 0.25, 0.25, 1.]).into()
+            } else {
+                colors::BACKGROUND.into()
+            },
+            rounding: Rounding::all(5.),
+            ..Default::default()
+        }
+    }
+}
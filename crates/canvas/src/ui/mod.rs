@@ -5,15 +5,30 @@ use input::{CursorIcon, Modifiers};
 use renderer::GrowableMeshBuffer;
 
 use crate::{
-    tools::{ToolKind, ToolNodeMap},
+    tools::{EnabledTools, Snapping, ToolKind, ToolNodeMap},
+    ui::help::ShortcutsHelp,
+    ui::layers::{LayersMessage, LayersPanel},
     ui::options::{OptionsMessage, OptionsTree},
+    ui::palette::CommandPalette,
+    ui::session::UnsavedChangesPrompt,
+    ui::status::StatusLine,
     RedrawRequest,
 };
 
+pub mod help;
+pub mod layers;
 pub mod options;
+pub mod palette;
+pub mod session;
+pub mod settings;
+pub mod status;
 pub mod styles;
 pub mod toolbar;
 
+/// How far the pointer has to move from where a grab area was pressed before the panel moves,
+/// in pixels.
+pub const GRAB_THRESHOLD: f32 = 4.;
+
 #[derive(Clone, Copy, Debug)]
 pub enum Message {
     CursorIcon(input::CursorIcon),
@@ -25,6 +40,25 @@ pub enum Message {
 
     SwapTool(ToolKind),
     ToolOptions(OptionsMessage),
+    /// Snapping is shared with the tools, so this is handled by the view.
+    ToggleSnapping,
+
+    ClosePalette,
+    /// Runs the palette entry shown in the given row, this is handled by the view since actions
+    /// can reach outside of the gui.
+    PaletteSelect(usize),
+    PaletteScroll(i32),
+
+    /// The shortcuts help was closed with one of its buttons or by clicking outside of it.
+    HelpResult(gui::modal::ModalResult),
+    HelpScroll(i32),
+
+    /// The unsaved changes prompt was answered, this is handled by the view as it opens the
+    /// pending session.
+    SessionConfirm(gui::modal::ModalResult),
+
+    /// Layer changes are handled by the view, as the layers live in the canvas scene.
+    Layers(LayersMessage),
 }
 
 pub struct Application {
@@ -32,11 +66,23 @@ pub struct Application {
     pub gui_buffer: GrowableMeshBuffer,
 
     pub drag_start: Option<DragState>,
+    /// How far a grab has to move before the panel follows it, so a click does not nudge it.
+    pub grab_threshold: f32,
 
     pub selected_tool: ToolKind,
+    pub enabled_tools: EnabledTools,
 
     pub tool_nodes: ToolNodeMap,
+    pub snapping: Snapping,
+    /// The toolbar button showing whether snapping is enabled.
+    pub snapping_node: NodeId,
     pub options: OptionsTree,
+    pub palette: CommandPalette,
+    pub help: ShortcutsHelp,
+    pub layers: LayersPanel,
+    /// The hint for the selected tool along the bottom of the viewport.
+    pub status: StatusLine,
+    pub unsaved_changes: UnsavedChangesPrompt,
 
     pub modifiers: Modifiers,
 }
@@ -47,6 +93,8 @@ pub struct DragState {
     origin: Point2D<f32>,
     /// Where to calculate the change in position from.
     start: Point2D<f32>,
+    /// Whether the pointer has moved past the grab threshold, until then the panel stays put.
+    moving: bool,
 }
 
 pub fn handle_message<T: RedrawRequest + Clone + 'static>(
@@ -60,6 +108,9 @@ pub fn handle_message<T: RedrawRequest + Clone + 'static>(
         Message::CursorIcon(icon) => {
             cursor_icon = Some(*icon);
         }
+        Message::SwapTool(tool_kind) if !app.enabled_tools.is_enabled(*tool_kind) => {
+            tracing::warn!("ignoring a swap to the disabled {tool_kind:?} tool");
+        }
         Message::SwapTool(tool_kind) => {
             tracing::info!(
                 "swapping tool from {:?} to {:?}",
@@ -67,15 +118,21 @@ pub fn handle_message<T: RedrawRequest + Clone + 'static>(
                 tool_kind
             );
 
-            app.gui
-                .get_node_mut(app.selected_tool.get_node_id(&app.tool_nodes))
-                .as_button_mut()
-                .map(|x| x.set_active(false));
+            if let Some(node) = app.selected_tool.get_node_id(&app.tool_nodes) {
+                app.gui
+                    .get_node_mut(node)
+                    .as_button_mut()
+                    .map(|x| x.set_active(false));
+            }
             app.selected_tool = *tool_kind;
-            app.gui
-                .get_node_mut(app.selected_tool.get_node_id(&app.tool_nodes))
-                .as_button_mut()
-                .map(|x| x.set_active(true));
+            app.status
+                .update(&mut app.gui, app.selected_tool, app.modifiers);
+            if let Some(node) = app.selected_tool.get_node_id(&app.tool_nodes) {
+                app.gui
+                    .get_node_mut(node)
+                    .as_button_mut()
+                    .map(|x| x.set_active(true));
+            }
 
             tracing::info!("called swap tool to : {tool_kind:?}");
         }
@@ -84,11 +141,18 @@ pub fn handle_message<T: RedrawRequest + Clone + 'static>(
             app.drag_start = Some(DragState {
                 origin: Point2D::new(style.location.x, style.location.y),
                 start: *point,
+                moving: false,
             });
         }
         Message::HandleGrabMove(point) => {
-            if let Some(state) = app.drag_start {
+            if let Some(state) = &mut app.drag_start {
                 cursor_icon = Some(CursorIcon::Grabbing);
+                if !state.moving {
+                    if (*point - state.start).length() < app.grab_threshold {
+                        return cursor_icon;
+                    }
+                    state.moving = true;
+                }
                 let new_origin = (state.origin + (*point - state.start)).round();
                 // TODO: constant
                 let grab_style = styles::floating_grab(100., new_origin);
@@ -137,6 +201,26 @@ pub fn handle_message<T: RedrawRequest + Clone + 'static>(
         Message::ToolOptions(message) => {
             app.options.update(&mut app.gui, message);
         }
+        Message::ClosePalette => {
+            app.palette.close(&mut app.gui);
+            redraw_manager.request_redraw();
+        }
+        Message::PaletteScroll(delta) => {
+            app.palette.scroll(&mut app.gui, *delta);
+            redraw_manager.request_redraw();
+        }
+        Message::HelpResult(_) => {
+            app.help.close(&mut app.gui);
+            redraw_manager.request_redraw();
+        }
+        Message::HelpScroll(delta) => {
+            app.help.scroll(&mut app.gui, *delta);
+            redraw_manager.request_redraw();
+        }
+        Message::PaletteSelect(_)
+        | Message::Layers(_)
+        | Message::ToggleSnapping
+        | Message::SessionConfirm(_) => {}
     };
     cursor_icon
 }
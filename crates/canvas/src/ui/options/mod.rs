@@ -1,13 +1,20 @@
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
-use color::{Hsl, PremulColor};
+use color::{Hsl, PremulColor, Srgb};
 use euclid::default::Point2D;
 use graphics::primitives::{RectangleOptions, TextOptions};
 use graphics::{BasicLinearGradient, Rounding};
 
 use gui::prelude::*;
 use gui::tree::{UITree, ZIndexProperties};
-use gui::widgets::{BackgroundWidget, ContainerWidget, SliderWidget, TextWidget, Widget};
+use gui::widgets::background::reserve_stroke;
+use gui::widgets::button::{ButtonOptions, FADE_DURATION};
+use gui::widgets::color_area::{hsl_to_hsv, hsv_to_hsl};
+use gui::widgets::{
+    BackgroundWidget, ButtonWidget, ColorAreaWidget, ContainerWidget, SliderWidget, TextWidget,
+    Widget,
+};
 
 use input::{CursorIcon, MouseButton, MouseEvent, MouseEventKind};
 
@@ -18,9 +25,14 @@ use crate::ui::Message;
 mod color_swatches;
 mod rectangle;
 
+/// How long the panel takes to collapse or expand.
+pub const COLLAPSE_DURATION: Duration = Duration::from_millis(150);
+
 #[derive(Clone, Copy, Debug)]
 pub enum OptionsMessage {
     ColorPicker(ColorPickerMessage),
+    /// Hides everything below the header of the panel, or shows it again.
+    ToggleCollapsed,
 }
 
 impl From<OptionsMessage> for Message {
@@ -33,6 +45,24 @@ pub struct OptionsTree {
     pub grab_area_node: NodeId,
     pub background_node: NodeId,
     pub color_picker: ColorPickerTree,
+    /// The nodes below the header, which are detached from the panel while it is collapsed.
+    contents: Vec<NodeId>,
+    header_node: NodeId,
+    background_style: Style,
+    collapse_label: NodeId,
+    collapsed: bool,
+    /// The height of the panel when it was last expanded, which expanding grows back to.
+    expanded_height: f32,
+    animation: Option<CollapseAnimation>,
+}
+
+/// The height of the panel moving between its collapsed and expanded heights, the contents are
+/// detached for the whole animation since nothing in the tree clips them.
+#[derive(Clone, Copy, Debug)]
+struct CollapseAnimation {
+    start: Instant,
+    from: f32,
+    to: f32,
 }
 
 pub fn grab_fn(_: &mut ContainerWidget<Message>, ctx: &mut EventContext<MouseEvent, Message>) {
@@ -80,6 +110,116 @@ impl OptionsTree {
     pub fn update(&mut self, tree: &mut UITree<Widget<Message>>, message: &OptionsMessage) {
         match message {
             OptionsMessage::ColorPicker(message) => self.color_picker.update(tree, message),
+            OptionsMessage::ToggleCollapsed => self.set_collapsed(tree, !self.collapsed),
+        }
+    }
+
+    pub const fn is_collapsed(&self) -> bool {
+        self.collapsed
+    }
+
+    /// Collapses the panel down to its header, detached contents are neither drawn nor hit.
+    ///
+    /// The height of the panel animates over [`COLLAPSE_DURATION`], driven by
+    /// [`OptionsTree::step_animation`].
+    pub fn set_collapsed(&mut self, tree: &mut UITree<Widget<Message>>, collapsed: bool) {
+        if self.collapsed == collapsed {
+            return;
+        }
+        let from = tree.relative_layout(self.background_node).size.height;
+        if collapsed && self.animation.is_none() {
+            self.expanded_height = from;
+        }
+        // The padding around the header is even, so it is twice the offset of the header.
+        let header = tree.relative_layout(self.header_node);
+        let collapsed_height = header.size.height + header.location.y * 2.;
+
+        self.set_collapsed_now(tree, collapsed);
+        let to = if collapsed {
+            collapsed_height
+        } else {
+            self.expanded_height
+        };
+        // Without a known height to grow back to, the panel expands at once.
+        if to <= 0. || from <= 0. || from == to {
+            return;
+        }
+        // Expanding only attaches the contents once the panel has grown to fit them.
+        self.detach_contents(tree);
+        self.set_height(tree, Some(from));
+        self.animation = Some(CollapseAnimation {
+            start: Instant::now(),
+            from,
+            to,
+        });
+    }
+
+    /// Collapses or expands the panel without animating, such as when restoring it on startup.
+    pub fn set_collapsed_now(&mut self, tree: &mut UITree<Widget<Message>>, collapsed: bool) {
+        if self.animation.take().is_some() {
+            self.set_height(tree, None);
+        }
+        self.collapsed = collapsed;
+        if collapsed {
+            self.detach_contents(tree);
+        } else {
+            self.attach_contents(tree);
+        }
+        if let Some(label) = tree.get_node_mut(self.collapse_label).as_text_mut() {
+            label.update_content(Self::collapse_text(collapsed).to_string());
+        }
+    }
+
+    pub const fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Advances the running collapse animation to `now`, returning whether it is still running.
+    pub fn step_animation(&mut self, tree: &mut UITree<Widget<Message>>, now: Instant) -> bool {
+        let Some(animation) = self.animation else {
+            return false;
+        };
+        let elapsed = now.saturating_duration_since(animation.start);
+        let t = (elapsed.as_secs_f32() / COLLAPSE_DURATION.as_secs_f32()).min(1.);
+        if t >= 1. {
+            self.set_collapsed_now(tree, self.collapsed);
+            return false;
+        }
+        // Ease out, like zooming, so the panel reacts immediately and settles gently.
+        let eased = 1. - (1. - t).powi(3);
+        let height = animation.from + (animation.to - animation.from) * eased;
+        self.set_height(tree, Some(height));
+        true
+    }
+
+    fn attach_contents(&self, tree: &mut UITree<Widget<Message>>) {
+        let children = tree.children(self.background_node);
+        for node in self.contents.iter().filter(|node| !children.contains(node)) {
+            tree.add_child(self.background_node, *node);
+        }
+    }
+
+    fn detach_contents(&self, tree: &mut UITree<Widget<Message>>) {
+        let children = tree.children(self.background_node);
+        for node in self.contents.iter().filter(|node| children.contains(node)) {
+            tree.remove_child(self.background_node, *node);
+        }
+    }
+
+    /// Fixes the height of the panel, `None` lets it fit its contents again.
+    fn set_height(&self, tree: &mut UITree<Widget<Message>>, height: Option<f32>) {
+        let mut style = self.background_style.clone();
+        if let Some(height) = height {
+            style.size.height = Dimension::length(height);
+        }
+        tree.set_style(self.background_node, style);
+    }
+
+    const fn collapse_text(collapsed: bool) -> &'static str {
+        if collapsed {
+            "+"
+        } else {
+            "-"
         }
     }
     pub fn build(tree: &mut UITree<Widget<Message>>, root: NodeId) -> Self {
@@ -94,15 +234,15 @@ impl OptionsTree {
         );
         tree.add_child(root, grab_area_node);
 
-        let background_node = tree.new_leaf(
-            BackgroundWidget::new(RectangleOptions {
-                color: colors::BACKGROUND.into(),
-                rounding: Rounding::all(5.),
-                stroke_width: 3.,
-                stroke_color: PremulColor::new([0.9, 0.9, 0.9, 1.]).into(),
-                box_sizing: graphics::BoxSizing::ContentBox,
-            })
-            .as_widget(),
+        let background = RectangleOptions {
+            color: colors::BACKGROUND.into(),
+            rounding: Rounding::all(5.),
+            stroke_width: 3.,
+            stroke_color: PremulColor::new([0.9, 0.9, 0.9, 1.]).into(),
+            box_sizing: graphics::BoxSizing::ContentBox,
+        };
+        let background_style = reserve_stroke(
+            &background,
             Style {
                 display: Display::Flex,
                 flex_direction: FlexDirection::Column,
@@ -111,13 +251,29 @@ impl OptionsTree {
                 ..Style::DEFAULT
             },
         );
+        let background_node = tree.new_leaf(
+            BackgroundWidget::new(background).as_widget(),
+            background_style.clone(),
+        );
         tree.add_child(grab_area_node, background_node);
 
+        let header_node = tree.new_leaf(
+            ContainerWidget::new(false).as_widget(),
+            Style {
+                display: Display::Flex,
+                justify_content: Some(JustifyContent::SpaceBetween),
+                align_items: Some(AlignItems::Center),
+                gap: Size::<LengthPercentage>::length(10.),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(background_node, header_node);
+
         let color_picker_label = tree.new_leaf(
             TextWidget::new(
                 "Color Picker".to_string(),
                 TextOptions {
-                    color: PremulColor::new([1., 1., 1., 1.]),
+                    fill: Some(PremulColor::new([1., 1., 1., 1.])),
                     font_size: 16.,
                     ..Default::default()
                 },
@@ -133,27 +289,25 @@ impl OptionsTree {
                 ..Style::DEFAULT
             },
         );
-        tree.add_child(background_node, color_picker_label);
+        tree.add_child(header_node, color_picker_label);
+        let (collapse_button, collapse_label) = Self::collapse_button(tree);
+        tree.add_child(header_node, collapse_button);
         let color_picker = ColorPickerTree::build(tree);
 
         let color_picker_container = tree.new_leaf(
-            BackgroundWidget::new(RectangleOptions {
-                color: colors::BACKGROUND.into(),
-                rounding: Rounding::all(5.),
-                stroke_width: 3.,
-                stroke_color: PremulColor::new([0.9, 0.9, 0.9, 1.]).into(),
-                box_sizing: graphics::BoxSizing::ContentBox,
-            })
-            .as_widget(),
-            Style {
-                position: Position::Absolute,
-                inset: Rect {
-                    left: LengthPercentageAuto::percent(1.25),
-                    ..auto()
+            BackgroundWidget::new(background).as_widget(),
+            reserve_stroke(
+                &background,
+                Style {
+                    position: Position::Absolute,
+                    inset: Rect {
+                        left: LengthPercentageAuto::percent(1.25),
+                        ..auto()
+                    },
+                    padding: Rect::length(10.),
+                    ..Style::DEFAULT
                 },
-                padding: Rect::length(10.),
-                ..Style::DEFAULT
-            },
+            ),
         );
         tree.add_child(background_node, color_picker_container);
         tree.add_child(color_picker_container, color_picker.container);
@@ -173,8 +327,83 @@ impl OptionsTree {
             grab_area_node,
             background_node,
             color_picker,
+            contents: vec![color_picker_container, swatches.container],
+            header_node,
+            background_style,
+            collapse_label,
+            collapsed: false,
+            expanded_height: 0.,
+            animation: None,
         }
     }
+
+    /// The button in the header that collapses the panel, along with its label.
+    fn collapse_button(tree: &mut UITree<Widget<Message>>) -> (NodeId, NodeId) {
+        let options = |color: PremulColor<Srgb>| RectangleOptions {
+            color: color.into(),
+            rounding: Rounding::all(3.),
+            stroke_width: 1.,
+            stroke_color: colors::BORDER.into(),
+            ..Default::default()
+        };
+        let button = ButtonWidget::new(
+            ButtonOptions {
+                pressed: options(PremulColor::new([0.45, 0.45, 0.45, 1.])),
+                active: options(PremulColor::new([0.35, 0.35, 0.35, 1.])),
+                hovered: options(PremulColor::new([0.2, 0.2, 0.2, 1.])),
+                normal: options(colors::BACKGROUND),
+                disabled: options(PremulColor::new([0.3, 0.14, 0.14, 0.5])),
+            },
+            true,
+            false,
+            FADE_DURATION,
+        )
+        .mouse_handler(|_, ctx| {
+            match ctx.payload().kind {
+                MouseEventKind::Enter => {
+                    ctx.push_messages(vec![Message::CursorIcon(CursorIcon::Pointer)])
+                }
+                MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                    ctx.push_messages(vec![OptionsMessage::ToggleCollapsed.into()])
+                }
+                _ => {}
+            };
+            // Stopping here keeps a click on the button from starting a grab of the panel.
+            match ctx.current_phase() {
+                EventPhase::Direct | EventPhase::AtTarget | EventPhase::Bubbling => {
+                    ctx.stop_propagation();
+                }
+                _ => {}
+            }
+        });
+        let button_node = tree.new_leaf(
+            button.as_widget(),
+            Style {
+                display: Display::Flex,
+                justify_content: Some(JustifyContent::Center),
+                align_items: Some(AlignItems::Center),
+                size: Size::<Dimension>::from_lengths(20., 20.),
+                ..Style::DEFAULT
+            },
+        );
+        let label_node = tree.new_leaf(
+            TextWidget::new(
+                Self::collapse_text(false).to_string(),
+                TextOptions {
+                    fill: Some(colors::FOREGROUND),
+                    font_size: 12.,
+                    ..Default::default()
+                },
+            )
+            .as_widget(),
+            Style {
+                size: Size::<Dimension>::from_lengths(8., 14.),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(button_node, label_node);
+        (button_node, label_node)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -182,6 +411,19 @@ pub enum ColorPickerMessage {
     UpdateHue(f32),
     UpdateSaturation(f32),
     UpdateLightness(f32),
+    /// A saturation and value picked in the color area, in the hsv color space.
+    UpdateSaturationValue(f32, f32),
+    ToggleMode,
+}
+
+/// How the saturation and lightness of the color are picked, the hue always uses a slider.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorPickerMode {
+    /// A 2D saturation/value area.
+    #[default]
+    Area,
+    /// Separate saturation and lightness sliders.
+    Sliders,
 }
 
 impl From<ColorPickerMessage> for Message {
@@ -192,6 +434,9 @@ impl From<ColorPickerMessage> for Message {
 
 struct ColorPickerTree {
     container: NodeId,
+    mode: ColorPickerMode,
+    mode_label: NodeId,
+    color_area: NodeId,
 
     hue: f32,
     saturation: f32,
@@ -207,6 +452,9 @@ impl ColorPickerTree {
         match message {
             ColorPickerMessage::UpdateHue(value) => {
                 self.hue = *value;
+                if let Some(area) = tree.get_node_mut(self.color_area).as_color_area_mut() {
+                    area.set_hue(self.hue);
+                }
                 self.saturation_slider
                     .update_background(tree, [self.hue, self.saturation, self.lightness]);
                 self.lightness_slider
@@ -218,6 +466,7 @@ impl ColorPickerTree {
                     .update_background(tree, [self.hue, self.saturation, self.lightness]);
                 self.lightness_slider
                     .update_background(tree, [self.hue, self.saturation, self.lightness]);
+                self.sync_color_area(tree);
             }
             ColorPickerMessage::UpdateLightness(value) => {
                 self.lightness = *value;
@@ -225,6 +474,59 @@ impl ColorPickerTree {
                     .update_background(tree, [self.hue, self.saturation, self.lightness]);
                 self.saturation_slider
                     .update_background(tree, [self.hue, self.saturation, self.lightness]);
+                self.sync_color_area(tree);
+            }
+            ColorPickerMessage::UpdateSaturationValue(saturation, value) => {
+                let [_, saturation, lightness] = hsv_to_hsl([self.hue, *saturation, *value]);
+                self.saturation = saturation;
+                self.lightness = lightness;
+                let hsl = [self.hue, self.saturation, self.lightness];
+                self.hue_slider.update_background(tree, hsl);
+                self.saturation_slider.update_background(tree, hsl);
+                self.lightness_slider.update_background(tree, hsl);
+                // Keep the sliders in sync for when the mode is switched back.
+                self.saturation_slider
+                    .set_indicator(tree, self.saturation * 10., (0., 1000.));
+                self.lightness_slider
+                    .set_indicator(tree, self.lightness * 10., (0., 1000.));
+            }
+            ColorPickerMessage::ToggleMode => {
+                self.mode = match self.mode {
+                    ColorPickerMode::Area => ColorPickerMode::Sliders,
+                    ColorPickerMode::Sliders => ColorPickerMode::Area,
+                };
+                self.apply_mode(tree);
+            }
+        }
+    }
+
+    /// Moves the crosshair of the color area to the current saturation and lightness.
+    fn sync_color_area(&self, tree: &mut UITree<Widget<Message>>) {
+        let [_, saturation, value] = hsl_to_hsv([self.hue, self.saturation, self.lightness]);
+        if let Some(area) = tree.get_node_mut(self.color_area).as_color_area_mut() {
+            area.set_saturation_value(saturation, value);
+        }
+    }
+
+    /// Attaches the nodes used by the current mode, detaching the others.
+    fn apply_mode(&self, tree: &mut UITree<Widget<Message>>) {
+        let label = match self.mode {
+            ColorPickerMode::Area => "Mode: Area",
+            ColorPickerMode::Sliders => "Mode: Sliders",
+        };
+        if let Some(text) = tree.get_node_mut(self.mode_label).as_text_mut() {
+            text.update_content(label.to_string());
+        }
+        match self.mode {
+            ColorPickerMode::Area => {
+                tree.remove_child(self.container, self.saturation_slider.slider);
+                tree.remove_child(self.container, self.lightness_slider.slider);
+                tree.add_child(self.container, self.color_area);
+            }
+            ColorPickerMode::Sliders => {
+                tree.remove_child(self.container, self.color_area);
+                tree.add_child(self.container, self.saturation_slider.slider);
+                tree.add_child(self.container, self.lightness_slider.slider);
             }
         }
     }
@@ -239,6 +541,39 @@ impl ColorPickerTree {
             },
         );
 
+        let mode_label = tree.new_leaf(
+            TextWidget::new(
+                String::new(),
+                TextOptions {
+                    fill: Some(colors::FOREGROUND),
+                    font_size: 14.,
+                    ..Default::default()
+                },
+            )
+            .as_widget(),
+            Style {
+                size: Size::from_lengths(256., 18.),
+                ..Style::DEFAULT
+            },
+        );
+        let mode_toggle = ContainerWidget::new(false).mouse_handler(|_, ctx| {
+            if ctx.current_phase() != EventPhase::Capturing {
+                match ctx.payload().kind {
+                    MouseEventKind::Enter => {
+                        ctx.push_messages(vec![Message::CursorIcon(CursorIcon::Pointer)])
+                    }
+                    MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                        ctx.push_messages(vec![ColorPickerMessage::ToggleMode.into()])
+                    }
+                    _ => {}
+                }
+                ctx.stop_propagation();
+            }
+        });
+        let mode_toggle = tree.new_leaf(mode_toggle.as_widget(), Style::DEFAULT);
+        tree.add_child(mode_toggle, mode_label);
+        tree.add_child(container, mode_toggle);
+
         let initial_hsl = [0., 100., 50.];
 
         let hue_slider = SliderTree::new(
@@ -295,16 +630,48 @@ impl ColorPickerTree {
             initial_hsl,
             |val| ColorPickerMessage::UpdateLightness(val / 10.).into(),
         );
-        Self {
+        let [_, initial_saturation, initial_value] = hsl_to_hsv(initial_hsl);
+        let color_area = ColorAreaWidget::new(initial_hsl[0], initial_saturation, initial_value)
+            .mouse_handler(|_, ctx| {
+                if ctx.current_phase() != EventPhase::Capturing {
+                    if ctx.payload().kind == MouseEventKind::Enter {
+                        ctx.push_messages(vec![Message::CursorIcon(CursorIcon::Crosshair)]);
+                    }
+                    ctx.stop_propagation();
+                }
+            })
+            .change_handler(|_, ctx| {
+                if ctx.current_phase() != EventPhase::Capturing {
+                    let new = ctx.payload().new;
+                    ctx.push_messages(vec![ColorPickerMessage::UpdateSaturationValue(
+                        new.x, new.y,
+                    )
+                    .into()]);
+                }
+            });
+        let color_area = tree.new_leaf(
+            color_area.as_widget(),
+            Style {
+                size: Size::from_lengths(256., 160.),
+                ..Style::DEFAULT
+            },
+        );
+
+        let picker = Self {
             hue: initial_hsl[0],
             saturation: initial_hsl[1],
             lightness: initial_hsl[2],
 
             container,
+            mode: ColorPickerMode::default(),
+            mode_label,
+            color_area,
             hue_slider,
             saturation_slider,
             lightness_slider,
-        }
+        };
+        picker.apply_mode(tree);
+        picker
     }
 }
 fn slider_indicator_style(value: f32, start: f32, end: f32) -> Style {
@@ -328,6 +695,13 @@ struct SliderTree<const N: usize, T> {
 }
 
 impl<const N: usize, T> SliderTree<N, T> {
+    /// Moves the indicator without changing the value of the slider widget.
+    pub fn set_indicator(&self, tree: &mut UITree<Widget<Message>>, value: f32, range: (f32, f32)) {
+        tree.set_style(
+            self.indicator,
+            slider_indicator_style(value, range.0, range.1),
+        );
+    }
     pub fn update_background(&self, tree: &mut UITree<Widget<Message>>, value: T) {
         let individual_part_backgrounds = (self.background_fn)(value);
         for (idx, node_id) in self.background_parts.iter().enumerate() {
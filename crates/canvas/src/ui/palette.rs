@@ -0,0 +1,592 @@
+use color::PremulColor;
+use graphics::primitives::{RectangleOptions, TextOptions};
+use graphics::Rounding;
+
+use gui::prelude::*;
+use gui::tree::{UITree, ZIndexProperties};
+use gui::widgets::background::reserve_stroke;
+use gui::widgets::{BackgroundWidget, ContainerWidget, TextInputWidget, TextWidget, Widget};
+use input::{
+    Key, KeyboardEvent, KeyboardEventKind, Modifiers, MouseButton, MouseEventKind, SpecialKey,
+};
+
+use crate::align::{Alignment, Distribution};
+use crate::tools::{EnabledTools, ToolKind};
+use crate::ui::styles::colors;
+use crate::ui::Message;
+
+/// The maximum amount of entries shown at once, the rest are reached by scrolling.
+const VISIBLE_ROWS: usize = 8;
+const PANEL_WIDTH: f32 = 420.;
+const ROW_HEIGHT: f32 = 28.;
+/// The z-index of the palette, this should sit above every other floating panel.
+const PALETTE_Z_INDEX: usize = 1000;
+
+const PLACEHOLDER: &str = "Search actions...";
+
+/// An action that can be run from the command palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteAction {
+    SwapTool(ToolKind),
+    ResetZoom,
+    ZoomToContent,
+    RepeatLast,
+    Undo,
+    ToggleRecording,
+    ReplayMacro,
+    ExportMacro,
+    ToggleShapeRecognition,
+    ToggleSnapping,
+    ToggleContrastOutline,
+    FlipHorizontal,
+    FlipVertical,
+    ToggleMirrorText,
+    ToggleToolHints,
+    CycleGrid,
+    ToggleCrosshair,
+    ToggleRulers,
+    ClearGuides,
+    SaveSession,
+    SaveNewSession,
+    ExportPng,
+    ExportSelection,
+    ExportDocument,
+    Align(Alignment),
+    Distribute(Distribution),
+}
+
+/// The actions bound to Ctrl and a key, which run while no tool is focused.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shortcuts(pub Vec<(char, PaletteAction)>);
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self(vec![
+            ('z', PaletteAction::Undo),
+            ('r', PaletteAction::RepeatLast),
+            // Not Ctrl+Q, which quits in most applications.
+            ('k', PaletteAction::ToggleRecording),
+            ('e', PaletteAction::ReplayMacro),
+            ('m', PaletteAction::ToggleSnapping),
+            ('o', PaletteAction::ToggleContrastOutline),
+            ('h', PaletteAction::FlipHorizontal),
+            ('j', PaletteAction::FlipVertical),
+        ])
+    }
+}
+
+impl Shortcuts {
+    /// The action bound to the shortcut the event presses, if any.
+    pub fn action(&self, event: &KeyboardEvent) -> Option<PaletteAction> {
+        self.0
+            .iter()
+            .find(|(key, _)| event.is_ctrl_shortcut(*key))
+            .map(|(_, action)| *action)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PaletteEntry {
+    pub label: String,
+    pub action: PaletteAction,
+}
+
+impl PaletteEntry {
+    /// Every action that is available from the palette, with a tool swap for each of the enabled
+    /// tools.
+    pub fn all(enabled_tools: &EnabledTools) -> Vec<Self> {
+        let mut entries = enabled_tools
+            .iter()
+            .map(|tool| PaletteEntry {
+                label: format!("Tool: {tool:?}"),
+                action: PaletteAction::SwapTool(tool),
+            })
+            .collect::<Vec<_>>();
+        entries.extend([
+            PaletteEntry {
+                label: "View: Reset Zoom".to_string(),
+                action: PaletteAction::ResetZoom,
+            },
+            PaletteEntry {
+                label: "View: Zoom to Content".to_string(),
+                action: PaletteAction::ZoomToContent,
+            },
+            PaletteEntry {
+                label: "Edit: Repeat Last Shape".to_string(),
+                action: PaletteAction::RepeatLast,
+            },
+            PaletteEntry {
+                label: "Edit: Undo".to_string(),
+                action: PaletteAction::Undo,
+            },
+            PaletteEntry {
+                label: "Edit: Flip Selection Horizontally".to_string(),
+                action: PaletteAction::FlipHorizontal,
+            },
+            PaletteEntry {
+                label: "Edit: Flip Selection Vertically".to_string(),
+                action: PaletteAction::FlipVertical,
+            },
+            PaletteEntry {
+                label: "Edit: Toggle Mirroring Text When Flipping".to_string(),
+                action: PaletteAction::ToggleMirrorText,
+            },
+        ]);
+        entries.extend(
+            [
+                ("Left Edges", Alignment::Left),
+                ("Right Edges", Alignment::Right),
+                ("Top Edges", Alignment::Top),
+                ("Bottom Edges", Alignment::Bottom),
+                ("Horizontal Centers", Alignment::CenterHorizontal),
+                ("Vertical Centers", Alignment::CenterVertical),
+            ]
+            .map(|(edge, alignment)| PaletteEntry {
+                label: format!("Arrange: Align {edge}"),
+                action: PaletteAction::Align(alignment),
+            }),
+        );
+        entries.extend([
+            PaletteEntry {
+                label: "Arrange: Distribute Horizontally".to_string(),
+                action: PaletteAction::Distribute(Distribution::Horizontal),
+            },
+            PaletteEntry {
+                label: "Arrange: Distribute Vertically".to_string(),
+                action: PaletteAction::Distribute(Distribution::Vertical),
+            },
+            PaletteEntry {
+                label: "Session: Save".to_string(),
+                action: PaletteAction::SaveSession,
+            },
+            PaletteEntry {
+                label: "Session: Save As New".to_string(),
+                action: PaletteAction::SaveNewSession,
+            },
+            PaletteEntry {
+                label: "Export: PNG".to_string(),
+                action: PaletteAction::ExportPng,
+            },
+            PaletteEntry {
+                label: "Export: Selection as PNG".to_string(),
+                action: PaletteAction::ExportSelection,
+            },
+            PaletteEntry {
+                label: "Export: PNG for Documents".to_string(),
+                action: PaletteAction::ExportDocument,
+            },
+            PaletteEntry {
+                label: "Macro: Start/Stop Recording".to_string(),
+                action: PaletteAction::ToggleRecording,
+            },
+            PaletteEntry {
+                label: "Macro: Replay".to_string(),
+                action: PaletteAction::ReplayMacro,
+            },
+            PaletteEntry {
+                label: "Macro: Export as GIF".to_string(),
+                action: PaletteAction::ExportMacro,
+            },
+            PaletteEntry {
+                label: "View: Toggle Snapping".to_string(),
+                action: PaletteAction::ToggleSnapping,
+            },
+            PaletteEntry {
+                label: "View: Toggle Contrast Outline".to_string(),
+                action: PaletteAction::ToggleContrastOutline,
+            },
+            PaletteEntry {
+                label: "View: Cycle Grid Style".to_string(),
+                action: PaletteAction::CycleGrid,
+            },
+            PaletteEntry {
+                label: "View: Toggle Crosshair".to_string(),
+                action: PaletteAction::ToggleCrosshair,
+            },
+            PaletteEntry {
+                label: "View: Toggle Rulers".to_string(),
+                action: PaletteAction::ToggleRulers,
+            },
+            PaletteEntry {
+                label: "View: Clear Guides".to_string(),
+                action: PaletteAction::ClearGuides,
+            },
+            PaletteEntry {
+                label: "View: Toggle Tool Hints".to_string(),
+                action: PaletteAction::ToggleToolHints,
+            },
+            PaletteEntry {
+                label: "Pen: Toggle Shape Recognition".to_string(),
+                action: PaletteAction::ToggleShapeRecognition,
+            },
+        ]);
+        entries
+    }
+}
+
+/// A searchable overlay listing every action and tool.
+///
+/// The palette lives detached from the tree while closed, opening it attaches the overlay to the
+/// root node in its own isolated z-layer.
+pub struct CommandPalette {
+    overlay_node: NodeId,
+    query_node: NodeId,
+    list_node: NodeId,
+    /// The (background, label) nodes of each row in the list.
+    rows: Vec<(NodeId, NodeId)>,
+    /// The amount of rows currently attached to the list.
+    attached_rows: usize,
+
+    entries: Vec<PaletteEntry>,
+    /// Indices into `entries` that match the current query, best match first.
+    matches: Vec<usize>,
+    query: String,
+    /// The selected index within `matches`.
+    selected: usize,
+    /// The index within `matches` of the first visible row.
+    scroll: usize,
+    open: bool,
+}
+
+impl CommandPalette {
+    pub fn build(tree: &mut UITree<Widget<Message>>, enabled_tools: &EnabledTools) -> Self {
+        let overlay = ContainerWidget::new(false).mouse_handler(|_, ctx| {
+            // Clicking anywhere outside of the panel closes the palette.
+            if ctx.current_phase() == EventPhase::AtTarget {
+                if let MouseEventKind::Press { .. } = ctx.payload().kind {
+                    ctx.push_messages(vec![Message::ClosePalette]);
+                }
+            }
+        });
+        let overlay_node = tree.new_leaf_with_z(
+            overlay.as_widget(),
+            Style::DEFAULT,
+            ZIndexProperties {
+                z_index: PALETTE_Z_INDEX,
+                isolate_z: true,
+            },
+        );
+
+        let panel = RectangleOptions {
+            color: colors::BACKGROUND.into(),
+            rounding: Rounding::all(5.),
+            stroke_width: 3.,
+            stroke_color: colors::BORDER.into(),
+            box_sizing: graphics::BoxSizing::ContentBox,
+        };
+        let panel_node = tree.new_leaf(
+            BackgroundWidget::new(panel).as_widget(),
+            reserve_stroke(
+                &panel,
+                Style {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    size: Size {
+                        width: Dimension::length(PANEL_WIDTH),
+                        height: Dimension::auto(),
+                    },
+                    padding: Rect::<LengthPercentage>::length(10.),
+                    gap: Size::<LengthPercentage>::length(10.),
+                    ..Style::DEFAULT
+                },
+            ),
+        );
+        tree.add_child(overlay_node, panel_node);
+
+        let query_node = tree.new_leaf(
+            TextInputWidget::new(PLACEHOLDER.to_string(), Self::text_options()).as_widget(),
+            Style {
+                size: Size {
+                    width: Dimension::percent(1.),
+                    height: Dimension::length(ROW_HEIGHT),
+                },
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(panel_node, query_node);
+
+        let list = ContainerWidget::new(false).mouse_handler(|_, ctx| {
+            if let MouseEventKind::Axis { vertical, .. } = ctx.payload().kind {
+                if vertical.discrete != 0 {
+                    ctx.push_messages(vec![Message::PaletteScroll(vertical.discrete.signum())]);
+                } else if vertical.absolute != 0. {
+                    ctx.push_messages(vec![Message::PaletteScroll(
+                        vertical.absolute.signum() as i32,
+                    )]);
+                }
+            }
+        });
+        let list_node = tree.new_leaf(
+            list.as_widget(),
+            Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                gap: Size::<LengthPercentage>::length(2.),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(panel_node, list_node);
+
+        let rows = (0..VISIBLE_ROWS)
+            .map(|row| {
+                let background = BackgroundWidget::new(Self::row_options(false)).mouse_handler(
+                    move |_, ctx| match ctx.payload().kind {
+                        MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                            ctx.push_messages(vec![Message::PaletteSelect(row)]);
+                            ctx.stop_propagation();
+                        }
+                        _ => {}
+                    },
+                );
+                let background_node = tree.new_leaf(
+                    background.as_widget(),
+                    Style {
+                        padding: Rect {
+                            left: LengthPercentage::length(8.),
+                            right: LengthPercentage::length(8.),
+                            top: LengthPercentage::length(4.),
+                            bottom: LengthPercentage::length(4.),
+                        },
+                        ..Style::DEFAULT
+                    },
+                );
+                let label_node = tree.new_leaf(
+                    TextWidget::new(String::new(), Self::text_options()).as_widget(),
+                    Style {
+                        size: Size {
+                            width: Dimension::length(PANEL_WIDTH - 36.),
+                            height: Dimension::length(ROW_HEIGHT - 8.),
+                        },
+                        ..Style::DEFAULT
+                    },
+                );
+                tree.add_child(background_node, label_node);
+                (background_node, label_node)
+            })
+            .collect();
+
+        Self {
+            overlay_node,
+            query_node,
+            list_node,
+            rows,
+            attached_rows: 0,
+
+            entries: PaletteEntry::all(enabled_tools),
+            matches: vec![],
+            query: String::new(),
+            selected: 0,
+            scroll: 0,
+            open: false,
+        }
+    }
+
+    pub const fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Whether the event is the shortcut used to open the palette (`Ctrl+P`).
+    pub fn is_open_shortcut(event: &KeyboardEvent) -> bool {
+        event.is_ctrl_shortcut('p')
+    }
+
+    pub fn open(&mut self, tree: &mut UITree<Widget<Message>>) {
+        if self.open {
+            return;
+        }
+        // The overlay covers the whole viewport so that outside clicks can be caught.
+        tree.set_style(
+            self.overlay_node,
+            Style {
+                display: Display::Flex,
+                position: Position::Absolute,
+                justify_content: Some(JustifyContent::Center),
+                align_items: Some(AlignItems::FlexStart),
+                size: Size::<Dimension>::from_lengths(tree.viewport.width, tree.viewport.height),
+                padding: Rect {
+                    top: LengthPercentage::length(tree.viewport.height * 0.2),
+                    ..Rect::zero()
+                },
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(tree.root_node(), self.overlay_node);
+        self.open = true;
+
+        self.query.clear();
+        self.refilter(tree);
+    }
+
+    pub fn close(&mut self, tree: &mut UITree<Widget<Message>>) {
+        if !self.open {
+            return;
+        }
+        tree.remove_child(tree.root_node(), self.overlay_node);
+        self.open = false;
+    }
+
+    /// Handles a keyboard event while the palette is open, returning the action to run if an
+    /// entry was chosen.
+    pub fn keyboard_event(
+        &mut self,
+        tree: &mut UITree<Widget<Message>>,
+        event: &KeyboardEvent,
+    ) -> Option<PaletteAction> {
+        let KeyboardEventKind::Press(key) = &event.kind else {
+            return None;
+        };
+        match key {
+            Key::SpecialKey(SpecialKey::Escape) => self.close(tree),
+            Key::SpecialKey(SpecialKey::Enter) => {
+                return self.select(tree, self.selected.saturating_sub(self.scroll));
+            }
+            Key::SpecialKey(SpecialKey::Up) => self.move_selection(tree, -1),
+            Key::SpecialKey(SpecialKey::Down) | Key::SpecialKey(SpecialKey::Tab) => {
+                self.move_selection(tree, 1)
+            }
+            Key::SpecialKey(SpecialKey::PageUp) => {
+                self.move_selection(tree, -(VISIBLE_ROWS as isize))
+            }
+            Key::SpecialKey(SpecialKey::PageDown) => {
+                self.move_selection(tree, VISIBLE_ROWS as isize)
+            }
+            Key::SpecialKey(SpecialKey::Backspace) => {
+                if self.query.pop().is_some() {
+                    self.refilter(tree);
+                }
+            }
+            Key::Character(text) if !event.modifiers.contains(Modifiers::CTRL) => {
+                let text = text.chars().filter(|c| !c.is_control()).collect::<String>();
+                if !text.is_empty() {
+                    self.query.push_str(&text);
+                    self.refilter(tree);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Closes the palette and returns the action of the entry shown in the given row.
+    pub fn select(
+        &mut self,
+        tree: &mut UITree<Widget<Message>>,
+        row: usize,
+    ) -> Option<PaletteAction> {
+        let action = self
+            .matches
+            .get(self.scroll + row)
+            .map(|idx| self.entries[*idx].action);
+        self.close(tree);
+        action
+    }
+
+    /// Scrolls the visible rows by the given amount of entries.
+    pub fn scroll(&mut self, tree: &mut UITree<Widget<Message>>, delta: i32) {
+        self.move_selection(tree, delta as isize);
+    }
+
+    fn move_selection(&mut self, tree: &mut UITree<Widget<Message>>, delta: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        self.selected = self
+            .selected
+            .saturating_add_signed(delta)
+            .min(self.matches.len() - 1);
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + VISIBLE_ROWS {
+            self.scroll = self.selected + 1 - VISIBLE_ROWS;
+        }
+        self.update_rows(tree);
+    }
+
+    fn refilter(&mut self, tree: &mut UITree<Widget<Message>>) {
+        let mut scored = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| fuzzy_score(&self.query, &entry.label).map(|s| (s, idx)))
+            .collect::<Vec<_>>();
+        scored.sort();
+        self.matches = scored.into_iter().map(|(_, idx)| idx).collect();
+        self.selected = 0;
+        self.scroll = 0;
+
+        let query = if self.query.is_empty() {
+            PLACEHOLDER.to_string()
+        } else {
+            self.query.clone()
+        };
+        if let Some(input) = tree.get_node_mut(self.query_node).as_text_input_mut() {
+            input.update_content(query);
+        }
+        self.update_rows(tree);
+    }
+
+    /// Syncs the attached rows with the current matches, scroll and selection.
+    fn update_rows(&mut self, tree: &mut UITree<Widget<Message>>) {
+        let visible = self.matches.len().saturating_sub(self.scroll).min(VISIBLE_ROWS);
+        while self.attached_rows > visible {
+            self.attached_rows -= 1;
+            tree.remove_child(self.list_node, self.rows[self.attached_rows].0);
+        }
+        while self.attached_rows < visible {
+            tree.add_child(self.list_node, self.rows[self.attached_rows].0);
+            self.attached_rows += 1;
+        }
+
+        for (row, (background, label)) in self.rows.iter().take(visible).enumerate() {
+            let entry = &self.entries[self.matches[self.scroll + row]];
+            let is_selected = self.scroll + row == self.selected;
+            if let Some(label) = tree.get_node_mut(*label).as_text_mut() {
+                label.update_content(entry.label.clone());
+            }
+            if let Some(background) = tree.get_node_mut(*background).as_background_mut() {
+                background.change_options(Self::row_options(is_selected));
+            }
+        }
+    }
+
+    fn text_options() -> TextOptions {
+        TextOptions {
+            fill: Some(colors::FOREGROUND),
+            font_size: 16.,
+            ..Default::default()
+        }
+    }
+
+    fn row_options(selected: bool) -> RectangleOptions {
+        RectangleOptions {
+            color: if selected {
+                PremulColor::new([0.25, 0.25, 0.25, 1.]).into()
+            } else {
+                colors::BACKGROUND.into()
+            },
+            rounding: Rounding::all(5.),
+            ..Default::default()
+        }
+    }
+}
+
+/// Scores how well `query` matches `label` as a case-insensitive subsequence, returning `None`
+/// if it does not match at all.
+///
+/// Lower scores are better, each character skipped between matches adds to the score.
+fn fuzzy_score(query: &str, label: &str) -> Option<usize> {
+    let mut score = 0;
+    let mut last = None;
+    let mut chars = label.chars().enumerate();
+    for needle in query.chars().filter(|c| !c.is_whitespace()) {
+        let (idx, _) = chars
+            .by_ref()
+            .find(|(_, c)| c.eq_ignore_ascii_case(&needle))?;
+        score += match last {
+            Some(last) => idx - last - 1,
+            None => idx,
+        };
+        last = Some(idx);
+    }
+    Some(score)
+}
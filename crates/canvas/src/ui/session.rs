@@ -0,0 +1,96 @@
+use graphics::primitives::{RectangleOptions, TextOptions};
+use graphics::Rounding;
+
+use gui::modal::{Modal, ModalAction, ModalOptions, ModalResult};
+use gui::prelude::*;
+use gui::tree::UITree;
+use gui::widgets::{TextWidget, Widget};
+use input::KeyboardEvent;
+
+use crate::ui::styles::colors;
+use crate::ui::Message;
+
+const PANEL_WIDTH: f32 = 380.;
+const FONT_SIZE: f32 = 16.;
+
+/// Asks whether to discard the unsaved changes of the canvas before another session replaces it.
+///
+/// Confirming gives [`ModalResult::Confirm`], after which the view loads the session it was
+/// about to open.
+pub struct UnsavedChangesPrompt {
+    modal: Modal,
+}
+
+impl UnsavedChangesPrompt {
+    pub fn build(tree: &mut UITree<Widget<Message>>) -> Self {
+        let text = TextOptions {
+            fill: Some(colors::FOREGROUND),
+            font_size: FONT_SIZE,
+            ..Default::default()
+        };
+        let modal = Modal::build(
+            tree,
+            ModalOptions {
+                title: "Unsaved Changes".to_string(),
+                actions: vec![
+                    ModalAction::new("Cancel", ModalResult::Cancel),
+                    ModalAction::new("Discard", ModalResult::Confirm),
+                ],
+                default_result: ModalResult::Cancel,
+                panel: RectangleOptions {
+                    color: colors::BACKGROUND.into(),
+                    rounding: Rounding::all(5.),
+                    stroke_width: 3.,
+                    stroke_color: colors::BORDER.into(),
+                    box_sizing: graphics::BoxSizing::ContentBox,
+                },
+                text: text.clone(),
+                width: PANEL_WIDTH,
+                ..Default::default()
+            },
+            Message::SessionConfirm,
+        );
+
+        let body = tree.new_leaf(
+            TextWidget::new(
+                "The canvas has changes that were not saved, opening another session discards \
+                 them."
+                    .to_string(),
+                text,
+            )
+            .as_widget(),
+            Style {
+                size: Size {
+                    width: Dimension::percent(1.),
+                    height: Dimension::length(FONT_SIZE * 1.5 * 2.),
+                },
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(modal.content_node(), body);
+
+        Self { modal }
+    }
+
+    pub const fn is_open(&self) -> bool {
+        self.modal.is_open()
+    }
+
+    pub fn open(&mut self, tree: &mut UITree<Widget<Message>>) {
+        self.modal.open(tree);
+    }
+
+    pub fn close(&mut self, tree: &mut UITree<Widget<Message>>) {
+        self.modal.close(tree);
+    }
+
+    /// Handles a keyboard event while the prompt is open, returning the result of Escape or
+    /// Enter.
+    pub fn keyboard_event(
+        &mut self,
+        tree: &mut UITree<Widget<Message>>,
+        event: &KeyboardEvent,
+    ) -> Option<ModalResult> {
+        self.modal.keyboard_event(tree, event)
+    }
+}
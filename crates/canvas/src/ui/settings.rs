@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// The state of the interface that is kept between runs, in a json file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UiSettings {
+    /// Whether the options panel is collapsed down to its header.
+    #[serde(default)]
+    pub options_collapsed: bool,
+}
+
+impl UiSettings {
+    /// Reads the settings, a file that does not exist yet gives the defaults.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read the settings {path:?}"));
+            }
+        };
+        serde_json::from_str(&contents).with_context(|| format!("failed to parse {path:?}"))
+    }
+
+    /// Writes the settings, creating the directory they are in if needed.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create the directory {parent:?}"))?;
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents).with_context(|| format!("failed to write {path:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip() {
+        let directory =
+            std::env::temp_dir().join(format!("draw-ui-settings-{}", std::process::id()));
+        let path = directory.join("nested").join("ui.json");
+        assert_eq!(UiSettings::load(&path).unwrap(), UiSettings::default());
+
+        let settings = UiSettings {
+            options_collapsed: true,
+        };
+        settings.save(&path).unwrap();
+        assert_eq!(UiSettings::load(&path).unwrap(), settings);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn missing_fields_use_the_defaults() {
+        assert_eq!(
+            serde_json::from_str::<UiSettings>("{}").unwrap(),
+            UiSettings::default()
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use graphics::primitives::TextOptions;
+
+use gui::prelude::*;
+use gui::tree::{UITree, ZIndexProperties};
+use gui::widgets::{TextWidget, Widget};
+use input::Modifiers;
+
+use crate::tools::ToolKind;
+use crate::ui::styles::colors;
+use crate::ui::Message;
+
+const FONT_SIZE: f32 = 14.;
+const LINE_HEIGHT: f32 = FONT_SIZE * 1.5;
+/// The distance between the line and the bottom left corner of the viewport.
+const MARGIN: f32 = 10.;
+/// The z-index of the line, below every floating panel so it never covers one.
+const STATUS_Z_INDEX: usize = 0;
+
+/// A line of text along the bottom of the viewport, hinting at what the selected tool does with
+/// the modifiers that are currently held.
+///
+/// Warnings are shown even while the hints are hidden.
+pub struct StatusLine {
+    text_node: NodeId,
+    /// Whether the hints are shown.
+    visible: bool,
+    /// Whether the line is in the tree, which it is while hints or a warning are shown.
+    attached: bool,
+    /// The tool and modifiers the shown hint is for.
+    shown: Option<(ToolKind, Modifiers)>,
+    /// Shown instead of the hint while set.
+    warning: Option<String>,
+}
+
+impl StatusLine {
+    pub fn build(tree: &mut UITree<Widget<Message>>) -> Self {
+        let text_node = tree.new_leaf_with_z(
+            TextWidget::new(String::new(), Self::text_options()).as_widget(),
+            Self::style(tree),
+            ZIndexProperties {
+                z_index: STATUS_Z_INDEX,
+                isolate_z: true,
+            },
+        );
+        // The line spans the bottom of the viewport, so it lets presses through to the canvas.
+        tree.set_hittable(text_node, false);
+        tree.add_child(tree.root_node(), text_node);
+        Self {
+            text_node,
+            visible: true,
+            attached: true,
+            shown: None,
+            warning: None,
+        }
+    }
+
+    /// The line sits in the bottom left corner, so it is placed from the viewport size.
+    fn style(tree: &UITree<Widget<Message>>) -> Style {
+        Style {
+            position: Position::Absolute,
+            inset: Rect {
+                left: LengthPercentageAuto::length(MARGIN),
+                top: LengthPercentageAuto::length(tree.viewport.height - LINE_HEIGHT - MARGIN),
+                right: LengthPercentageAuto::AUTO,
+                bottom: LengthPercentageAuto::AUTO,
+            },
+            size: Size::<Dimension>::from_lengths(tree.viewport.width - MARGIN * 2., LINE_HEIGHT),
+            ..Style::DEFAULT
+        }
+    }
+
+    /// Moves the line back to the bottom of the viewport after it was resized.
+    pub fn update_viewport(&mut self, tree: &mut UITree<Widget<Message>>) {
+        tree.set_style(self.text_node, Self::style(tree));
+    }
+
+    /// Whether the hints are shown.
+    pub const fn is_visible(&self) -> bool {
+        self.visible
+    }
+    /// Whether the line is on screen, with either a hint or a warning.
+    pub const fn is_shown(&self) -> bool {
+        self.attached
+    }
+
+    /// Shows or hides the hints, a warning stays on screen either way.
+    pub fn set_visible(&mut self, tree: &mut UITree<Widget<Message>>, visible: bool) {
+        self.visible = visible;
+        self.update_attached(tree);
+    }
+
+    fn update_attached(&mut self, tree: &mut UITree<Widget<Message>>) {
+        let attached = self.visible || self.warning.is_some();
+        if self.attached == attached {
+            return;
+        }
+        self.attached = attached;
+        if attached {
+            tree.add_child(tree.root_node(), self.text_node);
+        } else {
+            tree.remove_child(tree.root_node(), self.text_node);
+        }
+    }
+
+    /// Shows the hint for the tool and modifiers, returning whether the line changed.
+    pub fn update(
+        &mut self,
+        tree: &mut UITree<Widget<Message>>,
+        tool: ToolKind,
+        modifiers: Modifiers,
+    ) -> bool {
+        // Locks don't change what a tool does, so they shouldn't re-layout the hint either.
+        let modifiers = modifiers - Modifiers::CAPS_LOCK - Modifiers::NUM_LOCK;
+        if self.shown == Some((tool, modifiers)) {
+            return false;
+        }
+        self.shown = Some((tool, modifiers));
+        if self.warning.is_none() {
+            self.set_text(tree, tool.hint(modifiers).to_string());
+        }
+        true
+    }
+
+    /// Shows a warning in place of the hint until it is cleared with `None`, even while the hints
+    /// are hidden.
+    pub fn set_warning(&mut self, tree: &mut UITree<Widget<Message>>, warning: Option<String>) {
+        if self.warning == warning {
+            return;
+        }
+        self.warning = warning;
+        let text = match (&self.warning, self.shown) {
+            (Some(warning), _) => warning.clone(),
+            (None, Some((tool, modifiers))) => tool.hint(modifiers).to_string(),
+            (None, None) => String::new(),
+        };
+        self.set_text(tree, text);
+        self.update_attached(tree);
+    }
+
+    fn set_text(&self, tree: &mut UITree<Widget<Message>>, text: String) {
+        if let Some(node) = tree.get_node_mut(self.text_node).as_text_mut() {
+            node.update_content(text);
+        }
+    }
+
+    fn text_options() -> TextOptions {
+        TextOptions {
+            fill: Some(colors::FOREGROUND),
+            font_size: FONT_SIZE,
+            ..Default::default()
+        }
+    }
+}
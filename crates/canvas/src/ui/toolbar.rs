@@ -5,20 +5,23 @@ use graphics::{primitives::RectangleOptions, Rounding};
 use gui::prelude::*;
 
 use gui::tree::{UITree, ZIndexProperties};
+use gui::widgets::background::reserve_stroke;
 use gui::widgets::button::{ButtonOptions, FADE_DURATION};
 use gui::widgets::{BackgroundWidget, ButtonWidget, ContainerWidget, SvgWidget};
 use gui::Element;
 use input::{CursorIcon, MouseButton, MouseEventKind};
-use strum::IntoEnumIterator;
 
-use crate::tools::{ToolKind, ToolNodeMap};
+use crate::tools::{EnabledTools, ToolKind, ToolNodeMap};
 use crate::ui::styles::{colors, floating_grab};
 use crate::ui::Message;
 
+/// Builds the toolbar, returning its root, the button of each tool and the snapping toggle.
 pub fn create_toolbar(
     tree: &mut UITree<gui::widgets::Widget<Message>>,
+    enabled_tools: &EnabledTools,
     selected_tool: ToolKind,
-) -> (NodeId, ToolNodeMap) {
+    snapping_enabled: bool,
+) -> (NodeId, ToolNodeMap, NodeId) {
     let grab_container = ContainerWidget::new(true).mouse_handler(|_, ctx| {
         match ctx.current_phase() {
             EventPhase::Bubbling | EventPhase::AtTarget | EventPhase::Direct => {
@@ -71,115 +74,146 @@ pub fn create_toolbar(
         },
     );
 
-    let toolbar_style = Style {
-        display: Display::Flex,
-        padding: Rect::<LengthPercentage>::length(5.),
-        gap: Size::<LengthPercentage>::length(5.),
-        ..Default::default()
+    let toolbar_options = RectangleOptions {
+        color: colors::BACKGROUND.into(),
+        rounding: Rounding::all(5.),
+        stroke_width: 3.,
+        stroke_color: colors::BORDER.into(),
+        box_sizing: graphics::BoxSizing::ContentBox,
     };
+    let toolbar_style = reserve_stroke(
+        &toolbar_options,
+        Style {
+            display: Display::Flex,
+            padding: Rect::<LengthPercentage>::length(5.),
+            gap: Size::<LengthPercentage>::length(5.),
+            ..Default::default()
+        },
+    );
 
     let toolbar = tree.new_leaf(
-        BackgroundWidget::new(RectangleOptions {
-            color: colors::BACKGROUND.into(),
-            rounding: Rounding::all(5.),
-            stroke_width: 3.,
-            stroke_color: colors::BORDER.into(),
-            box_sizing: graphics::BoxSizing::ContentBox,
-        })
-        .as_widget(),
+        BackgroundWidget::new(toolbar_options).as_widget(),
         toolbar_style,
     );
 
-    let tools = ToolKind::iter();
-    let mut tool_nodes = ToolNodeMap::zero();
+    let mut tool_nodes = ToolNodeMap::new();
 
-    for tool in tools {
-        let tool_style = Style {
-            display: Display::Flex,
-            justify_content: Some(AlignContent::Center),
-            align_items: Some(AlignItems::Stretch),
-            size: Size::<Dimension>::from_lengths(48., 48.),
-            ..Default::default()
-        };
-        let button_container = ButtonWidget::new(
-            ButtonOptions {
-                pressed: RectangleOptions {
-                    color: PremulColor::new([0.32, 0.32, 0.32, 1.]).into(),
-                    rounding: Rounding::all(5.),
-                    ..Default::default()
-                },
-                active: RectangleOptions {
-                    color: PremulColor::new([0.25, 0.25, 0.25, 1.]).into(),
-                    rounding: Rounding::all(5.),
-                    ..Default::default()
-                },
-                hovered: RectangleOptions {
-                    color: PremulColor::new([0.2, 0.2, 0.2, 1.]).into(),
-                    rounding: Rounding::all(5.),
-                    ..Default::default()
-                },
-                normal: RectangleOptions {
-                    color: colors::BACKGROUND.into(),
-                    rounding: Rounding::all(5.),
-                    ..Default::default()
-                },
-                disabled: RectangleOptions {
-                    color: PremulColor::new([0.3, 0.14, 0.14, 0.5]).into(),
-                    rounding: Rounding::all(5.),
-                    ..Default::default()
-                },
-            },
-            true,
+    for tool in enabled_tools.iter() {
+        let button_node = toolbar_button(
+            tree,
             tool == selected_tool,
-            FADE_DURATION,
-        )
-        .mouse_handler(move |_, ctx| {
-            match ctx.payload().kind {
-                MouseEventKind::Enter => {
-                    ctx.push_messages(vec![Message::CursorIcon(CursorIcon::Pointer)])
-                }
-                MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
-                    ctx.push_messages(vec![Message::SwapTool(tool)])
-                }
-                _ => {}
-            };
-            match ctx.current_phase() {
-                EventPhase::Direct | EventPhase::AtTarget | EventPhase::Bubbling => {
-                    ctx.stop_propagation();
-                }
-                _ => {}
-            }
-        });
+            Message::SwapTool(tool),
+            tool.svg_icon(),
+            &format!("{tool:?} tool"),
+        );
+        tool_nodes.set(tool, button_node);
+        tree.add_child(toolbar, button_node);
+    }
 
-        let button_node = tree.new_leaf(button_container.as_widget(), tool_style);
+    // The snapping toggle is shown as active while snapping is enabled.
+    let snapping_node = toolbar_button(
+        tree,
+        snapping_enabled,
+        Message::ToggleSnapping,
+        include_bytes!("../../../../resources/magnet.svg"),
+        "snapping",
+    );
+    tree.add_child(toolbar, snapping_node);
 
-        tool_nodes.set(tool, button_node);
+    tree.add_child(grab_container_node, toolbar);
+    (grab_container_node, tool_nodes, snapping_node)
+}
 
-        let icon_style = Style {
-            flex_grow: 1.,
-            margin: Rect::length(8.),
-            ..Default::default()
+/// A square toolbar button with an icon, which sends the message when clicked.
+fn toolbar_button(
+    tree: &mut UITree<gui::widgets::Widget<Message>>,
+    active: bool,
+    message: Message,
+    icon: &[u8],
+    name: &str,
+) -> NodeId {
+    let button_style = Style {
+        display: Display::Flex,
+        justify_content: Some(AlignContent::Center),
+        align_items: Some(AlignItems::Stretch),
+        size: Size::<Dimension>::from_lengths(48., 48.),
+        ..Default::default()
+    };
+    let button = ButtonWidget::new(
+        ButtonOptions {
+            pressed: RectangleOptions {
+                color: PremulColor::new([0.32, 0.32, 0.32, 1.]).into(),
+                rounding: Rounding::all(5.),
+                ..Default::default()
+            },
+            active: RectangleOptions {
+                color: PremulColor::new([0.25, 0.25, 0.25, 1.]).into(),
+                rounding: Rounding::all(5.),
+                ..Default::default()
+            },
+            hovered: RectangleOptions {
+                color: PremulColor::new([0.2, 0.2, 0.2, 1.]).into(),
+                rounding: Rounding::all(5.),
+                ..Default::default()
+            },
+            normal: RectangleOptions {
+                color: colors::BACKGROUND.into(),
+                rounding: Rounding::all(5.),
+                ..Default::default()
+            },
+            disabled: RectangleOptions {
+                color: PremulColor::new([0.3, 0.14, 0.14, 0.5]).into(),
+                rounding: Rounding::all(5.),
+                ..Default::default()
+            },
+        },
+        true,
+        active,
+        FADE_DURATION,
+    )
+    .mouse_handler(move |_, ctx| {
+        match ctx.payload().kind {
+            MouseEventKind::Enter => {
+                ctx.push_messages(vec![Message::CursorIcon(CursorIcon::Pointer)])
+            }
+            MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                ctx.push_messages(vec![message])
+            }
+            _ => {}
         };
-        let svg_node = tree.new_leaf(
-            SvgWidget::new(
-                tool.svg_icon().into(),
-                gui::widgets::svg::SvgOptions {
-                    normal: graphics::primitives::SvgOptions {
-                        fill_color: Some(colors::WHITE),
-                        stroke_color: Some(colors::WHITE),
-                        ..Default::default()
-                    },
-                    hover: None,
-                },
-            )
-            .as_widget(),
-            icon_style,
-        );
+        match ctx.current_phase() {
+            EventPhase::Direct | EventPhase::AtTarget | EventPhase::Bubbling => {
+                ctx.stop_propagation();
+            }
+            _ => {}
+        }
+    });
 
-        tree.add_child(button_node, svg_node);
-        tree.add_child(toolbar, button_node);
-    }
+    let button_node = tree.new_leaf(button.as_widget(), button_style);
 
-    tree.add_child(grab_container_node, toolbar);
-    (grab_container_node, tool_nodes)
+    let icon_style = Style {
+        flex_grow: 1.,
+        margin: Rect::length(8.),
+        ..Default::default()
+    };
+    let svg_node = tree.new_leaf(
+        // The icons are bundled, so an invalid one is a bug that should surface on startup.
+        SvgWidget::try_new(
+            icon.into(),
+            gui::widgets::svg::SvgOptions {
+                normal: graphics::primitives::SvgOptions {
+                    fill_color: Some(colors::WHITE),
+                    stroke_color: Some(colors::WHITE),
+                    ..Default::default()
+                },
+                hover: None,
+            },
+        )
+        .unwrap_or_else(|err| panic!("the {name} icon is invalid: {err:#}"))
+        .as_widget(),
+        icon_style,
+    );
+
+    tree.add_child(button_node, svg_node);
+    button_node
 }
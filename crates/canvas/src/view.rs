@@ -1,25 +1,52 @@
-use euclid::default::Size2D;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+
+use color::{PremulColor, Srgb};
+use euclid::default::{Box2D, Point2D, Size2D, Vector2D};
 use graphics::{
+    primitives::{FlipAxis, Svg, SvgOptions, Text, TextOptions},
     systems::{SystemsOwned, TextState, TextureState},
-    Drawable,
+    BlendMode, CanvasCoordinates, Drawable, Mesh, Primitive, Vertex, VertexKind,
 };
-use tracing::{info, instrument};
+use tracing::{info, instrument, trace_span, warn};
 
+use gui::modal::ModalResult;
 use gui::prelude::{EventResult, Redraw};
 use gui::UITree;
-use input::{CursorIcon, KeyboardEvent, Modifiers, MouseEvent};
+use input::{
+    CursorIcon, Key, KeyboardEvent, KeyboardEventKind, Modifiers, MouseButton, MouseEvent,
+    MouseEventKind, SpecialKey,
+};
 use renderer::GrowableMeshBuffer;
 
 use crate::{
-    canvas::Canvas,
-    pipeline::{Binds, DrawPipeline, ProjectionBind},
-    projection::Projection,
-    tools::{ToolKind, ToolMessage, Tools},
+    align::{Alignment, Distribution},
+    canvas::{Canvas, SceneLimits, SceneStats},
+    export::{self, AnimationOutput, ExportOptions},
+    grid::GridStyle,
+    guides::{Guide, GuideAxis, GuideStyle},
+    import,
+    pipeline::{
+        Binds, ColorCorrection, ContrastOutline, ContrastTarget, DrawPipeline, ProjectionBind,
+    },
+    projection::{Projection, YDirection},
+    recorder::Recorder,
+    scene::{DrawBatch, NodeId},
+    session::{SessionInfo, SessionManager},
+    tools::{snap_rotation, EnabledTools, Snapping, ToolKind, ToolMessage, Tools},
     ui::{Application, Message},
-    RedrawRequest,
+    ClickResult, RedrawRequest,
 };
 
-use crate::ui::options::OptionsTree;
+use crate::ui::help::{HelpEntry, ShortcutsHelp};
+use crate::ui::layers::{LayersMessage, LayersPanel};
+use crate::ui::options::{OptionsMessage, OptionsTree};
+use crate::ui::palette::{CommandPalette, PaletteAction, Shortcuts};
+use crate::ui::session::UnsavedChangesPrompt;
+use crate::ui::settings::UiSettings;
+use crate::ui::status::StatusLine;
 
 pub struct View<T: RedrawRequest + Clone + 'static> {
     pub canvas: Canvas,
@@ -33,7 +60,228 @@ pub struct View<T: RedrawRequest + Clone + 'static> {
     pub tools: Tools,
     pub binds: Option<Binds>,
 
+    /// The currently selected canvas nodes.
+    pub selection: Vec<NodeId>,
+    /// The node committed last, which becomes the selection when switching to the select tool.
+    last_commit: Option<NodeId>,
+    /// The node that handled the current select click, it receives drags until the tool is
+    /// released.
+    pub interacting: Option<NodeId>,
+    /// How far the arrow keys move the selection, in world units.
+    pub nudge_step: f32,
+    /// How far the arrow keys move the selection while Shift is held, in world units.
+    pub nudge_large_step: f32,
+    /// How long zooming eases into the new zoom, a zero duration zooms instantly.
+    pub zoom_duration: Duration,
+    /// What clicking on empty canvas does besides starting the tool.
+    pub empty_click: EmptyClick,
+    /// The time and viewport position of the last press on empty canvas, to spot double clicks.
+    last_empty_click: Option<(u32, Point2D<f32>)>,
+    /// The Ctrl shortcuts of the view, the shortcuts help lists whatever is bound when it opens.
+    pub shortcuts: Shortcuts,
+    /// Applied to every color drawn by the view, see [`View::set_color_correction`].
+    color_correction: ColorCorrection,
+    /// Drawn behind the scene when set, see [`View::set_contrast_outline`].
+    contrast_outline: Option<ContrastOutline>,
+    /// Whether flipping the selection mirrors its text as well, instead of keeping it readable.
+    pub mirror_text: bool,
+    /// Exceeding these bakes the oldest nodes or shows a warning in the status line, see
+    /// [`View::scene_stats`].
+    pub scene_limits: SceneLimits,
+    /// Whether the status line is warning about the scene limits.
+    scene_warning: bool,
+    /// Drawn behind the scene when set, see [`View::set_grid`].
+    grid: Option<GridStyle>,
+    /// Drawn through the cursor when set, see [`View::set_crosshair`].
+    crosshair: Option<Crosshair>,
+    /// How the rulers and the guides of the scene look and snap.
+    pub guide_style: GuideStyle,
+    /// Whether the rulers are shown, guides can only be pulled out or moved while they are.
+    rulers: bool,
+    /// The guide being pulled out of a ruler or moved.
+    guide_drag: Option<GuideDrag>,
+
+    pub recorder: Recorder,
+    /// Where sessions are saved to and loaded from, sessions are unavailable while unset.
+    pub sessions: Option<SessionManager>,
+    /// The session to open once the unsaved changes prompt is confirmed.
+    pending_session: Option<PathBuf>,
+    /// Where exported images are written to, exporting is unavailable while unset.
+    pub exports: Option<PathBuf>,
+    /// The file the interface settings are kept in, they only last as long as the view while
+    /// unset, see [`View::load_ui_settings`].
+    pub ui_settings: Option<PathBuf>,
+    /// The last known mouse position in viewport coordinates.
+    pub cursor: Point2D<f32>,
+
     pub redraw_manager: T,
+
+    frame_stats: FrameStats,
+    /// The animated outline around the selection, in viewport coordinates.
+    selection_buffer: GrowableMeshBuffer,
+    /// The background grid, in viewport coordinates.
+    grid_buffer: GrowableMeshBuffer,
+    /// Whether the grid has to be rebuilt even though the projection did not change.
+    grid_dirty: bool,
+    /// The crosshair guides, in viewport coordinates.
+    crosshair_buffer: GrowableMeshBuffer,
+    /// Whether the cursor moved since the crosshair was last built.
+    crosshair_dirty: bool,
+    /// The guides and rulers, in viewport coordinates.
+    guides_buffer: GrowableMeshBuffer,
+    /// Whether the guides have to be rebuilt even though the projection did not change.
+    guides_dirty: bool,
+    /// Whether the cursor is over the view, the crosshair is hidden while it is not.
+    cursor_inside: bool,
+    /// The time the marching ants of the selection outline are animated from.
+    created: Instant,
+    /// The time of the last auto-pan step, set while a drag is held near the viewport edge.
+    auto_pan_frame: Option<Instant>,
+    /// The time of the last nudge, nudges in quick succession are undone together.
+    last_nudge: Option<Instant>,
+    /// The node being turned by the rotate tool.
+    rotating: Option<RotationDrag>,
+}
+
+/// A rotation of a node by dragging around its center.
+#[derive(Clone, Copy, Debug)]
+struct RotationDrag {
+    id: NodeId,
+    center: Point2D<f32>,
+    /// The rotation of the node when the drag started.
+    start_rotation: f32,
+    /// The angle of the pointer around the center when the drag started.
+    start_angle: f32,
+    /// Whether the drag has rotated the node yet, later steps extend the same undo entry.
+    rotated: bool,
+}
+
+/// A guide following the pointer, which is only written to the scene once it is dropped.
+#[derive(Clone, Copy, Debug)]
+struct GuideDrag {
+    guide: Guide,
+    /// The index of the guide in the scene when an existing guide is being moved.
+    existing: Option<usize>,
+}
+
+const SELECTION_DASH: f32 = 6.;
+const SELECTION_GAP: f32 = 4.;
+const SELECTION_WIDTH: f32 = 1.5;
+/// How fast the selection outline dashes move along the outline, in pixels per second.
+const SELECTION_SPEED: f32 = 20.;
+/// How long the selection outline keeps animating after a frame, renewed on every frame that
+/// still has a selection.
+const SELECTION_ANIMATION_STEP: Duration = Duration::from_millis(100);
+
+/// The distance from the viewport edge within which a drag starts panning, in pixels.
+const AUTO_PAN_MARGIN: f32 = 32.;
+/// How fast the canvas pans with the pointer at the very edge, in pixels per second.
+const AUTO_PAN_SPEED: f32 = 800.;
+const AUTO_PAN_STEP: Duration = Duration::from_millis(16);
+
+const ZOOM_DURATION: Duration = Duration::from_millis(150);
+const ZOOM_ANIMATION_STEP: Duration = Duration::from_millis(16);
+const PANEL_ANIMATION_STEP: Duration = Duration::from_millis(16);
+
+/// How far from the pointer the rotation readout is drawn, in world units.
+const ROTATION_LABEL_OFFSET: f32 = 16.;
+
+/// Nudges closer together than this are coalesced into a single undo step.
+const NUDGE_COALESCE: Duration = Duration::from_millis(750);
+
+/// Two presses on empty canvas within this many milliseconds make a double click.
+const DOUBLE_CLICK_TIME: u32 = 400;
+/// How far apart, in viewport pixels, the presses of a double click can be.
+const DOUBLE_CLICK_DISTANCE: f32 = 4.;
+/// The space left around the content when fitting the view to it, in viewport pixels.
+const FIT_MARGIN: f32 = 32.;
+/// The frame rate recorded macros are exported at, which gifs can show exactly.
+const MACRO_EXPORT_FPS: f32 = 25.;
+
+/// What a left click on empty canvas does, besides whatever the tool does with it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EmptyClick {
+    /// Clicking empty canvas with the select tool clears the selection.
+    pub deselect: bool,
+    /// Pressing on empty canvas while holding these modifiers pans the view instead of using
+    /// the tool, whichever tool is selected. `None` always leaves the press to the tool.
+    pub pan_modifiers: Option<Modifiers>,
+    /// Double clicking empty canvas with the select tool fits the view to the content.
+    pub fit_on_double_click: bool,
+}
+
+impl Default for EmptyClick {
+    fn default() -> Self {
+        Self {
+            deselect: true,
+            pan_modifiers: None,
+            fit_on_double_click: true,
+        }
+    }
+}
+
+/// The cursor shown while dragging a guide, which points the way the guide can move.
+const fn guide_cursor(axis: GuideAxis) -> CursorIcon {
+    match axis {
+        GuideAxis::Horizontal => CursorIcon::RowResize,
+        GuideAxis::Vertical => CursorIcon::ColResize,
+    }
+}
+
+/// Thin lines through the cursor across the whole viewport, which help with placing things
+/// precisely when the overlay covers the whole screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crosshair {
+    pub color: PremulColor<Srgb>,
+    /// The width of the lines, in viewport pixels.
+    pub width: f32,
+}
+
+impl Default for Crosshair {
+    fn default() -> Self {
+        Self {
+            color: PremulColor::new([0.25, 0.25, 0.25, 0.5]),
+            width: 1.,
+        }
+    }
+}
+
+impl Crosshair {
+    /// The lines through `cursor`, in viewport coordinates.
+    pub fn mesh(&self, cursor: Point2D<f32>, viewport: Size2D<f32>) -> Mesh<Vertex> {
+        let kind = VertexKind::ColorViewport(self.color);
+        let half = self.width / 2.;
+        Mesh::from_slice(&[
+            Mesh::new_color_quad(
+                Box2D::new(
+                    Point2D::new(0., cursor.y - half),
+                    Point2D::new(viewport.width, cursor.y + half),
+                ),
+                kind,
+            ),
+            Mesh::new_color_quad(
+                Box2D::new(
+                    Point2D::new(cursor.x - half, 0.),
+                    Point2D::new(cursor.x + half, viewport.height),
+                ),
+                kind,
+            ),
+        ])
+    }
+}
+
+/// Timings of the phases of the last rendered frame, in microseconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Time spent computing the gui layout.
+    pub layout_us: u64,
+    /// Time spent tessellating the gui.
+    pub tessellate_us: u64,
+    /// Time spent uploading meshes to the gpu.
+    pub upload_us: u64,
+    /// Time spent encoding, submitting and presenting the frame.
+    pub gpu_submit_us: u64,
+    pub total_us: u64,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -48,6 +296,7 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
         viewport: Size2D<f32>,
         scale_factor: f64,
         redraw_manager: T,
+        enabled_tools: EnabledTools,
     ) -> Self {
         let text = TextState::default();
         let texture = TextureState::new(&renderer.device);
@@ -57,15 +306,29 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
         let canvas = Canvas::new(&mut systems.to_ref(&renderer.device, &renderer.queue));
 
         let mut gui_buffer = GrowableMeshBuffer::new(&renderer.device, 1024, 2048);
+        let selection_buffer = GrowableMeshBuffer::new(&renderer.device, 256, 384);
         let mut gui = UITree::new(viewport, scale_factor);
 
         let root_node = gui.root_node();
 
-        let (toolbar_id, tool_nodes) =
-            crate::ui::toolbar::create_toolbar(&mut gui, ToolKind::default());
+        let selected_tool = enabled_tools.default_tool();
+        let snapping = Snapping::default();
+        let (toolbar_id, tool_nodes, snapping_node) = crate::ui::toolbar::create_toolbar(
+            &mut gui,
+            &enabled_tools,
+            selected_tool,
+            snapping.enabled,
+        );
         _ = gui.add_child(gui.root_node(), toolbar_id);
 
         let options = OptionsTree::build(&mut gui, root_node);
+        let palette = CommandPalette::build(&mut gui, &enabled_tools);
+        let help = ShortcutsHelp::build(&mut gui);
+        let mut layers = LayersPanel::build(&mut gui, root_node);
+        layers.update(&mut gui, canvas.scene.layers(), canvas.scene.active_layer());
+        let mut status = StatusLine::build(&mut gui);
+        status.update(&mut gui, selected_tool, Modifiers::empty());
+        let unsaved_changes = UnsavedChangesPrompt::build(&mut gui);
 
         _ = gui_buffer.replace_with_mesh(
             &renderer.device,
@@ -78,11 +341,20 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
             gui_buffer,
 
             drag_start: None,
+            grab_threshold: crate::ui::GRAB_THRESHOLD,
 
-            selected_tool: ToolKind::default(),
+            selected_tool,
+            enabled_tools,
             tool_nodes,
+            snapping,
+            snapping_node,
 
             options,
+            palette,
+            help,
+            layers,
+            status,
+            unsaved_changes,
 
             modifiers: Modifiers::empty(),
         };
@@ -99,19 +371,418 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
             tools: Tools::default(),
 
             binds: None,
+
+            selection: vec![],
+            last_commit: None,
+            interacting: None,
+            nudge_step: 1.,
+            nudge_large_step: 10.,
+            zoom_duration: ZOOM_DURATION,
+            empty_click: EmptyClick::default(),
+            last_empty_click: None,
+            shortcuts: Shortcuts::default(),
+            color_correction: ColorCorrection::default(),
+            contrast_outline: None,
+            mirror_text: false,
+            grid: None,
+            crosshair: None,
+            guide_style: GuideStyle::default(),
+            rulers: false,
+            guide_drag: None,
+            scene_limits: SceneLimits::default(),
+            scene_warning: false,
+
+            recorder: Recorder::default(),
+            sessions: None,
+            pending_session: None,
+            exports: None,
+            ui_settings: None,
+            cursor: Point2D::zero(),
+
             redraw_manager,
+
+            frame_stats: FrameStats::default(),
+            selection_buffer,
+            grid_buffer: GrowableMeshBuffer::new(&renderer.device, 1024, 1536),
+            grid_dirty: false,
+            crosshair_buffer: GrowableMeshBuffer::new(&renderer.device, 8, 12),
+            crosshair_dirty: false,
+            guides_buffer: GrowableMeshBuffer::new(&renderer.device, 256, 384),
+            guides_dirty: true,
+            cursor_inside: false,
+            created: Instant::now(),
+            auto_pan_frame: None,
+            last_nudge: None,
+            rotating: None,
+        }
+    }
+
+    /// The timings of the last rendered frame.
+    pub const fn last_frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+    /// The current size of the scene, to see how close it is to the [`View::scene_limits`].
+    pub fn scene_stats(&self) -> SceneStats {
+        self.canvas.stats()
+    }
+
+    /// Recreates every gpu resource of the view on the current device of the renderer, after the
+    /// previous device was lost. The scene is kept, the undo history is not.
+    pub fn recover_device(&mut self, renderer: &renderer::State) {
+        self.systems = SystemsOwned::new(TextState::default(), TextureState::new(&renderer.device));
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        if let Err(err) = self.canvas.recover_device(systems) {
+            warn!("failed to rebuild the scene after losing the device: {err:#}");
+            self.selection.clear();
+        }
+        self.tools.text.anchors = self.canvas.text_anchors(systems);
+
+        self.app.gui.clear_caches();
+        self.app.gui_buffer = GrowableMeshBuffer::new(&renderer.device, 1024, 2048);
+        self.selection_buffer = GrowableMeshBuffer::new(&renderer.device, 256, 384);
+        self.grid_buffer = GrowableMeshBuffer::new(&renderer.device, 1024, 1536);
+        self.grid_dirty = true;
+        self.crosshair_buffer = GrowableMeshBuffer::new(&renderer.device, 8, 12);
+        self.crosshair_dirty = true;
+        self.guides_buffer = GrowableMeshBuffer::new(&renderer.device, 256, 384);
+        self.guides_dirty = true;
+        self.binds = None;
+        self.redraw_manager.request_redraw();
+    }
+
+    pub const fn color_correction(&self) -> ColorCorrection {
+        self.color_correction
+    }
+    /// Changes the correction applied to the colors of the view, to tune it to its output.
+    pub fn set_color_correction(
+        &mut self,
+        renderer: &renderer::State,
+        color_correction: ColorCorrection,
+    ) {
+        self.color_correction = color_correction;
+        if let Some(binds) = &self.binds {
+            binds
+                .projection
+                .update_color_correction(renderer, color_correction);
+        }
+        self.redraw_manager.request_redraw();
+    }
+
+    pub const fn contrast_outline(&self) -> Option<ContrastOutline> {
+        self.contrast_outline
+    }
+    /// Draws a dark outline behind all of the canvas content, or stops drawing it with `None`.
+    pub fn set_contrast_outline(&mut self, contrast_outline: Option<ContrastOutline>) {
+        self.contrast_outline = contrast_outline;
+        // The offsets are baked into the binds, they are created again on the next frame.
+        if let Some(binds) = &mut self.binds {
+            binds.contrast = None;
+            binds.contrast_target = None;
+        }
+        self.redraw_manager.request_redraw();
+    }
+    pub fn toggle_contrast_outline(&mut self) {
+        let outline = match self.contrast_outline {
+            Some(_) => None,
+            None => Some(ContrastOutline::default()),
+        };
+        self.set_contrast_outline(outline);
+        info!("contrast outline enabled: {}", outline.is_some());
+    }
+
+    pub const fn grid(&self) -> Option<GridStyle> {
+        self.grid
+    }
+    /// Draws a grid behind the scene, or stops drawing it with `None`.
+    pub fn set_grid(&mut self, grid: Option<GridStyle>) {
+        self.grid = grid;
+        self.grid_dirty = true;
+        self.redraw_manager.request_redraw();
+    }
+    /// Cycles the grid through each [`GridKind`](crate::grid::GridKind) and then off again.
+    pub fn cycle_grid(&mut self) {
+        let grid = match self.grid {
+            None => Some(GridStyle::default()),
+            Some(grid) => grid.kind.next().map(|kind| GridStyle { kind, ..grid }),
+        };
+        self.set_grid(grid);
+        info!("grid: {:?}", grid.map(|grid| grid.kind));
+    }
+
+    pub const fn crosshair(&self) -> Option<Crosshair> {
+        self.crosshair
+    }
+    /// Draws guide lines through the cursor, or stops drawing them with `None`.
+    pub fn set_crosshair(&mut self, crosshair: Option<Crosshair>) {
+        self.crosshair = crosshair;
+        self.crosshair_dirty = true;
+        self.redraw_manager.request_redraw();
+    }
+    pub fn toggle_crosshair(&mut self) {
+        let crosshair = match self.crosshair {
+            Some(_) => None,
+            None => Some(Crosshair::default()),
+        };
+        self.set_crosshair(crosshair);
+        info!("crosshair enabled: {}", crosshair.is_some());
+    }
+
+    pub const fn rulers(&self) -> bool {
+        self.rulers
+    }
+    /// Shows or hides the rulers along the top and left of the view, the guides stay visible.
+    pub fn set_rulers(&mut self, rulers: bool) {
+        self.rulers = rulers;
+        if !rulers {
+            self.guide_drag = None;
+        }
+        self.guides_dirty = true;
+        self.redraw_manager.request_redraw();
+    }
+    pub fn toggle_rulers(&mut self) {
+        self.set_rulers(!self.rulers);
+        info!("rulers enabled: {}", self.rulers);
+    }
+    /// Removes every guide from the scene.
+    pub fn clear_guides(&mut self) {
+        self.canvas.clear_guides();
+        self.guide_drag = None;
+        self.guides_dirty = true;
+        self.redraw_manager.request_redraw();
+    }
+
+    /// Starts dragging a new guide out of a ruler, or an existing guide near the pointer, on a
+    /// left click while the rulers are shown.
+    ///
+    /// Existing guides can only be grabbed with the select and grab tools, the drawing tools
+    /// would otherwise lose every press that lands close to a guide.
+    fn start_guide_drag(&mut self, event: &MouseEvent) -> Option<CursorIcon> {
+        let MouseEventKind::Press {
+            button: MouseButton::Left,
+            ..
+        } = event.kind
+        else {
+            return None;
+        };
+        if !self.rulers {
+            return None;
+        }
+        let world = self.projection.viewport_to_world(event.position);
+        let drag = match self.guide_style.ruler_at(event.position) {
+            Some(axis) => GuideDrag {
+                guide: Guide::through(axis, world),
+                existing: None,
+            },
+            None if matches!(self.app.selected_tool, ToolKind::Select | ToolKind::Grab) => {
+                let guides = self.canvas.guides();
+                let index = self
+                    .guide_style
+                    .guide_at(guides, &self.projection, event.position)?;
+                GuideDrag {
+                    guide: guides[index],
+                    existing: Some(index),
+                }
+            }
+            None => return None,
+        };
+        self.guide_drag = Some(drag);
+        self.guides_dirty = true;
+        self.redraw_manager.request_redraw();
+        Some(guide_cursor(drag.guide.axis))
+    }
+
+    /// Moves the dragged guide with the pointer, and drops it on release. Dropping it back onto
+    /// a ruler or outside of the view removes it. Returns `None` when no guide is being dragged.
+    fn drag_guide(&mut self, event: &MouseEvent) -> Option<CursorIcon> {
+        let drag = self.guide_drag.as_mut()?;
+        let world = self.projection.viewport_to_world(event.position);
+        self.guides_dirty = true;
+        self.redraw_manager.request_redraw();
+        match event.kind {
+            MouseEventKind::Release {
+                button: MouseButton::Left,
+                ..
+            }
+            | MouseEventKind::Leave => {}
+            MouseEventKind::Motion { .. } => {
+                drag.guide = Guide::through(drag.guide.axis, world);
+                return Some(guide_cursor(drag.guide.axis));
+            }
+            _ => return Some(guide_cursor(drag.guide.axis)),
+        }
+
+        let drag = self.guide_drag.take()?;
+        let viewport = self.projection.get_viewport();
+        let ruler = self.guide_style.ruler_size;
+        let removed = event.kind == MouseEventKind::Leave
+            || event.position.x < ruler
+            || event.position.y < ruler
+            || event.position.x > viewport.width
+            || event.position.y > viewport.height;
+        match (drag.existing, removed) {
+            (Some(index), true) => {
+                _ = self.canvas.remove_guide(index);
+            }
+            (Some(index), false) => self.canvas.set_guide(index, drag.guide),
+            (None, false) => {
+                _ = self.canvas.add_guide(drag.guide);
+            }
+            (None, true) => {}
+        }
+        Some(self.tool_cursor())
+    }
+
+    /// Pans and zooms the view so that all of the content fits into it.
+    pub fn zoom_to_content(&mut self) {
+        let Some(target) = self
+            .canvas
+            .content_bounds()
+            .and_then(|bounds| self.projection.fit_transform(bounds, FIT_MARGIN))
+        else {
+            return;
+        };
+        self.projection.animate_to(target, self.zoom_duration);
+        self.redraw_manager.request_redraw();
+    }
+
+    /// Handles a left press on empty canvas as configured by [`View::empty_click`], returning
+    /// the cursor if the press was used up and should not reach the selected tool.
+    fn empty_canvas_press(
+        &mut self,
+        event: MouseEvent,
+        renderer: &renderer::State,
+    ) -> Option<CursorIcon> {
+        let MouseEventKind::Press {
+            button: MouseButton::Left,
+            time,
+        } = event.kind
+        else {
+            return None;
+        };
+        let world = self.projection.viewport_to_world(event.position);
+        if !self.canvas.nodes_at_position(world).is_empty() {
+            self.last_empty_click = None;
+            return None;
+        }
+        let double_click = self
+            .last_empty_click
+            .replace((time, event.position))
+            .is_some_and(|(last, position)| {
+                time.wrapping_sub(last) <= DOUBLE_CLICK_TIME
+                    && (position - event.position).length() <= DOUBLE_CLICK_DISTANCE
+            });
+
+        if let Some(pan) = self.empty_click.pan_modifiers
+            && !pan.is_empty()
+            && self.app.modifiers.contains(pan)
+        {
+            // The grab tool takes the focus, so the rest of the drag goes to it as well.
+            let messages = ToolKind::Grab.mouse_event(
+                &mut self.systems.to_ref(&renderer.device, &renderer.queue),
+                &mut self.tools,
+                event,
+                self.app.modifiers,
+                &self.projection,
+            );
+            return Some(
+                self.handle_tool(ToolKind::Grab, messages, renderer)
+                    .unwrap_or(CursorIcon::Grabbing),
+            );
+        }
+        if self.app.selected_tool != ToolKind::Select {
+            return None;
+        }
+        // Clearing an empty selection would only cause a pointless redraw.
+        if self.empty_click.deselect && !self.selection.is_empty() {
+            self.selection.clear();
+            self.interacting = None;
+            self.redraw_manager.request_redraw();
+        }
+        if self.empty_click.fit_on_double_click && double_click {
+            self.last_empty_click = None;
+            self.zoom_to_content();
+        }
+        None
+    }
+
+    /// Snaps the pointer of a tool event onto the guides near it while snapping is active.
+    fn snap_to_guides(&self, tool: ToolKind, event: MouseEvent) -> MouseEvent {
+        let guides = self.canvas.guides();
+        // Panning and zooming follow the pointer itself, so they never snap.
+        if guides.is_empty()
+            || matches!(tool, ToolKind::Grab | ToolKind::Zoom)
+            || matches!(event.kind, MouseEventKind::Enter | MouseEventKind::Leave)
+            || !self.app.snapping.is_active(self.app.modifiers)
+        {
+            return event;
+        }
+        MouseEvent {
+            position: self
+                .guide_style
+                .snap(guides, &self.projection, event.position),
+            ..event
         }
     }
 
     pub fn update_viewport(&mut self, viewport: Size2D<f32>, scale_factor: f64) {
         self.app.gui.update_viewport(viewport, scale_factor);
         self.projection.set_viewport(viewport);
+        self.app.status.update_viewport(&mut self.app.gui);
+        self.crosshair_dirty = true;
         self.redraw_manager.request_redraw();
         info!("Updated the viewport size to: {viewport:?}!");
     }
 
     pub fn keyboard_event(&mut self, event: KeyboardEvent, renderer: &renderer::State) {
         self.app.modifiers = event.modifiers;
+        let (tool, modifiers) = (self.app.selected_tool, event.modifiers);
+        if self.app.status.update(&mut self.app.gui, tool, modifiers) {
+            self.redraw_manager.request_redraw();
+        }
+
+        // The command palette takes all keyboard input while it is open.
+        if self.app.palette.is_open() {
+            if let Some(action) = self.app.palette.keyboard_event(&mut self.app.gui, &event) {
+                self.run_palette_action(action, renderer);
+            }
+            self.redraw_manager.request_redraw();
+            return;
+        }
+        // As do the modals.
+        if self.app.unsaved_changes.is_open() {
+            if let Some(result) = self
+                .app
+                .unsaved_changes
+                .keyboard_event(&mut self.app.gui, &event)
+            {
+                self.resolve_unsaved_changes(result, renderer);
+            }
+            self.redraw_manager.request_redraw();
+            return;
+        }
+        // So does the shortcuts help.
+        if self.app.help.is_open() {
+            self.app.help.keyboard_event(&mut self.app.gui, &event);
+            self.redraw_manager.request_redraw();
+            return;
+        }
+        if self.focused_tool.is_none() {
+            if CommandPalette::is_open_shortcut(&event) {
+                self.app.palette.open(&mut self.app.gui);
+                self.redraw_manager.request_redraw();
+                return;
+            }
+            if ShortcutsHelp::is_toggle_shortcut(&event) {
+                let entries = HelpEntry::all(&self.shortcuts, &self.app.enabled_tools);
+                self.app.help.open(&mut self.app.gui, entries);
+                self.redraw_manager.request_redraw();
+                return;
+            }
+            if let Some(action) = self.shortcuts.action(&event) {
+                self.run_palette_action(action, renderer);
+                return;
+            }
+        }
 
         // If we are currently focused on a tool, pass the event to the tool handler .
         if let Some(tool) = &self.focused_tool {
@@ -125,7 +796,9 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
         };
         // Otherwise pass the event to the gui event handler
         if let Some(events) = self.app.gui.keyboard_event(event.clone()) {
-            self.handle_gui(events);
+            self.handle_gui(events, renderer);
+        } else if self.nudge_selection(&event, renderer) {
+            self.redraw_manager.request_redraw();
         } else {
             let messages = self.app.selected_tool.keyboard_event(
                 &mut self.systems.to_ref(&renderer.device, &renderer.queue),
@@ -136,15 +809,248 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
         }
     }
 
+    /// Turns every kind of snapping on or off at once, holding Ctrl still inverts it.
+    pub fn toggle_snapping(&mut self) {
+        let snapping = Snapping {
+            enabled: !self.app.snapping.enabled,
+        };
+        self.app.snapping = snapping;
+        self.tools.set_snapping(snapping);
+        if let Some(button) = self
+            .app
+            .gui
+            .get_node_mut(self.app.snapping_node)
+            .as_button_mut()
+        {
+            button.set_active(snapping.enabled);
+        }
+        info!("snapping enabled: {}", snapping.enabled);
+        self.redraw_manager.request_redraw();
+    }
+
+    /// Whether the user is in the middle of something, such as a drag or a pan and zoom
+    /// animation, which keeps the overlay from timing out.
+    ///
+    /// The marching ants around the selection keep redrawing for as long as anything is
+    /// selected, but they are only decoration and don't count.
+    pub fn is_busy(&self) -> bool {
+        self.focused_tool.is_some()
+            || self.guide_drag.is_some()
+            || self.auto_pan_frame.is_some()
+            || self.projection.is_animating()
+    }
+
+    /// Whether Escape is handled by the view, by a tool that holds focus such as text being typed
+    /// or by an open palette or modal, in which case it should not hide the overlay.
+    pub fn captures_escape(&self) -> bool {
+        self.focused_tool.is_some()
+            || self.app.palette.is_open()
+            || self.app.unsaved_changes.is_open()
+            || self.app.help.is_open()
+    }
+
+    /// Whether the view is idle enough for app-level shortcuts, such as copy and paste, to act on
+    /// it.
+    pub fn accepts_shortcuts(&self) -> bool {
+        self.focused_tool.is_none() && !self.app.palette.is_open() && !self.app.help.is_open()
+    }
+
+    /// Serializes the selected nodes, so they can be pasted into any view.
+    ///
+    /// Only the description of the primitives is kept, each view tessellates them again with
+    /// its own atlases.
+    pub fn copy_selection(&self) -> Option<String> {
+        let nodes = self
+            .selection
+            .iter()
+            .filter_map(|id| self.canvas.primitive(*id))
+            .collect::<Vec<_>>();
+        if nodes.is_empty() {
+            return None;
+        }
+        serde_json::to_string(&nodes)
+            .inspect_err(|err| warn!("failed to serialize the selection: {err}"))
+            .ok()
+    }
+
+    /// The mesh, its batches and the world region to export for "export selection", tight to the
+    /// selected nodes.
+    ///
+    /// With nothing selected this falls back to the whole canvas and its export region.
+    pub fn export_selection(
+        &mut self,
+        renderer: &renderer::State,
+    ) -> Option<(Mesh<Vertex>, Vec<DrawBatch>, Box2D<f32>)> {
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        if self.selection.is_empty() {
+            let region = self.canvas.export_region()?;
+            let (mesh, batches) = self.canvas.export_mesh(systems);
+            return Some((mesh, batches, region));
+        }
+        let region = self.canvas.nodes_bounds(&self.selection)?;
+        let (mesh, batches) = self.canvas.export_nodes_mesh(systems, &self.selection);
+        Some((mesh, batches, region))
+    }
+
+    /// Copies the selection and then removes it from the canvas.
+    pub fn cut_selection(&mut self, renderer: &renderer::State) -> Option<String> {
+        let copied = self.copy_selection()?;
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        let selection = std::mem::take(&mut self.selection);
+        self.canvas.remove_nodes(systems, &selection);
+        self.tools.text.anchors = self.canvas.text_anchors(systems);
+        self.redraw_manager.request_redraw();
+        Some(copied)
+    }
+
+    /// Adds nodes from [`View::copy_selection`] centered on the cursor, selecting them.
+    pub fn paste(&mut self, copied: &str, renderer: &renderer::State) {
+        let mut nodes: Vec<Primitive<CanvasCoordinates>> = match serde_json::from_str(copied) {
+            Ok(nodes) => nodes,
+            Err(err) => {
+                warn!("failed to deserialize the pasted nodes: {err}");
+                return;
+            }
+        };
+        let Some(bounds) = nodes
+            .iter()
+            .map(|node| node.bounding_box())
+            .reduce(|a, b| a.union(&b))
+        else {
+            return;
+        };
+        let offset = self.projection.viewport_to_world(self.cursor) - bounds.center();
+
+        for node in &mut nodes {
+            node.translate(offset);
+        }
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        self.selection = self.canvas.add_nodes(systems, nodes);
+        self.tools.text.anchors = self.canvas.text_anchors(systems);
+        self.redraw_manager.request_redraw();
+    }
+
+    /// Adds a png pasted from the clipboard centered in the viewport at its own size on screen,
+    /// selecting it.
+    ///
+    /// Images larger than the atlas are scaled down to fit it, see [`import::pasted_png`].
+    pub fn paste_image(&mut self, png: &[u8], renderer: &renderer::State) -> Result<()> {
+        let max_dimension = renderer.device.limits().max_texture_dimension_2d;
+        let image = import::pasted_png(png, max_dimension)?;
+
+        let center = (self.projection.get_viewport().to_vector() / 2.).to_point();
+        let size = image.size.to_f32() / self.projection.scale();
+        let origin = self.projection.viewport_to_world(center) - size.to_vector() / 2.;
+        let node = Primitive::Svg(Svg::new(origin, size, image.svg, SvgOptions::default()));
+
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        self.selection = vec![self.canvas.add_node(systems, node)];
+        self.redraw_manager.request_redraw();
+        Ok(())
+    }
+
+    /// Moves the selection with the arrow keys, returning whether the event was used.
+    fn nudge_selection(&mut self, event: &KeyboardEvent, renderer: &renderer::State) -> bool {
+        let KeyboardEventKind::Press(Key::SpecialKey(key)) = &event.kind else {
+            return false;
+        };
+        // Up moves towards the top of the screen, whichever way the world y axis points.
+        let up = match self.projection.y_direction() {
+            YDirection::Down => -1.,
+            YDirection::Up => 1.,
+        };
+        let direction = match key {
+            SpecialKey::Left => Vector2D::new(-1., 0.),
+            SpecialKey::Right => Vector2D::new(1., 0.),
+            SpecialKey::Up => Vector2D::new(0., up),
+            SpecialKey::Down => Vector2D::new(0., -up),
+            _ => return false,
+        };
+        if self.selection.is_empty() {
+            return false;
+        }
+        let step = if event.modifiers.intersects(Modifiers::SHIFT) {
+            self.nudge_large_step
+        } else {
+            self.nudge_step
+        };
+
+        let now = Instant::now();
+        let coalesce = self
+            .last_nudge
+            .is_some_and(|last| now.duration_since(last) < NUDGE_COALESCE);
+        self.last_nudge = Some(now);
+
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        self.canvas
+            .translate_nodes(systems, &self.selection, direction * step, coalesce);
+        self.tools.text.anchors = self.canvas.text_anchors(systems);
+        true
+    }
+
+    /// Mirrors the selection about its center, the selection outline follows on the next frame.
+    pub fn flip_selection(&mut self, axis: FlipAxis, renderer: &renderer::State) {
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        if self
+            .canvas
+            .flip_nodes(systems, &self.selection, axis, self.mirror_text)
+        {
+            self.tools.text.anchors = self.canvas.text_anchors(systems);
+            self.redraw_manager.request_redraw();
+        }
+    }
+
+    /// Lines the selected nodes up, which needs at least two of them.
+    pub fn align_selection(&mut self, alignment: Alignment, renderer: &renderer::State) {
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        if self.canvas.align_nodes(systems, &self.selection, alignment) {
+            self.tools.text.anchors = self.canvas.text_anchors(systems);
+            self.redraw_manager.request_redraw();
+        } else {
+            info!(
+                "aligning needs at least {} selected nodes",
+                Alignment::MIN_NODES
+            );
+        }
+    }
+
+    /// Spreads the selected nodes out evenly, which needs at least three of them.
+    pub fn distribute_selection(&mut self, distribution: Distribution, renderer: &renderer::State) {
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        if self
+            .canvas
+            .distribute_nodes(systems, &self.selection, distribution)
+        {
+            self.tools.text.anchors = self.canvas.text_anchors(systems);
+            self.redraw_manager.request_redraw();
+        } else {
+            info!(
+                "distributing needs at least {} selected nodes",
+                Distribution::MIN_NODES
+            );
+        }
+    }
+
     // TODO: support multiple pointers.
     pub fn mouse_event(
         &mut self,
         event: MouseEvent,
         renderer: &renderer::State,
     ) -> Option<CursorIcon> {
+        self.cursor = event.position;
+        self.cursor_inside = event.kind != MouseEventKind::Leave;
+        // The guides only need their own small mesh rebuilt, the scene is left alone.
+        if self.crosshair.is_some() {
+            self.crosshair_dirty = true;
+            self.redraw_manager.request_redraw();
+        }
+        if let Some(cursor) = self.drag_guide(&event) {
+            return Some(cursor);
+        }
         // If we are currently focused on a tool, handle the event for the tool.
         if let Some(tool) = self.focused_tool {
             self.last_interaction = InteractionKind::Tool(tool);
+            let event = self.snap_to_guides(tool, event);
             let messages = tool.mouse_event(
                 &mut self.systems.to_ref(&renderer.device, &renderer.queue),
                 &mut self.tools,
@@ -152,7 +1058,16 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
                 self.app.modifiers,
                 &self.projection,
             );
-            return self.handle_tool(tool, messages, renderer);
+            let result = self.handle_tool(tool, messages, renderer);
+            if self.auto_pan_velocity().is_some() && self.auto_pan_frame.is_none() {
+                self.redraw_manager.request_redraw();
+            }
+            // A tool can hold the focus in place of the selected one, such as the grab tool
+            // while panning from empty canvas, so the cursor goes back once it lets go.
+            if self.focused_tool.is_none() && tool != self.app.selected_tool {
+                return Some(self.tool_cursor());
+            }
+            return result;
         }
 
         // Otherwise, pass the event to the gui, which returns none if it did not hit.
@@ -169,7 +1084,10 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
             };
 
             self.last_interaction = InteractionKind::Gui;
-            return self.handle_gui(result);
+            return self.handle_gui(result, renderer);
+        }
+        if let Some(cursor) = self.start_guide_drag(&event) {
+            return Some(cursor);
         }
         // Otherwise, since the event wasn't for the gui, pass it on to the selected tool,
         // here we need to handle enter/exit events for the tools if the selected tool has changed.
@@ -193,7 +1111,7 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
                 );
                 result = self
                     .handle_tool(self.app.selected_tool, enter_messages, renderer)
-                    .or(Some(self.app.selected_tool.default_cursor()))
+                    .or(Some(self.tool_cursor()))
             }
             InteractionKind::Gui => {
                 let messages = self.app.selected_tool.mouse_event(
@@ -205,12 +1123,17 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
                 );
                 result = self
                     .handle_tool(self.app.selected_tool, messages, renderer)
-                    .or(Some(self.app.selected_tool.default_cursor()))
+                    .or(Some(self.tool_cursor()))
             }
             _ => {}
         }
         self.last_interaction = InteractionKind::Tool(self.app.selected_tool);
 
+        if let Some(cursor) = self.empty_canvas_press(event, renderer) {
+            return Some(cursor);
+        }
+        let kind = event.kind;
+        let event = self.snap_to_guides(self.app.selected_tool, event);
         let messages = self.app.selected_tool.mouse_event(
             &mut self.systems.to_ref(&renderer.device, &renderer.queue),
             &mut self.tools,
@@ -218,17 +1141,69 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
             self.app.modifiers,
             &self.projection,
         );
-        self.handle_tool(self.app.selected_tool, messages, renderer)
-            .or(result)
+        let cursor = self
+            .handle_tool(self.app.selected_tool, messages, renderer)
+            .or(result);
+        // Entering the canvas does not always produce a cursor message, so the cursor would
+        // otherwise be left as it was outside of the surface until the tool changes it.
+        match kind {
+            MouseEventKind::Enter => cursor.or(Some(self.tool_cursor())),
+            _ => cursor,
+        }
+    }
+
+    /// The cursor the tool under the pointer currently wants.
+    pub fn tool_cursor(&self) -> CursorIcon {
+        let tool = self.focused_tool.unwrap_or(self.app.selected_tool);
+        tool.cursor(&self.tools, self.app.modifiers)
+    }
+
+    /// The cursor to show after the modifiers changed, `None` while the pointer is over the gui.
+    pub fn current_cursor(&self) -> Option<CursorIcon> {
+        match self.last_interaction {
+            InteractionKind::Tool(_) => Some(self.tool_cursor()),
+            InteractionKind::Gui => None,
+        }
     }
 
-    pub fn handle_gui(&mut self, events: EventResult<Message>) -> Option<CursorIcon> {
+    pub fn handle_gui(
+        &mut self,
+        events: EventResult<Message>,
+        renderer: &renderer::State,
+    ) -> Option<CursorIcon> {
         let mut cursor_icon = None;
         for (node, messages) in events.messages() {
             for message in messages {
+                if let Message::PaletteSelect(row) = message {
+                    if let Some(action) = self.app.palette.select(&mut self.app.gui, *row) {
+                        self.run_palette_action(action, renderer);
+                    }
+                    self.redraw_manager.request_redraw();
+                    continue;
+                }
+                if let Message::Layers(message) = message {
+                    self.handle_layers_message(*message, renderer);
+                    continue;
+                }
+                if let Message::SessionConfirm(result) = message {
+                    self.app.unsaved_changes.close(&mut self.app.gui);
+                    self.resolve_unsaved_changes(*result, renderer);
+                    self.redraw_manager.request_redraw();
+                    continue;
+                }
+                if let Message::ToggleSnapping = message {
+                    self.toggle_snapping();
+                    continue;
+                }
+                let previous_tool = self.app.selected_tool;
                 cursor_icon =
                     crate::ui::handle_message(&mut self.app, *node, message, &self.redraw_manager)
                         .or(cursor_icon);
+                self.tool_swapped(previous_tool);
+                if let Message::ToolOptions(OptionsMessage::ToggleCollapsed) = message {
+                    self.redraw_manager.request_redraw();
+                    self.save_ui_settings();
+                }
             }
         }
 
@@ -241,46 +1216,579 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
         cursor_icon
     }
 
-    pub fn handle_tool(
-        &mut self,
-        tool: ToolKind,
-        messages: Vec<ToolMessage>,
-        renderer: &renderer::State,
-    ) -> Option<CursorIcon> {
-        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
-        let mut cursor_icon = None;
+    /// Selects the node committed last when switching to the select tool, so whatever was just
+    /// drawn can be moved right away.
+    fn tool_swapped(&mut self, previous_tool: ToolKind) {
+        if previous_tool == self.app.selected_tool || self.app.selected_tool != ToolKind::Select {
+            return;
+        }
+        if let Some(id) = self.last_commit.take()
+            && self.canvas.primitive(id).is_some()
+        {
+            self.selection = vec![id];
+            self.redraw_manager.request_redraw();
+        }
+    }
+
+    fn sessions(&mut self) -> Result<&mut SessionManager> {
+        self.sessions
+            .as_mut()
+            .ok_or_else(|| anyhow!("no session directory is configured"))
+    }
+
+    /// Reads the interface settings from `path` and applies them, they are saved back there
+    /// whenever they change.
+    pub fn load_ui_settings(&mut self, path: PathBuf) {
+        match UiSettings::load(&path) {
+            Ok(settings) => self
+                .app
+                .options
+                .set_collapsed_now(&mut self.app.gui, settings.options_collapsed),
+            Err(err) => warn!("failed to load the interface settings: {err:#}"),
+        }
+        self.ui_settings = Some(path);
+        self.redraw_manager.request_redraw();
+    }
+
+    fn save_ui_settings(&self) {
+        let Some(path) = &self.ui_settings else {
+            return;
+        };
+        let settings = UiSettings {
+            options_collapsed: self.app.options.is_collapsed(),
+        };
+        if let Err(err) = settings.save(path) {
+            warn!("failed to save the interface settings: {err:#}");
+        }
+    }
+
+    /// Every saved session, most recently modified first.
+    pub fn list_sessions(&self) -> Result<Vec<SessionInfo>> {
+        match &self.sessions {
+            Some(sessions) => sessions.list(),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Whether the canvas changed since the session was last saved or loaded.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.sessions
+            .as_ref()
+            .is_some_and(|sessions| sessions.is_dirty(self.canvas.revision()))
+    }
+
+    /// Saves the canvas to the open session, or to a new timestamped one if none is open.
+    pub fn save_session(&mut self) -> Result<PathBuf> {
+        let revision = self.canvas.revision();
+        let path = self
+            .sessions
+            .as_mut()
+            .ok_or_else(|| anyhow!("no session directory is configured"))?
+            .save(&self.canvas.scene, revision)?;
+        info!("saved the session to {path:?}");
+        Ok(path)
+    }
+
+    /// Exports the export region of the canvas as a png in the export directory, returning the
+    /// path it was written to.
+    pub fn export_png(
+        &mut self,
+        options: ExportOptions,
+        renderer: &renderer::State,
+    ) -> Result<PathBuf> {
+        let region = self
+            .canvas
+            .export_region()
+            .context("there is nothing to export")?;
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        let (mesh, batches) = self.canvas.export_mesh(systems);
+        self.write_png(&mesh, &batches, region, options, renderer)
+    }
+
+    /// Exports only the selection as a png tight to it, see [`View::export_selection`].
+    pub fn export_selection_png(
+        &mut self,
+        options: ExportOptions,
+        renderer: &renderer::State,
+    ) -> Result<PathBuf> {
+        let (mesh, batches, region) = self
+            .export_selection(renderer)
+            .context("there is nothing to export")?;
+        self.write_png(&mesh, &batches, region, options, renderer)
+    }
+
+    fn write_png(
+        &mut self,
+        mesh: &Mesh<Vertex>,
+        batches: &[DrawBatch],
+        region: Box2D<f32>,
+        options: ExportOptions,
+        renderer: &renderer::State,
+    ) -> Result<PathBuf> {
+        let image = export::render(
+            renderer,
+            &self.systems.texture,
+            mesh,
+            batches,
+            region,
+            self.projection.y_direction(),
+        )?;
+        let path = self.export_path("png")?;
+        std::fs::write(&path, image.encode_png(&options))
+            .with_context(|| format!("failed to write the export {path:?}"))?;
+        info!("exported the canvas to {path:?}");
+        Ok(path)
+    }
+
+    /// Exports a playback of the recorded macro, see [`export::export_animation`].
+    pub fn export_animation(
+        &mut self,
+        fps: f32,
+        out: &AnimationOutput,
+        renderer: &renderer::State,
+    ) -> Result<()> {
+        let log = self
+            .recorder
+            .recorded()
+            .filter(|_| !self.recorder.is_recording())
+            .context("no macro has been recorded")?;
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        export::export_animation(
+            renderer,
+            systems,
+            log,
+            fps,
+            out,
+            self.projection.y_direction(),
+        )?;
+        info!("exported the macro to {out:?}");
+        Ok(())
+    }
+
+    /// A new file in the export directory, named by the current time.
+    fn export_path(&self, extension: &str) -> Result<PathBuf> {
+        let directory = self
+            .exports
+            .as_ref()
+            .ok_or_else(|| anyhow!("no export directory is configured"))?;
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("failed to create the export directory {directory:?}"))?;
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis());
+        Ok(directory.join(format!("export-{millis}.{extension}")))
+    }
+
+    /// Saves the canvas as a new session, named by the current time if `name` is `None`.
+    pub fn save_session_as(&mut self, name: Option<&str>) -> Result<PathBuf> {
+        let name = name.map_or_else(SessionManager::timestamped_name, str::to_string);
+        let revision = self.canvas.revision();
+        let path = self
+            .sessions
+            .as_mut()
+            .ok_or_else(|| anyhow!("no session directory is configured"))?
+            .save_as(&self.canvas.scene, revision, &name)?;
+        info!("saved the session to {path:?}");
+        Ok(path)
+    }
+
+    /// Replaces the canvas with a saved session, returning whether it was opened.
+    ///
+    /// With unsaved changes the session is only opened once the prompt asking to discard them is
+    /// confirmed, in which case this returns `false`.
+    pub fn open_session(&mut self, path: &Path, renderer: &renderer::State) -> Result<bool> {
+        self.sessions()?;
+        if self.has_unsaved_changes() {
+            self.pending_session = Some(path.to_path_buf());
+            self.app.unsaved_changes.open(&mut self.app.gui);
+            self.redraw_manager.request_redraw();
+            return Ok(false);
+        }
+        self.load_session(path, renderer)?;
+        Ok(true)
+    }
+
+    fn load_session(&mut self, path: &Path, renderer: &renderer::State) -> Result<()> {
+        let scene = self.sessions()?.load(path)?;
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        self.canvas.replace_scene(systems, scene);
+        self.selection.clear();
+        self.last_commit = None;
+        self.interacting = None;
+        self.tools.text.anchors = self.canvas.text_anchors(systems);
+        self.guide_drag = None;
+        self.guides_dirty = true;
+        let revision = self.canvas.revision();
+        self.sessions()?.mark_saved(revision);
+        let scene = &self.canvas.scene;
+        self.app
+            .layers
+            .update(&mut self.app.gui, scene.layers(), scene.active_layer());
+        self.redraw_manager.request_redraw();
+        info!("opened the session {path:?}");
+        Ok(())
+    }
+
+    fn resolve_unsaved_changes(&mut self, result: ModalResult, renderer: &renderer::State) {
+        let Some(path) = self.pending_session.take() else {
+            return;
+        };
+        if result == ModalResult::Confirm
+            && let Err(err) = self.load_session(&path, renderer)
+        {
+            warn!("failed to open the session {path:?}: {err:#}");
+        }
+    }
+
+    pub fn handle_layers_message(&mut self, message: LayersMessage, renderer: &renderer::State) {
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        let layer_at_row = |row| {
+            self.app
+                .layers
+                .layer_at_row(row)
+                .and_then(|id| self.canvas.scene.layer(id))
+                .cloned()
+        };
+        match message {
+            LayersMessage::Select(row) => {
+                if let Some(layer) = layer_at_row(row) {
+                    self.canvas.scene.set_active_layer(layer.id);
+                }
+            }
+            LayersMessage::ToggleVisible(row) => {
+                if let Some(layer) = layer_at_row(row) {
+                    self.canvas
+                        .set_layer_visible(systems, layer.id, !layer.visible);
+                }
+            }
+            LayersMessage::ToggleLocked(row) => {
+                if let Some(layer) = layer_at_row(row) {
+                    self.canvas.set_layer_locked(layer.id, !layer.locked);
+                }
+            }
+            LayersMessage::Add => {
+                let name = format!("Layer {}", self.canvas.scene.layers().len() + 1);
+                self.canvas.scene.add_layer(name);
+            }
+            LayersMessage::Scroll(delta) => {
+                self.app.layers.scroll(
+                    &mut self.app.gui,
+                    self.canvas.scene.layers(),
+                    self.canvas.scene.active_layer(),
+                    delta,
+                );
+            }
+        }
+        // Nodes on hidden or locked layers can no longer be edited, so drop them from the
+        // selection.
+        let scene = &self.canvas.scene;
+        self.selection.retain(|id| scene.is_node_editable(*id));
+        self.tools.text.anchors = self.canvas.text_anchors(systems);
+
+        self.app.layers.update(
+            &mut self.app.gui,
+            self.canvas.scene.layers(),
+            self.canvas.scene.active_layer(),
+        );
+        self.redraw_manager.request_redraw();
+    }
+
+    pub fn run_palette_action(&mut self, action: PaletteAction, renderer: &renderer::State) {
+        let cursor = self.projection.viewport_to_world(self.cursor);
+        match action {
+            PaletteAction::SwapTool(tool) => {
+                if let Some(node) = tool.get_node_id(&self.app.tool_nodes) {
+                    let previous_tool = self.app.selected_tool;
+                    crate::ui::handle_message(
+                        &mut self.app,
+                        node,
+                        &Message::SwapTool(tool),
+                        &self.redraw_manager,
+                    );
+                    self.tool_swapped(previous_tool);
+                }
+            }
+            PaletteAction::ResetZoom => {
+                self.projection.reset_zoom_animated(self.zoom_duration);
+                self.redraw_manager.request_redraw();
+            }
+            PaletteAction::ZoomToContent => self.zoom_to_content(),
+            PaletteAction::RepeatLast => {
+                if let Some(primitive) = self.recorder.repeat_last(cursor) {
+                    self.handle_tool(
+                        self.app.selected_tool,
+                        vec![ToolMessage::Commit(primitive)],
+                        renderer,
+                    );
+                }
+            }
+            PaletteAction::Undo => {
+                let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+                if self.canvas.undo(systems) {
+                    let scene = &self.canvas.scene;
+                    self.selection.retain(|id| scene.get_node(*id).is_some());
+                    self.tools.text.anchors = self.canvas.text_anchors(systems);
+                    self.redraw_manager.request_redraw();
+                }
+            }
+            PaletteAction::FlipHorizontal => self.flip_selection(FlipAxis::Horizontal, renderer),
+            PaletteAction::FlipVertical => self.flip_selection(FlipAxis::Vertical, renderer),
+            PaletteAction::ToggleMirrorText => {
+                self.mirror_text = !self.mirror_text;
+                info!("mirroring text when flipping: {}", self.mirror_text);
+            }
+            PaletteAction::Align(alignment) => self.align_selection(alignment, renderer),
+            PaletteAction::Distribute(distribution) => {
+                self.distribute_selection(distribution, renderer)
+            }
+            PaletteAction::ToggleRecording => self.recorder.toggle_recording(cursor),
+            PaletteAction::ToggleSnapping => self.toggle_snapping(),
+            PaletteAction::ToggleContrastOutline => self.toggle_contrast_outline(),
+            PaletteAction::CycleGrid => self.cycle_grid(),
+            PaletteAction::ToggleCrosshair => self.toggle_crosshair(),
+            PaletteAction::ToggleRulers => self.toggle_rulers(),
+            PaletteAction::ClearGuides => self.clear_guides(),
+            PaletteAction::SaveSession => {
+                if let Err(err) = self.save_session() {
+                    warn!("failed to save the session: {err:#}");
+                }
+            }
+            PaletteAction::SaveNewSession => {
+                if let Err(err) = self.save_session_as(None) {
+                    warn!("failed to save the session: {err:#}");
+                }
+            }
+            PaletteAction::ExportPng => {
+                if let Err(err) = self.export_png(ExportOptions::default(), renderer) {
+                    warn!("failed to export the canvas: {err:#}");
+                }
+            }
+            PaletteAction::ExportSelection => {
+                if let Err(err) = self.export_selection_png(ExportOptions::default(), renderer) {
+                    warn!("failed to export the selection: {err:#}");
+                }
+            }
+            PaletteAction::ExportDocument => {
+                let options = ExportOptions::document(self.app.gui.scale_factor as f32);
+                if let Err(err) = self.export_png(options, renderer) {
+                    warn!("failed to export the canvas: {err:#}");
+                }
+            }
+            PaletteAction::ToggleToolHints => {
+                let visible = !self.app.status.is_visible();
+                self.app.status.set_visible(&mut self.app.gui, visible);
+            }
+            PaletteAction::ToggleShapeRecognition => {
+                let pen = &mut self.tools.pen;
+                pen.recognize_shapes = !pen.recognize_shapes;
+                info!("pen shape recognition enabled: {}", pen.recognize_shapes);
+            }
+            PaletteAction::ReplayMacro => {
+                let messages = self.recorder.replay(cursor);
+                self.handle_tool(self.app.selected_tool, messages, renderer);
+            }
+            PaletteAction::ExportMacro => {
+                let exported = self.export_path("gif").and_then(|path| {
+                    self.export_animation(MACRO_EXPORT_FPS, &AnimationOutput::Gif(path), renderer)
+                });
+                if let Err(err) = exported {
+                    warn!("failed to export the macro: {err:#}");
+                }
+            }
+        }
+        self.redraw_manager.request_redraw();
+    }
+
+    pub fn handle_tool(
+        &mut self,
+        tool: ToolKind,
+        messages: Vec<ToolMessage>,
+        renderer: &renderer::State,
+    ) -> Option<CursorIcon> {
+        let systems = &mut self.systems.to_ref(&renderer.device, &renderer.queue);
+        let mut cursor_icon = None;
+        let mut committed = None;
         for message in messages {
+            self.recorder.record(&message);
             match message {
                 ToolMessage::CursorIcon(icon) => {
                     cursor_icon = Some(icon);
                 }
-                ToolMessage::Commit(primitive) => {
+                ToolMessage::Commit(_) | ToolMessage::AppendCommit(_) => {
                     self.canvas.clear_scratch();
-                    self.canvas.add_node(systems, primitive);
+                    let id = match message {
+                        ToolMessage::AppendCommit(primitive) => {
+                            self.canvas.append_node(systems, primitive)
+                        }
+                        ToolMessage::Commit(primitive) => self.canvas.add_node(systems, primitive),
+                        _ => unreachable!(),
+                    };
+                    // Drawing tools leave the selection alone, the node is selected once the
+                    // select tool is picked.
+                    if tool == ToolKind::Select {
+                        self.selection = vec![id];
+                    } else {
+                        self.last_commit = Some(id);
+                    }
+                    committed = Some(id);
+                    self.tools.text.anchors = self.canvas.text_anchors(systems);
                     self.redraw_manager.request_redraw();
                 }
+                ToolMessage::ReplaceCommit(primitive) => {
+                    if let Some(id) = committed {
+                        self.canvas.replace_node(systems, id, primitive);
+                        self.tools.text.anchors = self.canvas.text_anchors(systems);
+                        self.redraw_manager.request_redraw();
+                    }
+                }
                 ToolMessage::Scratch(mesh) => {
-                    self.canvas
-                        .update_scratch(&systems.device, &systems.queue, mesh);
+                    self.canvas.update_scratch(
+                        &systems.device,
+                        &systems.queue,
+                        mesh,
+                        BlendMode::Normal,
+                    );
+                    self.redraw_manager.request_redraw();
+                }
+                ToolMessage::EraseScratch(mesh) => {
+                    self.canvas.update_scratch(
+                        &systems.device,
+                        &systems.queue,
+                        mesh,
+                        BlendMode::Erase,
+                    );
                     self.redraw_manager.request_redraw();
                 }
                 ToolMessage::ClearScratch => {
                     self.canvas.clear_scratch();
                     self.redraw_manager.request_redraw();
                 }
-                ToolMessage::ChangePrimaryColor(_color) => {
-                    tracing::info!("primary color changed aaaa")
+                ToolMessage::ChangePrimaryColor(color) => self.tools.set_color(color),
+                ToolMessage::EditText(position) => {
+                    if let Some(id) = self.canvas.text_at(systems, position)
+                        && let Some(Primitive::Text(text)) = self.canvas.primitive(id).cloned()
+                    {
+                        self.canvas.set_editing(systems, Some(id));
+                        let mesh = self.tools.text.edit(systems, text);
+                        self.canvas.update_scratch(
+                            &systems.device,
+                            &systems.queue,
+                            mesh,
+                            BlendMode::Normal,
+                        );
+                        self.redraw_manager.request_redraw();
+                    }
+                }
+                ToolMessage::CommitEdit(primitive) => {
+                    if let Some(id) = self.canvas.editing() {
+                        self.canvas.set_editing(systems, None);
+                        self.canvas.clear_scratch();
+                        match primitive {
+                            Some(primitive) => self.canvas.replace_node(systems, id, primitive),
+                            None => self.canvas.remove_node_id(systems, id),
+                        }
+                        self.tools.text.anchors = self.canvas.text_anchors(systems);
+                        self.redraw_manager.request_redraw();
+                    }
+                }
+                ToolMessage::CancelEdit => {
+                    self.canvas.set_editing(systems, None);
+                    self.canvas.clear_scratch();
+                    self.redraw_manager.request_redraw();
                 }
                 ToolMessage::SetFocus => self.focused_tool = Some(tool),
                 ToolMessage::ReleaseFocus => {
                     self.focused_tool = None;
+                    self.interacting = None;
+                    self.rotating = None;
                 }
-                ToolMessage::Select(point) => {
+                ToolMessage::Select(point, depth) => {
                     cursor_icon = Some(CursorIcon::Grabbing);
-                    if let Some(node) = self.canvas.get_node_at_position(point) {
-                        tracing::info!("attempted to select a node : {node:?}");
+                    let stack = self.canvas.nodes_at_position(point);
+                    if let Some(&id) = stack.get(depth % stack.len().max(1)) {
+                        // Interactive nodes get the first chance at the click, only selecting
+                        // the node if it was not consumed.
+                        match self.canvas.click_node(systems, id, point) {
+                            ClickResult::Handled => {
+                                self.interacting = Some(id);
+                                self.redraw_manager.request_redraw();
+                            }
+                            ClickResult::Unhandled => {
+                                tracing::info!("selected node: {id:?}");
+                                self.selection = vec![id];
+                            }
+                        }
                     };
                 }
+                ToolMessage::SelectDrag(point) => {
+                    if let Some(id) = self.interacting {
+                        self.canvas.drag_node(systems, id, point);
+                        self.redraw_manager.request_redraw();
+                    }
+                }
+                ToolMessage::Rotate(point) => {
+                    let stack = self.canvas.nodes_at_position(point);
+                    self.rotating = stack.into_iter().find_map(|id| {
+                        let primitive = self.canvas.primitive(id)?;
+                        let center = primitive.bounding_box().center();
+                        let offset = point - center;
+                        Some(RotationDrag {
+                            id,
+                            center,
+                            start_rotation: primitive.rotation()?,
+                            start_angle: offset.y.atan2(offset.x),
+                            rotated: false,
+                        })
+                    });
+                    if let Some(rotating) = &self.rotating {
+                        self.selection = vec![rotating.id];
+                    }
+                    self.redraw_manager.request_redraw();
+                }
+                ToolMessage::RotateDrag(point, snap) => {
+                    if let Some(rotating) = &mut self.rotating {
+                        let offset = point - rotating.center;
+                        let delta = offset.y.atan2(offset.x) - rotating.start_angle;
+                        let rotation = snap_rotation(rotating.start_rotation, delta, snap);
+                        self.canvas
+                            .rotate_node(systems, rotating.id, rotation, rotating.rotated);
+                        rotating.rotated = true;
+
+                        // The readout follows the pointer, showing the rotation in degrees.
+                        let mut label = Text::<CanvasCoordinates>::new(
+                            format!("{:.1}°", rotation.to_degrees().rem_euclid(360.)),
+                            TextOptions {
+                                fill: Some(PremulColor::WHITE),
+                                font_size: 14.,
+                                ..Default::default()
+                            },
+                            Box2D::from_origin_and_size(
+                                point + Vector2D::splat(ROTATION_LABEL_OFFSET),
+                                Size2D::new(f32::MAX, f32::MAX),
+                            ),
+                        );
+                        self.canvas.update_scratch(
+                            &systems.device,
+                            &systems.queue,
+                            label.render(systems).clone(),
+                            BlendMode::Normal,
+                        );
+                        self.redraw_manager.request_redraw();
+                    }
+                }
+                ToolMessage::RotateHover(point) => {
+                    let stack = self.canvas.nodes_at_position(point);
+                    let rotatable = stack.iter().any(|id| {
+                        self.canvas
+                            .primitive(*id)
+                            .is_some_and(|primitive| primitive.rotation().is_some())
+                    });
+                    cursor_icon = Some(if stack.is_empty() || rotatable {
+                        CursorIcon::Grab
+                    } else {
+                        CursorIcon::NotAllowed
+                    });
+                }
                 ToolMessage::GrabMove(origin, position) => {
                     self.projection.pan_by(position - origin);
                     self.redraw_manager.request_redraw();
@@ -288,19 +1796,37 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
                 ToolMessage::Erase(point) => {
                     if let Some(id) = self.canvas.get_node_id_at_position(point) {
                         self.canvas.remove_node_id(systems, id);
+                        self.selection.retain(|selected| *selected != id);
+                        self.tools.text.anchors = self.canvas.text_anchors(systems);
                         self.redraw_manager.request_redraw();
                     };
                 }
                 ToolMessage::ZoomIn(point) => {
-                    self.projection.zoom_at(point, 1.1);
+                    self.projection
+                        .zoom_at_animated(point, 1.1, self.zoom_duration);
                     self.redraw_manager.request_redraw();
                 }
                 ToolMessage::ZoomOut(point) => {
-                    self.projection.zoom_at(point, 0.9);
+                    self.projection
+                        .zoom_at_animated(point, 0.9, self.zoom_duration);
                     self.redraw_manager.request_redraw();
                 }
                 ToolMessage::ResetZoom => {
-                    self.projection.reset_zoom();
+                    self.projection.reset_zoom_animated(self.zoom_duration);
+                    self.redraw_manager.request_redraw();
+                }
+                ToolMessage::Crop(crop) => {
+                    self.canvas
+                        .set_crop(&systems.device, &systems.queue, Some(crop));
+                    self.redraw_manager.request_redraw();
+                }
+                ToolMessage::CropToContent => {
+                    let crop = self.canvas.content_bounds();
+                    self.canvas.set_crop(&systems.device, &systems.queue, crop);
+                    self.redraw_manager.request_redraw();
+                }
+                ToolMessage::ClearCrop => {
+                    self.canvas.set_crop(&systems.device, &systems.queue, None);
                     self.redraw_manager.request_redraw();
                 }
             };
@@ -308,50 +1834,154 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
         cursor_icon
     }
 
-    #[instrument(skip_all)]
+    /// The velocity to pan the canvas at while a drag is held near the viewport edge, faster the
+    /// closer the pointer is to the edge.
+    fn auto_pan_velocity(&self) -> Option<Vector2D<f32>> {
+        if !self.focused_tool.is_some_and(|tool| tool.auto_pans()) {
+            return None;
+        }
+        let viewport = self.projection.get_viewport();
+        let speed = |position: f32, length: f32| {
+            let near = (AUTO_PAN_MARGIN - position).max(0.);
+            let far = (position - (length - AUTO_PAN_MARGIN)).max(0.);
+            // Panning towards an edge moves the content away from it.
+            (near - far).clamp(-AUTO_PAN_MARGIN, AUTO_PAN_MARGIN) / AUTO_PAN_MARGIN * AUTO_PAN_SPEED
+        };
+        let velocity = Vector2D::new(
+            speed(self.cursor.x, viewport.width),
+            speed(self.cursor.y, viewport.height),
+        );
+        (velocity != Vector2D::zero()).then_some(velocity)
+    }
+
+    /// Pans the canvas while a drag is held near the viewport edge, replaying the pointer
+    /// position to the tool so the drag follows the canvas.
+    fn auto_pan(&mut self, state: &renderer::State) {
+        let (Some(velocity), Some(tool)) = (self.auto_pan_velocity(), self.focused_tool) else {
+            self.auto_pan_frame = None;
+            return;
+        };
+        let now = Instant::now();
+        let elapsed = self
+            .auto_pan_frame
+            .map_or(AUTO_PAN_STEP, |frame| now.duration_since(frame));
+        self.auto_pan_frame = Some(now);
+
+        self.projection.pan_by(velocity * elapsed.as_secs_f32());
+        let messages = tool.mouse_event(
+            &mut self.systems.to_ref(&state.device, &state.queue),
+            &mut self.tools,
+            MouseEvent::new(self.cursor, MouseEventKind::Motion { time: 0 }),
+            self.app.modifiers,
+            &self.projection,
+        );
+        self.handle_tool(tool, messages, state);
+        self.redraw_manager.request_redraw_duration(AUTO_PAN_STEP);
+    }
+
+    /// Draws a frame, with a span around each phase so a tracing subscriber can time them.
+    #[instrument(name = "View::render", level = "trace", skip_all)]
     pub fn render(
         &mut self,
         state: &renderer::State,
         surface: &wgpu::Surface,
         pipeline: &DrawPipeline,
     ) {
-        let start = std::time::Instant::now();
+        let start = Instant::now();
+        let mut stats = FrameStats::default();
+        {
+            let _span = trace_span!("prepare").entered();
+            if self.projection.step_animation(Instant::now()) {
+                self.redraw_manager
+                    .request_redraw_duration(ZOOM_ANIMATION_STEP);
+            }
+            if self
+                .app
+                .options
+                .step_animation(&mut self.app.gui, Instant::now())
+            {
+                self.redraw_manager
+                    .request_redraw_duration(PANEL_ANIMATION_STEP);
+            }
+            self.auto_pan(state);
+            self.update_grid(state);
+            self.update_scene_limits(state);
+            self.update_selection_outline(state);
+            self.update_guides(state);
+            self.update_crosshair(state);
+        }
+        let bind_span = trace_span!("bind_update").entered();
         let binds = self.binds.get_or_insert(Binds {
-            projection: ProjectionBind::new(state, &pipeline.bind_group_layouts, &self.projection),
+            projection: ProjectionBind::new(
+                state,
+                &pipeline.bind_group_layouts,
+                &self.projection,
+                self.color_correction,
+            ),
             texture_atlases: pipeline.bind_group_layouts.new_texture_atlas_bind_group(
                 &state.device,
                 &self.systems.texture.mask_atlas.texture_view,
                 &self.systems.texture.color_atlas.texture_view,
                 &pipeline.sampler,
             ),
+            contrast: None,
+            contrast_target: None,
         });
+        if self.contrast_outline.is_some() && binds.contrast.is_none() {
+            binds.contrast = Some(ProjectionBind::new(
+                state,
+                &pipeline.bind_group_layouts,
+                &self.projection,
+                ColorCorrection::SILHOUETTE,
+            ));
+        }
         if self.projection.needs_rebinding() {
             binds.projection.update_viewport(state, &self.projection);
+            if let Some(contrast) = &binds.contrast {
+                contrast.update_viewport(state, &self.projection);
+            }
             self.projection.mark_bound();
             info!("Updated the projection bind!");
         }
-        if self.systems.texture.needs_rebinding() {
-            // TODO: move this
+        drop(bind_span);
+        if self.app.gui.is_dirty() {
+            tracing::info!("gui is dirty, redrawing!");
+            let gui_span = trace_span!("gui_render").entered();
+            let mesh = self
+                .app
+                .gui
+                .render(&mut self.systems.to_ref(&state.device, &state.queue));
+            drop(gui_span);
+            let upload_start = Instant::now();
+            {
+                let _span = trace_span!("upload").entered();
+                _ = self
+                    .app
+                    .gui_buffer
+                    .replace_with_mesh(&state.device, &state.queue, mesh);
+            }
+            stats.upload_us = upload_start.elapsed().as_micros() as u64;
+
+            let timings = self.app.gui.last_render_timings();
+            stats.layout_us = timings.layout.as_micros() as u64;
+            stats.tessellate_us = timings.tessellate.as_micros() as u64;
+        }
+
+        // Rendering the gui can grow the atlases, so this has to happen after it.
+        if self.systems.texture.take_rebind_token().is_some() {
+            let _span = trace_span!("bind_update").entered();
             binds.texture_atlases = pipeline.bind_group_layouts.new_texture_atlas_bind_group(
                 &state.device,
                 &self.systems.texture.mask_atlas.texture_view,
                 &self.systems.texture.color_atlas.texture_view,
                 &pipeline.sampler,
             );
-            self.systems.texture.mark_bound();
             info!("Updated the texture bind!");
         }
-        if self.app.gui.is_dirty() {
-            tracing::info!("gui is dirty, redrawing!");
-            _ = self.app.gui_buffer.replace_with_mesh(
-                &state.device,
-                &state.queue,
-                self.app
-                    .gui
-                    .render(&mut self.systems.to_ref(&state.device, &state.queue)),
-            );
-        }
 
+        let submit_start = Instant::now();
+
+        let encode_span = trace_span!("encode").entered();
         let frame = surface.get_current_texture().unwrap();
         let view = frame
             .texture
@@ -363,6 +1993,46 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
                 label: Some("Canvas Render Encoder"),
             });
 
+        // The silhouette for the contrast outline is drawn on its own first, so that the outline
+        // can be composited from it in a single draw.
+        if let (Some(outline), Some(contrast)) = (self.contrast_outline, &binds.contrast) {
+            let (size, format) = (frame.texture.size(), frame.texture.format());
+            if !binds
+                .contrast_target
+                .as_ref()
+                .is_some_and(|target| target.fits(size, format))
+            {
+                binds.contrast_target =
+                    Some(ContrastTarget::new(state, pipeline, outline, size, format));
+            }
+            let target = binds.contrast_target.as_ref().unwrap();
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Canvas Contrast Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_bind_group(0, &contrast.bind_group, &[]);
+            render_pass.set_bind_group(1, &binds.texture_atlases, &[]);
+            for (buffer, batches) in self.canvas.scene_layers() {
+                render_pass.set_vertex_buffer(0, buffer.vertex.buf.slice(..));
+                render_pass.set_index_buffer(buffer.index.buf.slice(..), wgpu::IndexFormat::Uint32);
+                for batch in batches {
+                    render_pass.set_pipeline(pipeline.for_blend_mode(batch.blend_mode));
+                    render_pass.draw_indexed(batch.indices.clone(), 0, 0..1);
+                }
+            }
+        }
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Canvas Render Pass"),
@@ -384,22 +2054,84 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
             render_pass.set_bind_group(0, &binds.projection.bind_group, &[]);
             render_pass.set_bind_group(1, &binds.texture_atlases, &[]);
 
-            if self.canvas.scene_buffer.num_indices > 0 {
-                render_pass.set_vertex_buffer(0, self.canvas.scene_buffer.vertex.buf.slice(..));
+            // Erase strokes only cut into what was drawn before them, so the batches have to be
+            // drawn in order, starting with the baked layer.
+            for (buffer, batches) in self.canvas.scene_layers() {
+                render_pass.set_vertex_buffer(0, buffer.vertex.buf.slice(..));
+                render_pass.set_index_buffer(buffer.index.buf.slice(..), wgpu::IndexFormat::Uint32);
+                for batch in batches {
+                    render_pass.set_pipeline(pipeline.for_blend_mode(batch.blend_mode));
+                    render_pass.draw_indexed(batch.indices.clone(), 0, 0..1);
+                }
+            }
+
+            let scratch_buffer = self.canvas.scratch_buffer();
+            if scratch_buffer.num_indices > 0 {
+                render_pass.set_pipeline(pipeline.for_blend_mode(self.canvas.scratch_blend_mode));
+                render_pass.set_vertex_buffer(0, scratch_buffer.vertex.buf.slice(..));
                 render_pass.set_index_buffer(
-                    self.canvas.scene_buffer.index.buf.slice(..),
+                    scratch_buffer.index.buf.slice(..),
                     wgpu::IndexFormat::Uint32,
                 );
-                render_pass.draw_indexed(0..self.canvas.scene_buffer.num_indices, 0, 0..1);
+                render_pass.draw_indexed(0..scratch_buffer.num_indices, 0, 0..1);
             }
 
-            if self.canvas.scratch_buffer.num_indices > 0 {
-                render_pass.set_vertex_buffer(0, self.canvas.scratch_buffer.vertex.buf.slice(..));
+            // The contrast outline and the grid go below everything drawn so far instead of being
+            // drawn first, so the erase strokes of the scene only cut into the scene itself.
+            if let Some(target) = &binds.contrast_target
+                && self.contrast_outline.is_some()
+            {
+                render_pass.set_pipeline(&pipeline.contrast_pipeline);
+                render_pass.set_bind_group(0, &target.bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+                render_pass.set_bind_group(0, &binds.projection.bind_group, &[]);
+                render_pass.set_bind_group(1, &binds.texture_atlases, &[]);
+            }
+            if self.grid_buffer.num_indices > 0 {
+                render_pass.set_pipeline(&pipeline.behind_pipeline);
+                render_pass.set_vertex_buffer(0, self.grid_buffer.vertex.buf.slice(..));
                 render_pass.set_index_buffer(
-                    self.canvas.scratch_buffer.index.buf.slice(..),
+                    self.grid_buffer.index.buf.slice(..),
                     wgpu::IndexFormat::Uint32,
                 );
-                render_pass.draw_indexed(0..self.canvas.scratch_buffer.num_indices, 0, 0..1);
+                render_pass.draw_indexed(0..self.grid_buffer.num_indices, 0, 0..1);
+            }
+
+            render_pass.set_pipeline(&pipeline.render_pipeline);
+            if self.canvas.crop_buffer.num_indices > 0 {
+                render_pass.set_vertex_buffer(0, self.canvas.crop_buffer.vertex.buf.slice(..));
+                render_pass.set_index_buffer(
+                    self.canvas.crop_buffer.index.buf.slice(..),
+                    wgpu::IndexFormat::Uint32,
+                );
+                render_pass.draw_indexed(0..self.canvas.crop_buffer.num_indices, 0, 0..1);
+            }
+
+            if self.selection_buffer.num_indices > 0 {
+                render_pass.set_vertex_buffer(0, self.selection_buffer.vertex.buf.slice(..));
+                render_pass.set_index_buffer(
+                    self.selection_buffer.index.buf.slice(..),
+                    wgpu::IndexFormat::Uint32,
+                );
+                render_pass.draw_indexed(0..self.selection_buffer.num_indices, 0, 0..1);
+            }
+
+            if self.guides_buffer.num_indices > 0 {
+                render_pass.set_vertex_buffer(0, self.guides_buffer.vertex.buf.slice(..));
+                render_pass.set_index_buffer(
+                    self.guides_buffer.index.buf.slice(..),
+                    wgpu::IndexFormat::Uint32,
+                );
+                render_pass.draw_indexed(0..self.guides_buffer.num_indices, 0, 0..1);
+            }
+
+            if self.crosshair_buffer.num_indices > 0 {
+                render_pass.set_vertex_buffer(0, self.crosshair_buffer.vertex.buf.slice(..));
+                render_pass.set_index_buffer(
+                    self.crosshair_buffer.index.buf.slice(..),
+                    wgpu::IndexFormat::Uint32,
+                );
+                render_pass.draw_indexed(0..self.crosshair_buffer.num_indices, 0, 0..1);
             }
 
             if self.app.gui_buffer.num_indices > 0 {
@@ -413,8 +2145,893 @@ impl<T: RedrawRequest + Clone + 'static> View<T> {
             }
         }
 
-        state.queue.submit(std::iter::once(encoder.finish()));
-        frame.present();
-        tracing::trace!("Time taken to render: {:?}", start.elapsed());
+        let commands = encoder.finish();
+        drop(encode_span);
+
+        trace_span!("submit").in_scope(|| {
+            state.queue.submit(std::iter::once(commands));
+            frame.present();
+        });
+        stats.gpu_submit_us = submit_start.elapsed().as_micros() as u64;
+        stats.total_us = start.elapsed().as_micros() as u64;
+        self.frame_stats = stats;
+    }
+
+    /// Bakes the oldest nodes once the live scene is larger than the limits, if enabled. Otherwise
+    /// warns in the status line while it is, and clears the warning once it is back under them.
+    fn update_scene_limits(&mut self, state: &renderer::State) {
+        let mut stats = self.canvas.stats();
+        if self.scene_limits.auto_bake && stats.exceeds(self.scene_limits) {
+            let count = stats.nodes_to_bake(self.scene_limits);
+            if count > 0 {
+                let systems = &mut self.systems.to_ref(&state.device, &state.queue);
+                let baked = self.canvas.baked_len();
+                self.canvas.bake(systems, baked + count);
+                info!("baked {count} nodes into the static layer");
+                stats = self.canvas.stats();
+            }
+        }
+        let exceeded = stats.exceeds(self.scene_limits);
+        if exceeded == self.scene_warning {
+            return;
+        }
+        self.scene_warning = exceeded;
+        let warning = exceeded.then(|| {
+            warn!("the scene is larger than its limits: {stats:?}");
+            format!(
+                "The scene is getting large ({} nodes, {} MiB), drawing may slow down",
+                stats.nodes,
+                stats.mesh_bytes / (1024 * 1024)
+            )
+        });
+        self.app.status.set_warning(&mut self.app.gui, warning);
+    }
+
+    /// Rebuilds the grid whenever the pan or zoom moved it.
+    fn update_grid(&mut self, state: &renderer::State) {
+        if !self.grid_dirty && !self.projection.needs_rebinding() {
+            return;
+        }
+        self.grid_dirty = false;
+        match self.grid {
+            Some(grid) => {
+                let mesh = grid.mesh(&self.projection);
+                _ = self
+                    .grid_buffer
+                    .replace_with_mesh(&state.device, &state.queue, &mesh);
+            }
+            None => self.grid_buffer.reset_no_wipe(),
+        }
+    }
+
+    /// Rebuilds the guides and rulers whenever the pan or zoom moved them.
+    fn update_guides(&mut self, state: &renderer::State) {
+        if !self.guides_dirty && !self.projection.needs_rebinding() {
+            return;
+        }
+        self.guides_dirty = false;
+        // The guide being moved is drawn where it is dragged to instead of where it was.
+        let mut guides = self.canvas.guides().to_vec();
+        if let Some(index) = self.guide_drag.and_then(|drag| drag.existing)
+            && index < guides.len()
+        {
+            guides.remove(index);
+        }
+        if guides.is_empty() && self.guide_drag.is_none() && !self.rulers {
+            self.guides_buffer.reset_no_wipe();
+            return;
+        }
+        let dragging = self.guide_drag.map(|drag| drag.guide);
+        let mesh = self
+            .guide_style
+            .mesh(&self.projection, &guides, dragging, self.rulers);
+        _ = self
+            .guides_buffer
+            .replace_with_mesh(&state.device, &state.queue, &mesh);
+    }
+
+    fn update_crosshair(&mut self, state: &renderer::State) {
+        if !self.crosshair_dirty {
+            return;
+        }
+        self.crosshair_dirty = false;
+        match self.crosshair {
+            Some(crosshair) if self.cursor_inside => {
+                let mesh = crosshair.mesh(self.cursor, self.projection.get_viewport());
+                _ = self
+                    .crosshair_buffer
+                    .replace_with_mesh(&state.device, &state.queue, &mesh);
+            }
+            _ => self.crosshair_buffer.reset_no_wipe(),
+        }
+    }
+
+    /// Rebuilds the marching ants outline around the selection, keeping the animation running
+    /// for as long as something is selected.
+    fn update_selection_outline(&mut self, state: &renderer::State) {
+        let bounds = self
+            .selection
+            .iter()
+            .filter_map(|id| self.canvas.primitive(*id))
+            .map(|primitive| primitive.bounding_box())
+            .reduce(|a, b| a.union(&b));
+        let Some(bounds) = bounds else {
+            self.selection_buffer.reset_no_wipe();
+            return;
+        };
+        let bounds = Box2D::from_points([
+            self.projection.world_to_viewport(bounds.min),
+            self.projection.world_to_viewport(bounds.max),
+        ]);
+        let phase = self.created.elapsed().as_secs_f32() * SELECTION_SPEED;
+        let mesh = marching_ants(bounds, phase);
+        _ = self
+            .selection_buffer
+            .replace_with_mesh(&state.device, &state.queue, &mesh);
+        self.redraw_manager
+            .request_redraw_duration(SELECTION_ANIMATION_STEP);
+    }
+}
+
+/// A dashed outline around `bounds` in viewport coordinates, with the dashes shifted `phase`
+/// pixels along the outline.
+fn marching_ants(bounds: Box2D<f32>, phase: f32) -> Mesh<Vertex> {
+    let kind = VertexKind::ColorViewport(PremulColor::WHITE);
+    let period = SELECTION_DASH + SELECTION_GAP;
+    let corners = [
+        bounds.min,
+        Point2D::new(bounds.max.x, bounds.min.y),
+        bounds.max,
+        Point2D::new(bounds.min.x, bounds.max.y),
+    ];
+
+    let mut mesh = Mesh::empty();
+    // The distance along the outline at which the current edge starts.
+    let mut offset = 0.;
+    for (i, start) in corners.iter().enumerate() {
+        let end = corners[(i + 1) % corners.len()];
+        let length = (end - *start).length();
+        let direction = (end - *start) / length.max(f32::EPSILON);
+
+        // The first dash that overlaps this edge.
+        let mut dash = ((offset + phase) / period).floor() * period - phase;
+        while dash < offset + length {
+            let from = dash.max(offset) - offset;
+            let to = (dash + SELECTION_DASH).min(offset + length) - offset;
+            if to > from {
+                let segment =
+                    Box2D::from_points([*start + direction * from, *start + direction * to])
+                        .inflate(SELECTION_WIDTH / 2., SELECTION_WIDTH / 2.);
+                mesh.append(&Mesh::new_color_quad(segment, kind));
+            }
+            dash += period;
+        }
+        offset += length;
+    }
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use graphics::primitives::{Rectangle, RectangleOptions};
+
+    use super::*;
+    use crate::history::HistoryLimits;
+    use crate::tools::EnabledTools;
+    use crate::ui::options::COLLAPSE_DURATION;
+
+    #[derive(Clone, Default)]
+    struct NoRedraw;
+
+    impl RedrawRequest for NoRedraw {
+        fn request_redraw(&self) {}
+        fn request_redraw_duration(&self, _duration: Duration) {}
+    }
+
+    /// A renderer on any available adapter.
+    ///
+    /// The tests using it need a gpu, so they are ignored by default and run with
+    /// `cargo test -- --ignored` on machines that have one.
+    fn renderer() -> renderer::State {
+        let mut init = pin!(renderer::State::init(renderer::InitOptions::default()));
+        let mut context = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(result) = init.as_mut().poll(&mut context) {
+                return result.expect("the gpu tests need a gpu");
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    /// A renderer along with a view on it, see [`renderer`].
+    fn gpu_view() -> (renderer::State, View<NoRedraw>) {
+        let renderer = renderer();
+        let view = view(&renderer);
+        (renderer, view)
+    }
+
+    fn view(renderer: &renderer::State) -> View<NoRedraw> {
+        View::new(
+            renderer,
+            Size2D::new(800., 600.),
+            1.,
+            NoRedraw,
+            EnabledTools::default(),
+        )
+    }
+
+    fn rectangle(origin: Point2D<f32>) -> Primitive<CanvasCoordinates> {
+        Primitive::Rectangle(Rectangle::new(
+            origin,
+            Size2D::new(40., 30.),
+            RectangleOptions::DEFAULT,
+        ))
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn selection_does_not_keep_the_view_busy() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        let id = view
+            .canvas
+            .add_node(systems, rectangle(Point2D::new(100., 100.)));
+        view.selection = vec![id];
+        // Building the outline renews the marching ants animation.
+        view.update_selection_outline(&renderer);
+
+        assert!(view.selection_buffer.num_indices > 0);
+        assert!(!view.is_busy());
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn commits_are_selected_once_the_select_tool_is_picked() {
+        let (renderer, mut view) = gpu_view();
+        let commit = ToolMessage::Commit(rectangle(Point2D::new(100., 100.)));
+        view.handle_tool(ToolKind::Rectangle, vec![commit], &renderer);
+        assert!(view.selection.is_empty());
+
+        view.run_palette_action(PaletteAction::SwapTool(ToolKind::Select), &renderer);
+        assert_eq!(view.app.selected_tool, ToolKind::Select);
+        assert_eq!(view.selection.len(), 1);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn presses_on_the_status_line_reach_the_tool() {
+        let (renderer, mut view) = gpu_view();
+        view.app
+            .status
+            .set_warning(&mut view.app.gui, Some("warning".to_owned()));
+        view.app
+            .gui
+            .update_layout(&mut view.systems.to_ref(&renderer.device, &renderer.queue));
+        view.run_palette_action(PaletteAction::SwapTool(ToolKind::Rectangle), &renderer);
+
+        let press = MouseEventKind::Press {
+            time: 0,
+            button: MouseButton::Left,
+        };
+        view.mouse_event(MouseEvent::new(Point2D::new(600., 585.), press), &renderer);
+        assert_eq!(view.focused_tool, Some(ToolKind::Rectangle));
+    }
+
+    fn press_on_guide(tool: ToolKind) -> (View<NoRedraw>, renderer::State) {
+        let (renderer, mut view) = gpu_view();
+        view.set_rulers(true);
+        _ = view.canvas.add_guide(Guide {
+            axis: GuideAxis::Vertical,
+            position: 500.,
+        });
+        view.run_palette_action(PaletteAction::SwapTool(tool), &renderer);
+
+        let position = view.projection.world_to_viewport(Point2D::new(500., 400.));
+        let press = MouseEventKind::Press {
+            time: 0,
+            button: MouseButton::Left,
+        };
+        view.mouse_event(MouseEvent::new(position, press), &renderer);
+        (view, renderer)
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn drawing_tools_press_through_guides() {
+        let (view, _renderer) = press_on_guide(ToolKind::Rectangle);
+        assert!(view.guide_drag.is_none());
+        assert_eq!(view.focused_tool, Some(ToolKind::Rectangle));
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn select_tool_grabs_guides() {
+        let (view, _renderer) = press_on_guide(ToolKind::Select);
+        let drag = view.guide_drag.expect("the guide should be grabbed");
+        assert_eq!(drag.existing, Some(0));
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn removing_many_nodes_rebuilds_the_scene_once() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        let ids = (0..100)
+            .map(|i| {
+                let origin = Point2D::new((i % 10) as f32 * 50., (i / 10) as f32 * 40.);
+                view.canvas.add_node(systems, rectangle(origin))
+            })
+            .collect::<Vec<_>>();
+
+        // Every re-tessellation bumps the revision, so it tells how often the scene was rebuilt.
+        let revision = view.canvas.revision();
+        view.canvas.remove_nodes(systems, &ids);
+        assert_eq!(view.canvas.revision(), revision + 1);
+        assert!(view.canvas.scene.is_empty());
+
+        // The removal is a single history entry as well.
+        assert!(view.canvas.undo(systems));
+        assert_eq!(view.canvas.revision(), revision + 2);
+        assert_eq!(view.canvas.scene.len(), ids.len());
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn text_is_mirrored_in_a_y_up_world() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        let anchor = Point2D::new(100., 100.);
+        let mut text = Primitive::Text(Text::new(
+            "draw".to_string(),
+            TextOptions::default(),
+            Box2D::from_origin_and_size(anchor, Size2D::splat(f32::MAX)),
+        ));
+        let mesh = text.render(systems).clone();
+        // The extent of the text on screen, relative to where its anchor is shown.
+        let extent = |projection: &Projection| {
+            let anchor = projection.world_to_viewport(anchor).y;
+            mesh.vertices
+                .iter()
+                .map(|vertex| {
+                    projection
+                        .world_to_viewport(Point2D::from(vertex.position))
+                        .y
+                        - anchor
+                })
+                .fold((f32::MAX, f32::MIN), |(min, max), y| {
+                    (min.min(y), max.max(y))
+                })
+        };
+
+        // The text hangs below its anchor in a y-down world.
+        let (top, bottom) = extent(&view.projection);
+        assert!(top >= -0.01 && bottom > 0.);
+
+        // And stands above it in a y-up one, upside down.
+        view.projection.set_y_direction(YDirection::Up);
+        view.projection.set_origin(Point2D::new(0., 600.));
+        let (top, bottom) = extent(&view.projection);
+        assert!(top < 0. && bottom <= 0.01);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn escaping_a_re_edit_keeps_the_text() {
+        let (renderer, mut view) = gpu_view();
+        let text = |content: &str| {
+            Primitive::Text(Text::new(
+                content.to_string(),
+                TextOptions::default(),
+                Box2D::from_origin_and_size(Point2D::new(100., 100.), Size2D::splat(f32::MAX)),
+            ))
+        };
+        let content = |view: &View<NoRedraw>, id| match view.canvas.primitive(id) {
+            Some(Primitive::Text(text)) => Some(text.content()),
+            _ => None,
+        };
+        view.handle_tool(
+            ToolKind::Text,
+            vec![ToolMessage::Commit(text("draw"))],
+            &renderer,
+        );
+        let id = view.last_commit.unwrap();
+
+        // Clicking beside the text starts new text instead.
+        let beside = Point2D::new(90., 105.);
+        view.handle_tool(
+            ToolKind::Text,
+            vec![ToolMessage::EditText(beside)],
+            &renderer,
+        );
+        assert_eq!(view.canvas.editing(), None);
+
+        let inside = Point2D::new(102., 105.);
+        view.handle_tool(
+            ToolKind::Text,
+            vec![ToolMessage::EditText(inside)],
+            &renderer,
+        );
+        assert_eq!(view.canvas.editing(), Some(id));
+        view.handle_tool(ToolKind::Text, vec![ToolMessage::CancelEdit], &renderer);
+        assert_eq!(view.canvas.editing(), None);
+        assert_eq!(content(&view, id).as_deref(), Some("draw"));
+
+        view.handle_tool(
+            ToolKind::Text,
+            vec![ToolMessage::EditText(inside)],
+            &renderer,
+        );
+        view.handle_tool(
+            ToolKind::Text,
+            vec![ToolMessage::CommitEdit(Some(text("drawn")))],
+            &renderer,
+        );
+        assert_eq!(content(&view, id).as_deref(), Some("drawn"));
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        assert!(view.canvas.undo(systems));
+        assert_eq!(content(&view, id).as_deref(), Some("draw"));
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn escape_reverts_a_re_edit_without_hiding_the_overlay() {
+        let (renderer, mut view) = gpu_view();
+        let text = Text::new(
+            "draw".to_string(),
+            TextOptions::default(),
+            Box2D::from_origin_and_size(Point2D::new(400., 300.), Size2D::splat(f32::MAX)),
+        );
+        view.handle_tool(
+            ToolKind::Text,
+            vec![ToolMessage::Commit(Primitive::Text(text))],
+            &renderer,
+        );
+        let id = view.last_commit.unwrap();
+        view.run_palette_action(PaletteAction::SwapTool(ToolKind::Text), &renderer);
+        assert!(!view.captures_escape());
+
+        let press = MouseEventKind::Press {
+            time: 0,
+            button: MouseButton::Left,
+        };
+        view.mouse_event(MouseEvent::new(Point2D::new(402., 305.), press), &renderer);
+        assert_eq!(view.canvas.editing(), Some(id));
+        let key = |key: Key| KeyboardEvent {
+            modifiers: Modifiers::empty(),
+            kind: KeyboardEventKind::Press(key),
+        };
+        view.keyboard_event(key(Key::Character("n".to_string())), &renderer);
+        // The platform hides the overlay on Escape unless the view takes it.
+        assert!(view.captures_escape());
+
+        view.keyboard_event(key(Key::SpecialKey(SpecialKey::Escape)), &renderer);
+        assert_eq!(view.canvas.editing(), None);
+        let Some(Primitive::Text(text)) = view.canvas.primitive(id) else {
+            panic!("the edited text should still be in the canvas");
+        };
+        assert_eq!(text.content(), "draw");
+        assert!(!view.captures_escape());
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn nodes_pasted_into_another_view_are_undone_at_once() {
+        let renderer = renderer();
+        let mut source = view(&renderer);
+        let systems = &mut source.systems.to_ref(&renderer.device, &renderer.queue);
+        let nodes = [
+            rectangle(Point2D::new(0., 0.)),
+            rectangle(Point2D::new(50., 0.)),
+        ];
+        source.selection = source.canvas.add_nodes(systems, nodes);
+        let copied = source.copy_selection().unwrap();
+
+        let mut target = view(&renderer);
+        target.paste(&copied, &renderer);
+        assert_eq!(target.canvas.scene.len(), 2);
+        assert_eq!(target.selection.len(), 2);
+
+        let systems = &mut target.systems.to_ref(&renderer.device, &renderer.queue);
+        assert!(target.canvas.undo(systems));
+        assert!(target.canvas.scene.is_empty());
+        assert!(!target.canvas.undo(systems));
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn a_stamp_drag_is_undone_at_once() {
+        let (renderer, mut view) = gpu_view();
+        view.handle_tool(
+            ToolKind::Stamp,
+            vec![
+                ToolMessage::Commit(rectangle(Point2D::new(0., 0.))),
+                ToolMessage::AppendCommit(rectangle(Point2D::new(50., 0.))),
+            ],
+            &renderer,
+        );
+        view.handle_tool(
+            ToolKind::Stamp,
+            vec![ToolMessage::AppendCommit(rectangle(Point2D::new(100., 0.)))],
+            &renderer,
+        );
+        assert_eq!(view.canvas.scene.len(), 3);
+
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        assert!(view.canvas.undo(systems));
+        assert!(view.canvas.scene.is_empty());
+        assert!(!view.canvas.undo(systems));
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn baking_moves_the_bottom_nodes_out_of_the_live_mesh() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        let ids = (0..3)
+            .map(|i| {
+                view.canvas
+                    .add_node(systems, rectangle(Point2D::new(i as f32 * 50., 0.)))
+            })
+            .collect::<Vec<_>>();
+        let indices = view.canvas.render_cache.indices.len();
+
+        let revision = view.canvas.revision();
+        view.canvas.bake(systems, 2);
+        assert_eq!(view.canvas.revision(), revision);
+        let stats = view.canvas.stats();
+        assert_eq!((stats.nodes, stats.baked), (1, 2));
+        assert_eq!(view.canvas.render_cache.indices.len(), indices / 3);
+        assert_eq!(view.canvas.scene_layers().count(), 2);
+        // Exports still include the baked nodes.
+        let (mesh, _) = view.canvas.export_mesh(systems);
+        assert_eq!(mesh.indices.len(), indices);
+
+        // Adding on top keeps the bake, changing a baked node draws it live again.
+        view.canvas
+            .add_node(systems, rectangle(Point2D::new(200., 0.)));
+        assert_eq!(view.canvas.baked_len(), 2);
+        view.canvas
+            .drag_node(systems, ids[0], Point2D::new(0., 100.));
+        assert_eq!(view.canvas.baked_len(), 0);
+        assert_eq!(view.canvas.stats().nodes, 4);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn hovering_shapes_that_cannot_rotate_is_not_allowed() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        view.canvas
+            .add_node(systems, rectangle(Point2D::new(100., 100.)));
+
+        let mut hover = |position| {
+            view.handle_tool(
+                ToolKind::Rotate,
+                vec![ToolMessage::RotateHover(position)],
+                &renderer,
+            )
+        };
+        assert_eq!(
+            hover(Point2D::new(110., 110.)),
+            Some(CursorIcon::NotAllowed)
+        );
+        assert_eq!(hover(Point2D::new(500., 500.)), Some(CursorIcon::Grab));
+    }
+
+    #[test]
+    fn baking_keeps_half_of_the_limits_live() {
+        let limits = SceneLimits {
+            max_nodes: 100,
+            max_mesh_bytes: 1000,
+            auto_bake: true,
+        };
+        let stats = |nodes, mesh_bytes| SceneStats {
+            nodes,
+            mesh_bytes,
+            ..Default::default()
+        };
+        assert_eq!(stats(150, 100).nodes_to_bake(limits), 100);
+        // Large nodes are baked until the live mesh is half of the byte limit.
+        assert_eq!(stats(40, 2000).nodes_to_bake(limits), 30);
+        assert_eq!(stats(0, 0).nodes_to_bake(limits), 0);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn exceeding_the_limits_bakes_the_oldest_nodes() {
+        let (renderer, mut view) = gpu_view();
+        view.scene_limits.max_nodes = 4;
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        for i in 0..6 {
+            view.canvas
+                .add_node(systems, rectangle(Point2D::new(i as f32 * 50., 0.)));
+        }
+
+        view.update_scene_limits(&renderer);
+        let stats = view.scene_stats();
+        assert_eq!((stats.nodes, stats.baked), (2, 4));
+        assert!(!view.scene_warning);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn scene_warnings_show_while_hints_are_hidden() {
+        let (renderer, mut view) = gpu_view();
+        view.scene_limits.max_nodes = 1;
+        view.scene_limits.auto_bake = false;
+        view.run_palette_action(PaletteAction::ToggleToolHints, &renderer);
+        assert!(!view.app.status.is_shown());
+
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        let ids = (0..2)
+            .map(|i| {
+                view.canvas
+                    .add_node(systems, rectangle(Point2D::new(i as f32 * 50., 0.)))
+            })
+            .collect::<Vec<_>>();
+        view.update_scene_limits(&renderer);
+        assert!(view.scene_warning);
+        assert!(view.app.status.is_shown());
+        assert!(!view.app.status.is_visible());
+
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        view.canvas.remove_nodes(systems, &ids);
+        view.update_scene_limits(&renderer);
+        assert!(!view.app.status.is_shown());
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn removing_a_baked_node_draws_the_rest_live() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        let first = view
+            .canvas
+            .add_node(systems, rectangle(Point2D::new(0., 0.)));
+        view.canvas
+            .add_node(systems, rectangle(Point2D::new(50., 0.)));
+        view.canvas.bake(systems, 2);
+
+        view.canvas.remove_node_id(systems, first);
+        assert_eq!(view.canvas.baked_len(), 0);
+        assert_eq!(view.canvas.stats().nodes, 1);
+        assert_eq!(view.canvas.scene_layers().count(), 1);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn exports_cover_the_export_region() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        view.canvas
+            .add_node(systems, rectangle(Point2D::new(100., 100.)));
+        let region = view.canvas.export_region().unwrap();
+        let (mesh, batches) = view.canvas.export_mesh(systems);
+        let image = export::render(
+            &renderer,
+            &view.systems.texture,
+            &mesh,
+            &batches,
+            region,
+            YDirection::Down,
+        )
+        .unwrap();
+
+        assert_eq!(image.size, Size2D::new(40, 30));
+        assert_eq!(image.pixels.len(), 40 * 30 * 4);
+        // The middle of the rectangle is opaque, it is filled with an opaque gray.
+        assert_eq!(image.pixels[(15 * 40 + 20) * 4 + 3], 255);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn svg_images_decode_once() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        // A 2x2 png, embedded the way svg editors do.
+        let data = concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="2" height="2">"#,
+            r#"<image width="2" height="2" href="data:image/png;base64,"#,
+            "iVBORw0KGgoAAAANSUhEUgAAAAIAAAACCAYAAABytg0kAAAAFUlEQVR42mP4z8DwHwgbWBjBNEMDADqJBgGerBgxAAAAAElFTkSuQmCC",
+            r#""/></svg>"#,
+        );
+        let stamp = |origin| {
+            Primitive::Svg(Svg::new(
+                origin,
+                Size2D::splat(20.),
+                data.as_bytes().to_vec(),
+                SvgOptions::default(),
+            ))
+        };
+
+        let mut first = stamp(Point2D::new(0., 0.));
+        assert_eq!(first.render(systems).indices.len(), 6);
+        let mut second = stamp(Point2D::new(50., 0.));
+        assert_eq!(second.render(systems).indices.len(), 6);
+        assert_eq!(systems.image.len(), 1);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn recorded_macros_export_a_frame_per_step() {
+        let (renderer, mut view) = gpu_view();
+        view.recorder.toggle_recording(Point2D::origin());
+        view.handle_tool(
+            ToolKind::Rectangle,
+            vec![
+                ToolMessage::Commit(rectangle(Point2D::new(100., 100.))),
+                ToolMessage::Commit(rectangle(Point2D::new(300., 200.))),
+            ],
+            &renderer,
+        );
+        view.recorder.toggle_recording(Point2D::origin());
+
+        let directory =
+            std::env::temp_dir().join(format!("draw-macro-frames-{}", std::process::id()));
+        let fps = 10.;
+        let frames = view.recorder.recorded().unwrap().frame_steps(fps).len();
+        view.export_animation(fps, &AnimationOutput::Frames(directory.clone()), &renderer)
+            .unwrap();
+        let written = std::fs::read_dir(&directory).unwrap().count();
+        assert!(directory.join("frame-0000.png").exists());
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(written, frames);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn exporting_the_selection_falls_back_to_the_canvas() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        let first = view
+            .canvas
+            .add_node(systems, rectangle(Point2D::new(100., 100.)));
+        view.canvas
+            .add_node(systems, rectangle(Point2D::new(300., 200.)));
+
+        let (_, _, region) = view.export_selection(&renderer).unwrap();
+        assert_eq!(region, view.canvas.export_region().unwrap());
+
+        view.selection = vec![first];
+        let (mesh, _, region) = view.export_selection(&renderer).unwrap();
+        assert_eq!(region, view.canvas.nodes_bounds(&[first]).unwrap());
+        assert!(!mesh.indices.is_empty());
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn zooming_keeps_the_view_busy() {
+        let (renderer, mut view) = gpu_view();
+        view.projection
+            .zoom_at_animated(Point2D::new(400., 300.), 1.1, ZOOM_DURATION);
+        assert!(view.is_busy());
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn history_evicts_the_oldest_entries_past_the_count() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        let history = HistoryLimits {
+            max_entries: 3,
+            ..HistoryLimits::default()
+        };
+        view.canvas.history.set_limits(history);
+        let nodes = (0..5)
+            .map(|i| {
+                view.canvas
+                    .add_node(systems, rectangle(Point2D::new(i as f32 * 50., 0.)))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(view.canvas.history.len(), 3);
+
+        // Only the three newest additions can be undone, the first two nodes stay.
+        while view.canvas.undo(systems) {}
+        let remaining = view
+            .canvas
+            .primitives()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        assert_eq!(remaining.len(), 2);
+        assert!(nodes[..2].iter().all(|id| remaining.contains(id)));
+
+        // Lowering the limit evicts right away.
+        for i in 0..3 {
+            view.canvas
+                .add_node(systems, rectangle(Point2D::new(i as f32 * 50., 100.)));
+        }
+        view.canvas.history.set_limits(HistoryLimits {
+            max_entries: 1,
+            ..history
+        });
+        assert_eq!(view.canvas.history.len(), 1);
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn history_evicts_the_oldest_entries_past_the_budget() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        let first = view
+            .canvas
+            .add_node(systems, rectangle(Point2D::new(0., 0.)));
+        let second = view
+            .canvas
+            .add_node(systems, rectangle(Point2D::new(100., 0.)));
+        let added = view.canvas.history.approximate_size();
+
+        // A removal holds on to the tessellated node, so it is far larger than an addition.
+        view.canvas.remove_node_id(systems, first);
+        let removed = view.canvas.history.approximate_size() - added;
+        assert!(removed > added);
+        assert_eq!(view.canvas.history.len(), 3);
+
+        // Room for a single removal evicts both additions before it.
+        view.canvas.history.set_limits(HistoryLimits {
+            max_bytes: removed,
+            ..HistoryLimits::default()
+        });
+        assert_eq!(view.canvas.history.len(), 1);
+        assert_eq!(view.canvas.history.approximate_size(), removed);
+
+        // A second removal no longer fits next to the first, which is evicted in turn.
+        view.canvas.remove_node_id(systems, second);
+        assert_eq!(view.canvas.history.len(), 1);
+        assert!(view.canvas.history.approximate_size() <= removed);
+        assert!(view.canvas.undo(systems));
+        assert!(view.canvas.primitive(second).is_some());
+        assert!(view.canvas.primitive(first).is_none());
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn the_options_panel_collapses_over_time() {
+        let (renderer, mut view) = gpu_view();
+        let systems = &mut view.systems.to_ref(&renderer.device, &renderer.queue);
+        let (options, gui) = (&mut view.app.options, &mut view.app.gui);
+        let panel = options.background_node;
+        let panel_height = |tree: &mut UITree<gui::widgets::Widget<Message>>,
+                            systems: &mut graphics::Systems| {
+            tree.update_layout(systems);
+            tree.relative_layout(panel).size.height
+        };
+
+        let expanded = panel_height(gui, systems);
+        options.set_collapsed(gui, true);
+        let start = Instant::now();
+        assert!(options.step_animation(gui, start + COLLAPSE_DURATION / 2));
+        let halfway = panel_height(gui, systems);
+        assert!(!options.step_animation(gui, start + COLLAPSE_DURATION));
+        let collapsed = panel_height(gui, systems);
+        assert!(collapsed < halfway && halfway < expanded);
+
+        // The contents only come back once the panel has grown to fit them.
+        options.set_collapsed(gui, false);
+        let start = Instant::now();
+        assert!(options.step_animation(gui, start + COLLAPSE_DURATION / 2));
+        assert!(panel_height(gui, systems) < expanded);
+        assert!(!options.step_animation(gui, start + COLLAPSE_DURATION));
+        assert_eq!(panel_height(gui, systems), expanded);
+        assert!(!options.is_collapsed());
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn pasted_images_are_centered_in_the_viewport_and_selected() {
+        let (renderer, mut view) = gpu_view();
+        let png = export::png::encode(Size2D::new(20, 10), &[255; 20 * 10 * 4], None);
+        view.paste_image(&png, &renderer).unwrap();
+        assert!(view.paste_image(b"not a png", &renderer).is_err());
+
+        let [id] = view.selection[..] else {
+            panic!("the pasted image is selected on its own");
+        };
+        let bounds = view.canvas.primitive(id).unwrap().bounding_box();
+        let center = view.projection.viewport_to_world(Point2D::new(400., 300.));
+        assert!((bounds.center() - center).length() < 0.01);
+        assert_eq!(bounds.size(), Size2D::new(20., 10.));
     }
 }
@@ -1,15 +1,17 @@
 #![feature(const_trait_impl)]
 
-use std::{borrow::Borrow, sync::OnceLock};
+use std::{borrow::Borrow, collections::BTreeMap, ops::Range, sync::OnceLock};
 
+use anyhow::{bail, Result};
 use atlas::{TextureMesh, TextureVertex};
 use bytemuck::{Pod, Zeroable};
-use color::{HueDirection, PremulColor, Srgb};
+use color::{HueDirection, Oklch, PremulColor, Srgb};
 use euclid::default::{Box2D, Point2D, Vector2D};
 use lyon::{math::Point, path::builder::BorderRadii};
 use serde::{Deserialize, Serialize};
 
 // pub mod line;
+pub mod png;
 pub mod primitives;
 pub mod systems;
 
@@ -33,6 +35,31 @@ where
             indices: vec![],
         }
     }
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+    /// Checks that the indices describe whole triangles and only reference existing vertices,
+    /// returning an error describing the first problem found.
+    pub fn validate(&self) -> Result<()> {
+        if self.indices.len() % 3 != 0 {
+            bail!("index count {} is not a multiple of 3", self.indices.len());
+        }
+        if let Some((position, index)) = self
+            .indices
+            .iter()
+            .enumerate()
+            .find(|(_, index)| **index as usize >= self.vertices.len())
+        {
+            bail!(
+                "index {index} at position {position} is out of range for {} vertices",
+                self.vertices.len()
+            );
+        }
+        Ok(())
+    }
     pub fn offset_indices(&mut self, offset: u32) {
         for index in self.indices.iter_mut() {
             *index += offset
@@ -97,6 +124,115 @@ impl Mesh<Vertex> {
     }
 }
 
+/// Combines many meshes into a single one, allocating the combined size once instead of growing
+/// with every append.
+///
+/// Meshes that can be borrowed at the same time go through [`MeshBatcher::batch`]. Drawables
+/// that render through `&mut self` can only hand out one mesh at a time, so they are first
+/// passed to [`MeshBatcher::reserve`] and then pushed in a second pass, which hits their render
+/// cache.
+#[derive(Debug, Default)]
+pub struct MeshBatcher<V: Clone + Pod + Zeroable> {
+    mesh: Mesh<V>,
+    reserved_vertices: usize,
+    reserved_indices: usize,
+}
+
+/// A run of indices whose vertices share a [`VertexKind::kind_id`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KindBatch {
+    pub kind: u32,
+    pub indices: Range<u32>,
+}
+
+impl<V> MeshBatcher<V>
+where
+    V: Clone + Pod + Zeroable,
+{
+    pub const fn new() -> Self {
+        Self {
+            mesh: Mesh::empty(),
+            reserved_vertices: 0,
+            reserved_indices: 0,
+        }
+    }
+
+    /// Combines the meshes into one.
+    pub fn batch<'a>(meshes: impl IntoIterator<Item = &'a Mesh<V>> + Clone) -> Mesh<V> {
+        let mut batcher = Self::new();
+        for mesh in meshes.clone() {
+            batcher.reserve(mesh);
+        }
+        for mesh in meshes {
+            batcher.push(mesh);
+        }
+        batcher.finish()
+    }
+
+    /// Accounts for the size of a mesh that will be pushed later.
+    pub fn reserve(&mut self, mesh: &Mesh<V>) {
+        self.reserved_vertices += mesh.vertices.len();
+        self.reserved_indices += mesh.indices.len();
+    }
+
+    /// Appends the mesh, returning the range its indices ended up at.
+    pub fn push(&mut self, mesh: &Mesh<V>) -> Range<u32> {
+        // Everything reserved so far is allocated on the first push.
+        if self.reserved_vertices > 0 || self.reserved_indices > 0 {
+            self.mesh.vertices.reserve(self.reserved_vertices);
+            self.mesh.indices.reserve(self.reserved_indices);
+            self.reserved_vertices = 0;
+            self.reserved_indices = 0;
+        }
+        let start = self.mesh.indices.len() as u32;
+        self.mesh.append(mesh);
+        start..self.mesh.indices.len() as u32
+    }
+
+    pub fn finish(self) -> Mesh<V> {
+        self.mesh
+    }
+}
+
+impl MeshBatcher<Vertex> {
+    /// Finishes the mesh with its triangles grouped by the kind of their first vertex, so each
+    /// kind can be drawn with a single call.
+    ///
+    /// This reorders triangles, so it should only be used when the kinds do not overlap or are
+    /// drawn with an order independent blend.
+    pub fn finish_grouped(self) -> (Mesh<Vertex>, Vec<KindBatch>) {
+        let Mesh { vertices, indices } = self.mesh;
+        let mut groups: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for triangle in indices.chunks_exact(3) {
+            let kind = vertices[triangle[0] as usize].kind;
+            groups.entry(kind).or_default().extend_from_slice(triangle);
+        }
+
+        let mut indices = Vec::with_capacity(indices.len());
+        let batches = groups
+            .into_iter()
+            .map(|(kind, group)| {
+                let start = indices.len() as u32;
+                indices.extend(group);
+                KindBatch {
+                    kind,
+                    indices: start..indices.len() as u32,
+                }
+            })
+            .collect();
+        (Mesh { vertices, indices }, batches)
+    }
+}
+
+/// How a primitive is blended with the content drawn before it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    /// Subtracts the alpha of the primitive from the content below it, cutting holes into it.
+    Erase,
+}
+
 pub trait Drawable<V = Vertex>
 where
     V: Clone + Pod + Zeroable,
@@ -291,6 +427,34 @@ pub struct RadialGradient {
     pub(crate) radius: Vector2D<f32>,
 }
 
+/// How two colors are mixed when interpolating between them.
+///
+/// Colors are mixed as premultiplied srgb components by default, so a transparent endpoint fades
+/// the other color out instead of tinting it. Interpolating the hue keeps the saturation of
+/// colorful transitions, at the cost of passing through colors neither endpoint has.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorInterpolation {
+    #[default]
+    Rectangular,
+    /// Interpolates in oklch, taking the shorter way around the hue circle.
+    ShorterHue,
+    /// Interpolates in oklch, taking the longer way around the hue circle.
+    LongerHue,
+}
+
+impl ColorInterpolation {
+    pub fn mix(self, from: PremulColor<Srgb>, to: PremulColor<Srgb>, t: f32) -> PremulColor<Srgb> {
+        let direction = match self {
+            ColorInterpolation::Rectangular => return from.lerp_rect(to, t),
+            ColorInterpolation::ShorterHue => HueDirection::Shorter,
+            ColorInterpolation::LongerHue => HueDirection::Longer,
+        };
+        from.convert::<Oklch>()
+            .lerp(to.convert::<Oklch>(), t, direction)
+            .convert()
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BasicLinearGradient {
     pub(crate) start_color: PremulColor<Srgb>,
@@ -300,6 +464,8 @@ pub struct BasicLinearGradient {
     pub(crate) p2: Point2D<f32>,
 
     pub(crate) spread: SpreadMethod,
+    #[serde(default)]
+    pub(crate) interpolation: ColorInterpolation,
 }
 impl BasicLinearGradient {
     pub const fn new(start_color: PremulColor<Srgb>, end_color: PremulColor<Srgb>) -> Self {
@@ -309,6 +475,7 @@ impl BasicLinearGradient {
             p1: Point2D::new(0., 0.),
             p2: Point2D::new(0., 0.),
             spread: SpreadMethod::Pad,
+            interpolation: ColorInterpolation::Rectangular,
         }
     }
     pub const fn new_with_points(
@@ -324,8 +491,14 @@ impl BasicLinearGradient {
             p1,
             p2,
             spread: spread_method,
+            interpolation: ColorInterpolation::Rectangular,
         }
     }
+    /// Changes how the colors are mixed along the gradient.
+    pub const fn with_interpolation(mut self, interpolation: ColorInterpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
     pub const fn update_points(&mut self, p1: Point2D<f32>, p2: Point2D<f32>) {
         self.p1 = p1;
         self.p2 = p2;
@@ -373,17 +546,24 @@ impl BasicLinearGradient {
             }
         };
 
-        self.start_color
-            .lerp(self.end_color, t, HueDirection::Shorter)
+        self.interpolation.mix(self.start_color, self.end_color, t)
     }
 
+    /// Interpolates the colors and points of both gradients, mixing the colors the way this
+    /// gradient does.
     pub fn lerp(&self, other: Self, t: f32) -> Self {
+        let mix = |from, to| self.interpolation.mix(from, to, t);
         Self {
-            start_color: self.start_color.lerp_rect(other.start_color, t),
-            end_color: self.end_color.lerp_rect(other.end_color, t),
+            start_color: mix(self.start_color, other.start_color),
+            end_color: mix(self.end_color, other.end_color),
             p1: self.p1.lerp(other.p1, t),
             p2: self.p2.lerp(other.p2, t),
             spread: if t < 1. { self.spread } else { other.spread },
+            interpolation: if t < 1. {
+                self.interpolation
+            } else {
+                other.interpolation
+            },
         }
     }
 }
@@ -395,16 +575,21 @@ pub enum BasicColor {
 }
 
 impl BasicColor {
+    /// Interpolates between the colors with [`ColorInterpolation::Rectangular`], gradients mix
+    /// with their own interpolation.
     pub fn lerp(&self, other: Self, t: f32) -> Self {
+        self.lerp_with(other, t, ColorInterpolation::Rectangular)
+    }
+
+    /// Interpolates between the colors, mixing solid colors with the given interpolation.
+    pub fn lerp_with(&self, other: Self, t: f32, interpolation: ColorInterpolation) -> Self {
         match self {
             BasicColor::Solid(color) => match other {
-                BasicColor::Solid(color2) => color.lerp_rect(color2, t).into(),
+                BasicColor::Solid(color2) => interpolation.mix(*color, color2, t).into(),
                 BasicColor::LinearGradient(gradient) => BasicLinearGradient {
-                    start_color: color.lerp_rect(gradient.start_color, t),
-                    end_color: color.lerp_rect(gradient.end_color, t),
-                    p1: gradient.p1,
-                    p2: gradient.p2,
-                    spread: gradient.spread,
+                    start_color: gradient.interpolation.mix(*color, gradient.start_color, t),
+                    end_color: gradient.interpolation.mix(*color, gradient.end_color, t),
+                    ..gradient
                 }
                 .into(),
             },
@@ -412,11 +597,9 @@ impl BasicColor {
                 BasicColor::Solid(color) => {
                     if t < 1. {
                         BasicLinearGradient {
-                            start_color: gradient.start_color.lerp_rect(color, t),
-                            end_color: gradient.end_color.lerp_rect(color, t),
-                            p1: gradient.p1,
-                            p2: gradient.p2,
-                            spread: gradient.spread,
+                            start_color: gradient.interpolation.mix(gradient.start_color, color, t),
+                            end_color: gradient.interpolation.mix(gradient.end_color, color, t),
+                            ..*gradient
                         }
                         .into()
                     } else {
@@ -454,3 +637,142 @@ pub const fn make_positive_box(mut area: Box2D<f32>) -> Box2D<f32> {
     };
     area
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_quad_is_valid() {
+        let quad = Mesh::new_color_quad(
+            Box2D::new(Point2D::new(0., 0.), Point2D::new(10., 10.)),
+            VertexKind::Color(PremulColor::WHITE),
+        );
+        assert_eq!(quad.vertex_count(), 4);
+        assert_eq!(quad.triangle_count(), 2);
+        assert!(quad.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_malformed_indices() {
+        let mut mesh = Mesh::new_color_quad(
+            Box2D::new(Point2D::new(0., 0.), Point2D::new(10., 10.)),
+            VertexKind::Color(PremulColor::WHITE),
+        );
+        mesh.indices.push(0);
+        assert!(mesh.validate().is_err());
+
+        mesh.indices.extend([1, 4]);
+        assert!(mesh.validate().is_err());
+
+        mesh.indices.truncate(6);
+        assert!(mesh.validate().is_ok());
+    }
+
+    fn assert_color_eq(color: PremulColor<Srgb>, expected: [f32; 4]) {
+        let close = (color.components.iter().zip(expected)).all(|(a, b)| (a - b).abs() < 1e-3);
+        assert!(close, "{:?} != {expected:?}", color.components);
+    }
+
+    #[test]
+    fn solid_lerp_mixes_premultiplied_components() {
+        let red = PremulColor::new([1., 0., 0., 1.]);
+        let blue = PremulColor::new([0., 0., 1., 1.]);
+        let BasicColor::Solid(mid) = BasicColor::from(red).lerp(blue.into(), 0.5) else {
+            panic!("two solid colors should mix to a solid color");
+        };
+        assert_color_eq(mid, [0.5, 0., 0.5, 1.]);
+
+        // A transparent endpoint fades the color out instead of darkening it.
+        let faded = ColorInterpolation::Rectangular.mix(red, PremulColor::TRANSPARENT, 0.5);
+        assert_color_eq(faded, [0.5, 0., 0., 0.5]);
+    }
+
+    #[test]
+    fn gradient_midpoint_matches_its_interpolation() {
+        let red = PremulColor::new([1., 0., 0., 1.]);
+        let blue = PremulColor::new([0., 0., 1., 1.]);
+        let gradient = BasicLinearGradient::new_with_points(
+            red,
+            blue,
+            Point2D::new(0., 0.),
+            Point2D::new(10., 0.),
+            SpreadMethod::Pad,
+        );
+        assert_color_eq(gradient.get_point(Point2D::new(5., 0.)), [0.5, 0., 0.5, 1.]);
+        let BasicColor::Solid(mid) = BasicColor::from(red).lerp(blue.into(), 0.5) else {
+            panic!("two solid colors should mix to a solid color");
+        };
+        assert_eq!(gradient.get_point(Point2D::new(5., 0.)), mid);
+
+        // Interpolating the hue keeps the endpoints, but the midpoint is more saturated than the
+        // rectangular mix.
+        let hue = gradient.with_interpolation(ColorInterpolation::ShorterHue);
+        assert_color_eq(hue.get_point(Point2D::new(0., 0.)), [1., 0., 0., 1.]);
+        assert_color_eq(hue.get_point(Point2D::new(10., 0.)), [0., 0., 1., 1.]);
+        let [r, _, b, _] = hue.get_point(Point2D::new(5., 0.)).components;
+        assert!(r.max(b) > 0.5);
+    }
+
+    #[test]
+    fn hue_interpolation_goes_around_the_hue_circle() {
+        let red = PremulColor::new([1., 0., 0., 1.]);
+        let blue = PremulColor::new([0., 0., 1., 1.]);
+        let hue = |color: PremulColor<Srgb>| color.convert::<Oklch>().components[2];
+        let assert_hue_eq = |color: PremulColor<Srgb>, expected: f32| {
+            let difference = (hue(color) - expected).rem_euclid(360.);
+            assert!(
+                difference.min(360. - difference) < 0.1,
+                "{} != {expected}",
+                hue(color)
+            );
+        };
+
+        // Red and blue are more than half a turn apart, so the shorter way passes through zero.
+        let (from, to) = (hue(red), hue(blue));
+        assert!(to - from > 180.);
+        let shorter = ColorInterpolation::ShorterHue.mix(red, blue, 0.5);
+        assert_hue_eq(shorter, (from + to - 360.) / 2.);
+        let longer = ColorInterpolation::LongerHue.mix(red, blue, 0.5);
+        assert_hue_eq(longer, (from + to) / 2.);
+        assert_hue_eq(
+            ColorInterpolation::ShorterHue.mix(red, blue, 0.25),
+            from - (from + 360. - to) / 4.,
+        );
+    }
+
+    #[test]
+    fn batcher_groups_triangles_by_kind() {
+        let area = Box2D::new(Point2D::new(0., 0.), Point2D::new(10., 10.));
+        let color = Mesh::new_color_quad(area, VertexKind::Color(PremulColor::WHITE));
+        let viewport = Mesh::new_color_quad(area, VertexKind::ColorViewport(PremulColor::WHITE));
+
+        let mut batcher = MeshBatcher::new();
+        for mesh in [&color, &viewport, &color] {
+            batcher.reserve(mesh);
+        }
+        assert_eq!(batcher.push(&color), 0..6);
+        assert_eq!(batcher.push(&viewport), 6..12);
+        assert_eq!(batcher.push(&color), 12..18);
+
+        let (mesh, batches) = batcher.finish_grouped();
+        assert!(mesh.validate().is_ok());
+        assert_eq!(
+            batches,
+            vec![
+                KindBatch {
+                    kind: 0,
+                    indices: 0..12
+                },
+                KindBatch {
+                    kind: 3,
+                    indices: 12..18
+                },
+            ]
+        );
+        assert_eq!(
+            MeshBatcher::batch([&color, &viewport]).indices,
+            [0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7]
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! A png decoder for the raster images embedded in svgs, decoding through the
+//! [`ImageCache`](crate::systems::ImageCache).
+//!
+//! Every color type and bit depth is supported, interlaced images are not. The images come from
+//! untrusted files, so their size is capped before anything is allocated for them, and the pixel
+//! data is never inflated past the size the header calls for.
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use miniz_oxide::inflate::TINFLStatus;
+
+use crate::systems::DecodedImage;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// The widest and tallest image that is decoded, the largest texture every gpu supports, as the
+/// image could not be put in the atlas past that anyway.
+pub const MAX_DIMENSION: u32 = 8192;
+
+/// Decodes a png into straight alpha rgba8 pixels.
+pub fn decode(bytes: &[u8]) -> Result<DecodedImage> {
+    decode_with_limit(bytes, MAX_DIMENSION)
+}
+
+/// Decodes a png into straight alpha rgba8 pixels, rejecting it if it is wider or taller than
+/// `max_dimension`, for callers that scale the image down themselves.
+pub fn decode_with_limit(bytes: &[u8], max_dimension: u32) -> Result<DecodedImage> {
+    ensure!(bytes.starts_with(&SIGNATURE), "not a png");
+    let mut rest = &bytes[SIGNATURE.len()..];
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = vec![];
+    while rest.len() >= 12 {
+        let len = u32::from_be_bytes(rest[..4].try_into()?) as usize;
+        ensure!(len <= rest.len() - 12, "truncated png chunk");
+        let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+        match kind {
+            b"IHDR" => header = Some(Header::parse(data, max_dimension)?),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        rest = &rest[12 + len..];
+    }
+    let header = header.context("the png has no header")?;
+
+    let bits_per_pixel = header.channels() * header.depth as usize;
+    let stride = (header.width as usize)
+        .checked_mul(bits_per_pixel)
+        .context("the png is too large")?
+        .div_ceil(8);
+    // Every row starts with its filter byte.
+    let filtered_size = (stride + 1)
+        .checked_mul(header.height as usize)
+        .context("the png is too large")?;
+    let pixels_size = (header.width as usize)
+        .checked_mul(header.height as usize)
+        .and_then(|area| area.checked_mul(4))
+        .context("the png is too large")?;
+
+    let filtered = inflate_zlib(&compressed, filtered_size)?;
+    let rows = unfilter(
+        &filtered,
+        stride,
+        bits_per_pixel.div_ceil(8),
+        header.height as usize,
+    )?;
+
+    let mut pixels = Vec::with_capacity(pixels_size);
+    for row in rows.chunks_exact(stride) {
+        for x in 0..header.width as usize {
+            let channel = |channel| sample(row, x * header.channels() + channel, header.depth);
+            let scale = |value| scale_sample(value, header.depth);
+            let opaque = |samples: &[u16]| {
+                if transparent(transparency, samples) {
+                    0
+                } else {
+                    255
+                }
+            };
+            let pixel = match header.color {
+                ColorType::Gray => {
+                    let gray = channel(0);
+                    let [r, g, b] = [scale(gray); 3];
+                    [r, g, b, opaque(&[gray])]
+                }
+                ColorType::Rgb => {
+                    let rgb = [channel(0), channel(1), channel(2)];
+                    let [r, g, b] = rgb.map(scale);
+                    [r, g, b, opaque(&rgb)]
+                }
+                ColorType::Palette => {
+                    let index = channel(0) as usize;
+                    let color = palette
+                        .get(index * 3..index * 3 + 3)
+                        .context("the png palette is too short")?;
+                    let alpha = transparency.get(index).copied().unwrap_or(255);
+                    [color[0], color[1], color[2], alpha]
+                }
+                ColorType::GrayAlpha => {
+                    let gray = scale(channel(0));
+                    [gray, gray, gray, scale(channel(1))]
+                }
+                ColorType::Rgba => [0, 1, 2, 3].map(|index| scale(channel(index))),
+            };
+            pixels.extend(pixel);
+        }
+    }
+
+    Ok(DecodedImage {
+        width: header.width,
+        height: header.height,
+        pixels,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorType {
+    Gray,
+    Rgb,
+    Palette,
+    GrayAlpha,
+    Rgba,
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    depth: u8,
+    color: ColorType,
+}
+
+impl Header {
+    fn parse(data: &[u8], max_dimension: u32) -> Result<Self> {
+        ensure!(data.len() == 13, "the png header has the wrong length");
+        let width = u32::from_be_bytes(data[0..4].try_into()?);
+        let height = u32::from_be_bytes(data[4..8].try_into()?);
+        let depth = data[8];
+        let color = match data[9] {
+            0 => ColorType::Gray,
+            2 => ColorType::Rgb,
+            3 => ColorType::Palette,
+            4 => ColorType::GrayAlpha,
+            6 => ColorType::Rgba,
+            color => bail!("unknown png color type {color}"),
+        };
+        let depths: &[u8] = match color {
+            ColorType::Gray => &[1, 2, 4, 8, 16],
+            ColorType::Palette => &[1, 2, 4, 8],
+            _ => &[8, 16],
+        };
+        ensure!(depths.contains(&depth), "invalid png bit depth {depth}");
+        ensure!(data[12] == 0, "interlaced pngs are not supported");
+        ensure!(width > 0 && height > 0, "the png is empty");
+        ensure!(
+            width <= max_dimension && height <= max_dimension,
+            "the png is {width}x{height}, larger than {max_dimension}x{max_dimension}"
+        );
+        Ok(Self {
+            width,
+            height,
+            depth,
+            color,
+        })
+    }
+
+    const fn channels(&self) -> usize {
+        match self.color {
+            ColorType::Gray | ColorType::Palette => 1,
+            ColorType::GrayAlpha => 2,
+            ColorType::Rgb => 3,
+            ColorType::Rgba => 4,
+        }
+    }
+}
+
+/// The `index`th sample of a row, samples narrower than a byte are packed from the high bits.
+fn sample(row: &[u8], index: usize, depth: u8) -> u16 {
+    match depth {
+        16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+        8 => row[index] as u16,
+        depth => {
+            let per_byte = 8 / depth as usize;
+            let shift = 8 - depth as usize * (index % per_byte + 1);
+            ((row[index / per_byte] >> shift) & ((1 << depth) - 1)) as u16
+        }
+    }
+}
+
+/// Scales a sample to 8 bits.
+fn scale_sample(value: u16, depth: u8) -> u8 {
+    match depth {
+        16 => (value >> 8) as u8,
+        depth => (value as u32 * 255 / ((1 << depth) - 1)) as u8,
+    }
+}
+
+/// Whether the samples are the single transparent color of a gray or rgb `tRNS` chunk.
+fn transparent(transparency: &[u8], samples: &[u16]) -> bool {
+    transparency.len() == samples.len() * 2
+        && transparency
+            .chunks_exact(2)
+            .zip(samples)
+            .all(|(key, sample)| u16::from_be_bytes([key[0], key[1]]) == *sample)
+}
+
+/// Reverses the filter of every scanline, returning the rows without their filter bytes.
+fn unfilter(data: &[u8], stride: usize, bytes_per_pixel: usize, rows: usize) -> Result<Vec<u8>> {
+    ensure!(
+        data.len() >= (stride + 1) * rows,
+        "the png has too little pixel data"
+    );
+    let mut out = vec![0u8; stride * rows];
+    for y in 0..rows {
+        let line = &data[y * (stride + 1)..(y + 1) * (stride + 1)];
+        let (before, current) = out.split_at_mut(y * stride);
+        let previous = (y > 0).then(|| &before[(y - 1) * stride..]);
+        let current = &mut current[..stride];
+        for x in 0..stride {
+            let left = if x >= bytes_per_pixel {
+                current[x - bytes_per_pixel]
+            } else {
+                0
+            };
+            let up = previous.map_or(0, |previous| previous[x]);
+            let up_left = match previous {
+                Some(previous) if x >= bytes_per_pixel => previous[x - bytes_per_pixel],
+                _ => 0,
+            };
+            let predicted = match line[0] {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                filter => bail!("unknown png filter {filter}"),
+            };
+            current[x] = line[x + 1].wrapping_add(predicted);
+        }
+    }
+    Ok(out)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let [to_left, to_up, to_up_left] =
+        [left, up, up_left].map(|value| (estimate - value as i16).abs());
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Inflates a zlib stream of at most `limit` bytes, without checking its checksum.
+fn inflate_zlib(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    ensure!(data.len() >= 2, "the png has no pixel data");
+    ensure!(
+        data[0] & 0x0f == 8 && u16::from_be_bytes([data[0], data[1]]) % 31 == 0,
+        "the png pixel data is not deflated"
+    );
+    ensure!(
+        data[1] & 0x20 == 0,
+        "preset zlib dictionaries are not supported"
+    );
+    inflate(&data[2..], limit)
+}
+
+/// Inflates raw deflate data, failing once it inflates to more than `limit` bytes.
+fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_with_limit(data, limit).map_err(|err| {
+        match err.status {
+            TINFLStatus::HasMoreOutput => anyhow!("the png pixel data inflates past {limit} bytes"),
+            status => anyhow!("corrupt png pixel data: {status:?}"),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x2 rgba png, using the paeth filter on its second row.
+    const RGBA: &[u8] = &[
+        137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 2, 0, 0, 0, 2, 8, 6,
+        0, 0, 0, 114, 182, 13, 36, 0, 0, 0, 21, 73, 68, 65, 84, 120, 218, 99, 248, 207, 192, 240,
+        31, 8, 27, 88, 24, 193, 52, 67, 3, 0, 58, 137, 6, 1, 158, 172, 24, 49, 0, 0, 0, 0, 73, 69,
+        78, 68, 174, 66, 96, 130,
+    ];
+    /// A 3x1 png with a 1 bit palette and a transparent first entry.
+    const PALETTE: &[u8] = &[
+        137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 3, 0, 0, 0, 1, 1, 3,
+        0, 0, 0, 33, 46, 134, 247, 0, 0, 0, 6, 80, 76, 84, 69, 0, 0, 0, 10, 20, 30, 124, 81, 214,
+        47, 0, 0, 0, 1, 116, 82, 78, 83, 0, 64, 230, 216, 102, 0, 0, 0, 10, 73, 68, 65, 84, 120,
+        218, 99, 112, 0, 0, 0, 66, 0, 65, 132, 191, 142, 98, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66,
+        96, 130,
+    ];
+    /// A 16x16 rgb gradient using the sub filter, large enough for zlib to pick dynamic codes.
+    const GRADIENT: &[u8] = &[
+        137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 16, 0, 0, 0, 16, 8,
+        2, 0, 0, 0, 144, 145, 104, 54, 0, 0, 0, 121, 73, 68, 65, 84, 120, 218, 149, 145, 65, 13, 0,
+        33, 16, 3, 91, 7, 72, 64, 2, 18, 144, 128, 4, 36, 32, 1, 9, 72, 64, 2, 18, 144, 128, 4, 36,
+        32, 225, 46, 240, 225, 183, 108, 210, 76, 250, 105, 210, 100, 8, 192, 192, 188, 135, 63,
+        13, 150, 129, 123, 36, 97, 237, 158, 142, 205, 32, 118, 194, 157, 245, 73, 18, 59, 225,
+        253, 117, 177, 136, 157, 8, 225, 122, 217, 196, 78, 196, 120, 125, 149, 73, 164, 251, 171,
+        28, 34, 103, 165, 135, 82, 148, 30, 106, 85, 122, 104, 77, 233, 161, 119, 165, 135, 49,
+        148, 30, 230, 84, 122, 88, 75, 229, 225, 3, 111, 155, 150, 17, 59, 118, 31, 107, 0, 0, 0,
+        0, 73, 69, 78, 68, 174, 66, 96, 130,
+    ];
+
+    #[test]
+    fn rgba_pngs_decode() {
+        let image = decode(RGBA).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(
+            image.pixels,
+            [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 255, 255, 255, 255, 0]
+        );
+    }
+
+    #[test]
+    fn palette_pngs_decode() {
+        let image = decode(PALETTE).unwrap();
+        assert_eq!((image.width, image.height), (3, 1));
+        assert_eq!(image.pixels, [0, 0, 0, 0, 10, 20, 30, 255, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn dynamic_codes_decode() {
+        let image = decode(GRADIENT).unwrap();
+        assert_eq!((image.width, image.height), (16, 16));
+        for (index, pixel) in image.pixels.chunks_exact(4).enumerate() {
+            let (x, y) = (index as u8 % 16, index as u8 / 16);
+            assert_eq!(pixel, [x * 16, y * 16, (x ^ y) * 16, 255]);
+        }
+    }
+
+    #[test]
+    fn stored_and_compressed_deflate_agree() {
+        // "hello hello hello", stored and then compressed with fixed codes by zlib.
+        let stored = [1, 17, 0, 238, 255];
+        let data = b"hello hello hello";
+        assert_eq!(inflate(&[&stored[..], data].concat(), 64).unwrap(), data);
+        let fixed = [203, 72, 205, 201, 201, 87, 200, 64, 144, 0];
+        assert_eq!(inflate(&fixed, 64).unwrap(), data);
+    }
+
+    /// A png of the given size with 8 bit rgba pixels, the checksums are left out as the decoder
+    /// doesn't check them.
+    fn png(width: u32, height: u32, idat: &[u8]) -> Vec<u8> {
+        let chunk = |kind: &[u8], data: &[u8]| {
+            [&(data.len() as u32).to_be_bytes(), kind, data, &[0; 4]].concat()
+        };
+        let header = [
+            &width.to_be_bytes()[..],
+            &height.to_be_bytes(),
+            &[8, 6, 0, 0, 0],
+        ]
+        .concat();
+        [
+            &SIGNATURE[..],
+            &chunk(b"IHDR", &header),
+            &chunk(b"IDAT", idat),
+            &chunk(b"IEND", &[]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn oversized_pngs_are_rejected_before_decoding() {
+        let idat = miniz_oxide::deflate::compress_to_vec_zlib(&[0; 5], 6);
+        assert!(decode(&png(1, 1, &idat)).is_ok());
+        assert!(decode(&png(u32::MAX, u32::MAX, &idat)).is_err());
+        assert!(decode(&png(MAX_DIMENSION + 1, 1, &idat)).is_err());
+    }
+
+    #[test]
+    fn pixel_data_is_not_inflated_past_the_image() {
+        // Sixteen megabytes of zeros compress to a few kilobytes, far more than a 2x2 image needs.
+        let bomb = miniz_oxide::deflate::compress_to_vec_zlib(&vec![0; 16 << 20], 9);
+        assert!(bomb.len() < 1 << 16);
+        let err = decode(&png(2, 2, &bomb)).unwrap_err();
+        assert!(err.to_string().contains("inflates past 18 bytes"), "{err}");
+    }
+
+    #[test]
+    fn corrupt_deflate_data_is_rejected() {
+        // A dynamic block that is cut off while its codes are read.
+        assert!(inflate(&[0b101, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], 64).is_err());
+        // A block type that doesn't exist.
+        assert!(inflate(&[0b111], 64).is_err());
+    }
+
+    #[test]
+    fn other_data_is_rejected() {
+        assert!(decode(b"<svg/>").is_err());
+        assert!(decode(&RGBA[..RGBA.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use std::f32::consts::{PI, TAU};
+
+use color::PremulColor;
+use euclid::default::{Box2D, Point2D, Size2D, Vector2D};
+use lyon::path::Path;
+use lyon::tessellation::{
+    BuffersBuilder, StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::primitives::{FlipAxis, Line, LineOptions, Text, TextOptions};
+use crate::{ApplyCoordinates, Drawable, Mesh, Systems, Vertex, VertexKind};
+
+/// How many line segments a full turn of the arc is drawn with.
+const ARC_SEGMENTS_PER_TURN: f32 = 64.;
+/// The space between the arc and the label.
+const LABEL_GAP: f32 = 4.;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Options {
+    /// The style of both rays and of the arc between them.
+    pub line: LineOptions,
+    pub label: TextOptions,
+    /// The distance of the arc from the vertex.
+    pub arc_radius: f32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            line: LineOptions {
+                color: PremulColor::WHITE,
+                width: 2.,
+                ..Default::default()
+            },
+            label: TextOptions {
+                fill: Some(PremulColor::WHITE),
+                font_size: 14.,
+                ..Default::default()
+            },
+            arc_radius: 32.,
+        }
+    }
+}
+
+/// Two rays from a shared vertex, with an arc between them labeled with the angle in degrees.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Angle<C: ApplyCoordinates> {
+    #[serde(skip)]
+    render_cache: Option<Mesh<Vertex>>,
+
+    vertex: Point2D<f32>,
+    start: Point2D<f32>,
+    end: Point2D<f32>,
+    /// Measures the outside of the rays, so the angle is above 180 degrees.
+    reflex: bool,
+
+    rays: [Line<C>; 2],
+    label: Text<C>,
+    /// The measured size of the label, the text area itself is unbounded.
+    #[serde(skip)]
+    label_size: Size2D<f32>,
+
+    options: Options,
+}
+
+impl<C: ApplyCoordinates> Angle<C> {
+    /// Creates an angle at the vertex, measured from the ray through `start` to the ray through
+    /// `end`.
+    pub fn new(
+        vertex: Point2D<f32>,
+        start: Point2D<f32>,
+        end: Point2D<f32>,
+        options: Options,
+    ) -> Self {
+        Self {
+            render_cache: None,
+
+            vertex,
+            start,
+            end,
+            reflex: false,
+
+            rays: [
+                Line::new(vertex, start, options.line.clone()),
+                Line::new(vertex, end, options.line.clone()),
+            ],
+            label: Text::new(
+                String::new(),
+                options.label.clone(),
+                Box2D::from_origin_and_size(vertex, Size2D::new(f32::MAX, f32::MAX)),
+            ),
+            label_size: Size2D::zero(),
+
+            options,
+        }
+    }
+
+    pub fn vertex(&self) -> Point2D<f32> {
+        self.vertex
+    }
+    pub fn set_start(&mut self, start: Point2D<f32>) {
+        self.start = start;
+        self.rays[0].set_destination(start);
+        self.render_cache = None;
+    }
+    pub fn set_end(&mut self, end: Point2D<f32>) {
+        self.end = end;
+        self.rays[1].set_destination(end);
+        self.render_cache = None;
+    }
+    pub fn set_reflex(&mut self, reflex: bool) {
+        if self.reflex != reflex {
+            self.reflex = reflex;
+            self.render_cache = None;
+        }
+    }
+
+    /// The angle the arc starts at, and the signed angle it sweeps through, in radians.
+    fn sweep(&self) -> (f32, f32) {
+        let (start, end) = (self.start - self.vertex, self.end - self.vertex);
+        let start_angle = start.y.atan2(start.x);
+        let mut sweep = (end.y.atan2(end.x) - start_angle).rem_euclid(TAU);
+        if sweep > PI {
+            sweep -= TAU;
+        }
+        if self.reflex {
+            sweep -= sweep.signum() * TAU;
+        }
+        (start_angle, sweep)
+    }
+
+    /// The measured angle in degrees, in the `0..=360` range.
+    pub fn degrees(&self) -> f32 {
+        self.sweep().1.abs().to_degrees()
+    }
+
+    pub fn flip(&mut self, axis: FlipAxis, at: f32) {
+        self.vertex = axis.flip_point(self.vertex, at);
+        self.start = axis.flip_point(self.start, at);
+        self.end = axis.flip_point(self.end, at);
+        for ray in &mut self.rays {
+            ray.flip(axis, at);
+        }
+        self.render_cache = None;
+    }
+
+    pub fn translate(&mut self, dx: Vector2D<f32>) {
+        self.vertex += dx;
+        self.start += dx;
+        self.end += dx;
+        for ray in &mut self.rays {
+            ray.translate(dx);
+        }
+        self.label.translate(dx);
+        if let Some(cache) = &mut self.render_cache {
+            cache.translate(dx);
+        }
+    }
+
+    fn point_at(&self, angle: f32, radius: f32) -> Point2D<f32> {
+        self.vertex + Vector2D::new(angle.cos(), angle.sin()) * radius
+    }
+
+    fn tessellate_arc(&self) -> Mesh<Vertex> {
+        let (start_angle, sweep) = self.sweep();
+        let segments = (sweep.abs() / TAU * ARC_SEGMENTS_PER_TURN).ceil().max(1.) as usize;
+        let mut builder = Path::builder();
+        builder.begin(self.point_at(start_angle, self.options.arc_radius));
+        for segment in 1..=segments {
+            let angle = start_angle + sweep * segment as f32 / segments as f32;
+            builder.line_to(self.point_at(angle, self.options.arc_radius));
+        }
+        builder.end(false);
+        let path = builder.build();
+
+        let color = self.options.line.color;
+        let mut buffers = VertexBuffers::<Vertex, u32>::new();
+        let mut builder = BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex<'_, '_>| {
+            Vertex::with_color(vertex.position(), C::apply(VertexKind::Color(color)))
+        });
+        let options = StrokeOptions::default()
+            .with_line_width(self.options.line.width)
+            .with_line_cap(self.options.line.line_cap)
+            .with_line_join(self.options.line.line_join);
+        if let Err(err) = StrokeTessellator::new().tessellate_path(&path, &options, &mut builder) {
+            warn!("Error while tessellating angle arc: {err}");
+        }
+        Mesh {
+            vertices: buffers.vertices,
+            indices: buffers.indices,
+        }
+    }
+}
+
+impl<C: ApplyCoordinates> Drawable for Angle<C> {
+    fn render(&mut self, systems: &mut Systems) -> &Mesh<Vertex> {
+        if let Some(ref cache) = self.render_cache {
+            return cache;
+        }
+
+        let mut result = self.tessellate_arc();
+        for ray in &mut self.rays {
+            result.append(ray.render(systems));
+        }
+
+        // The label sits just outside the middle of the arc, pushed out by half its diagonal so
+        // it does not overlap the arc whichever way the angle faces.
+        self.label.set_content(format!("{:.1}°", self.degrees()));
+        self.label_size = self.label.measure(systems);
+        let size = self.label_size.to_vector();
+        let (start_angle, sweep) = self.sweep();
+        let distance = self.options.arc_radius + LABEL_GAP + size.length() / 2.;
+        let origin = self.point_at(start_angle + sweep / 2., distance) - size / 2.;
+        self.label.translate(origin - self.label.origin());
+        result.append(self.label.render(systems));
+
+        self.render_cache = Some(result);
+        self.render_cache.as_ref().unwrap()
+    }
+
+    fn bounding_box(&self) -> Box2D<f32> {
+        let radius = self.options.arc_radius;
+        Box2D::from_points([self.vertex, self.start, self.end])
+            .union(&Box2D::from_points([self.vertex]).inflate(radius, radius))
+            .union(&Box2D::from_origin_and_size(
+                self.label.origin(),
+                self.label_size,
+            ))
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.render_cache.is_none()
+    }
+}
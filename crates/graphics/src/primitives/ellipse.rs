@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 
 use color::{PremulColor, Srgb};
-use euclid::default::Box2D;
+use euclid::default::{Box2D, Vector2D};
 use lyon::math::{Angle, Point, Vector};
 use lyon::path::{Path, Winding};
 use lyon::tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, VertexBuffers};
@@ -77,6 +77,14 @@ impl<C: ApplyCoordinates> Ellipse<C> {
         self.render_cache = None;
     }
 
+    /// Fits the ellipse into the given bounds.
+    pub fn set_bounds(&mut self, bounds: Box2D<f32>) {
+        self.origin = bounds.center();
+        self.radius = (bounds.size() / 2.).to_vector();
+        self.path = Self::build_path(&self.origin, self.radius);
+        self.render_cache = None;
+    }
+
     pub fn resize_square(&mut self) {
         // rebuild path
         self.radius = self.radius.max(self.radius.yx());
@@ -88,6 +96,14 @@ impl<C: ApplyCoordinates> Ellipse<C> {
     pub fn radius(&self) -> Vector {
         self.radius
     }
+
+    pub fn translate(&mut self, dx: Vector2D<f32>) {
+        self.origin = self.origin + dx;
+        self.path = Self::build_path(&self.origin, self.radius);
+        if let Some(cache) = &mut self.render_cache {
+            cache.translate(dx);
+        }
+    }
 }
 
 impl<C: ApplyCoordinates> Drawable for Ellipse<C> {
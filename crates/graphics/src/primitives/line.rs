@@ -5,27 +5,72 @@ use euclid::default::{Box2D, Vector2D};
 use lyon::algorithms::aabb::fast_bounding_box;
 use lyon::geom::LineSegment;
 use lyon::math::Point;
-use lyon::path::Path;
+use lyon::path::{LineCap, LineJoin, Path};
 use lyon::tessellation::{
     BuffersBuilder, StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
 };
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 
-use crate::{ApplyCoordinates, Drawable, Mesh, Systems, Vertex, VertexKind};
+use crate::primitives::FlipAxis;
+use crate::{
+    ApplyCoordinates, BasicLinearGradient, Drawable, Mesh, SpreadMethod, Systems, Vertex,
+    VertexKind,
+};
+
+/// Which way a [`StrokeGradient`] runs over the stroke.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GradientDirection {
+    /// From the origin of the line to its destination.
+    Along,
+    /// From one edge of the stroke to the other.
+    Across,
+}
+
+/// A two color gradient that is sampled for each vertex of the stroke.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StrokeGradient {
+    pub start_color: PremulColor<Srgb>,
+    pub end_color: PremulColor<Srgb>,
+    pub direction: GradientDirection,
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Options {
     pub color: PremulColor<Srgb>,
+    /// Colors the stroke with a gradient instead of the solid `color` when set.
+    pub gradient: Option<StrokeGradient>,
     pub width: f32,
+    #[serde(default = "Options::default_line_cap")]
+    pub line_cap: LineCap,
+    #[serde(default = "Options::default_line_join")]
+    pub line_join: LineJoin,
+    #[serde(
+        default = "crate::primitives::default_miter_limit",
+        deserialize_with = "crate::primitives::deserialize_miter_limit"
+    )]
+    pub miter_limit: f32,
     pub text: Option<String>,
 }
 
+impl Options {
+    const fn default_line_cap() -> LineCap {
+        StrokeOptions::DEFAULT_LINE_CAP
+    }
+    const fn default_line_join() -> LineJoin {
+        StrokeOptions::DEFAULT_LINE_JOIN
+    }
+}
+
 impl Default for Options {
     fn default() -> Self {
         Self {
             color: PremulColor::new([1., 0., 0., 0.5]),
+            gradient: None,
             width: 3.,
+            line_cap: Self::default_line_cap(),
+            line_join: Self::default_line_join(),
+            miter_limit: crate::primitives::default_miter_limit(),
             text: None,
         }
     }
@@ -57,6 +102,12 @@ impl<C: ApplyCoordinates> Line<C> {
             _marker: PhantomData,
         }
     }
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+    pub fn destination(&self) -> Point {
+        self.destination
+    }
     fn build_path(origin: &Point, destination: &Point) -> Path {
         let mut builder = Path::builder();
         builder.add_line_segment(&LineSegment {
@@ -88,9 +139,44 @@ impl<C: ApplyCoordinates> Line<C> {
 
         self.set_destination(self.origin + snapped_delta);
     }
+    /// Lays the gradient option out over the line, returning `None` when the stroke is solid.
+    fn stroke_gradient(&self) -> Option<BasicLinearGradient> {
+        let gradient = self.options.gradient.as_ref()?;
+        let direction = (self.destination - self.origin).try_normalize()?;
+        let (p1, p2) = match gradient.direction {
+            GradientDirection::Along => (self.origin, self.destination),
+            GradientDirection::Across => {
+                let normal = Vector2D::new(-direction.y, direction.x) * self.options.width / 2.;
+                (self.origin - normal, self.origin + normal)
+            }
+        };
+        Some(BasicLinearGradient::new_with_points(
+            gradient.start_color,
+            gradient.end_color,
+            p1,
+            p2,
+            SpreadMethod::Pad,
+        ))
+    }
     pub fn is_empty(&mut self) -> bool {
         self.origin == self.destination
     }
+
+    pub fn flip(&mut self, axis: FlipAxis, at: f32) {
+        self.origin = axis.flip_point(self.origin, at);
+        self.destination = axis.flip_point(self.destination, at);
+        self.path = Self::build_path(&self.origin, &self.destination);
+        self.render_cache = None;
+    }
+
+    pub fn translate(&mut self, dx: Vector2D<f32>) {
+        self.origin = self.origin + dx;
+        self.destination = self.destination + dx;
+        self.path = Self::build_path(&self.origin, &self.destination);
+        if let Some(cache) = &mut self.render_cache {
+            cache.translate(dx);
+        }
+    }
 }
 
 impl<C: ApplyCoordinates> Drawable for Line<C> {
@@ -98,15 +184,20 @@ impl<C: ApplyCoordinates> Drawable for Line<C> {
         if let Some(ref cache) = self.render_cache {
             return cache;
         }
+        let gradient = self.stroke_gradient();
         let mut buffers = VertexBuffers::<Vertex, u32>::new();
         let mut builder = BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex<'_, '_>| {
-            Vertex::with_color(
-                vertex.position(),
-                C::apply(VertexKind::Color(self.options.color)),
-            )
+            let color = gradient.as_ref().map_or(self.options.color, |gradient| {
+                gradient.get_point(vertex.position())
+            });
+            Vertex::with_color(vertex.position(), C::apply(VertexKind::Color(color)))
         });
 
-        let options = StrokeOptions::default().with_line_width(self.options.width);
+        let options = StrokeOptions::default()
+            .with_line_width(self.options.width)
+            .with_line_cap(self.options.line_cap)
+            .with_line_join(self.options.line_join)
+            .with_miter_limit(self.options.miter_limit);
         let mut tessellator = StrokeTessellator::new();
 
         let tessellation_result = tessellator.tessellate_path(&self.path, &options, &mut builder);
@@ -134,3 +225,56 @@ impl<C: ApplyCoordinates> Drawable for Line<C> {
         self.render_cache.is_none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CanvasCoordinates;
+
+    fn gradient_line(direction: GradientDirection) -> Line<CanvasCoordinates> {
+        Line::new(
+            Point::new(0., 0.),
+            Point::new(0., 100.),
+            Options {
+                gradient: Some(StrokeGradient {
+                    start_color: PremulColor::new([1., 0., 0., 1.]),
+                    end_color: PremulColor::new([0., 0., 1., 1.]),
+                    direction,
+                }),
+                width: 10.,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn gradient_follows_path() {
+        let gradient = gradient_line(GradientDirection::Along)
+            .stroke_gradient()
+            .unwrap();
+        let start = gradient.get_point(Point::new(0., 0.)).components;
+        let end = gradient.get_point(Point::new(0., 100.)).components;
+        assert!(start[0] > start[2]);
+        assert!(end[2] > end[0]);
+        // Moving across the stroke does not change the color.
+        assert_eq!(gradient.get_point(Point::new(5., 0.)).components, start);
+    }
+
+    #[test]
+    fn gradient_across_path() {
+        let gradient = gradient_line(GradientDirection::Across)
+            .stroke_gradient()
+            .unwrap();
+        let left = gradient.get_point(Point::new(5., 50.)).components;
+        let right = gradient.get_point(Point::new(-5., 50.)).components;
+        assert_ne!(left, right);
+        assert_eq!(gradient.get_point(Point::new(5., 0.)).components, left);
+    }
+
+    #[test]
+    fn degenerate_line_is_solid() {
+        let mut line = gradient_line(GradientDirection::Along);
+        line.set_destination(Point::new(0., 0.));
+        assert!(line.stroke_gradient().is_none());
+    }
+}
@@ -1,5 +1,7 @@
+mod angle;
 mod ellipse;
 mod line;
+mod note;
 mod pen;
 mod quad;
 mod rectangle;
@@ -7,20 +9,65 @@ mod svg;
 mod text;
 mod triangle;
 
-use euclid::default::Box2D;
-use serde::{Deserialize, Serialize};
+use euclid::default::{Box2D, Point2D, Vector2D};
+use lyon::tessellation::StrokeOptions;
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::{ApplyCoordinates, Drawable, Mesh, Systems, Vertex};
+use crate::{ApplyCoordinates, BlendMode, Drawable, Mesh, Systems, Vertex};
 
+pub use angle::{Angle, Options as AngleOptions};
 pub use ellipse::{Ellipse, Options as EllipseOptions};
-pub use line::{Line, Options as LineOptions};
-pub use pen::{Options as PenOptions, Pen};
+pub use line::{GradientDirection, Line, Options as LineOptions, StrokeGradient};
+pub use note::{Note, Options as NoteOptions};
+pub use pen::{Options as PenOptions, Pen, Recognition, RecognizedShape};
 pub use quad::{Options as QuadOptions, Quad, QuadPoints};
 pub use rectangle::{Options as RectangleOptions, Rectangle};
-pub use svg::{Options as SvgOptions, Svg};
-pub use text::{Options as TextOptions, Text};
+pub use svg::{ImageFit, Options as SvgOptions, Svg};
+pub use text::{Options as TextOptions, Text, TextRun, TextStroke, TextStyle, VerticalAlign};
 pub use triangle::{Options as TriangleOptions, Triangle};
 
+/// The direction a primitive is mirrored in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlipAxis {
+    /// Mirrors left and right, about a vertical line.
+    Horizontal,
+    /// Mirrors top and bottom, about a horizontal line.
+    Vertical,
+}
+
+impl FlipAxis {
+    /// Mirrors the point about the line at `at`, an x coordinate for horizontal flips and a y
+    /// coordinate for vertical ones.
+    pub fn flip_point(self, point: Point2D<f32>, at: f32) -> Point2D<f32> {
+        match self {
+            FlipAxis::Horizontal => Point2D::new(2. * at - point.x, point.y),
+            FlipAxis::Vertical => Point2D::new(point.x, 2. * at - point.y),
+        }
+    }
+    /// Mirrors the box about the line at `at`, keeping its min below its max.
+    pub fn flip_box(self, area: Box2D<f32>, at: f32) -> Box2D<f32> {
+        Box2D::from_points([self.flip_point(area.min, at), self.flip_point(area.max, at)])
+    }
+    /// Mirrors every vertex of the mesh about the line at `at`.
+    pub fn flip_mesh(self, mesh: &mut Mesh<Vertex>, at: f32) {
+        for vertex in &mut mesh.vertices {
+            vertex.position = self.flip_point(vertex.position.into(), at).to_array();
+        }
+    }
+}
+
+/// Reads the miter limit of a stroke, raising limits below the smallest one lyon accepts, as
+/// tessellating with them panics.
+pub(crate) fn deserialize_miter_limit<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<f32, D::Error> {
+    Ok(f32::deserialize(deserializer)?.max(StrokeOptions::MINIMUM_MITER_LIMIT))
+}
+
+pub(crate) const fn default_miter_limit() -> f32 {
+    StrokeOptions::DEFAULT_MITER_LIMIT
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Primitive<C: ApplyCoordinates + Clone> {
     Ellipse(Ellipse<C>),
@@ -31,6 +78,83 @@ pub enum Primitive<C: ApplyCoordinates + Clone> {
     Triangle(Triangle<C>),
     Text(Text<C>),
     Svg(Svg<C>),
+    Note(Note<C>),
+    Angle(Angle<C>),
+}
+
+impl<C: ApplyCoordinates + Clone> Primitive<C> {
+    /// Moves the primitive by the given offset, keeping its tessellation if it has one.
+    pub fn translate(&mut self, dx: Vector2D<f32>) {
+        match self {
+            Primitive::Ellipse(elem) => elem.translate(dx),
+            Primitive::Line(elem) => elem.translate(dx),
+            Primitive::Pen(elem) => elem.translate(dx),
+            Primitive::Rectangle(elem) => elem.translate(dx),
+            Primitive::Quad(elem) => elem.translate(dx),
+            Primitive::Triangle(elem) => elem.translate(dx),
+            Primitive::Text(elem) => elem.translate(dx),
+            Primitive::Svg(elem) => elem.translate(dx),
+            Primitive::Note(elem) => elem.translate(dx),
+            Primitive::Angle(elem) => elem.translate(dx),
+        }
+    }
+
+    /// Mirrors the primitive about the line at `at`, see [`FlipAxis::flip_point`].
+    ///
+    /// Text keeps reading the right way, so only where it sits is mirrored, see
+    /// [`Primitive::mirror`] to mirror the text as well. Flipping twice about the same line gives
+    /// back the original primitive.
+    pub fn flip(&mut self, axis: FlipAxis, at: f32) {
+        match self {
+            Primitive::Line(elem) => elem.flip(axis, at),
+            Primitive::Pen(elem) => elem.flip(axis, at),
+            Primitive::Rectangle(elem) => elem.flip(axis, at),
+            Primitive::Quad(elem) => elem.flip(axis, at),
+            Primitive::Triangle(elem) => elem.flip(axis, at),
+            Primitive::Svg(elem) => elem.flip(axis, at),
+            Primitive::Angle(elem) => elem.flip(axis, at),
+            // Ellipses are symmetric, so moving them is the same as mirroring them.
+            Primitive::Ellipse(_) | Primitive::Text(_) | Primitive::Note(_) => {
+                let area = self.bounding_box();
+                self.translate(axis.flip_box(area, at).min - area.min);
+            }
+        }
+    }
+
+    /// Mirrors the primitive like [`Primitive::flip`], including any text, which then reads
+    /// backwards.
+    pub fn mirror(&mut self, axis: FlipAxis, at: f32) {
+        self.flip(axis, at);
+        match self {
+            Primitive::Text(elem) => elem.mirror(axis),
+            Primitive::Note(elem) => elem.mirror(axis),
+            _ => {}
+        }
+    }
+
+    /// The blend mode the primitive is drawn with.
+    pub fn blend_mode(&self) -> BlendMode {
+        match self {
+            Primitive::Pen(elem) => elem.options().blend_mode,
+            _ => BlendMode::Normal,
+        }
+    }
+
+    /// The clockwise rotation of the primitive in radians, `None` if it can not be rotated.
+    pub fn rotation(&self) -> Option<f32> {
+        match self {
+            Primitive::Svg(elem) => Some(elem.rotation()),
+            _ => None,
+        }
+    }
+    /// Rotates the primitive about its center, returning whether it can be rotated.
+    pub fn set_rotation(&mut self, rotation: f32) -> bool {
+        match self {
+            Primitive::Svg(elem) => elem.set_rotation(rotation),
+            _ => return false,
+        }
+        true
+    }
 }
 
 // TODO: (low_priority) use macro to expand
@@ -46,6 +170,8 @@ impl<C: ApplyCoordinates + Clone> Drawable<Vertex> for Primitive<C> {
             Primitive::Triangle(elem) => elem.render(systems),
             Primitive::Text(elem) => elem.render(systems),
             Primitive::Svg(elem) => elem.render(systems),
+            Primitive::Note(elem) => elem.render(systems),
+            Primitive::Angle(elem) => elem.render(systems),
         }
     }
     fn bounding_box(&self) -> Box2D<f32> {
@@ -58,6 +184,8 @@ impl<C: ApplyCoordinates + Clone> Drawable<Vertex> for Primitive<C> {
             Primitive::Triangle(elem) => elem.bounding_box(),
             Primitive::Text(elem) => elem.bounding_box(),
             Primitive::Svg(elem) => elem.bounding_box(),
+            Primitive::Note(elem) => elem.bounding_box(),
+            Primitive::Angle(elem) => elem.bounding_box(),
         }
     }
     fn is_dirty(&self) -> bool {
@@ -70,6 +198,8 @@ impl<C: ApplyCoordinates + Clone> Drawable<Vertex> for Primitive<C> {
             Primitive::Triangle(elem) => elem.is_dirty(),
             Primitive::Text(elem) => elem.is_dirty(),
             Primitive::Svg(elem) => elem.is_dirty(),
+            Primitive::Note(elem) => elem.is_dirty(),
+            Primitive::Angle(elem) => elem.is_dirty(),
         }
     }
 }
@@ -102,3 +232,107 @@ impl From<Rounding> for lyon::path::builder::BorderRadii {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use euclid::default::Size2D;
+
+    use super::*;
+    use crate::{CanvasCoordinates, VertexKind};
+
+    #[test]
+    fn miter_limits_are_raised_to_the_minimum() {
+        use serde::de::value::{Error, F32Deserializer};
+        use serde::de::IntoDeserializer;
+
+        let read = |limit: f32| {
+            let deserializer: F32Deserializer<Error> = limit.into_deserializer();
+            deserialize_miter_limit(deserializer).unwrap()
+        };
+        assert_eq!(read(0.5), StrokeOptions::MINIMUM_MITER_LIMIT);
+        assert_eq!(read(f32::NAN), StrokeOptions::MINIMUM_MITER_LIMIT);
+        assert_eq!(read(10.), 10.);
+    }
+
+    #[test]
+    fn points_and_boxes_mirror_about_the_line() {
+        let point = Point2D::new(3., 5.);
+        assert_eq!(
+            FlipAxis::Horizontal.flip_point(point, 10.),
+            Point2D::new(17., 5.)
+        );
+        assert_eq!(
+            FlipAxis::Vertical.flip_point(point, 10.),
+            Point2D::new(3., 15.)
+        );
+
+        let area = Box2D::new(Point2D::new(0., 0.), Point2D::new(4., 2.));
+        assert_eq!(
+            FlipAxis::Horizontal.flip_box(area, 1.),
+            Box2D::new(Point2D::new(-2., 0.), Point2D::new(2., 2.))
+        );
+        assert_eq!(
+            FlipAxis::Vertical.flip_box(area, 3.),
+            Box2D::new(Point2D::new(0., 4.), Point2D::new(4., 6.))
+        );
+    }
+
+    #[test]
+    fn meshes_mirror_every_vertex() {
+        let area = Box2D::new(Point2D::new(0., 0.), Point2D::new(4., 2.));
+        let mut mesh = Mesh::new_color_quad(area, VertexKind::ColorTexture);
+        FlipAxis::Horizontal.flip_mesh(&mut mesh, 5.);
+        let xs = mesh
+            .vertices
+            .iter()
+            .map(|vertex| vertex.position[0])
+            .collect::<Vec<_>>();
+        assert_eq!(xs, [10., 6., 6., 10.]);
+    }
+
+    #[test]
+    fn flipping_twice_gives_back_the_primitive() {
+        let rounding = crate::Rounding {
+            top_left: 1.,
+            top_right: 2.,
+            bottom_left: 3.,
+            bottom_right: 4.,
+        };
+        let rectangle = Rectangle::<CanvasCoordinates>::new(
+            Point2D::new(10., 20.),
+            Size2D::new(40., 30.),
+            RectangleOptions {
+                rounding,
+                ..RectangleOptions::DEFAULT
+            },
+        );
+        let mut svg = Svg::<CanvasCoordinates>::new(
+            Point2D::new(-5., 8.),
+            Size2D::new(20., 10.),
+            vec![],
+            SvgOptions::default(),
+        );
+        svg.set_rotation(0.4);
+
+        for axis in [FlipAxis::Horizontal, FlipAxis::Vertical] {
+            let mut flipped = Primitive::Rectangle(rectangle.clone());
+            flipped.mirror(axis, 7.);
+            assert_ne!(flipped.bounding_box(), rectangle.bounding_box());
+            flipped.mirror(axis, 7.);
+            let Primitive::Rectangle(flipped) = flipped else {
+                unreachable!()
+            };
+            assert_eq!(flipped.origin(), rectangle.origin());
+            assert_eq!(flipped.options(), rectangle.options());
+
+            let mut flipped = Primitive::Svg(svg.clone());
+            flipped.flip(axis, 7.);
+            flipped.flip(axis, 7.);
+            assert_eq!(
+                flipped.bounding_box(),
+                Primitive::Svg(svg.clone()).bounding_box()
+            );
+            assert_eq!(flipped.rotation(), Some(svg.rotation()));
+        }
+    }
+}
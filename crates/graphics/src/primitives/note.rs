@@ -0,0 +1,124 @@
+use color::PremulColor;
+use euclid::default::{Box2D, Point2D, Size2D, Vector2D};
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{FlipAxis, Rectangle, RectangleOptions, Text, TextOptions};
+use crate::{ApplyCoordinates, Drawable, Mesh, Rounding, Systems, Vertex};
+
+/// The smallest note, so an empty note is still visible and clickable.
+const MIN_CONTENT_SIZE: Size2D<f32> = Size2D::new(40., 20.);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Options {
+    pub text: TextOptions,
+    pub background: RectangleOptions,
+    /// The space between the text and the edge of the background.
+    pub padding: f32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            text: TextOptions {
+                fill: Some(PremulColor::new([0.1, 0.1, 0.1, 1.])),
+                ..Default::default()
+            },
+            background: RectangleOptions {
+                color: PremulColor::new([1., 0.9, 0.45, 1.]).into(),
+                stroke_color: PremulColor::new([0.8, 0.65, 0.2, 1.]).into(),
+                stroke_width: 1.,
+                rounding: Rounding::all(6.),
+                ..Default::default()
+            },
+            padding: 8.,
+        }
+    }
+}
+
+/// Text on a rounded background that grows to fit the text, drawn and moved as one primitive.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Note<C: ApplyCoordinates> {
+    #[serde(skip)]
+    render_cache: Option<Mesh<Vertex>>,
+
+    text: Text<C>,
+    background: Rectangle<C>,
+
+    padding: f32,
+}
+
+impl<C: ApplyCoordinates> Note<C> {
+    /// Creates a new note, where the origin corresponds to the top-left corner of the background.
+    pub fn new(content: String, origin: Point2D<f32>, options: Options) -> Self {
+        let text_origin = origin + Vector2D::splat(options.padding);
+        Self {
+            render_cache: None,
+
+            text: Text::new(
+                content,
+                options.text,
+                Box2D::from_origin_and_size(text_origin, Size2D::new(f32::MAX, f32::MAX)),
+            ),
+            background: Rectangle::new(origin, Size2D::zero(), options.background),
+
+            padding: options.padding,
+        }
+    }
+
+    pub fn origin(&self) -> Point2D<f32> {
+        self.background.origin()
+    }
+
+    pub fn text(&self) -> &Text<C> {
+        &self.text
+    }
+    /// The text of the note, editing it resizes the note on the next render.
+    pub fn text_mut(&mut self) -> &mut Text<C> {
+        self.clear_cache();
+        &mut self.text
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.render_cache = None;
+    }
+
+    /// Mirrors the text of the note in place, see [`Text::mirror`].
+    pub fn mirror(&mut self, axis: FlipAxis) {
+        self.text_mut().mirror(axis);
+    }
+
+    pub fn translate(&mut self, dx: Vector2D<f32>) {
+        self.text.translate(dx);
+        self.background.translate(dx);
+        if let Some(cache) = &mut self.render_cache {
+            cache.translate(dx);
+        }
+    }
+}
+
+impl<C: ApplyCoordinates> Drawable for Note<C> {
+    fn render(&mut self, systems: &mut Systems) -> &Mesh<Vertex> {
+        if let Some(ref cache) = self.render_cache {
+            return cache;
+        }
+
+        let content = self.text.measure(systems).max(MIN_CONTENT_SIZE);
+        let size = content + Size2D::splat(self.padding * 2.);
+        if self.background.size() != size {
+            self.background.set_size(size);
+        }
+
+        let mut result = self.background.render(systems).clone();
+        result.append(self.text.render(systems));
+        self.render_cache = Some(result);
+        self.render_cache.as_ref().unwrap()
+    }
+
+    fn bounding_box(&self) -> Box2D<f32> {
+        self.background.bounding_box()
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.render_cache.is_none()
+    }
+}
@@ -1,29 +1,76 @@
+use std::f32::consts::{FRAC_PI_4, FRAC_PI_6, TAU};
 use std::marker::PhantomData;
 
 use color::{PremulColor, Srgb};
-use euclid::default::Box2D;
+use euclid::default::{Box2D, Vector2D};
 use lyon::algorithms::aabb::fast_bounding_box;
 use lyon::math::Point;
-use lyon::path::{LineJoin, Path};
+use lyon::path::{LineCap, LineJoin, Path};
 use lyon::tessellation::{
     BuffersBuilder, StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
 };
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 
-use crate::{ApplyCoordinates, Drawable, Mesh, Systems, Vertex, VertexKind};
+use crate::primitives::FlipAxis;
+use crate::{ApplyCoordinates, BlendMode, Drawable, Mesh, Systems, Vertex, VertexKind};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Options {
     pub color: PremulColor<Srgb>,
+    /// Erase strokes cut into the content below them, only the alpha of `color` is used.
+    #[serde(default)]
+    pub blend_mode: BlendMode,
     pub width: f32,
+    #[serde(default = "Options::default_line_cap")]
+    pub line_cap: LineCap,
+    #[serde(default = "Options::default_line_join")]
+    pub line_join: LineJoin,
+    #[serde(
+        default = "crate::primitives::default_miter_limit",
+        deserialize_with = "crate::primitives::deserialize_miter_limit"
+    )]
+    pub miter_limit: f32,
+    /// The distance over which the stroke narrows to a point at its start, zero for a flat end.
+    #[serde(default)]
+    pub taper_start: f32,
+    /// The distance over which the stroke narrows to a point at its end.
+    #[serde(default)]
+    pub taper_end: f32,
+    /// How many of the latest pressure samples the width follows the average of, which evens
+    /// out jittery styluses. One disables smoothing.
+    #[serde(default = "Options::default_pressure_smoothing")]
+    pub pressure_smoothing: usize,
+}
+
+impl Options {
+    pub fn is_tapered(&self) -> bool {
+        self.taper_start > 0. || self.taper_end > 0.
+    }
+    const fn default_pressure_smoothing() -> usize {
+        1
+    }
+    const fn default_line_cap() -> LineCap {
+        StrokeOptions::DEFAULT_LINE_CAP
+    }
+    /// Strokes were always drawn with round joins before the join could be picked.
+    const fn default_line_join() -> LineJoin {
+        LineJoin::Round
+    }
 }
 
 impl Default for Options {
     fn default() -> Self {
         Self {
             color: PremulColor::new([1., 1., 1., 1.0]),
+            blend_mode: BlendMode::Normal,
             width: 2.5,
+            line_cap: Self::default_line_cap(),
+            line_join: Self::default_line_join(),
+            miter_limit: crate::primitives::default_miter_limit(),
+            taper_start: 0.,
+            taper_end: 0.,
+            pressure_smoothing: 4,
         }
     }
 }
@@ -39,6 +86,9 @@ pub struct Pen<C: ApplyCoordinates> {
     render_cache: Option<Mesh<Vertex>>,
 
     points: Vec<Point>,
+    /// The stylus pressure at each point, empty for strokes drawn without pressure.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pressures: Vec<f32>,
 
     options: Options,
     _marker: PhantomData<C>,
@@ -46,41 +96,111 @@ pub struct Pen<C: ApplyCoordinates> {
 
 impl<C: ApplyCoordinates> Pen<C> {
     pub fn new(start: Point, options: Options) -> Self {
-        let points = vec![start];
-        Self {
-            path: Self::build_path(&points),
-            render_cache: None,
-
-            points,
-
-            options,
-            _marker: PhantomData,
-        }
+        Self::from_points(vec![start], options)
+    }
+    /// Starts the stroke with the pressure of the first point, strokes without pressure keep a
+    /// constant width.
+    pub fn with_pressure(mut self, pressure: Option<f32>) -> Self {
+        self.pressures = match pressure {
+            Some(pressure) => vec![pressure.clamp(0., 1.); self.points.len()],
+            None => vec![],
+        };
+        self.path = Self::build_path(&self.points, &self.pressures, &self.options);
+        self.render_cache = None;
+        self
     }
-    fn build_path(points: &Vec<Point>) -> Path {
-        let mut builder = Path::builder();
-        builder.begin(*points.first().unwrap());
-        for point in points {
-            builder.line_to(*point);
+    /// Builds the path through the points, strokes with a varying width carry their width
+    /// factor at each point as the only attribute.
+    fn build_path(points: &[Point], pressures: &[f32], options: &Options) -> Path {
+        let Some(factors) = width_factors(points, pressures, options) else {
+            let mut builder = Path::builder();
+            builder.begin(*points.first().unwrap());
+            for point in points {
+                builder.line_to(*point);
+            }
+            builder.end(false);
+            return builder.build();
+        };
+
+        let mut builder = Path::builder_with_attributes(1);
+        builder.begin(*points.first().unwrap(), &[factors[0]]);
+        for (point, factor) in points.iter().zip(&factors) {
+            builder.line_to(*point, &[*factor]);
         }
         builder.end(false);
         builder.build()
     }
 
     pub fn handle_drag(&mut self, position: lyon::math::Point) {
-        // rebuild path
+        self.push_point(position, None);
+    }
+    /// Extends the stroke to `position`, a missing pressure repeats the previous one.
+    pub fn push_point(&mut self, position: Point, pressure: Option<f32>) {
         self.points.push(position);
-        self.path = Self::build_path(&self.points);
+        match (pressure, self.pressures.last().copied()) {
+            (Some(pressure), _) if self.pressures.is_empty() => {
+                // The stroke started without pressure, so the earlier points are given this one.
+                self.pressures = vec![pressure.clamp(0., 1.); self.points.len()];
+            }
+            (Some(pressure), _) => self.pressures.push(pressure.clamp(0., 1.)),
+            (None, Some(previous)) => self.pressures.push(previous),
+            (None, None) => {}
+        }
+        // rebuild path
+        self.path = Self::build_path(&self.points, &self.pressures, &self.options);
         // clear tessellation cache
         self.render_cache = None;
     }
+    /// Creates a stroke through the points, which must not be empty.
+    pub fn from_points(points: Vec<Point>, options: Options) -> Self {
+        Self {
+            path: Self::build_path(&points, &[], &options),
+            render_cache: None,
+
+            points,
+            pressures: vec![],
+
+            options,
+            _marker: PhantomData,
+        }
+    }
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+    pub fn pressures(&self) -> &[f32] {
+        &self.pressures
+    }
     pub fn options(&self) -> &Options {
         &self.options
     }
     pub fn update_options(&mut self, options: Options) {
+        if options.taper_start != self.options.taper_start
+            || options.taper_end != self.options.taper_end
+            || options.pressure_smoothing != self.options.pressure_smoothing
+        {
+            self.path = Self::build_path(&self.points, &self.pressures, &options);
+        }
         self.options = options;
         self.render_cache = None;
     }
+
+    pub fn flip(&mut self, axis: FlipAxis, at: f32) {
+        for point in &mut self.points {
+            *point = axis.flip_point(*point, at);
+        }
+        self.path = Self::build_path(&self.points, &self.pressures, &self.options);
+        self.render_cache = None;
+    }
+
+    pub fn translate(&mut self, dx: Vector2D<f32>) {
+        for point in &mut self.points {
+            *point = *point + dx;
+        }
+        self.path = Self::build_path(&self.points, &self.pressures, &self.options);
+        if let Some(cache) = &mut self.render_cache {
+            cache.translate(dx);
+        }
+    }
 }
 
 impl<C: ApplyCoordinates> Drawable for Pen<C> {
@@ -96,9 +216,14 @@ impl<C: ApplyCoordinates> Drawable for Pen<C> {
             )
         });
 
-        let options = StrokeOptions::default()
+        let mut options = StrokeOptions::default()
             .with_line_width(self.options.width)
-            .with_line_join(LineJoin::Round);
+            .with_line_cap(self.options.line_cap)
+            .with_line_join(self.options.line_join)
+            .with_miter_limit(self.options.miter_limit);
+        if self.options.is_tapered() || !self.pressures.is_empty() {
+            options = options.with_variable_line_width(0);
+        }
         let mut tessellator = StrokeTessellator::new();
 
         let tessellation_result = tessellator.tessellate_path(&self.path, &options, &mut builder);
@@ -125,3 +250,443 @@ impl<C: ApplyCoordinates> Drawable for Pen<C> {
         self.render_cache.is_none()
     }
 }
+
+/// The factor the width of the stroke is scaled by at each point, from the tapers and the
+/// smoothed pressure, or `None` for a stroke of constant width.
+fn width_factors(points: &[Point], pressures: &[f32], options: &Options) -> Option<Vec<f32>> {
+    let has_pressure = pressures.len() == points.len();
+    if !options.is_tapered() && !has_pressure {
+        return None;
+    }
+    let mut factors = taper_factors(points, options.taper_start, options.taper_end);
+    if has_pressure {
+        let smoothed = smooth_pressures(pressures, options.pressure_smoothing);
+        for (factor, pressure) in factors.iter_mut().zip(smoothed) {
+            *factor *= pressure;
+        }
+    }
+    Some(factors)
+}
+
+/// The average of each pressure and up to `window - 1` before it. Only earlier samples are
+/// used, so extending a stroke never changes the width it already has.
+fn smooth_pressures(pressures: &[f32], window: usize) -> Vec<f32> {
+    let window = window.max(1);
+    let mut sum = 0.;
+    pressures
+        .iter()
+        .enumerate()
+        .map(|(i, pressure)| {
+            sum += pressure;
+            if i >= window {
+                sum -= pressures[i - window];
+            }
+            sum / (i + 1).min(window) as f32
+        })
+        .collect()
+}
+
+/// The factor the width of the stroke is scaled by at each point, growing from zero at the
+/// tapered ends to one over the taper distances.
+///
+/// When the tapers are longer than the stroke itself, both are shrunk so that they meet,
+/// keeping their ratio.
+fn taper_factors(points: &[Point], taper_start: f32, taper_end: f32) -> Vec<f32> {
+    let mut distances = Vec::with_capacity(points.len());
+    let mut length = 0.;
+    for (index, point) in points.iter().enumerate() {
+        if index > 0 {
+            length += (*point - points[index - 1]).length();
+        }
+        distances.push(length);
+    }
+
+    let (taper_start, taper_end) = (taper_start.max(0.), taper_end.max(0.));
+    let scale = if taper_start + taper_end > length {
+        length / (taper_start + taper_end)
+    } else {
+        1.
+    };
+    let (taper_start, taper_end) = (taper_start * scale, taper_end * scale);
+
+    distances
+        .into_iter()
+        .map(|distance| {
+            let mut factor: f32 = 1.;
+            if taper_start > 0. {
+                factor = factor.min(distance / taper_start);
+            }
+            if taper_end > 0. {
+                factor = factor.min((length - distance) / taper_end);
+            }
+            factor.clamp(0., 1.)
+        })
+        .collect()
+}
+
+/// Strokes shorter than this are dots or handwriting, and are never recognized.
+const MIN_SHAPE_LENGTH: f32 = 24.;
+/// How far apart the ends of a stroke may be, relative to its diagonal, for it to be closed.
+const CLOSED_GAP: f32 = 0.2;
+/// The turn above which a point of a resampled stroke counts as a corner.
+const CORNER_ANGLE: f32 = FRAC_PI_4;
+/// How many points a stroke is resampled to before its corners are counted.
+const CORNER_SAMPLES: usize = 64;
+/// The length of an arrow head relative to its shaft.
+const ARROW_HEAD_RANGE: std::ops::RangeInclusive<f32> = 0.1..=0.5;
+/// Rectangles and ellipses within this of a 1:1 aspect become squares and circles.
+const SQUARE_TOLERANCE: f32 = 0.1;
+
+/// A clean shape that a freehand stroke approximates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecognizedShape {
+    Line {
+        from: Point,
+        to: Point,
+    },
+    Rectangle(Box2D<f32>),
+    Ellipse {
+        center: Point,
+        radius: Vector2D<f32>,
+    },
+    /// A line with two barbs at `to`, each `head` long.
+    Arrow {
+        from: Point,
+        to: Point,
+        head: f32,
+    },
+}
+
+impl RecognizedShape {
+    /// The points of the shape as a single stroke.
+    pub fn outline(&self) -> Vec<Point> {
+        match *self {
+            RecognizedShape::Line { from, to } => vec![from, to],
+            RecognizedShape::Rectangle(rect) => vec![
+                rect.min,
+                Point::new(rect.max.x, rect.min.y),
+                rect.max,
+                Point::new(rect.min.x, rect.max.y),
+                rect.min,
+            ],
+            RecognizedShape::Ellipse { center, radius } => (0..=CORNER_SAMPLES)
+                .map(|i| {
+                    let angle = TAU * i as f32 / CORNER_SAMPLES as f32;
+                    center + Vector2D::new(angle.cos() * radius.x, angle.sin() * radius.y)
+                })
+                .collect(),
+            RecognizedShape::Arrow { from, to, head } => {
+                let back = (from - to).normalize() * head;
+                let barb = |angle: f32| {
+                    let (sin, cos) = angle.sin_cos();
+                    to + Vector2D::new(back.x * cos - back.y * sin, back.x * sin + back.y * cos)
+                };
+                vec![from, to, barb(FRAC_PI_6), to, barb(-FRAC_PI_6)]
+            }
+        }
+    }
+}
+
+/// The result of [`Pen::recognize`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Recognition {
+    pub shape: RecognizedShape,
+    /// How closely the stroke matches the shape, from 0 to 1.
+    pub confidence: f32,
+}
+
+impl<C: ApplyCoordinates> Pen<C> {
+    /// Guesses which shape the stroke was drawn as, from whether it is closed, how many corners
+    /// it has and how well it fits its bounds.
+    ///
+    /// Ambiguous strokes, such as scribbles or a rounded rectangle, are not recognized.
+    pub fn recognize(&self) -> Option<Recognition> {
+        let points = self.points.as_slice();
+        if points.len() < 3 || path_length(points) < MIN_SHAPE_LENGTH {
+            return None;
+        }
+        let (first, last) = (points[0], points[points.len() - 1]);
+        let bounds = Box2D::from_points(points);
+        if (last - first).length() < CLOSED_GAP * (bounds.max - bounds.min).length() {
+            return recognize_closed(points, bounds);
+        }
+
+        let line = Recognition {
+            shape: RecognizedShape::Line {
+                from: first,
+                to: last,
+            },
+            confidence: straightness(points, first, last),
+        };
+        match recognize_arrow(points) {
+            Some(arrow) if arrow.confidence > line.confidence => Some(arrow),
+            _ => Some(line).filter(|line| line.confidence > 0.),
+        }
+    }
+}
+
+fn path_length(points: &[Point]) -> f32 {
+    points.windows(2).map(|w| (w[1] - w[0]).length()).sum()
+}
+
+/// How close the points stay to the segment between `from` and `to`, from 0 to 1.
+fn straightness(points: &[Point], from: Point, to: Point) -> f32 {
+    let chord = to - from;
+    let length = chord.length();
+    if length == 0. {
+        return 0.;
+    }
+    let deviation = points
+        .iter()
+        .map(|point| chord.cross(*point - from).abs() / length)
+        .fold(0., f32::max);
+    (1. - 5. * deviation / length).max(0.)
+}
+
+/// An open stroke that turns back at its far end, drawing the head of an arrow.
+fn recognize_arrow(points: &[Point]) -> Option<Recognition> {
+    let first = points[0];
+    // The head is drawn back over the tip, so the first visit to the tip ends the shaft.
+    let distance = |i: usize| (points[i] - first).length();
+    let tip_index = (0..points.len()).fold(
+        0,
+        |tip, i| if distance(i) > distance(tip) { i } else { tip },
+    );
+    let (tip, shaft) = (points[tip_index], distance(tip_index));
+    let head = points[tip_index..]
+        .iter()
+        .map(|point| (*point - tip).length())
+        .fold(0., f32::max);
+    if shaft == 0. || !ARROW_HEAD_RANGE.contains(&(head / shaft)) {
+        return None;
+    }
+    Some(Recognition {
+        shape: RecognizedShape::Arrow {
+            from: first,
+            to: tip,
+            head,
+        },
+        confidence: straightness(&points[..=tip_index], first, tip),
+    })
+}
+
+/// A closed stroke, fitted against the rectangle and the ellipse inscribed in its bounds.
+fn recognize_closed(points: &[Point], bounds: Box2D<f32>) -> Option<Recognition> {
+    let center = bounds.center();
+    let half = bounds.size().to_vector() / 2.;
+    if half.x.min(half.y) * 2. < MIN_SHAPE_LENGTH {
+        return None;
+    }
+
+    // Both errors are relative to the size of the shape, so they are comparable.
+    let mut ellipse_error = 0.;
+    let mut rectangle_error = 0.;
+    for point in points {
+        let offset = *point - center;
+        let normalized = Vector2D::new(offset.x / half.x, offset.y / half.y);
+        ellipse_error += (normalized.length() - 1.).abs();
+        rectangle_error += 1. - normalized.x.abs().max(normalized.y.abs()).min(1.);
+    }
+
+    // Strokes often start on a corner, which is not counted as it has no turn before it.
+    let corners = count_corners(points);
+    let (shape_is_rectangle, best, other) = if rectangle_error < ellipse_error {
+        (true, rectangle_error, ellipse_error)
+    } else {
+        (false, ellipse_error, rectangle_error)
+    };
+    let corners_match = if shape_is_rectangle {
+        (3..=4).contains(&corners)
+    } else {
+        corners <= 1
+    };
+    if !corners_match {
+        return None;
+    }
+
+    let half = if (half.x / half.y - 1.).abs() < SQUARE_TOLERANCE {
+        Vector2D::splat((half.x + half.y) / 2.)
+    } else {
+        half
+    };
+    let shape = if shape_is_rectangle {
+        RecognizedShape::Rectangle(Box2D::new(center - half, center + half))
+    } else {
+        RecognizedShape::Ellipse {
+            center,
+            radius: half,
+        }
+    };
+    Some(Recognition {
+        shape,
+        confidence: 1. - best / other.max(f32::EPSILON),
+    })
+}
+
+/// Counts the sharp turns of the stroke, after resampling it to evenly spaced points so the
+/// pointer speed does not matter.
+fn count_corners(points: &[Point]) -> usize {
+    let samples = resample(points, CORNER_SAMPLES);
+    let window = 3;
+    let turns = (window..samples.len().saturating_sub(window))
+        .map(|i| {
+            let before = samples[i] - samples[i - window];
+            let after = samples[i + window] - samples[i];
+            before.angle_to(after).radians.abs()
+        })
+        .collect::<Vec<_>>();
+
+    let mut corners = 0;
+    let mut i = 0;
+    while i < turns.len() {
+        if turns[i] > CORNER_ANGLE {
+            corners += 1;
+            // A single corner spans the whole window, so it is only counted once.
+            i += window * 2;
+        } else {
+            i += 1;
+        }
+    }
+    corners
+}
+
+/// Resamples the stroke to `count` points evenly spaced along its length.
+fn resample(points: &[Point], count: usize) -> Vec<Point> {
+    let step = path_length(points) / (count - 1) as f32;
+    let mut result = vec![points[0]];
+    let mut travelled = 0.;
+    for segment in points.windows(2) {
+        let length = (segment[1] - segment[0]).length();
+        while result.len() < count && travelled + length >= step * result.len() as f32 {
+            let t = if length == 0. {
+                0.
+            } else {
+                (step * result.len() as f32 - travelled) / length
+            };
+            result.push(segment[0].lerp(segment[1], t));
+        }
+        travelled += length;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CanvasCoordinates;
+
+    fn stroke(points: impl IntoIterator<Item = (f32, f32)>) -> Pen<CanvasCoordinates> {
+        let points = points.into_iter().map(|(x, y)| Point::new(x, y)).collect();
+        Pen::from_points(points, Options::default())
+    }
+
+    /// Walks the corners of a polygon in steps of a few pixels, like pointer motion would.
+    fn polygon(corners: &[(f32, f32)]) -> Pen<CanvasCoordinates> {
+        let mut points = vec![];
+        for pair in corners.windows(2) {
+            let (from, to) = (
+                Point::new(pair[0].0, pair[0].1),
+                Point::new(pair[1].0, pair[1].1),
+            );
+            let steps = ((to - from).length() / 4.).ceil() as usize;
+            points.extend((0..steps).map(|i| from.lerp(to, i as f32 / steps as f32)));
+        }
+        let last = corners[corners.len() - 1];
+        points.push(Point::new(last.0, last.1));
+        Pen::from_points(points, Options::default())
+    }
+
+    #[test]
+    fn recognizes_a_wobbly_line() {
+        let pen = stroke((0..=50).map(|i| (i as f32 * 4., (i % 2) as f32)));
+        let recognition = pen.recognize().unwrap();
+        assert!(matches!(recognition.shape, RecognizedShape::Line { .. }));
+        assert!(recognition.confidence > 0.9);
+    }
+
+    #[test]
+    fn recognizes_a_rectangle() {
+        let pen = polygon(&[(0., 0.), (200., 0.), (200., 100.), (0., 100.), (0., 4.)]);
+        let recognition = pen.recognize().unwrap();
+        let RecognizedShape::Rectangle(rect) = recognition.shape else {
+            panic!("expected a rectangle, got {:?}", recognition.shape);
+        };
+        assert!((rect.width() - 200.).abs() < 1. && (rect.height() - 100.).abs() < 1.);
+        assert!(recognition.confidence > 0.8);
+    }
+
+    #[test]
+    fn recognizes_a_near_circle_as_a_circle() {
+        let pen = stroke((0..=60).map(|i| {
+            let angle = TAU * i as f32 / 60.;
+            (100. * angle.cos(), 95. * angle.sin())
+        }));
+        let recognition = pen.recognize().unwrap();
+        let RecognizedShape::Ellipse { radius, .. } = recognition.shape else {
+            panic!("expected an ellipse, got {:?}", recognition.shape);
+        };
+        assert_eq!(radius.x, radius.y);
+        assert!(recognition.confidence > 0.8);
+    }
+
+    #[test]
+    fn recognizes_an_arrow() {
+        let pen = polygon(&[(0., 0.), (200., 0.), (170., -20.), (200., 0.), (170., 20.)]);
+        let recognition = pen.recognize().unwrap();
+        assert!(matches!(recognition.shape, RecognizedShape::Arrow { .. }));
+    }
+
+    #[test]
+    fn leaves_scribbles_and_dots_alone() {
+        let zigzag = polygon(&[(0., 0.), (50., 80.), (100., 0.), (150., 80.), (200., 0.)]);
+        assert!(zigzag.recognize().is_none_or(|r| r.confidence < 0.5));
+        assert_eq!(stroke([(0., 0.), (2., 1.), (3., 3.)]).recognize(), None);
+    }
+
+    #[test]
+    fn smooths_noisy_pressure() {
+        let points: Vec<_> = (0..40).map(|i| Point::new(i as f32 * 4., 0.)).collect();
+        let noisy: Vec<_> = (0..40)
+            .map(|i| if i % 2 == 0 { 0.3 } else { 0.9 })
+            .collect();
+        // How much the width goes up and down along the stroke in total.
+        let variation = |options: &Options| {
+            let factors = width_factors(&points, &noisy, options).unwrap();
+            factors.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f32>()
+        };
+
+        let raw = variation(&Options {
+            pressure_smoothing: 1,
+            ..Default::default()
+        });
+        let smoothed = variation(&Options {
+            pressure_smoothing: 4,
+            ..Default::default()
+        });
+        assert!((raw - 39. * 0.6).abs() < 1e-3);
+        assert!(
+            smoothed < raw / 10.,
+            "{smoothed} is not smoother than {raw}"
+        );
+
+        let averages = smooth_pressures(&[0.2, 0.4, 0.6, 0.8], 2);
+        for (average, expected) in averages.iter().zip([0.2, 0.3, 0.5, 0.7]) {
+            assert!((average - expected).abs() < 1e-5);
+        }
+        assert_eq!(width_factors(&points, &[], &Options::default()), None);
+    }
+
+    #[test]
+    fn clamps_tapers_to_short_strokes() {
+        let points: Vec<_> = (0..=10).map(|i| Point::new(i as f32, 0.)).collect();
+        let factors = taper_factors(&points, 20., 20.);
+        assert_eq!(factors[0], 0.);
+        assert_eq!(factors[10], 0.);
+        assert_eq!(factors[5], 1.);
+        assert!((factors[2] - 0.4).abs() < 1e-5);
+
+        let factors = taper_factors(&points, 4., 0.);
+        assert_eq!(&factors[..6], &[0., 0.25, 0.5, 0.75, 1., 1.]);
+        assert_eq!(factors[10], 1.);
+    }
+}
@@ -1,9 +1,10 @@
 use std::marker::PhantomData;
 
 use color::{PremulColor, Srgb};
-use euclid::default::{Box2D, Point2D};
+use euclid::default::{Box2D, Point2D, Vector2D};
 use serde::{Deserialize, Serialize};
 
+use crate::primitives::FlipAxis;
 use crate::{make_positive_box, Vertex, VertexKind};
 use crate::{ApplyCoordinates, Drawable, Mesh, Systems};
 
@@ -112,6 +113,26 @@ impl<C: ApplyCoordinates> Quad<C> {
         self.points = points;
         self.clear_cache();
     }
+    pub fn flip(&mut self, axis: FlipAxis, at: f32) {
+        self.update_points(QuadPoints {
+            p1: axis.flip_point(self.points.p1, at),
+            p2: axis.flip_point(self.points.p2, at),
+            p3: axis.flip_point(self.points.p3, at),
+            p4: axis.flip_point(self.points.p4, at),
+        });
+    }
+
+    pub fn translate(&mut self, dx: Vector2D<f32>) {
+        self.points = QuadPoints {
+            p1: self.points.p1 + dx,
+            p2: self.points.p2 + dx,
+            p3: self.points.p3 + dx,
+            p4: self.points.p4 + dx,
+        };
+        if let Some(cache) = &mut self.render_cache {
+            cache.translate(dx);
+        }
+    }
     pub fn options(&self) -> &Options {
         &self.options
     }
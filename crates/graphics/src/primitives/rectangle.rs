@@ -1,11 +1,12 @@
 use std::marker::PhantomData;
 
 use color::{PremulColor, Srgb};
-use euclid::default::{Box2D, Point2D, Size2D};
+use euclid::default::{Box2D, Point2D, Size2D, Vector2D};
 use lyon::path::{Path, Winding};
 use lyon::tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, VertexBuffers};
 use serde::{Deserialize, Serialize};
 
+use crate::primitives::FlipAxis;
 use crate::{make_positive_box, BasicColor, BasicLinearGradient, Vertex};
 use crate::{ApplyCoordinates, Drawable, Mesh, Systems, VertexKind};
 use crate::{BoxSizing, Rounding};
@@ -164,6 +165,34 @@ impl<C: ApplyCoordinates> Rectangle<C> {
         self.origin
     }
 
+    pub fn translate(&mut self, dx: Vector2D<f32>) {
+        self.origin = self.origin + dx;
+        self.apply_area_to_color();
+        (self.fill_path, self.stroke_path) =
+            Self::build_path(&self.origin, &self.size, &self.options);
+        if let Some(cache) = &mut self.render_cache {
+            cache.translate(dx);
+        }
+    }
+
+    /// Mirrors the rectangle about the line at `at`, along with its rounded corners.
+    pub fn flip(&mut self, axis: FlipAxis, at: f32) {
+        let rounding = &mut self.options.rounding;
+        match axis {
+            FlipAxis::Horizontal => {
+                std::mem::swap(&mut rounding.top_left, &mut rounding.top_right);
+                std::mem::swap(&mut rounding.bottom_left, &mut rounding.bottom_right);
+            }
+            FlipAxis::Vertical => {
+                std::mem::swap(&mut rounding.top_left, &mut rounding.bottom_left);
+                std::mem::swap(&mut rounding.top_right, &mut rounding.bottom_right);
+            }
+        }
+        let area = self.bounding_box();
+        self.origin += axis.flip_box(area, at).min - area.min;
+        self.update_area(self.origin, self.size);
+    }
+
     pub fn set_size(&mut self, size: Size2D<f32>) {
         self.size = size;
         self.apply_area_to_color();
@@ -225,3 +254,42 @@ impl<C: ApplyCoordinates> Drawable for Rectangle<C> {
         self.render_cache.is_none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CanvasCoordinates;
+
+    fn rounded() -> Rectangle<CanvasCoordinates> {
+        Rectangle::new(
+            Point2D::new(10., 20.),
+            Size2D::new(40., 30.),
+            Options {
+                rounding: Rounding {
+                    top_left: 1.,
+                    top_right: 2.,
+                    bottom_left: 3.,
+                    bottom_right: 4.,
+                },
+                ..Options::DEFAULT
+            },
+        )
+    }
+
+    #[test]
+    fn flipping_swaps_the_rounded_corners() {
+        let mut rectangle = rounded();
+        rectangle.flip(FlipAxis::Horizontal, 0.);
+        assert_eq!(rectangle.origin(), Point2D::new(-50., 20.));
+        let rounding = rectangle.options().rounding;
+        assert_eq!([rounding.top_left, rounding.top_right], [2., 1.]);
+        assert_eq!([rounding.bottom_left, rounding.bottom_right], [4., 3.]);
+
+        let mut rectangle = rounded();
+        rectangle.flip(FlipAxis::Vertical, 0.);
+        assert_eq!(rectangle.origin(), Point2D::new(10., -50.));
+        let rounding = rectangle.options().rounding;
+        assert_eq!([rounding.top_left, rounding.top_right], [3., 4.]);
+        assert_eq!([rounding.bottom_left, rounding.bottom_right], [1., 2.]);
+    }
+}
@@ -1,7 +1,11 @@
 use std::marker::PhantomData;
+use std::sync::Arc;
 
+use anyhow::{Context, Result};
+use atlas::{formats::Rgba8, AllocatedTexture, UnallocatedTexture};
 use color::{PremulColor, Srgb};
 use euclid::default::{Box2D, Point2D, Size2D, Transform2D, Vector2D};
+use euclid::Angle;
 use lyon::path::{FillRule, LineCap, LineJoin};
 use lyon::tessellation::{
     BuffersBuilder, FillOptions, FillTessellator, FillVertex, StrokeOptions, StrokeTessellator,
@@ -9,15 +13,32 @@ use lyon::tessellation::{
 };
 use serde::{Deserialize, Serialize};
 use usvg::tiny_skia_path::PathSegment;
-use usvg::{Node, Paint, PaintOrder, Tree};
+use usvg::{ImageKind, Node, Paint, PaintOrder, Tree};
 
-use crate::{ApplyCoordinates, Drawable, Mesh, Systems};
+use crate::primitives::FlipAxis;
+use crate::systems::{CacheKey, ImageKey, TextureData};
+use crate::{png, ApplyCoordinates, Drawable, Mesh, Systems};
 use crate::{Vertex, VertexKind};
 
+/// How the content of an svg maps into a box of a different shape.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageFit {
+    /// Stretches the content to fill the box.
+    #[default]
+    Fill,
+    /// Scales the content to fit inside the box while keeping its aspect ratio, centered.
+    Fit,
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Options {
     pub fill_color: Option<PremulColor<Srgb>>,
     pub stroke_color: Option<PremulColor<Srgb>>,
+    #[serde(default)]
+    pub fit: ImageFit,
+    /// Keeps the intrinsic aspect ratio of the svg when it is resized.
+    #[serde(default)]
+    pub lock_aspect: bool,
 }
 
 impl Default for Options {
@@ -25,6 +46,8 @@ impl Default for Options {
         Self {
             fill_color: None,
             stroke_color: None,
+            fit: ImageFit::Fill,
+            lock_aspect: false,
         }
     }
 }
@@ -33,24 +56,96 @@ impl Default for Options {
 pub struct Svg<C: ApplyCoordinates> {
     #[serde(skip)]
     render_cache: Option<Mesh<Vertex>>,
+    /// The embedded images drawn by the render cache, which stay in the atlas while they're held.
+    #[serde(skip)]
+    images: Vec<Arc<AllocatedTexture<Rgba8, TextureData>>>,
 
     origin: Point2D<f32>,
     size: Size2D<f32>,
     data: Vec<u8>,
+    /// The size the svg declares for itself, unknown if the data does not parse.
+    #[serde(default)]
+    intrinsic_size: Option<Size2D<f32>>,
+    /// The clockwise rotation of the svg about the center of its box, in radians.
+    #[serde(default)]
+    rotation: f32,
+    /// Whether the content is mirrored left to right within its box, before the rotation.
+    #[serde(default)]
+    flip_horizontal: bool,
+    /// Whether the content is mirrored top to bottom within its box, before the rotation.
+    #[serde(default)]
+    flip_vertical: bool,
 
     options: Options,
     _marker: PhantomData<C>,
 }
 
+/// The color drawn over the area of an svg that could not be parsed.
+const BROKEN_COLOR: PremulColor<Srgb> = PremulColor::new([0.5, 0.1, 0.1, 0.5]);
+
+fn parse(data: &[u8]) -> Result<Tree> {
+    Tree::from_data(data, &usvg::Options::default()).context("failed to parse svg")
+}
+
+/// Converts a transform of usvg, which maps `x` to `sx * x + kx * y + tx`.
+fn to_transform(transform: usvg::Transform) -> Transform2D<f32> {
+    Transform2D::new(
+        transform.sx,
+        transform.ky,
+        transform.kx,
+        transform.sy,
+        transform.tx,
+        transform.ty,
+    )
+}
+
+/// Grows one side of `size` so it has the aspect ratio of `intrinsic`, so the box still reaches
+/// the point being dragged.
+fn constrain_aspect(size: Size2D<f32>, intrinsic: Size2D<f32>) -> Size2D<f32> {
+    let ratio = intrinsic.width / intrinsic.height;
+    if size.width / size.height > ratio {
+        Size2D::new(size.width, size.width / ratio)
+    } else {
+        Size2D::new(size.height * ratio, size.height)
+    }
+}
+
+/// The largest size with the aspect ratio of `intrinsic` that fits inside `size`.
+fn constrain_aspect_within(size: Size2D<f32>, intrinsic: Size2D<f32>) -> Size2D<f32> {
+    let scale = (size.width / intrinsic.width).min(size.height / intrinsic.height);
+    intrinsic * scale
+}
+
 impl<C: ApplyCoordinates> Svg<C> {
+    /// Creates a new SVG primitive, failing if the data is not a valid svg.
+    pub fn try_new(
+        origin: Point2D<f32>,
+        size: Size2D<f32>,
+        data: Vec<u8>,
+        options: Options,
+    ) -> Result<Self> {
+        parse(&data)?;
+        Ok(Self::new(origin, size, data, options))
+    }
+
     /// Creates a new SVG primitive, where the origin corresponds to the top-left corner.
+    ///
+    /// Data that fails to parse is rendered as a placeholder over the area of the svg.
     pub fn new(origin: Point2D<f32>, size: Size2D<f32>, data: Vec<u8>, options: Options) -> Self {
+        let intrinsic_size = parse(&data)
+            .ok()
+            .map(|tree| Size2D::new(tree.size().width(), tree.size().height()));
         Self {
             render_cache: None,
+            images: vec![],
 
             origin,
             size,
             data,
+            intrinsic_size,
+            rotation: 0.,
+            flip_horizontal: false,
+            flip_vertical: false,
 
             options,
             _marker: PhantomData,
@@ -67,6 +162,64 @@ impl<C: ApplyCoordinates> Svg<C> {
         self.clear_cache();
     }
 
+    pub fn intrinsic_size(&self) -> Option<Size2D<f32>> {
+        self.intrinsic_size
+    }
+
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+        self.clear_cache();
+    }
+
+    /// The rotation of the svg about the center of its box.
+    fn rotation_transform(&self) -> Transform2D<f32> {
+        let center = Box2D::from_origin_and_size(self.origin, self.size)
+            .center()
+            .to_vector();
+        Transform2D::translation(-center.x, -center.y)
+            .then_rotate(Angle::radians(self.rotation))
+            .then_translate(center)
+    }
+
+    /// The mirroring of the content about the center of its box.
+    fn flip_transform(&self) -> Transform2D<f32> {
+        let center = Box2D::from_origin_and_size(self.origin, self.size)
+            .center()
+            .to_vector();
+        let sign = |flipped: bool| if flipped { -1. } else { 1. };
+        Transform2D::translation(-center.x, -center.y)
+            .then_scale(sign(self.flip_horizontal), sign(self.flip_vertical))
+            .then_translate(center)
+    }
+
+    /// Mirrors the svg about the line at `at`, including its content.
+    pub fn flip(&mut self, axis: FlipAxis, at: f32) {
+        let center = Box2D::from_origin_and_size(self.origin, self.size).center();
+        self.origin = axis.flip_point(center, at) - self.size.to_vector() / 2.;
+        // Mirroring turns a clockwise rotation into an anticlockwise one.
+        self.rotation = -self.rotation;
+        match axis {
+            FlipAxis::Horizontal => self.flip_horizontal = !self.flip_horizontal,
+            FlipAxis::Vertical => self.flip_vertical = !self.flip_vertical,
+        }
+        self.clear_cache();
+    }
+
+    /// Resizes the svg as a resize handle would, keeping its intrinsic aspect ratio when
+    /// [`Options::lock_aspect`] is set or `keep_aspect` is passed, e.g. while Shift is held.
+    pub fn resize(&mut self, origin: Point2D<f32>, size: Size2D<f32>, keep_aspect: bool) {
+        let size = match self.intrinsic_size {
+            Some(intrinsic) if self.options.lock_aspect || keep_aspect => {
+                constrain_aspect(size, intrinsic)
+            }
+            _ => size,
+        };
+        self.update_rect(origin, size);
+    }
+
     /// Translate the svg by a given amount, applying the transformation to the cache without
     /// re-tesselating the svg.
     pub fn translate(&mut self, dx: Vector2D<f32>) {
@@ -76,6 +229,9 @@ impl<C: ApplyCoordinates> Svg<C> {
         }
     }
 
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
     pub fn update_options(&mut self, options: Options) {
         self.options = options;
         self.clear_cache();
@@ -109,7 +265,9 @@ impl<C: ApplyCoordinates> Svg<C> {
         let options = FillOptions::default()
             .with_tolerance(0.1)
             .with_fill_rule(fill_rule);
-        _ = tessellator.tessellate_path(lyon_path, &options, &mut builder);
+        if let Err(err) = tessellator.tessellate_path(lyon_path, &options, &mut builder) {
+            tracing::warn!("Error while filling svg path: {err}");
+        }
 
         Mesh {
             vertices: buffers.vertices,
@@ -158,33 +316,113 @@ impl<C: ApplyCoordinates> Svg<C> {
             .with_end_cap(line_cap)
             .with_miter_limit(miter_limit)
             .with_line_join(line_join);
-        _ = tessellator.tessellate_path(lyon_path, &options, &mut builder);
+        if let Err(err) = tessellator.tessellate_path(lyon_path, &options, &mut builder) {
+            tracing::warn!("Error while stroking svg path: {err}");
+        }
 
         Mesh {
             vertices: buffers.vertices,
             indices: buffers.indices,
         }
     }
+
+    /// A textured quad for a png embedded in the svg, placed by the transform of the svg content.
+    ///
+    /// The image is decoded through the image cache, so stamping the same svg again only has to
+    /// upload it while it isn't in the atlas anymore.
+    fn image_mesh(
+        &mut self,
+        systems: &mut Systems,
+        image: &usvg::Image,
+        root_transformation: &Transform2D<f32>,
+    ) -> Option<Mesh<Vertex>> {
+        let ImageKind::PNG(data) = image.kind() else {
+            tracing::warn!("only png images are supported in svgs");
+            return None;
+        };
+        let key = CacheKey::Image(ImageKey::from_bytes(data));
+        let atlas = &mut systems.texture.color_atlas;
+        let texture = match atlas.is_allocated(key.clone()) {
+            Some(texture) => texture,
+            None => {
+                let decoded = systems
+                    .image
+                    .load_bytes(data, png::decode)
+                    .inspect_err(|err| tracing::warn!("failed to decode an svg image: {err:#}"))
+                    .ok()?;
+                atlas
+                    .allocate(
+                        systems.device,
+                        systems.queue,
+                        UnallocatedTexture::new(&decoded.pixels, decoded.width, decoded.height),
+                        Some(key),
+                        TextureData::None,
+                    )
+                    .inspect_err(|err| tracing::warn!("failed to allocate an svg image: {err:#}"))
+                    .ok()?
+            }
+        };
+
+        let size = image.size();
+        let area = Box2D::new(Point2D::zero(), Point2D::new(size.width(), size.height()));
+        let mut mesh = Mesh::from_texture_mesh(
+            texture.to_mesh(area, atlas),
+            C::apply(VertexKind::ColorTexture),
+        );
+        let transform = to_transform(image.abs_transform()).then(root_transformation);
+        for vertex in &mut mesh.vertices {
+            let position = transform.transform_point(vertex.position.into());
+            vertex.position = position.to_array();
+        }
+        self.images.push(texture);
+        Some(mesh)
+    }
 }
 
 impl<C: ApplyCoordinates> Drawable for Svg<C> {
-    fn render(&mut self, _: &mut Systems) -> &Mesh<Vertex> {
+    fn render(&mut self, systems: &mut Systems) -> &Mesh<Vertex> {
         if let Some(ref cache) = self.render_cache {
             return cache;
         }
+        self.images.clear();
 
         let mut result = Mesh::empty();
-        let tree = Tree::from_data(self.data.as_slice(), &usvg::Options::default())
-            .expect("invalid svg --- TODO: dont die");
+        let tree = match parse(&self.data) {
+            Ok(tree) => tree,
+            Err(err) => {
+                tracing::warn!("Rendering a placeholder for an invalid svg: {err:#}");
+                self.render_cache = Some(Mesh::new_color_quad(
+                    self.bounding_box(),
+                    C::apply(VertexKind::Color(BROKEN_COLOR)),
+                ));
+                return self.render_cache.as_ref().unwrap();
+            }
+        };
 
         // Push root's children in reverse order onto the stack
         let mut stack: Vec<&Node> = tree.root().children().iter().rev().collect();
 
-        let root_transformation = Transform2D::scale(
-            self.size.width / tree.size().width(),
-            self.size.height / tree.size().height(),
-        )
-        .then_translate(Vector2D::new(self.origin.x, self.origin.y));
+        let intrinsic = Size2D::new(tree.size().width(), tree.size().height());
+        let (scale, offset) = match self.options.fit {
+            ImageFit::Fill => (
+                Vector2D::new(
+                    self.size.width / intrinsic.width,
+                    self.size.height / intrinsic.height,
+                ),
+                Vector2D::zero(),
+            ),
+            ImageFit::Fit => {
+                let scaled = constrain_aspect_within(self.size, intrinsic);
+                (
+                    Vector2D::splat(scaled.width / intrinsic.width),
+                    (self.size - scaled).to_vector() / 2.,
+                )
+            }
+        };
+        let root_transformation = Transform2D::scale(scale.x, scale.y)
+            .then_translate(self.origin.to_vector() + offset)
+            .then(&self.flip_transform())
+            .then(&self.rotation_transform());
 
         while let Some(node) = stack.pop() {
             match node {
@@ -194,16 +432,7 @@ impl<C: ApplyCoordinates> Drawable for Svg<C> {
                     }
                 }
                 Node::Path(path) => {
-                    let transform = path.abs_transform();
-
-                    let transform = Transform2D::new(
-                        transform.sx,
-                        transform.kx,
-                        transform.ky,
-                        transform.sy,
-                        transform.tx,
-                        transform.ty,
-                    );
+                    let transform = to_transform(path.abs_transform());
                     let mut builder = lyon::path::Path::svg_builder()
                         .transformed(transform.then(&root_transformation));
                     for segment in path.data().segments() {
@@ -252,9 +481,12 @@ impl<C: ApplyCoordinates> Drawable for Svg<C> {
                         }
                     }
                 }
-                Node::Image(_image) => {
-                    // TODO: support images
-                    tracing::warn!("image element in svg not yet implemented!");
+                Node::Image(image) => {
+                    if image.is_visible()
+                        && let Some(mesh) = self.image_mesh(systems, image, &root_transformation)
+                    {
+                        result.append(&mesh);
+                    }
                 }
                 Node::Text(text) => {
                     let transform = text.abs_transform();
@@ -284,7 +516,19 @@ impl<C: ApplyCoordinates> Drawable for Svg<C> {
         if area.min.y > area.max.y {
             std::mem::swap(&mut area.min.y, &mut area.max.y);
         }
-        area
+        if self.rotation == 0. {
+            return area;
+        }
+        let transform = self.rotation_transform();
+        Box2D::from_points(
+            [
+                area.min,
+                Point2D::new(area.max.x, area.min.y),
+                area.max,
+                Point2D::new(area.min.x, area.max.y),
+            ]
+            .map(|corner| transform.transform_point(corner)),
+        )
     }
 
     fn is_dirty(&self) -> bool {
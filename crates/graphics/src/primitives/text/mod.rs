@@ -1,18 +1,24 @@
-use std::{marker::PhantomData, sync::Arc};
+use std::{marker::PhantomData, ops::Range, sync::Arc};
 
 use color::{PremulColor, Srgb};
 use euclid::default::{Box2D, Point2D, Size2D, Vector2D};
+use lyon::path::{path::Builder, Path};
+use lyon::tessellation::{
+    BuffersBuilder, StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
+};
 use parley::{
     swash::{
         scale::{image::Content, Render, Scaler, Source, StrikeWith},
-        zeno::{Format, Vector},
+        zeno::{Command, Format, PathData, Vector},
         FontRef,
     },
     Alignment, AlignmentOptions, Cursor, FontFamily, FontStack, Glyph, GlyphRun, Layout,
     LineHeight, PositionedLayoutItem, StyleProperty,
 };
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
+use crate::primitives::FlipAxis;
 use crate::{
     make_positive_box,
     systems::{CacheKey, ColorBrush, GlyphCacheKey, TextData, TextureData},
@@ -24,7 +30,7 @@ use atlas::{
 };
 
 mod options;
-pub use options::{FontStretch, FontStyle, FontWeight};
+pub use options::{FontStretch, FontStyle, FontWeight, VerticalAlign};
 
 #[derive(Clone, Debug, Default)]
 pub struct TextureAtlasKeys {
@@ -32,9 +38,23 @@ pub struct TextureAtlasKeys {
     color_glyphs: Vec<Arc<AllocatedTexture<Rgba8, TextureData>>>,
 }
 
+/// An outline drawn along the edges of each glyph.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextStroke {
+    pub color: PremulColor<Srgb>,
+    pub width: f32,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Options {
-    pub color: PremulColor<Srgb>,
+    /// The color the glyphs are filled with, `None` leaves them unfilled.
+    ///
+    /// This used to be `color`, which older sessions still use.
+    #[serde(alias = "color")]
+    pub fill: Option<PremulColor<Srgb>>,
+    /// Strokes the vector outlines of the glyphs, which is heavier than filling them from the
+    /// glyph atlas but stays legible over busy backgrounds.
+    pub stroke: Option<TextStroke>,
 
     pub font_size: f32,
     pub line_height: f32,
@@ -45,12 +65,15 @@ pub struct Options {
     pub font_stretch: FontStretch,
 
     pub alignment: options::Alignment,
+    #[serde(default)]
+    pub vertical_alignment: VerticalAlign,
 }
 
 impl Default for Options {
     fn default() -> Self {
         Self {
-            color: PremulColor::new([1., 0., 0., 1.]),
+            fill: Some(PremulColor::new([1., 0., 0., 1.])),
+            stroke: None,
             line_height: 28.,
             font_size: 16.,
             font_family: options::FontFamily::Name("JetBrainsMono Nerd Font".to_string()),
@@ -58,10 +81,46 @@ impl Default for Options {
             font_style: FontStyle::Normal,
             font_stretch: FontStretch::Normal,
             alignment: options::Alignment::Left,
+            vertical_alignment: VerticalAlign::Top,
+        }
+    }
+}
+
+/// Overrides of the text options for a run of the content, unset fields use the options of the
+/// text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextStyle {
+    pub color: Option<PremulColor<Srgb>>,
+    pub font_size: Option<f32>,
+    pub font_weight: Option<FontWeight>,
+    pub font_style: Option<FontStyle>,
+}
+
+/// A piece of text along with the style it is drawn in.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextRun {
+    pub text: String,
+    pub style: TextStyle,
+}
+
+impl TextRun {
+    pub fn new(text: impl Into<String>, style: TextStyle) -> Self {
+        Self {
+            text: text.into(),
+            style,
         }
     }
 }
 
+impl Options {
+    /// The color used for things that are not glyphs, like decorations and inline boxes.
+    pub fn color(&self) -> PremulColor<Srgb> {
+        self.fill
+            .or(self.stroke.map(|stroke| stroke.color))
+            .unwrap_or(PremulColor::TRANSPARENT)
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Text<C: ApplyCoordinates> {
     #[serde(skip)]
@@ -72,10 +131,17 @@ pub struct Text<C: ApplyCoordinates> {
 
     #[serde(skip)]
     layout: Option<Layout<ColorBrush>>,
-    #[serde(skip)]
     content: String,
+    /// The byte ranges of the content with their style overrides, which never overlap.
+    styles: Vec<(Range<usize>, TextStyle)>,
 
     area: Box2D<f32>,
+    /// Whether the glyphs are mirrored left to right about the center of the laid out text.
+    #[serde(default)]
+    flip_horizontal: bool,
+    /// Whether the glyphs are mirrored top to bottom about the center of the laid out text.
+    #[serde(default)]
+    flip_vertical: bool,
 
     options: Options,
     _marker: PhantomData<C>,
@@ -87,7 +153,10 @@ impl<C: ApplyCoordinates> std::fmt::Debug for Text<C> {
             .field("atlas_keys", &self.atlas_keys)
             .field("layout", &self.layout.as_ref().map(|_| "Built layout"))
             .field("content", &self.content)
+            .field("styles", &self.styles)
             .field("area", &self.area)
+            .field("flip_horizontal", &self.flip_horizontal)
+            .field("flip_vertical", &self.flip_vertical)
             .field("options", &self.options)
             .field("_marker", &self._marker)
             .finish()
@@ -104,14 +173,72 @@ impl<C: ApplyCoordinates> Text<C> {
             layout: None,
 
             content,
+            styles: vec![],
 
             area,
+            flip_horizontal: false,
+            flip_vertical: false,
 
             options,
             _marker: PhantomData,
         }
     }
 
+    /// Creates text out of styled runs, which are laid out together as a single block.
+    pub fn from_runs(runs: Vec<TextRun>, options: Options, area: Box2D<f32>) -> Self {
+        let mut text = Self::new(String::new(), options, area);
+        for run in runs {
+            let start = text.content.len();
+            text.content.push_str(&run.text);
+            if run.style != TextStyle::default() {
+                text.styles.push((start..text.content.len(), run.style));
+            }
+        }
+        text
+    }
+
+    /// The content split into runs of the same style.
+    pub fn runs(&self) -> Vec<TextRun> {
+        let mut runs = vec![];
+        let mut end = 0;
+        for (range, style) in &self.styles {
+            if range.start > end {
+                runs.push(TextRun::new(
+                    &self.content[end..range.start],
+                    TextStyle::default(),
+                ));
+            }
+            runs.push(TextRun::new(&self.content[range.clone()], *style));
+            end = range.end;
+        }
+        if end < self.content.len() || runs.is_empty() {
+            runs.push(TextRun::new(&self.content[end..], TextStyle::default()));
+        }
+        runs
+    }
+
+    /// Overrides the style of the given byte range, replacing any overrides it overlaps.
+    pub fn set_style(&mut self, range: Range<usize>, style: TextStyle) {
+        let mut styles = Vec::with_capacity(self.styles.len() + 2);
+        for (existing, existing_style) in self.styles.drain(..) {
+            if existing.start < range.start {
+                styles.push((
+                    existing.start..existing.end.min(range.start),
+                    existing_style,
+                ));
+            }
+            if existing.end > range.end {
+                styles.push((existing.start.max(range.end)..existing.end, existing_style));
+            }
+        }
+        if style != TextStyle::default() && !range.is_empty() {
+            styles.push((range, style));
+        }
+        styles.sort_by_key(|(range, _)| range.start);
+        self.styles = styles;
+        self.clear_cache();
+    }
+
     fn glyph_to_mesh<T: AtlasFormat>(
         area: Box2D<f32>,
         glyph: &Arc<AllocatedTexture<T, TextureData>>,
@@ -123,16 +250,76 @@ impl<C: ApplyCoordinates> Text<C> {
     pub fn content(&self) -> String {
         self.content.clone()
     }
+    /// The top-left corner of the text area.
+    pub fn origin(&self) -> Point2D<f32> {
+        self.area.min
+    }
 
     pub fn translate(&mut self, dx: Vector2D<f32>) {
-        self.area.translate(dx);
+        self.area = self.area.translate(dx);
         if let Some(cache) = &mut self.render_cache {
             cache.translate(dx);
         }
     }
 
+    /// Replaces the content, dropping any style overrides.
     pub fn set_content(&mut self, content: String) {
         self.content = content;
+        self.styles.clear();
+        self.clear_cache();
+    }
+
+    /// Inserts the string at the given byte index of the content.
+    ///
+    /// Parley can not reshape part of a layout, so the layout is still rebuilt, but glyphs that
+    /// are already in the atlas are reused instead of being rasterized again.
+    ///
+    /// Text inserted inside or right after a styled run takes on the style of that run.
+    pub fn insert_at(&mut self, index: usize, string: &str) {
+        self.content.insert_str(index, string);
+        for (range, _) in &mut self.styles {
+            if range.start >= index {
+                range.start += string.len();
+            }
+            if range.end >= index {
+                range.end += string.len();
+            }
+        }
+        self.clear_cache();
+    }
+
+    /// Removes the given byte range from the content, see [`Text::insert_at`].
+    pub fn delete_range(&mut self, range: Range<usize>) {
+        self.content.replace_range(range.clone(), "");
+        let shift = |index: usize| {
+            if index >= range.end {
+                index - range.len()
+            } else {
+                index.min(range.start)
+            }
+        };
+        for (styled, _) in &mut self.styles {
+            *styled = shift(styled.start)..shift(styled.end);
+        }
+        self.styles.retain(|(styled, _)| !styled.is_empty());
+        self.clear_cache();
+    }
+
+    /// The length of the content in bytes.
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    /// Mirrors the glyphs in place, so the text reads backwards or upside down, mirroring again
+    /// turns it back.
+    pub fn mirror(&mut self, axis: FlipAxis) {
+        match axis {
+            FlipAxis::Horizontal => self.flip_horizontal = !self.flip_horizontal,
+            FlipAxis::Vertical => self.flip_vertical = !self.flip_vertical,
+        }
         self.clear_cache();
     }
 
@@ -162,7 +349,7 @@ impl<C: ApplyCoordinates> Text<C> {
 
         // Set default text colour styles (set foreground text color)
         let color_brush = ColorBrush {
-            color: self.options.color,
+            color: self.options.color(),
         };
         let brush_style = StyleProperty::Brush(color_brush);
         // let font_stack = FontStack::Single(FontFamily::Generic(parley::GenericFamily::SystemUi));
@@ -171,15 +358,48 @@ impl<C: ApplyCoordinates> Text<C> {
         builder.push_default(font_stack);
         builder.push_default(LineHeight::FontSizeRelative(1.3));
         builder.push_default(StyleProperty::FontSize(self.options.font_size));
-        builder.push_default(StyleProperty::FontWeight(parley::FontWeight::NORMAL));
+        builder.push_default(StyleProperty::FontWeight(
+            self.options.font_weight.as_parley(),
+        ));
+        builder.push_default(StyleProperty::FontStyle(
+            self.options.font_style.as_parley(),
+        ));
         builder.push_default(StyleProperty::OverflowWrap(parley::OverflowWrap::BreakWord));
 
+        for (range, style) in &self.styles {
+            if let Some(color) = style.color {
+                builder.push(StyleProperty::Brush(ColorBrush { color }), range.clone());
+            }
+            if let Some(font_size) = style.font_size {
+                builder.push(StyleProperty::FontSize(font_size), range.clone());
+            }
+            if let Some(font_weight) = style.font_weight {
+                builder.push(
+                    StyleProperty::FontWeight(font_weight.as_parley()),
+                    range.clone(),
+                );
+            }
+            if let Some(font_style) = style.font_style {
+                builder.push(
+                    StyleProperty::FontStyle(font_style.as_parley()),
+                    range.clone(),
+                );
+            }
+        }
+
         let mut layout: Layout<ColorBrush> = builder.build(&self.content);
         layout.break_all_lines(max_advance);
         layout.align(max_advance, Alignment::Start, AlignmentOptions::default());
         layout
     }
 
+    /// How far the laid out text is moved down from the top of the area by its vertical alignment.
+    fn vertical_offset(&self, layout: &Layout<ColorBrush>) -> f32 {
+        self.options
+            .vertical_alignment
+            .offset(self.area.height(), layout.height())
+    }
+
     pub fn measure(&mut self, systems: &mut Systems) -> Size2D<f32> {
         let layout = self.prepare_layout(systems);
         let res = Size2D::new(layout.full_width(), layout.height());
@@ -187,12 +407,25 @@ impl<C: ApplyCoordinates> Text<C> {
         res
     }
 
+    /// The distance from the top of the text area to the baseline of the first line.
+    pub fn first_baseline(&mut self, systems: &mut Systems) -> f32 {
+        let layout = self.prepare_layout(systems);
+        let baseline = layout
+            .lines()
+            .next()
+            .map_or(self.options.font_size, |line| line.metrics().baseline)
+            + self.vertical_offset(&layout);
+        self.layout = Some(layout);
+        baseline
+    }
+
     fn render_glyph_run(
         &mut self,
         systems: &mut Systems,
         glyph_run: &GlyphRun<'_, ColorBrush>,
         start_position: Point2D<f32>,
         mesh: &mut Mesh<Vertex>,
+        mut outline: Option<&mut Builder>,
     ) {
         // Resolve properties of the GlyphRun
         let mut run_x = glyph_run.offset() + start_position.x;
@@ -230,6 +463,12 @@ impl<C: ApplyCoordinates> Text<C> {
             let glyph_x = run_x + glyph.x;
             let glyph_y = run_y - glyph.y;
             run_x += glyph.advance;
+            if let Some(outline) = outline.as_deref_mut() {
+                Self::append_glyph_outline(outline, &mut scaler, glyph.id, glyph_x, glyph_y);
+            }
+            if self.options.fill.is_none() {
+                continue;
+            }
             tracing::info!("rendering glyph!!!!!");
             tracing::info!("mesh size before: {}", mesh.indices.len());
 
@@ -280,6 +519,67 @@ impl<C: ApplyCoordinates> Text<C> {
         }
     }
 
+    /// Adds the outline of the glyph with its origin at the given position to the path.
+    fn append_glyph_outline(
+        path: &mut Builder,
+        scaler: &mut Scaler<'_>,
+        glyph_id: u16,
+        glyph_x: f32,
+        glyph_y: f32,
+    ) {
+        let Some(outline) = scaler.scale_outline(glyph_id) else {
+            return;
+        };
+        // Outlines are y-up relative to the glyph origin.
+        let point = |vector: Vector| lyon::math::point(glyph_x + vector.x, glyph_y - vector.y);
+
+        let mut open = false;
+        for command in outline.path().commands() {
+            match command {
+                Command::MoveTo(to) => {
+                    if open {
+                        path.end(false);
+                    }
+                    path.begin(point(to));
+                    open = true;
+                }
+                Command::LineTo(to) => {
+                    path.line_to(point(to));
+                }
+                Command::QuadTo(ctrl, to) => {
+                    path.quadratic_bezier_to(point(ctrl), point(to));
+                }
+                Command::CurveTo(ctrl1, ctrl2, to) => {
+                    path.cubic_bezier_to(point(ctrl1), point(ctrl2), point(to));
+                }
+                Command::Close => {
+                    if open {
+                        path.end(true);
+                    }
+                    open = false;
+                }
+            }
+        }
+        if open {
+            path.end(false);
+        }
+    }
+
+    fn tessellate_outline(path: &Path, stroke: TextStroke) -> Mesh<Vertex> {
+        let mut buffers = VertexBuffers::<Vertex, u32>::new();
+        let mut builder = BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex<'_, '_>| {
+            Vertex::with_color(vertex.position(), C::apply(VertexKind::Color(stroke.color)))
+        });
+        let options = StrokeOptions::default().with_line_width(stroke.width);
+        if let Err(err) = StrokeTessellator::new().tessellate_path(path, &options, &mut builder) {
+            warn!("Error while tessellating text outline with stroke {stroke:?}: {err}");
+        }
+        Mesh {
+            vertices: buffers.vertices,
+            indices: buffers.indices,
+        }
+    }
+
     fn render_decoration(
         mesh: &mut Mesh<Vertex>,
         start_position: Point2D<f32>,
@@ -298,6 +598,16 @@ impl<C: ApplyCoordinates> Text<C> {
         ));
     }
 
+    /// The area of a glyph found in the atlas, matching the placement it was rasterized with.
+    fn cached_glyph_area(data: TextData, glyph_x: f32, glyph_y: f32) -> Box2D<f32> {
+        let glyph_x = (glyph_x.floor() as i32 + data.placement_left) as u32;
+        let glyph_y = (glyph_y.floor() as i32 - data.placement_top) as u32;
+        Box2D::from_origin_and_size(
+            Point2D::new(glyph_x as f32, glyph_y as f32),
+            Size2D::new(data.width as f32, data.height as f32),
+        )
+    }
+
     fn render_glyph(
         &mut self,
         queue: &wgpu::Queue,
@@ -313,7 +623,37 @@ impl<C: ApplyCoordinates> Text<C> {
         glyph_x: f32,
         glyph_y: f32,
     ) -> Option<()> {
-        // TODO: try get glyphs from atlas first before rendering
+        let cache_key = CacheKey::Text(GlyphCacheKey {
+            font_index,
+            glyph_id: glyph.id,
+            font_size_bits: font_size.to_bits(),
+        });
+
+        // Glyphs are usually already in an atlas, rasterizing them again is wasted work.
+        if let Some(allocated_glyph) = mask_atlas.is_allocated(cache_key.clone())
+            && let TextureData::Text(data) = allocated_glyph.data
+        {
+            mesh.append(&Self::glyph_to_mesh(
+                Self::cached_glyph_area(data, glyph_x, glyph_y),
+                &allocated_glyph,
+                mask_atlas,
+                C::apply(VertexKind::MaskTexture(brush.color)),
+            ));
+            self.atlas_keys.mask_glyphs.push(allocated_glyph);
+            return Some(());
+        }
+        if let Some(allocated_glyph) = color_atlas.is_allocated(cache_key.clone())
+            && let TextureData::Text(data) = allocated_glyph.data
+        {
+            mesh.append(&Self::glyph_to_mesh(
+                Self::cached_glyph_area(data, glyph_x, glyph_y),
+                &allocated_glyph,
+                color_atlas,
+                C::apply(VertexKind::ColorTexture),
+            ));
+            self.atlas_keys.color_glyphs.push(allocated_glyph);
+            return Some(());
+        }
 
         // Compute the fractional offset
         // You'll likely want to quantize this in a real renderer
@@ -355,12 +695,6 @@ impl<C: ApplyCoordinates> Text<C> {
             rendered_glyph.placement.width,
             rendered_glyph.placement.height,
         );
-        let cache_key = CacheKey::Text(GlyphCacheKey {
-            font_index,
-            glyph_id: glyph.id,
-            font_size_bits: font_size.to_bits(),
-        });
-
         match rendered_glyph.content {
             Content::SubpixelMask => unimplemented!(),
             Content::Mask => {
@@ -409,9 +743,9 @@ impl<C: ApplyCoordinates> Drawable for Text<C> {
         if let Some(ref cache) = self.render_cache {
             return cache;
         };
-        let start_position = self.area.min.round();
-
         let layout = self.prepare_layout(systems);
+        let start_position =
+            (self.area.min + Vector2D::new(0., self.vertical_offset(&layout))).round();
 
         let cursor = Cursor::from_byte_index(&layout, 3, parley::Affinity::Downstream);
 
@@ -420,6 +754,9 @@ impl<C: ApplyCoordinates> Drawable for Text<C> {
         // Reset the atlas keys
         self.atlas_keys = Default::default();
 
+        // The outlines are cached as part of the result, so they are only rebuilt along with it.
+        let mut outline = self.options.stroke.is_some().then(Path::builder);
+
         // Selection
         // TODO: handle completion
         // Cursor
@@ -453,7 +790,13 @@ impl<C: ApplyCoordinates> Drawable for Text<C> {
                 match item {
                     PositionedLayoutItem::GlyphRun(glyph_run) => {
                         tracing::info!("render glyph run");
-                        self.render_glyph_run(systems, &glyph_run, start_position, &mut result);
+                        self.render_glyph_run(
+                            systems,
+                            &glyph_run,
+                            start_position,
+                            &mut result,
+                            outline.as_mut(),
+                        );
                     }
                     PositionedLayoutItem::InlineBox(inline_box) => {
                         result.append(&Mesh::new_color_quad(
@@ -465,13 +808,28 @@ impl<C: ApplyCoordinates> Drawable for Text<C> {
                                         inline_box.y + inline_box.height,
                                     ),
                             ),
-                            C::apply(VertexKind::Color(self.options.color)),
+                            C::apply(VertexKind::Color(self.options.color())),
                         ));
                     }
                 }
             }
         }
 
+        if let (Some(stroke), Some(outline)) = (self.options.stroke, outline) {
+            result.append(&Self::tessellate_outline(&outline.build(), stroke));
+        }
+
+        let extent = Box2D::from_origin_and_size(
+            start_position,
+            Size2D::new(layout.full_width(), layout.height()),
+        );
+        if self.flip_horizontal {
+            FlipAxis::Horizontal.flip_mesh(&mut result, extent.center().x);
+        }
+        if self.flip_vertical {
+            FlipAxis::Vertical.flip_mesh(&mut result, extent.center().y);
+        }
+
         self.layout = Some(layout);
         // Update the stored glyph allocations, so that unused allocations can be dropped
         self.render_cache = Some(result.clone());
@@ -490,3 +848,76 @@ impl<C: ApplyCoordinates> Drawable for Text<C> {
         self.render_cache.is_none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CanvasCoordinates;
+
+    fn bold() -> TextStyle {
+        TextStyle {
+            font_weight: Some(FontWeight::Bold),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn vertical_alignment_centers_multiline_text() {
+        // Three lines of the default options in a box with room to spare.
+        let content = 3. * Options::default().line_height;
+        assert_eq!(VerticalAlign::Top.offset(120., content), 0.);
+        assert_eq!(VerticalAlign::Center.offset(120., content), 18.);
+        assert_eq!(VerticalAlign::Bottom.offset(120., content), 36.);
+        // Overflowing and unbounded areas keep the text at the top.
+        assert_eq!(VerticalAlign::Center.offset(40., content), 0.);
+        assert_eq!(VerticalAlign::Bottom.offset(f32::MAX, content), 0.);
+    }
+
+    #[test]
+    fn runs_survive_edits() {
+        let mut text = Text::<CanvasCoordinates>::from_runs(
+            vec![
+                TextRun::new("a ", TextStyle::default()),
+                TextRun::new("bold", bold()),
+                TextRun::new(" word", TextStyle::default()),
+            ],
+            Options::default(),
+            Box2D::zero(),
+        );
+        assert_eq!(text.content(), "a bold word");
+
+        text.insert_at(6, "er");
+        text.delete_range(0..2);
+        assert_eq!(
+            text.runs(),
+            vec![
+                TextRun::new("bolder", bold()),
+                TextRun::new(" word", TextStyle::default()),
+            ]
+        );
+
+        text.set_style(0..4, TextStyle::default());
+        assert_eq!(
+            text.runs(),
+            vec![
+                TextRun::new("bold", TextStyle::default()),
+                TextRun::new("er", bold()),
+                TextRun::new(" word", TextStyle::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn mirroring_twice_reads_the_right_way_again() {
+        let mut text =
+            Text::<CanvasCoordinates>::new("draw".into(), Options::default(), Box2D::zero());
+        text.mirror(FlipAxis::Horizontal);
+        text.mirror(FlipAxis::Vertical);
+        assert!(text.flip_horizontal && text.flip_vertical);
+        assert!(text.is_dirty());
+
+        text.mirror(FlipAxis::Horizontal);
+        text.mirror(FlipAxis::Vertical);
+        assert!(!text.flip_horizontal && !text.flip_vertical);
+    }
+}
@@ -16,6 +16,32 @@ pub enum Alignment {
     #[default]
     Center,
 }
+/// Where the laid out text sits vertically within its area.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Hash, Serialize, Deserialize)]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Center,
+    Bottom,
+}
+
+impl VerticalAlign {
+    /// The offset from the top of an area `available` high to content `content` high.
+    ///
+    /// Content taller than the area, or an area of unbounded height, stays at the top.
+    pub fn offset(self, available: f32, content: f32) -> f32 {
+        if available >= f32::MAX {
+            return 0.;
+        }
+        let space = (available - content).max(0.);
+        match self {
+            VerticalAlign::Top => 0.,
+            VerticalAlign::Center => space / 2.,
+            VerticalAlign::Bottom => space,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, Hash, Serialize, Deserialize)]
 #[repr(u16)]
 pub enum FontWeight {
@@ -31,6 +57,12 @@ pub enum FontWeight {
     Black = 900,
 }
 
+impl FontWeight {
+    pub fn as_parley(self) -> parley::FontWeight {
+        parley::FontWeight::new(self as u16 as f32)
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Hash, Serialize, Deserialize)]
 pub enum FontFamily {
     /// The name of a font family of choice.
@@ -75,6 +107,16 @@ pub enum FontStyle {
     Oblique,
 }
 
+impl FontStyle {
+    pub fn as_parley(self) -> parley::FontStyle {
+        match self {
+            FontStyle::Normal => parley::FontStyle::Normal,
+            FontStyle::Italic => parley::FontStyle::Italic,
+            FontStyle::Oblique => parley::FontStyle::Oblique(None),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, Hash, Serialize, Deserialize)]
 pub enum FontStretch {
     UltraCondensed,
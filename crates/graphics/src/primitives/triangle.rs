@@ -1,9 +1,10 @@
 use std::marker::PhantomData;
 
 use color::{PremulColor, Srgb};
-use euclid::default::{Box2D, Point2D};
+use euclid::default::{Box2D, Point2D, Vector2D};
 use serde::{Deserialize, Serialize};
 
+use crate::primitives::FlipAxis;
 use crate::{make_positive_box, Vertex, VertexKind};
 use crate::{ApplyCoordinates, Drawable, Mesh, Systems};
 
@@ -63,6 +64,21 @@ impl<C: ApplyCoordinates> Triangle<C> {
         self.p3 = p3;
         self.render_cache = None;
     }
+    pub fn flip(&mut self, axis: FlipAxis, at: f32) {
+        self.update_points(
+            axis.flip_point(self.p1, at),
+            axis.flip_point(self.p2, at),
+            axis.flip_point(self.p3, at),
+        );
+    }
+    pub fn translate(&mut self, dx: Vector2D<f32>) {
+        self.p1 = self.p1 + dx;
+        self.p2 = self.p2 + dx;
+        self.p3 = self.p3 + dx;
+        if let Some(cache) = &mut self.render_cache {
+            cache.translate(dx);
+        }
+    }
     pub fn options(&self) -> &Options {
         &self.options
     }
@@ -1,4 +1,10 @@
-use atlas::LayeredAtlas;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use atlas::{LayeredAtlas, RebindToken, TilingOptions};
 use color::{PremulColor, Srgb};
 use parley::{
     swash::scale::{image::Image, ScaleContext},
@@ -9,17 +15,23 @@ use parley::{
 pub struct SystemsOwned {
     pub text: TextState,
     pub texture: TextureState,
+    pub image: ImageCache,
 }
 
 impl<'a> SystemsOwned {
     pub fn new(text: TextState, texture: TextureState) -> Self {
-        Self { text, texture }
+        Self {
+            text,
+            texture,
+            image: ImageCache::default(),
+        }
     }
 
     pub fn to_ref(&'a mut self, device: &'a wgpu::Device, queue: &'a wgpu::Queue) -> Systems<'a> {
         Systems {
             text: &mut self.text,
             texture: &mut self.texture,
+            image: &mut self.image,
 
             device,
             queue,
@@ -31,6 +43,7 @@ impl<'a> SystemsOwned {
 pub struct Systems<'a> {
     pub text: &'a mut TextState,
     pub texture: &'a mut TextureState,
+    pub image: &'a mut ImageCache,
 
     pub device: &'a wgpu::Device,
     pub queue: &'a wgpu::Queue,
@@ -62,7 +75,7 @@ pub struct TextureState {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CacheKey {
     Text(GlyphCacheKey),
-    Image(String),
+    Image(ImageKey),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -112,16 +125,22 @@ impl Default for TextState {
 
 impl TextureState {
     pub fn new(device: &wgpu::Device) -> Self {
+        // glyphs are small, so allocate them whole where possible and otherwise avoid thin
+        // slivers for very tall or wide glyphs.
         let mask_atlas = LayeredAtlas::new(
             &device,
             atlas::DEFAULT_ATLAS_SIZE,
-            atlas::DEFAULT_TILE_SIZE,
+            TilingOptions {
+                whole_threshold: atlas::DEFAULT_TILE_SIZE * 2,
+                fit_content: true,
+                ..TilingOptions::DEFAULT
+            },
             device.limits(),
         );
         let color_atlas = LayeredAtlas::new(
             &device,
             atlas::DEFAULT_ATLAS_SIZE,
-            atlas::DEFAULT_TILE_SIZE,
+            TilingOptions::DEFAULT,
             device.limits(),
         );
         Self {
@@ -129,12 +148,282 @@ impl TextureState {
             color_atlas,
         }
     }
-    pub fn needs_rebinding(&self) -> bool {
-        self.mask_atlas.needs_rebinding || self.color_atlas.needs_rebinding
+    /// Takes the pending rebind of either atlas, see [`LayeredAtlas::take_rebind_token`].
+    pub fn take_rebind_token(&mut self) -> Option<RebindToken> {
+        // Both tokens have to be taken, otherwise the second would trigger another rebind.
+        let mask = self.mask_atlas.take_rebind_token();
+        let color = self.color_atlas.take_rebind_token();
+        mask.or(color)
+    }
+}
+
+/// A decoded image, as tightly packed rgba8 rows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl DecodedImage {
+    /// Multiplies the color channels by the alpha, which the atlas and the shaders expect.
+    pub fn premultiply(&mut self) {
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            let alpha = pixel[3] as u16;
+            for channel in &mut pixel[..3] {
+                *channel = ((*channel as u16 * alpha + 127) / 255) as u8;
+            }
+        }
+    }
+}
+
+/// What a cached image is looked up by.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ImageKey {
+    Path(PathBuf),
+    /// A hash of the encoded bytes, for images that don't come from a file.
+    Content(u64),
+}
+
+impl ImageKey {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        Self::Content(hasher.finish())
+    }
+}
+
+struct CachedImage {
+    image: Arc<DecodedImage>,
+    /// The modification time of the file when it was decoded.
+    modified: Option<SystemTime>,
+    last_used: u64,
+}
+
+/// Keeps decoded and premultiplied images around, so that stamping or reloading the same image
+/// doesn't decode it again.
+///
+/// The cache holds at most `budget` bytes of pixels and evicts the least recently used images
+/// beyond that. The decoder is passed in by the caller, [`crate::png::decode`] is used for the pngs
+/// embedded in svgs.
+pub struct ImageCache {
+    entries: HashMap<ImageKey, CachedImage>,
+    budget: usize,
+    used: usize,
+    /// Incremented on every lookup, ordering the entries by when they were last used.
+    clock: u64,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_BUDGET)
+    }
+}
+
+impl ImageCache {
+    /// 256 MiB, roughly eight 4k screenshots.
+    pub const DEFAULT_BUDGET: usize = 256 * 1024 * 1024;
+
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget,
+            used: 0,
+            clock: 0,
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+    /// Changes the budget, evicting images straight away if the cache is now over it.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+    /// The amount of bytes of pixels currently cached.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The image of the file at `path`, decoding it with `decode` if it isn't cached or the file
+    /// has been modified since it was.
+    pub fn load_path(
+        &mut self,
+        path: &Path,
+        decode: impl FnOnce(&[u8]) -> anyhow::Result<DecodedImage>,
+    ) -> anyhow::Result<Arc<DecodedImage>> {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let key = ImageKey::Path(path.to_path_buf());
+        if let Some(image) = self.get_if(&key, |entry| entry.modified == modified) {
+            return Ok(image);
+        }
+        let bytes = std::fs::read(path)?;
+        self.insert(key, decode(&bytes)?, modified)
+    }
+
+    /// The image encoded by `bytes`, decoding it with `decode` if it isn't cached.
+    pub fn load_bytes(
+        &mut self,
+        bytes: &[u8],
+        decode: impl FnOnce(&[u8]) -> anyhow::Result<DecodedImage>,
+    ) -> anyhow::Result<Arc<DecodedImage>> {
+        let key = ImageKey::from_bytes(bytes);
+        if let Some(image) = self.get_if(&key, |_| true) {
+            return Ok(image);
+        }
+        self.insert(key, decode(bytes)?, None)
+    }
+
+    /// The cached image, without decoding or checking whether its file changed.
+    pub fn get(&mut self, key: &ImageKey) -> Option<Arc<DecodedImage>> {
+        self.get_if(key, |_| true)
+    }
+
+    /// Forgets the image, so that the next load decodes it again.
+    pub fn invalidate(&mut self, key: &ImageKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.used -= entry.image.pixels.len();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used = 0;
+    }
+
+    fn get_if(
+        &mut self,
+        key: &ImageKey,
+        valid: impl FnOnce(&CachedImage) -> bool,
+    ) -> Option<Arc<DecodedImage>> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        if !valid(entry) {
+            self.invalidate(key);
+            return None;
+        }
+        entry.last_used = self.clock;
+        Some(entry.image.clone())
+    }
+
+    /// Premultiplies and caches the image, an image larger than the whole budget is returned
+    /// without being cached.
+    fn insert(
+        &mut self,
+        key: ImageKey,
+        mut image: DecodedImage,
+        modified: Option<SystemTime>,
+    ) -> anyhow::Result<Arc<DecodedImage>> {
+        let expected = image.width as usize * image.height as usize * 4;
+        anyhow::ensure!(
+            image.pixels.len() == expected,
+            "decoded image has {} bytes, expected {expected} for {}x{}",
+            image.pixels.len(),
+            image.width,
+            image.height
+        );
+        image.premultiply();
+        let image = Arc::new(image);
+        if expected > self.budget {
+            tracing::warn!("image of {expected} bytes is larger than the cache, not caching it");
+            return Ok(image);
+        }
+
+        self.invalidate(&key);
+        self.clock += 1;
+        self.used += expected;
+        self.entries.insert(
+            key,
+            CachedImage {
+                image: image.clone(),
+                modified,
+                last_used: self.clock,
+            },
+        );
+        self.evict();
+        Ok(image)
+    }
+
+    /// Drops the least recently used images until the cache is within its budget.
+    fn evict(&mut self) {
+        while self.used > self.budget {
+            let Some(key) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.invalidate(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(size: u32, pixel: [u8; 4]) -> anyhow::Result<DecodedImage> {
+        Ok(DecodedImage {
+            width: size,
+            height: size,
+            pixels: pixel.repeat((size * size) as usize),
+        })
+    }
+
+    #[test]
+    fn premultiplies_decoded_images() {
+        let mut cache = ImageCache::default();
+        let image = cache
+            .load_bytes(b"a", |_| solid(1, [255, 128, 0, 128]))
+            .unwrap();
+        assert_eq!(image.pixels, [128, 64, 0, 128]);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        // Room for two 2x2 images.
+        let mut cache = ImageCache::new(32);
+        cache.load_bytes(b"a", |_| solid(2, [0; 4])).unwrap();
+        cache.load_bytes(b"b", |_| solid(2, [0; 4])).unwrap();
+        // Using `a` again makes `b` the one to go.
+        assert!(cache.get(&ImageKey::from_bytes(b"a")).is_some());
+        cache.load_bytes(b"c", |_| solid(2, [0; 4])).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.used(), 32);
+        assert!(cache.get(&ImageKey::from_bytes(b"a")).is_some());
+        assert!(cache.get(&ImageKey::from_bytes(b"b")).is_none());
+
+        let mut decoded = false;
+        cache
+            .load_bytes(b"c", |_| {
+                decoded = true;
+                solid(2, [0; 4])
+            })
+            .unwrap();
+        assert!(!decoded);
     }
 
-    pub fn mark_bound(&mut self) {
-        self.mask_atlas.needs_rebinding = false;
-        self.color_atlas.needs_rebinding = false;
+    #[test]
+    fn rejects_mismatched_sizes() {
+        let mut cache = ImageCache::default();
+        let result = cache.load_bytes(b"a", |_| {
+            Ok(DecodedImage {
+                width: 2,
+                height: 2,
+                pixels: vec![0; 4],
+            })
+        });
+        assert!(result.is_err());
+        assert!(cache.is_empty());
     }
 }
@@ -2,6 +2,7 @@ use std::marker::PhantomData;
 use std::sync::Arc;
 use std::time::Duration;
 
+use euclid::default::Point2D;
 use taffy::NodeId;
 
 use crate::tree::TreeCommand;
@@ -17,6 +18,8 @@ pub enum TreeEvent {
     BlurEvent(BlurEvent),
     ChangeEventF32(ChangeEvent<f32>),
     ChangeEventString(ChangeEvent<String>),
+    /// A change of a two dimensional value, such as the position picked in a color area.
+    ChangeEventPoint(ChangeEvent<Point2D<f32>>),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
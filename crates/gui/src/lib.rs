@@ -13,6 +13,7 @@
 
 use crate::events::{BlurEvent, ChangeEvent, EventContext, FocusEvent};
 
+use euclid::default::Point2D;
 use graphics::{Mesh, Systems, Vertex};
 use input::{KeyboardEvent, MouseEvent};
 use widgets::Widget;
@@ -20,6 +21,7 @@ use widgets::Widget;
 mod macros;
 
 mod events;
+pub mod modal;
 pub mod tree;
 pub mod widgets;
 
@@ -66,4 +68,9 @@ pub trait Element {
 
     fn change_event_f32(&mut self, ctx: &mut EventContext<ChangeEvent<f32>, Self::Message>) {}
     fn change_event_string(&mut self, ctx: &mut EventContext<ChangeEvent<String>, Self::Message>) {}
+    fn change_event_point(
+        &mut self,
+        ctx: &mut EventContext<ChangeEvent<Point2D<f32>>, Self::Message>,
+    ) {
+    }
 }
@@ -131,6 +131,15 @@ pub(crate) mod widget {
                 }
             }
 
+            fn change_event_point(&mut self, ctx: &mut EventContext<ChangeEvent<euclid::default::Point2D<f32>>, Self::Message>) {
+                match self {
+                    $( Widget::$variant(w) => w.change_event_point(ctx), )*
+
+                    Widget::Other(w) => w.change_event_point(ctx),
+                    Widget::Layout => {},
+                }
+            }
+
         };
     }
 
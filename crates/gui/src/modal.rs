@@ -0,0 +1,315 @@
+//! A dialog that sits above the rest of the tree and blocks it until it is closed.
+
+use color::{PremulColor, Srgb};
+use graphics::primitives::{RectangleOptions, TextOptions};
+use graphics::Rounding;
+use input::{Key, KeyboardEvent, KeyboardEventKind, MouseButton, MouseEventKind, SpecialKey};
+
+use crate::prelude::*;
+use crate::tree::{UITree, ZIndexProperties};
+use crate::widgets::background::reserve_stroke;
+use crate::widgets::button::{ButtonOptions, FADE_DURATION};
+use crate::widgets::{BackgroundWidget, ButtonWidget, ContainerWidget, TextWidget, Widget};
+
+/// How a modal was closed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ModalResult {
+    Confirm,
+    Cancel,
+    /// A result specific to the dialog, the meaning of the index is up to the owner.
+    Custom(usize),
+}
+
+/// A button along the bottom of a modal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModalAction {
+    pub label: String,
+    pub result: ModalResult,
+}
+
+impl ModalAction {
+    pub fn new(label: impl Into<String>, result: ModalResult) -> Self {
+        Self {
+            label: label.into(),
+            result,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ModalOptions {
+    pub title: String,
+    /// The buttons of the dialog, from left to right.
+    pub actions: Vec<ModalAction>,
+    /// The result given when Enter is pressed.
+    pub default_result: ModalResult,
+    /// The color drawn over the rest of the tree while the modal is open.
+    pub backdrop: PremulColor<Srgb>,
+    /// Whether clicking the backdrop, outside of the panel, gives [`ModalResult::Cancel`].
+    pub close_on_backdrop: bool,
+    pub panel: RectangleOptions,
+    pub button: ButtonOptions,
+    pub text: TextOptions,
+    pub width: f32,
+    /// The z-index of the modal, this should sit above every other floating panel.
+    pub z_index: usize,
+}
+
+impl Default for ModalOptions {
+    fn default() -> Self {
+        let button = |color: [f32; 4]| RectangleOptions {
+            color: PremulColor::new(color).into(),
+            rounding: Rounding::all(5.),
+            ..Default::default()
+        };
+        Self {
+            title: String::new(),
+            actions: vec![
+                ModalAction::new("Cancel", ModalResult::Cancel),
+                ModalAction::new("OK", ModalResult::Confirm),
+            ],
+            default_result: ModalResult::Confirm,
+            backdrop: PremulColor::new([0., 0., 0., 0.5]),
+            close_on_backdrop: false,
+            panel: RectangleOptions {
+                color: PremulColor::new([0.08, 0.08, 0.08, 1.]).into(),
+                rounding: Rounding::all(5.),
+                stroke_width: 3.,
+                stroke_color: PremulColor::new([0.9, 0.9, 0.9, 1.]).into(),
+                box_sizing: graphics::BoxSizing::ContentBox,
+            },
+            button: ButtonOptions {
+                pressed: button([0.32, 0.32, 0.32, 1.]),
+                active: button([0.25, 0.25, 0.25, 1.]),
+                hovered: button([0.2, 0.2, 0.2, 1.]),
+                normal: button([0.14, 0.14, 0.14, 1.]),
+                disabled: button([0.3, 0.14, 0.14, 0.5]),
+            },
+            text: TextOptions {
+                fill: Some(PremulColor::WHITE),
+                font_size: 16.,
+                ..Default::default()
+            },
+            width: 360.,
+            z_index: 2000,
+        }
+    }
+}
+
+/// A dialog with a title, a content area and a row of buttons, over a backdrop that dims and
+/// swallows the input meant for the rest of the tree.
+///
+/// Like the command palette, the modal lives detached from the tree while closed. Pressing one of
+/// its buttons sends the message built by `on_result`, after which the owner should close it.
+/// While open, the owner should route keyboard events to [`Modal::keyboard_event`] first, which
+/// handles Escape and Enter.
+pub struct Modal {
+    backdrop_node: NodeId,
+    content_node: NodeId,
+    default_result: ModalResult,
+    open: bool,
+}
+
+impl Modal {
+    pub fn build<M: Clone + 'static>(
+        tree: &mut UITree<Widget<M>>,
+        options: ModalOptions,
+        on_result: fn(ModalResult) -> M,
+    ) -> Self {
+        // Stopping every event at the backdrop keeps clicks from reaching the nodes below it.
+        let close_on_backdrop = options.close_on_backdrop;
+        let backdrop = BackgroundWidget::new(RectangleOptions::only_color(options.backdrop))
+            .mouse_handler(move |_, ctx| {
+                if close_on_backdrop
+                    && ctx.current_phase() == EventPhase::AtTarget
+                    && let MouseEventKind::Press { .. } = ctx.payload().kind
+                {
+                    ctx.push_messages(vec![on_result(ModalResult::Cancel)]);
+                }
+                match ctx.current_phase() {
+                    EventPhase::Direct | EventPhase::AtTarget | EventPhase::Bubbling => {
+                        ctx.stop_propagation();
+                    }
+                    _ => {}
+                }
+            });
+        let backdrop_node = tree.new_leaf_with_z(
+            backdrop.as_widget(),
+            Style::DEFAULT,
+            ZIndexProperties {
+                z_index: options.z_index,
+                isolate_z: true,
+            },
+        );
+
+        let panel_node = tree.new_leaf(
+            BackgroundWidget::new(options.panel).as_widget(),
+            reserve_stroke(
+                &options.panel,
+                Style {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    size: Size {
+                        width: Dimension::length(options.width),
+                        height: Dimension::auto(),
+                    },
+                    padding: Rect::<LengthPercentage>::length(10.),
+                    gap: Size::<LengthPercentage>::length(10.),
+                    ..Style::DEFAULT
+                },
+            ),
+        );
+        tree.add_child(backdrop_node, panel_node);
+
+        if !options.title.is_empty() {
+            let title_node = tree.new_leaf(
+                TextWidget::new(
+                    options.title,
+                    TextOptions {
+                        font_size: options.text.font_size * 1.25,
+                        ..options.text.clone()
+                    },
+                )
+                .as_widget(),
+                Style {
+                    size: Size {
+                        width: Dimension::percent(1.),
+                        height: Dimension::length(options.text.font_size * 1.25 * 1.5),
+                    },
+                    ..Style::DEFAULT
+                },
+            );
+            tree.add_child(panel_node, title_node);
+        }
+
+        let content_node = tree.new_leaf(
+            ContainerWidget::new(false).as_widget(),
+            Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                gap: Size::<LengthPercentage>::length(6.),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(panel_node, content_node);
+
+        let actions_node = tree.new_leaf(
+            ContainerWidget::new(false).as_widget(),
+            Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Row,
+                justify_content: Some(JustifyContent::FlexEnd),
+                gap: Size::<LengthPercentage>::length(6.),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(panel_node, actions_node);
+
+        for action in options.actions {
+            let result = action.result;
+            let button = ButtonWidget::new(options.button, false, false, FADE_DURATION)
+                .mouse_handler(move |_, ctx| {
+                    if let MouseEventKind::Press { button, .. } = ctx.payload().kind
+                        && button == MouseButton::Left
+                    {
+                        ctx.push_messages(vec![on_result(result)]);
+                    }
+                    match ctx.current_phase() {
+                        EventPhase::Direct | EventPhase::AtTarget | EventPhase::Bubbling => {
+                            ctx.stop_propagation();
+                        }
+                        _ => {}
+                    }
+                });
+            let button_node = tree.new_leaf(
+                button.as_widget(),
+                Style {
+                    padding: Rect {
+                        left: LengthPercentage::length(12.),
+                        right: LengthPercentage::length(12.),
+                        top: LengthPercentage::length(4.),
+                        bottom: LengthPercentage::length(4.),
+                    },
+                    ..Style::DEFAULT
+                },
+            );
+            // The labels are unbounded text, so they are given a rough size up front.
+            let label_width = action.label.chars().count() as f32 * options.text.font_size * 0.6;
+            let label_node = tree.new_leaf(
+                TextWidget::new(action.label, options.text.clone()).as_widget(),
+                Style {
+                    size: Size::<Dimension>::from_lengths(
+                        label_width,
+                        options.text.font_size * 1.5,
+                    ),
+                    ..Style::DEFAULT
+                },
+            );
+            tree.add_child(button_node, label_node);
+            tree.add_child(actions_node, button_node);
+        }
+
+        Self {
+            backdrop_node,
+            content_node,
+            default_result: options.default_result,
+            open: false,
+        }
+    }
+
+    /// The node between the title and the buttons, the body of the dialog is added to it.
+    pub const fn content_node(&self) -> NodeId {
+        self.content_node
+    }
+
+    pub const fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open<M: Clone>(&mut self, tree: &mut UITree<Widget<M>>) {
+        if self.open {
+            return;
+        }
+        // The backdrop covers the whole viewport so that it catches every click.
+        tree.set_style(
+            self.backdrop_node,
+            Style {
+                display: Display::Flex,
+                position: Position::Absolute,
+                justify_content: Some(JustifyContent::Center),
+                align_items: Some(AlignItems::Center),
+                size: Size::<Dimension>::from_lengths(tree.viewport.width, tree.viewport.height),
+                ..Style::DEFAULT
+            },
+        );
+        tree.add_child(tree.root_node(), self.backdrop_node);
+        self.open = true;
+    }
+
+    pub fn close<M: Clone>(&mut self, tree: &mut UITree<Widget<M>>) {
+        if !self.open {
+            return;
+        }
+        tree.remove_child(tree.root_node(), self.backdrop_node);
+        self.open = false;
+    }
+
+    /// Handles a keyboard event while the modal is open, closing it and returning the result when
+    /// Escape or Enter is pressed.
+    pub fn keyboard_event<M: Clone>(
+        &mut self,
+        tree: &mut UITree<Widget<M>>,
+        event: &KeyboardEvent,
+    ) -> Option<ModalResult> {
+        if !self.open {
+            return None;
+        }
+        let result = match &event.kind {
+            KeyboardEventKind::Press(Key::SpecialKey(SpecialKey::Escape)) => ModalResult::Cancel,
+            KeyboardEventKind::Press(Key::SpecialKey(SpecialKey::Enter)) => self.default_result,
+            _ => return None,
+        };
+        self.close(tree);
+        Some(result)
+    }
+}
@@ -71,6 +71,9 @@ impl HittableLayout for LayoutTree<Linear> {
             );
         }
         for node in tree.render_order.render_order() {
+            if !tree.is_hittable(*node) {
+                continue;
+            }
             bounding_boxes.push((
                 *node,
                 box_from_layout(map.get(&(*node).into()).unwrap().abs_layout),
@@ -134,12 +137,20 @@ impl HittableLayout for LayoutTree<Spatial> {
             );
         }
         for node in tree.render_order.render_order() {
+            if !tree.is_hittable(*node) {
+                continue;
+            }
             bounding_boxes.push((
                 *node,
                 box_from_layout(map.get(&(*node).into()).unwrap().abs_layout),
             ));
         }
-        let spatial_tree = RTree::bulk_load(map.values().cloned().collect());
+        let spatial_tree = RTree::bulk_load(
+            map.values()
+                .filter(|layout_node| tree.is_hittable(layout_node.node))
+                .cloned()
+                .collect(),
+        );
         Self {
             map,
             bounding: Spatial {
@@ -1,5 +1,7 @@
+use std::time::{Duration, Instant};
+
 use euclid::default::{Box2D, Point2D, Size2D};
-use graphics::{Drawable, Mesh, Systems, Vertex};
+use graphics::{Drawable, Mesh, MeshBatcher, Systems, Vertex};
 use input::{KeyboardEvent, MouseEvent, MouseEventKind};
 use taffy::{prelude::TaffyMaxContent, Layout, NodeId, Size, Style, TaffyTree};
 
@@ -34,6 +36,14 @@ pub struct UITree<T> {
     prev_mouse_hit: Option<NodeId>,
 
     render_cache: Option<Mesh<Vertex>>,
+    render_timings: RenderTimings,
+}
+
+/// How long each phase of the last [`UITree::render`] took.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderTimings {
+    pub layout: Duration,
+    pub tessellate: Duration,
 }
 
 /// State about the tree's current keyboard focus and mouse capture.
@@ -62,6 +72,9 @@ pub struct TreeNode<T> {
     pub inner: T,
     /// The z-index set for this node
     pub z_index: ZIndexProperties,
+    /// Whether mouse events can hit this node, when false they pass through to whatever is
+    /// beneath it.
+    pub hittable: bool,
 }
 
 // TODO: follow this
@@ -85,6 +98,7 @@ impl<T: Default> UITree<T> {
                 TreeNode {
                     inner: T::default(),
                     z_index: ZIndexProperties::DEFAULT,
+                    hittable: true,
                 },
             )
             .expect("`TaffyTree::new_leaf` cannot fail");
@@ -108,10 +122,16 @@ impl<T: Default> UITree<T> {
             prev_mouse_hit: None,
 
             render_cache: None,
+            render_timings: RenderTimings::default(),
         }
     }
 }
 impl<T> UITree<T> {
+    /// The phase timings of the last render, phases that were skipped take no time.
+    pub const fn last_render_timings(&self) -> RenderTimings {
+        self.render_timings
+    }
+
     pub fn new_leaf(&mut self, widget: impl Into<T>, layout: Style) -> NodeId {
         self.inner
             .new_leaf_with_context(
@@ -119,6 +139,7 @@ impl<T> UITree<T> {
                 TreeNode {
                     inner: widget.into(),
                     z_index: ZIndexProperties::DEFAULT,
+                    hittable: true,
                 },
             )
             .expect("`TaffyTree::new_leaf_with_context` cannot fail")
@@ -135,6 +156,7 @@ impl<T> UITree<T> {
                 TreeNode {
                     inner: widget.into(),
                     z_index,
+                    hittable: true,
                 },
             )
             .expect("`TaffyTree::new_leaf_with_context` cannot fail")
@@ -164,6 +186,7 @@ impl<T> UITree<T> {
         // TODO: taffy's implementation can panic if given an invalid nodeid.
         self.inner.remove_child(parent, child).unwrap();
         self.layout_dirty = true;
+        self.render_order_dirty = true;
     }
 
     /// Adds a node as a child of another node.
@@ -198,6 +221,22 @@ impl<T> UITree<T> {
         self.render_order = ZIndexOrdering::new(&self);
     }
 
+    pub fn is_hittable(&self, node: NodeId) -> bool {
+        self.inner
+            .get_node_context(node)
+            .expect("All nodes within a UITree must have a context.")
+            .hittable
+    }
+    /// Sets whether mouse events can hit the node, such as for purely informational text that
+    /// should not block what is behind it.
+    pub fn set_hittable(&mut self, node: NodeId, hittable: bool) {
+        self.inner
+            .get_node_context_mut(node)
+            .expect("All nodes within a UITree must have a context.")
+            .hittable = hittable;
+        self.layout_dirty = true;
+    }
+
     pub fn get_node(&self, node: NodeId) -> &T {
         &self
             .inner
@@ -273,10 +312,9 @@ impl<T: Element> UITree<T> {
             )
             .expect("failed to compute layout");
 
-        let start = std::time::Instant::now();
+        let _span = tracing::trace_span!("layout_tree").entered();
         self.layout_tree = LayoutTree::new(&self);
         self.layout_dirty = false;
-        tracing::trace!("Time taken to update layout TREE: {:?}", start.elapsed());
     }
 
     pub(crate) fn draw_node(&mut self, systems: &mut Systems, node: NodeId) -> &Mesh<Vertex> {
@@ -288,6 +326,17 @@ impl<T: Element> UITree<T> {
 }
 
 impl<T: Element> UITree<T> {
+    /// Drops the render cache of the tree and of every attached node, so everything is
+    /// tessellated again on the next render, e.g. after the atlases were recreated.
+    pub fn clear_caches(&mut self) {
+        let mut stack = vec![self.root_node];
+        while let Some(node) = stack.pop() {
+            self.get_node_mut(node).clear_cache();
+            stack.extend(self.children(node));
+        }
+        self.render_cache = None;
+    }
+
     fn is_node_dirty(&self, node: NodeId) -> bool {
         self.render_cache.is_none() || {
             let mut stack = vec![node];
@@ -519,6 +568,12 @@ where
                     node.change_event_string(ctx);
                 })
             }
+            TreeEvent::ChangeEventPoint(payload) => {
+                let ctx = EventContext::new(payload, false, target_node);
+                self.dispatch_generic_event(ctx, |node, ctx| {
+                    node.change_event_point(ctx);
+                })
+            }
         }
     }
 
@@ -609,37 +664,45 @@ where
 }
 
 impl<T: Element> Drawable<Vertex> for UITree<T> {
+    #[tracing::instrument(name = "UITree::render", level = "trace", skip_all)]
     fn render(&mut self, systems: &mut Systems) -> &Mesh<Vertex> {
-        let start = std::time::Instant::now();
         if self.render_order_dirty {
+            let _span = tracing::trace_span!("render_order").entered();
             self.render_order = ZIndexOrdering::new(&self);
             self.render_cache = None;
             self.render_order_dirty = false;
         }
-        tracing::trace!("Time taken to update render_order: {:?}", start.elapsed());
 
-        let start = std::time::Instant::now();
+        let start = Instant::now();
         if self.layout_dirty {
+            let _span = tracing::trace_span!("layout").entered();
             self.update_layout(systems);
         }
-        tracing::trace!("Time taken to update layout: {:?}", start.elapsed());
+        self.render_timings.layout = start.elapsed();
 
-        let start = std::time::Instant::now();
+        let _span = tracing::trace_span!("tessellate").entered();
+        let start = Instant::now();
         if !self.is_dirty() {
             if let Some(ref cache) = self.render_cache {
                 tracing::info!("hit cache");
-                tracing::trace!("Time taken to tessellate: {:?}", start.elapsed());
+                self.render_timings.tessellate = start.elapsed();
                 return cache;
             }
         }
 
-        let mut mesh = Mesh::empty();
-        for node in self.render_order.render_order().clone() {
-            mesh.append(self.draw_node(systems, node))
+        // The first pass tessellates every node so the second only has to copy cached meshes.
+        let render_order = self.render_order.render_order().clone();
+        let mut batcher = MeshBatcher::new();
+        for &node in &render_order {
+            batcher.reserve(self.draw_node(systems, node));
+        }
+        for node in render_order {
+            batcher.push(self.draw_node(systems, node));
         }
+        let mesh = batcher.finish();
 
         self.render_cache = Some(mesh.clone());
-        tracing::trace!("Time taken to tessellate: {:?}", start.elapsed());
+        self.render_timings.tessellate = start.elapsed();
         self.render_cache.as_ref().unwrap()
     }
     fn bounding_box(&self) -> Box2D<f32> {
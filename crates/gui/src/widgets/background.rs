@@ -1,11 +1,12 @@
 use std::time::{Duration, Instant};
 
-use euclid::default::Point2D;
+use euclid::default::{Point2D, Size2D, Vector2D};
 use graphics::{
     primitives::{Rectangle, RectangleOptions},
-    Drawable, Mesh, Systems, Vertex, ViewportCoordinates,
+    BoxSizing, Drawable, Mesh, Systems, Vertex, ViewportCoordinates,
 };
 use input::{KeyboardEvent, MouseEvent};
+use taffy::{LengthPercentage, Rect, Style};
 
 use crate::{
     events::{EventContext, EventHandler, HandlesEvent},
@@ -13,6 +14,41 @@ use crate::{
     Element,
 };
 
+/// Reserves the stroke of a background with `options` as the border of `style`, so that the
+/// padding and the neighbouring nodes are laid out around the stroke instead of under it.
+///
+/// The size in `style` still includes the border, as it does for every node.
+pub fn reserve_stroke(options: &RectangleOptions, style: Style) -> Style {
+    Style {
+        border: Rect::<LengthPercentage>::length(options.stroke_width),
+        ..style
+    }
+}
+
+/// The area given to the rectangle of a background, from the layout of its node.
+///
+/// A border box stroke is drawn inside its area, so it covers the whole layout box. A content box
+/// stroke is drawn around its area, so the area is the layout box without the border reserved by
+/// [`reserve_stroke`]. Without a reserved border, a content box stroke extends past the layout box
+/// and may overlap the neighbouring nodes.
+fn rect_area(layout: &taffy::Layout, box_sizing: BoxSizing) -> (Point2D<f32>, Size2D<f32>) {
+    let origin = Point2D::new(layout.location.x, layout.location.y);
+    let size = Size2D::new(layout.size.width, layout.size.height);
+    match box_sizing {
+        BoxSizing::BorderBox => (origin, size),
+        BoxSizing::ContentBox => {
+            let border = layout.border;
+            (
+                origin + Vector2D::new(border.left, border.top),
+                Size2D::new(
+                    (size.width - border.left - border.right).max(0.),
+                    (size.height - border.top - border.bottom).max(0.),
+                ),
+            )
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct BackgroundWidget<M: Clone> {
     rect: Rectangle<ViewportCoordinates>,
@@ -40,6 +76,10 @@ impl<M: Clone> BackgroundWidget<M> {
         }
     }
     pub fn change_options(&mut self, options: RectangleOptions) {
+        if options.box_sizing != self.rect.options().box_sizing {
+            // Forces the area to be recomputed on the next render.
+            self.layout = taffy::Layout::new();
+        }
         self.rect.update_options(options);
     }
 }
@@ -54,10 +94,8 @@ impl<M: Clone> Element for BackgroundWidget<M> {
     fn render(&mut self, systems: &mut Systems, layout: taffy::Layout) -> &Mesh<Vertex> {
         if self.layout != layout {
             self.layout = layout;
-            self.rect.update_area(
-                Point2D::new(layout.location.x, layout.location.y),
-                lyon::math::Size::new(layout.size.width, layout.size.height),
-            );
+            let (origin, size) = rect_area(&layout, self.rect.options().box_sizing);
+            self.rect.update_area(origin, size);
         }
         self.rect.render(systems)
     }
@@ -139,6 +177,9 @@ impl<M: Clone> TransitionBackgroundWidget<M> {
             self.rect.update_options(options);
             self.transition_start = None;
         }
+        if options.box_sizing != self.options.box_sizing {
+            self.layout = taffy::Layout::new();
+        }
         self.prev_options = self.rect.options().clone();
         self.options = options;
         self.tessellation = None;
@@ -164,10 +205,8 @@ impl<M: Clone> Element for TransitionBackgroundWidget<M> {
         if self.layout != layout {
             self.layout = layout;
             self.tessellation = None;
-            self.rect.update_area(
-                Point2D::new(layout.location.x, layout.location.y),
-                lyon::math::Size::new(layout.size.width, layout.size.height),
-            );
+            let (origin, size) = rect_area(&layout, self.options.box_sizing);
+            self.rect.update_area(origin, size);
         }
         if let Some(ref cache) = self.tessellation {
             return cache;
@@ -215,3 +254,79 @@ impl<M: Clone> Element for TransitionBackgroundWidget<M> {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use graphics::Rounding;
+    use taffy::prelude::*;
+
+    use super::*;
+
+    fn options(box_sizing: BoxSizing) -> RectangleOptions {
+        RectangleOptions {
+            rounding: Rounding::all(0.),
+            stroke_width: 3.,
+            box_sizing,
+            ..Default::default()
+        }
+    }
+
+    /// Lays out two 20x20 backgrounds in a row with a gap of 4, returning where the outer edge
+    /// of each stroke is drawn.
+    fn stroke_bounds(box_sizing: BoxSizing, reserve: bool) -> [(Point2D<f32>, Size2D<f32>); 2] {
+        let options = options(box_sizing);
+        let style = Style {
+            size: Size::<Dimension>::from_lengths(20., 20.),
+            ..Style::DEFAULT
+        };
+        let style = if reserve {
+            reserve_stroke(&options, style)
+        } else {
+            style
+        };
+        let mut tree = TaffyTree::<()>::new();
+        let first = tree.new_leaf(style.clone()).unwrap();
+        let second = tree.new_leaf(style).unwrap();
+        let root = tree
+            .new_with_children(
+                Style {
+                    display: Display::Flex,
+                    gap: Size::<LengthPercentage>::length(4.),
+                    ..Style::DEFAULT
+                },
+                &[first, second],
+            )
+            .unwrap();
+        tree.compute_layout(root, Size::MAX_CONTENT).unwrap();
+
+        [first, second].map(|node| {
+            let (origin, size) = rect_area(tree.layout(node).unwrap(), box_sizing);
+            match box_sizing {
+                BoxSizing::BorderBox => (origin, size),
+                BoxSizing::ContentBox => (
+                    origin - Vector2D::splat(options.stroke_width),
+                    size + Size2D::splat(options.stroke_width * 2.),
+                ),
+            }
+        })
+    }
+
+    #[test]
+    fn reserved_strokes_stay_within_the_layout() {
+        for box_sizing in [BoxSizing::BorderBox, BoxSizing::ContentBox] {
+            let [first, second] = stroke_bounds(box_sizing, true);
+            assert_eq!(first, (Point2D::new(0., 0.), Size2D::new(20., 20.)));
+            assert_eq!(second, (Point2D::new(24., 0.), Size2D::new(20., 20.)));
+        }
+    }
+
+    #[test]
+    fn unreserved_content_box_strokes_extend_past_the_layout() {
+        let [first, second] = stroke_bounds(BoxSizing::ContentBox, false);
+        assert_eq!(first, (Point2D::new(-3., -3.), Size2D::new(26., 26.)));
+        assert_eq!(second, (Point2D::new(21., -3.), Size2D::new(26., 26.)));
+
+        let [first, _] = stroke_bounds(BoxSizing::BorderBox, false);
+        assert_eq!(first, (Point2D::new(0., 0.), Size2D::new(20., 20.)));
+    }
+}
@@ -0,0 +1,274 @@
+use color::{Hwb, PremulColor, Srgb};
+use euclid::default::{Box2D, Point2D, Size2D};
+use graphics::{Mesh, Systems, Vertex, VertexKind};
+use input::{KeyboardEvent, MouseButton, MouseEvent, MouseEventKind};
+use taffy::Layout;
+
+use crate::{
+    events::{ChangeEvent, EventContext, EventHandler, EventPhase, Redraw},
+    widgets::{Widget, WidgetInteractionState},
+    Element,
+};
+
+/// The amount of cells along each axis of the gradient, the colors are only exact at the
+/// corners of the cells.
+const GRID_CELLS: usize = 16;
+/// Half the length of each arm of the crosshair.
+const CROSSHAIR_RADIUS: f32 = 6.;
+const CROSSHAIR_WIDTH: f32 = 1.5;
+
+/// Converts a hsv color to hsl, all components except the hue are in the `0..=100` range.
+pub fn hsv_to_hsl([hue, saturation, value]: [f32; 3]) -> [f32; 3] {
+    let (saturation, value) = (saturation / 100., value / 100.);
+    let lightness = value * (1. - saturation / 2.);
+    let saturation = if lightness <= 0. || lightness >= 1. {
+        0.
+    } else {
+        (value - lightness) / lightness.min(1. - lightness)
+    };
+    [hue, saturation * 100., lightness * 100.]
+}
+
+/// Converts a hsl color to hsv, all components except the hue are in the `0..=100` range.
+pub fn hsl_to_hsv([hue, saturation, lightness]: [f32; 3]) -> [f32; 3] {
+    let (saturation, lightness) = (saturation / 100., lightness / 100.);
+    let value = lightness + saturation * lightness.min(1. - lightness);
+    let saturation = if value <= 0. {
+        0.
+    } else {
+        2. * (1. - lightness / value)
+    };
+    [hue, saturation * 100., value * 100.]
+}
+
+/// The color of a hsv color, the saturation and value are in the `0..=100` range.
+pub fn hsv_color(hue: f32, saturation: f32, value: f32) -> PremulColor<Srgb> {
+    // hwb is a direct reformulation of hsv, so the color crate can do the conversion.
+    let whiteness = (100. - saturation) * value / 100.;
+    let blackness = 100. - value;
+    PremulColor::<Hwb>::new([hue, whiteness, blackness, 1.]).convert()
+}
+
+/// A square showing every saturation and value for a single hue, saturation increases to the
+/// right and value increases upwards.
+///
+/// Dragging inside the area moves the crosshair and emits a [`ChangeEvent`] with the saturation
+/// as `x` and the value as `y`, both in the `0..=100` range.
+#[derive(Clone)]
+pub struct ColorAreaWidget<M: Clone> {
+    tessellation: Option<Mesh<Vertex>>,
+    layout: Layout,
+
+    pub mouse_handler: EventHandler<MouseEvent, Self>,
+    pub keyboard_handler: EventHandler<KeyboardEvent, Self>,
+    pub change_handler: EventHandler<ChangeEvent<Point2D<f32>>, Self>,
+
+    hue: f32,
+    saturation: f32,
+    value: f32,
+
+    state: WidgetInteractionState,
+}
+
+crate::macros::event_handlers::impl_event_handler! {
+    ColorAreaWidget,
+    MouseEvent => mouse_handler,
+    KeyboardEvent => keyboard_handler,
+    ChangeEvent<Point2D<f32>> => change_handler,
+}
+
+impl<M: Clone> ColorAreaWidget<M> {
+    pub fn new(hue: f32, saturation: f32, value: f32) -> Self {
+        Self {
+            tessellation: None,
+            layout: Layout::new(),
+
+            mouse_handler: EventHandler::none(),
+            keyboard_handler: EventHandler::none(),
+            change_handler: EventHandler::none(),
+
+            hue,
+            saturation,
+            value,
+
+            state: WidgetInteractionState::new(false, false, false, true),
+        }
+    }
+
+    /// Changes the hue the gradient is shown for.
+    pub fn set_hue(&mut self, hue: f32) {
+        if self.hue != hue {
+            self.hue = hue;
+            self.tessellation = None;
+        }
+    }
+
+    /// Moves the crosshair without emitting a change event.
+    pub fn set_saturation_value(&mut self, saturation: f32, value: f32) {
+        if self.saturation != saturation || self.value != value {
+            self.saturation = saturation;
+            self.value = value;
+            self.tessellation = None;
+        }
+    }
+
+    pub const fn hue(&self) -> f32 {
+        self.hue
+    }
+    pub const fn saturation(&self) -> f32 {
+        self.saturation
+    }
+    pub const fn value(&self) -> f32 {
+        self.value
+    }
+
+    fn area(&self) -> Box2D<f32> {
+        Box2D::from_origin_and_size(
+            Point2D::new(self.layout.location.x, self.layout.location.y),
+            Size2D::new(self.layout.size.width, self.layout.size.height),
+        )
+    }
+
+    fn update_area(&mut self, ctx: &mut EventContext<MouseEvent, M>) {
+        let area = self.area();
+        let position = ctx.payload().position;
+
+        let saturation = ((position.x - area.min.x) / area.width()).clamp(0., 1.) * 100.;
+        let value = (1. - (position.y - area.min.y) / area.height()).clamp(0., 1.) * 100.;
+
+        if saturation != self.saturation || value != self.value {
+            ctx.request_redraw(Redraw::Now);
+            ctx.push_event(crate::events::TreeEvent::ChangeEventPoint(ChangeEvent {
+                new: Point2D::new(saturation, value),
+                old: Point2D::new(self.saturation, self.value),
+            }));
+            self.set_saturation_value(saturation, value);
+        }
+    }
+
+    fn tessellate(&self) -> Mesh<Vertex> {
+        let area = self.area();
+        let row = GRID_CELLS + 1;
+
+        let mut vertices = Vec::with_capacity(row * row);
+        for y in 0..row {
+            for x in 0..row {
+                let (fx, fy) = (x as f32 / GRID_CELLS as f32, y as f32 / GRID_CELLS as f32);
+                let color = hsv_color(self.hue, fx * 100., (1. - fy) * 100.);
+                vertices.push(Vertex::with_color(
+                    Point2D::new(
+                        area.min.x + area.width() * fx,
+                        area.min.y + area.height() * fy,
+                    ),
+                    VertexKind::ColorViewport(color),
+                ));
+            }
+        }
+        let mut indices = Vec::with_capacity(GRID_CELLS * GRID_CELLS * 6);
+        for y in 0..GRID_CELLS {
+            for x in 0..GRID_CELLS {
+                let top_left = (y * row + x) as u32;
+                let bottom_left = top_left + row as u32;
+                indices.extend([
+                    top_left,
+                    top_left + 1,
+                    bottom_left + 1,
+                    top_left,
+                    bottom_left + 1,
+                    bottom_left,
+                ]);
+            }
+        }
+        let mut mesh = Mesh { vertices, indices };
+
+        // The crosshair is white on dark colors and black on light ones, so it stays visible.
+        let center = Point2D::new(
+            area.min.x + area.width() * self.saturation / 100.,
+            area.max.y - area.height() * self.value / 100.,
+        );
+        let crosshair = if self.value > 50. && self.saturation < 50. {
+            PremulColor::BLACK
+        } else {
+            PremulColor::WHITE
+        };
+        let kind = VertexKind::ColorViewport(crosshair);
+        mesh.append(&Mesh::new_color_quad(
+            Box2D::from_points([center, center]).inflate(CROSSHAIR_RADIUS, CROSSHAIR_WIDTH / 2.),
+            kind,
+        ));
+        mesh.append(&Mesh::new_color_quad(
+            Box2D::from_points([center, center]).inflate(CROSSHAIR_WIDTH / 2., CROSSHAIR_RADIUS),
+            kind,
+        ));
+        mesh
+    }
+}
+
+impl<M: Clone> Element for ColorAreaWidget<M> {
+    type Message = M;
+
+    fn as_widget(self) -> Widget<Self::Message> {
+        Widget::ColorArea(self)
+    }
+
+    fn render(&mut self, _: &mut Systems, layout: Layout) -> &Mesh<Vertex> {
+        if self.layout != layout {
+            self.layout = layout;
+            self.tessellation = None;
+        }
+        if self.tessellation.is_none() {
+            self.tessellation = Some(self.tessellate());
+        }
+        self.tessellation.as_ref().unwrap()
+    }
+
+    fn mouse_event(&mut self, ctx: &mut EventContext<MouseEvent, Self::Message>) {
+        if ctx.current_phase() != EventPhase::Capturing {
+            match ctx.payload().kind {
+                MouseEventKind::Enter => self.state.hovered = true,
+                // The mouse stays captured while pressed, so dragging keeps picking colors after
+                // the cursor leaves the area, until the button is released.
+                MouseEventKind::Leave => self.state.hovered = false,
+                MouseEventKind::Press { button, .. } if button == MouseButton::Left => {
+                    ctx.request_mouse_capture(ctx.current_node());
+                    self.state.pressed = true;
+                    self.update_area(ctx);
+                }
+                MouseEventKind::Release { button, .. } if button == MouseButton::Left => {
+                    if self.state.pressed {
+                        ctx.request_mouse_release();
+                        self.state.pressed = false;
+                    }
+                }
+                MouseEventKind::Motion { .. } if self.state.pressed => {
+                    self.update_area(ctx);
+                }
+                _ => {}
+            };
+        }
+
+        self.mouse_handler.clone().handle(self, ctx);
+    }
+
+    fn keyboard_event(&mut self, ctx: &mut EventContext<KeyboardEvent, Self::Message>) {
+        self.keyboard_handler.clone().handle(self, ctx);
+    }
+    fn change_event_point(
+        &mut self,
+        ctx: &mut EventContext<ChangeEvent<Point2D<f32>>, Self::Message>,
+    ) {
+        self.change_handler.clone().handle(self, ctx);
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.tessellation.is_none()
+    }
+
+    fn clear_cache(&mut self) {
+        self.tessellation = None;
+    }
+
+    fn focusable(&self) -> bool {
+        false
+    }
+}
@@ -8,6 +8,7 @@ use input::{KeyboardEvent, MouseEvent};
 
 pub mod background;
 pub mod button;
+pub mod color_area;
 pub mod container;
 pub mod slider;
 pub mod svg;
@@ -17,6 +18,7 @@ pub mod text_input;
 pub use background::BackgroundWidget;
 pub use background::TransitionBackgroundWidget;
 pub use button::ButtonWidget;
+pub use color_area::ColorAreaWidget;
 pub use container::ContainerWidget;
 pub use slider::SliderWidget;
 pub use svg::SvgWidget;
@@ -31,6 +33,7 @@ pub enum Widget<M: Clone> {
 
     Background(BackgroundWidget<M>),
     Button(ButtonWidget<M>),
+    ColorArea(ColorAreaWidget<M>),
     Container(ContainerWidget<M>),
     Slider(SliderWidget<M>),
     Svg(SvgWidget<M>),
@@ -42,9 +45,12 @@ pub enum Widget<M: Clone> {
 impl_as_variants! {
     container => Container(ContainerWidget<M>),
     button => Button(ButtonWidget<M>),
+    color_area => ColorArea(ColorAreaWidget<M>),
     svg => Svg(SvgWidget<M>),
     background => Background(BackgroundWidget<M>),
     transition_background => TransitionBackground(TransitionBackgroundWidget<M>),
+    text => Text(TextWidget<M>),
+    text_input => TextInput(TextInputWidget<M>),
 }
 
 impl<M: Clone> Element for Widget<M> {
@@ -57,6 +63,7 @@ impl<M: Clone> Element for Widget<M> {
     delegate_widget!(
         Background,
         Button,
+        ColorArea,
         Container,
         Slider,
         Svg,
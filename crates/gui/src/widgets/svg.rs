@@ -42,6 +42,21 @@ impl<M: Clone> SvgWidget<M> {
             data,
             options.normal,
         );
+        Self::from_svg(inner, options)
+    }
+
+    /// Creates the widget, failing if the data is not a valid svg.
+    pub fn try_new(data: Vec<u8>, options: SvgOptions) -> anyhow::Result<Self> {
+        let inner = graphics::primitives::Svg::try_new(
+            Point2D::zero(),
+            lyon::math::Size::zero(),
+            data,
+            options.normal,
+        )?;
+        Ok(Self::from_svg(inner, options))
+    }
+
+    fn from_svg(inner: primitives::Svg<ViewportCoordinates>, options: SvgOptions) -> Self {
         Self {
             inner,
             options,
@@ -46,6 +46,29 @@ pub struct KeyboardEvent {
     pub kind: KeyboardEventKind,
 }
 
+impl KeyboardEvent {
+    /// Whether the event is a press of `Ctrl` and the given character, ignoring case.
+    pub fn is_ctrl_shortcut(&self, key: char) -> bool {
+        if !self.modifiers.contains(Modifiers::CTRL) {
+            return false;
+        }
+        let KeyboardEventKind::Press(Key::Character(text)) = &self.kind else {
+            return false;
+        };
+        let mut chars = text.chars();
+        let (Some(pressed), None) = (chars.next(), chars.next()) else {
+            return false;
+        };
+        // with control held, letters are reported as their control character (e.g. ctrl+a is
+        // 0x01), so map them back.
+        let pressed = match pressed as u32 {
+            code @ 0x01..=0x1a => char::from(b'a' + code as u8 - 1),
+            _ => pressed,
+        };
+        pressed.eq_ignore_ascii_case(&key)
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum KeyboardEventKind {
@@ -59,7 +82,14 @@ pub enum KeyboardEventKind {
 pub enum Key {
     SpecialKey(SpecialKey),
     Character(String),
-    Unknown,
+    /// A key that is neither a special key nor produces text, this keeps the raw codes so that
+    /// custom keybinds can still act on it.
+    Unknown {
+        /// The platform keysym of the key, e.g. an xkb keysym on wayland.
+        keysym: u32,
+        /// The hardware scancode of the key.
+        scancode: u32,
+    },
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -135,11 +165,24 @@ pub enum MouseButton {
 pub struct MouseEvent {
     pub position: Point2D<f32>,
     pub kind: MouseEventKind,
+    /// How hard a stylus is pressed, from 0 to 1, `None` for pointers without pressure.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pressure: Option<f32>,
 }
 
 impl MouseEvent {
     pub const fn new(position: Point2D<f32>, kind: MouseEventKind) -> Self {
-        Self { position, kind }
+        Self {
+            position,
+            kind,
+            pressure: None,
+        }
+    }
+    pub const fn with_pressure(self, pressure: f32) -> Self {
+        Self {
+            pressure: Some(pressure),
+            ..self
+        }
     }
     pub const fn enter(position: Point2D<f32>) -> Self {
         Self::new(position, MouseEventKind::Enter)
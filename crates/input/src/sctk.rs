@@ -120,7 +120,7 @@ pub const fn modifiers(modifiers: &keyboard::Modifiers) -> Modifiers {
 }
 
 pub fn key(key: &KeyEvent) -> Key {
-    let special_key = match key.keysym {
+    match key.keysym {
         Keysym::Shift_L => Key::SpecialKey(SpecialKey::LShift),
         Keysym::Shift_R => Key::SpecialKey(SpecialKey::RShift),
         Keysym::Control_L => Key::SpecialKey(SpecialKey::LCtrl),
@@ -173,17 +173,13 @@ pub fn key(key: &KeyEvent) -> Key {
         Keysym::Tab => Key::SpecialKey(SpecialKey::Tab),
         Keysym::KP_Tab => Key::SpecialKey(SpecialKey::Tab),
 
-        _ => Key::Unknown,
-    };
-    match special_key {
-        Key::Unknown => {
-            if let Some(repr) = &key.utf8 {
-                Key::Character(repr.clone())
-            } else {
-                Key::Unknown
-            }
-        }
-        _ => special_key,
+        _ => match &key.utf8 {
+            Some(repr) => Key::Character(repr.clone()),
+            None => Key::Unknown {
+                keysym: key.keysym.raw(),
+                scancode: key.raw_code,
+            },
+        },
     }
 }
 
@@ -1,3 +1,6 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 use anyhow::{Context, Result};
 use bytemuck::{Pod, Zeroable};
 use wgpu::BufferUsages;
@@ -13,31 +16,55 @@ pub struct State {
     pub queue: wgpu::Queue,
 
     pub texture_format: wgpu::TextureFormat,
+
+    /// The backends the adapter was picked from, used to find a new one after a device loss.
+    backends: wgpu::Backends,
+    /// Set from the device lost callback, see [`State::is_lost`].
+    lost: Arc<AtomicBool>,
+}
+
+/// Options for [`State::init`].
+#[derive(Clone, Copy, Debug)]
+pub struct InitOptions {
+    /// The backends the adapter may be picked from, the `WGPU_BACKEND` environment variable
+    /// (e.g. `vulkan` or `gl`) takes precedence over this.
+    ///
+    /// Only the backends wgpu was compiled with are available.
+    pub backends: wgpu::Backends,
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self {
+            backends: wgpu::Backends::all(),
+        }
+    }
 }
 
 impl State {
-    pub async fn init() -> Result<Self> {
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::LowPower,
-                force_fallback_adapter: false,
-                compatible_surface: None,
-            })
-            .await
-            .context("Failed to find an appropriate adapter")?;
+    /// Picks an adapter from the requested backends, falling back to every backend when none of
+    /// them has one.
+    pub async fn init(options: InitOptions) -> Result<Self> {
+        let backends = wgpu::Backends::from_env().unwrap_or(options.backends);
+        let (instance, adapter, backends) = match Self::request_adapter(backends).await {
+            Ok((instance, adapter)) => (instance, adapter, backends),
+            Err(err) if backends != wgpu::Backends::all() => {
+                tracing::warn!("{err:#}, falling back to all backends");
+                let (instance, adapter) = Self::request_adapter(wgpu::Backends::all()).await?;
+                (instance, adapter, wgpu::Backends::all())
+            }
+            Err(err) => return Err(err),
+        };
+        let info = adapter.get_info();
+        tracing::info!(
+            "using the {} adapter ({:?}) with the {:?} backend",
+            info.name,
+            info.device_type,
+            info.backend
+        );
 
-        let features = wgpu::Features::empty();
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: None,
-                required_features: features,
-                required_limits: Default::default(),
-                memory_hints: wgpu::MemoryHints::MemoryUsage,
-                trace: wgpu::Trace::Off,
-            })
-            .await
-            .context("Failed to create device")?;
+        let lost = Arc::new(AtomicBool::new(false));
+        let (device, queue) = Self::request_device(&adapter, &lost).await?;
 
         tracing::warn!("wgpu device limits: {:?}", device.limits());
 
@@ -51,8 +78,91 @@ impl State {
             queue,
 
             texture_format,
+
+            backends,
+            lost,
         })
     }
+
+    /// Whether the device was lost, e.g. by a driver reset or a suspend/resume cycle.
+    ///
+    /// Nothing rendered with a lost device reaches the screen, so [`State::recover`] should be
+    /// called before the next frame.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Acquire)
+    }
+
+    /// Blocks until the gpu has finished all submitted work, so resources can be torn down
+    /// without any of them still being in use.
+    pub fn wait_idle(&self) -> Result<()> {
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .context("failed to wait for the gpu to finish its work")?;
+        Ok(())
+    }
+
+    /// Replaces a lost device and queue with new ones, from a newly picked adapter.
+    ///
+    /// Every resource created with the old device is unusable afterwards, so the owners have to
+    /// recreate their pipelines, buffers and textures, and reconfigure their surfaces.
+    pub async fn recover(&mut self) -> Result<()> {
+        let (_, adapter) = Self::request_adapter(self.backends).await?;
+        let lost = Arc::new(AtomicBool::new(false));
+        let (device, queue) = Self::request_device(&adapter, &lost).await?;
+        tracing::info!(
+            "recovered from a lost device with {:?}",
+            adapter.get_info().name
+        );
+
+        self.adapter = adapter;
+        self.device = device;
+        self.queue = queue;
+        self.lost = lost;
+        Ok(())
+    }
+
+    async fn request_device(
+        adapter: &wgpu::Adapter,
+        lost: &Arc<AtomicBool>,
+    ) -> Result<(wgpu::Device, wgpu::Queue)> {
+        let features = wgpu::Features::empty();
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: None,
+                required_features: features,
+                required_limits: Default::default(),
+                memory_hints: wgpu::MemoryHints::MemoryUsage,
+                trace: wgpu::Trace::Off,
+            })
+            .await
+            .context("Failed to create device")?;
+
+        let lost = lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // Dropping the device, like `recover` does with the old one, also reports it as lost.
+            if !matches!(reason, wgpu::DeviceLostReason::Destroyed) {
+                tracing::error!("the wgpu device was lost ({reason:?}): {message}");
+                lost.store(true, Ordering::Release);
+            }
+        });
+        Ok((device, queue))
+    }
+
+    async fn request_adapter(backends: wgpu::Backends) -> Result<(wgpu::Instance, wgpu::Adapter)> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::LowPower,
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })
+            .await
+            .with_context(|| format!("Failed to find an adapter for the {backends:?} backends"))?;
+        Ok((instance, adapter))
+    }
 }
 
 /// Owned growable buffer, the underlying buffer is destroyed when dropped.
@@ -1 +1,247 @@
-pub struct WlClipboard;
+//! Reads images from the wayland clipboard, so they can be pasted onto a canvas.
+//!
+//! Copying from the overlay keeps the primitives in [`Data::clipboard`](crate::Data::clipboard)
+//! instead of offering them to other clients, so only the selection of other clients is read.
+//! Pngs are the only images that can be decoded, other image types are ignored.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+
+use smithay_client_toolkit::data_device_manager::data_device::{DataDevice, DataDeviceHandler};
+use smithay_client_toolkit::data_device_manager::data_offer::{
+    DataOfferHandler, DragOffer, SelectionOffer,
+};
+use smithay_client_toolkit::data_device_manager::data_source::DataSourceHandler;
+use smithay_client_toolkit::data_device_manager::{DataDeviceManagerState, WritePipe};
+use smithay_client_toolkit::reexports::calloop::PostAction;
+use tracing::{trace, warn};
+use wayland_backend::client::ObjectId;
+use wayland_client::globals::{BindError, GlobalList};
+use wayland_client::protocol::{
+    wl_data_device::WlDataDevice, wl_data_device_manager::DndAction, wl_data_source::WlDataSource,
+    wl_seat::WlSeat, wl_surface::WlSurface,
+};
+use wayland_client::{Connection, Proxy, QueueHandle};
+
+use crate::views::View;
+use crate::{ShareableState, State};
+
+pub const PNG_MIME_TYPE: &str = "image/png";
+/// The largest image read from the clipboard, the decoder rejects anything that would not fit in
+/// the atlas long before this.
+const MAX_IMAGE_BYTES: usize = 256 * 1024 * 1024;
+/// How much of the image is read each time the pipe is readable.
+const READ_CHUNK: usize = 64 * 1024;
+
+pub struct WlClipboard {
+    manager: DataDeviceManagerState,
+    /// The data device of each seat, which holds the selection of that seat.
+    devices: HashMap<ObjectId, DataDevice>,
+    /// Whether the selection changed after the last copy inside the overlay, pasting takes
+    /// whichever was copied last.
+    selection_is_newer: bool,
+}
+
+impl WlClipboard {
+    pub fn bind(globals: &GlobalList, qh: &QueueHandle<State>) -> Result<Self, BindError> {
+        Ok(Self {
+            manager: DataDeviceManagerState::bind(globals, qh)?,
+            devices: HashMap::new(),
+            selection_is_newer: false,
+        })
+    }
+
+    pub fn add_seat(&mut self, seat: &WlSeat, qh: &QueueHandle<State>) {
+        self.devices
+            .insert(seat.id(), self.manager.get_data_device(qh, seat));
+    }
+
+    pub fn remove_seat(&mut self, seat: &WlSeat) {
+        self.devices.remove(&seat.id());
+    }
+
+    /// Called when something is copied inside the overlay, which is pasted before the selection
+    /// from then on.
+    pub fn copied(&mut self) {
+        self.selection_is_newer = false;
+    }
+
+    /// The selection of any seat that offers a png, unless something was copied inside the
+    /// overlay since it was set.
+    pub fn png_offer(&self) -> Option<SelectionOffer> {
+        if !self.selection_is_newer {
+            return None;
+        }
+        self.devices
+            .values()
+            .filter_map(|device| device.data().selection_offer())
+            .find(|offer| {
+                offer.with_mime_types(|mime_types| {
+                    mime_types
+                        .iter()
+                        .any(|mime_type| mime_type == PNG_MIME_TYPE)
+                })
+            })
+    }
+}
+
+/// Reads the png of `offer` from the event loop, pasting it onto the canvas view presenting
+/// `surface` once all of it arrived.
+pub(crate) fn paste_png(state: &ShareableState, offer: &SelectionOffer, surface: WlSurface) {
+    let pipe = match offer.receive(PNG_MIME_TYPE.to_owned()) {
+        Ok(pipe) => pipe,
+        Err(err) => {
+            warn!("failed to read the clipboard: {err}");
+            return;
+        }
+    };
+
+    let mut png = vec![];
+    let mut chunk = vec![0; READ_CHUNK];
+    let inserted = state.loop_handle.insert_source(pipe, move |_, file, state| {
+        let mut file: &File = file.as_ref();
+        let read = match file.read(&mut chunk) {
+            Ok(read) => read,
+            Err(err) if matches!(err.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock) => {
+                return PostAction::Continue;
+            }
+            Err(err) => {
+                warn!("failed to read the clipboard: {err}");
+                return PostAction::Remove;
+            }
+        };
+        if read > 0 {
+            png.extend_from_slice(&chunk[..read]);
+            if png.len() > MAX_IMAGE_BYTES {
+                warn!("the clipboard image is larger than {MAX_IMAGE_BYTES} bytes, not pasting it");
+                return PostAction::Remove;
+            }
+            return PostAction::Continue;
+        }
+
+        trace!("read a {} byte png from the clipboard", png.len());
+        // The view may have been closed while the image was being read.
+        if let Some(view) = state
+            .views
+            .canvas_views()
+            .into_iter()
+            .find(|view| view.surface() == &surface)
+        {
+            view.paste_image(&state.shareable, &png);
+        }
+        PostAction::Remove
+    });
+    if let Err(err) = inserted {
+        warn!("failed to read the clipboard: {err}");
+    }
+}
+
+impl DataDeviceHandler for State {
+    fn enter(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _data_device: &WlDataDevice,
+        _x: f64,
+        _y: f64,
+        _surface: &WlSurface,
+    ) {
+    }
+
+    fn leave(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _data_device: &WlDataDevice) {}
+
+    fn motion(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _data_device: &WlDataDevice,
+        _x: f64,
+        _y: f64,
+    ) {
+    }
+
+    fn selection(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _data_device: &WlDataDevice,
+    ) {
+        trace!("the clipboard selection changed");
+        if let Some(clipboard) = &mut self.shareable.wayland.clipboard {
+            clipboard.selection_is_newer = true;
+        }
+    }
+
+    fn drop_performed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _data_device: &WlDataDevice,
+    ) {
+    }
+}
+
+// Nothing is dragged onto the overlay, and it never offers data itself.
+impl DataOfferHandler for State {
+    fn source_actions(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _offer: &mut DragOffer,
+        _actions: DndAction,
+    ) {
+    }
+
+    fn selected_action(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _offer: &mut DragOffer,
+        _actions: DndAction,
+    ) {
+    }
+}
+
+impl DataSourceHandler for State {
+    fn accept_mime(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _source: &WlDataSource,
+        _mime: Option<String>,
+    ) {
+    }
+
+    fn send_request(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _source: &WlDataSource,
+        _mime: String,
+        _fd: WritePipe,
+    ) {
+    }
+
+    fn cancelled(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _source: &WlDataSource) {}
+
+    fn dnd_dropped(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _source: &WlDataSource) {
+    }
+
+    fn dnd_finished(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _source: &WlDataSource,
+    ) {
+    }
+
+    fn action(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _source: &WlDataSource,
+        _action: DndAction,
+    ) {
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use smithay_client_toolkit::reexports::calloop::timer::{TimeoutAction, Timer};
+use tracing::{trace, warn};
+
+use crate::views::LayerShellView;
+use crate::{OverlayMode, State};
+
+/// Leaves interactive mode after a period without input, so the overlay gets out of the way
+/// once an annotation is done.
+#[derive(Copy, Clone, Debug)]
+pub struct IdleTimeout {
+    /// How long the overlay waits for input, a zero duration disables the timeout.
+    pub timeout: Duration,
+    /// The mode the overlay switches to once the timeout elapses.
+    pub mode: OverlayMode,
+}
+
+impl Default for IdleTimeout {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::ZERO,
+            mode: OverlayMode::Hidden,
+        }
+    }
+}
+
+impl IdleTimeout {
+    pub fn is_enabled(&self) -> bool {
+        !self.timeout.is_zero()
+    }
+}
+
+impl State {
+    /// Changes the mode of every canvas view.
+    pub(crate) fn set_overlay_mode(&mut self, mode: OverlayMode) {
+        self.shareable.data.mode = mode;
+        for view in &mut self.views.canvas_views() {
+            if let Err(e) = view.set_mode(&mut self.shareable, mode) {
+                warn!("failed to change the mode of a canvas view: {e:?}");
+            }
+        }
+        self.reset_idle_timer();
+    }
+
+    /// Whether any view is in the middle of a drag or an animation, which keeps the overlay
+    /// from timing out.
+    fn is_busy(&mut self) -> bool {
+        self.views.canvas_views().iter().any(|view| view.is_busy())
+    }
+
+    /// Restarts the idle timeout, called on every input event.
+    ///
+    /// The timer only runs in interactive mode, and waits for drags and animations to end.
+    pub(crate) fn reset_idle_timer(&mut self) {
+        let data = &mut self.shareable.data;
+        if let Some(token) = data.idle_timer.take() {
+            self.shareable.loop_handle.remove(token);
+        }
+        let idle = data.idle_timeout;
+        if !idle.is_enabled() || !matches!(data.mode, OverlayMode::Interactive) {
+            return;
+        }
+
+        let token = self.shareable.loop_handle.insert_source(
+            Timer::from_duration(idle.timeout),
+            move |_, _, state| {
+                if state.is_busy() {
+                    return TimeoutAction::ToDuration(idle.timeout);
+                }
+                trace!(
+                    "overlay was idle for {:?}, switching to {:?}",
+                    idle.timeout,
+                    idle.mode
+                );
+                state.shareable.data.idle_timer = None;
+                state.set_overlay_mode(idle.mode);
+                TimeoutAction::Drop
+            },
+        );
+        match token {
+            Ok(token) => self.shareable.data.idle_timer = Some(token),
+            Err(e) => warn!("failed to insert the idle timer into the event loop: {e:?}"),
+        }
+    }
+}
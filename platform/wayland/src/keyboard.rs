@@ -79,12 +79,18 @@ impl KeyboardHandler for State {
         _serial: u32,
         event: KeyEvent,
     ) {
+        self.reset_idle_timer();
         let Some(kb) = self.keyboards.values().find(|x| x.id() == keyboard.id()) else {
             warn!("keyboard event `press_key` dispatched for keyboard not in state");
             return;
         };
 
-        if event.keysym == Keysym::Escape {
+        let captures_escape = kb
+            .last_surface
+            .as_ref()
+            .and_then(|surface| self.views.from_surface(surface))
+            .is_some_and(|view| view.captures_escape());
+        if event.keysym == Keysym::Escape && !captures_escape {
             for view in &mut self.views.canvas_views() {
                 let _ = view.set_mode(&mut self.shareable, crate::OverlayMode::Hidden);
                 if let Err(e) = view.render(&mut self.shareable) {
@@ -96,7 +102,10 @@ impl KeyboardHandler for State {
         let Some(surface) = &kb.last_surface else {
             return;
         };
-        let view = self.views.from_surface(&surface);
+        let Some(view) = self.views.from_surface(&surface) else {
+            warn!("keyboard event `press_key` dispatched for a surface without a view");
+            return;
+        };
         view.keyboard_event(
             &mut self.shareable,
             &KeyEventKind::Press((event, kb.last_modifiers)),
@@ -111,6 +120,7 @@ impl KeyboardHandler for State {
         _serial: u32,
         event: KeyEvent,
     ) {
+        self.reset_idle_timer();
         let Some(kb) = self.keyboards.values().find(|x| x.id() == keyboard.id()) else {
             warn!("keyboard event `release_key` dispatched for keyboard not in state");
             return;
@@ -118,7 +128,10 @@ impl KeyboardHandler for State {
         let Some(surface) = &kb.last_surface else {
             return;
         };
-        let view = self.views.from_surface(&surface);
+        let Some(view) = self.views.from_surface(&surface) else {
+            warn!("keyboard event `release_key` dispatched for a surface without a view");
+            return;
+        };
 
         view.keyboard_event(
             &mut self.shareable,
@@ -135,6 +148,7 @@ impl KeyboardHandler for State {
         modifiers: Modifiers,
         _layout: u32,
     ) {
+        self.reset_idle_timer();
         let Some(kb) = self
             .keyboards
             .values_mut()
@@ -148,10 +162,17 @@ impl KeyboardHandler for State {
         let Some(surface) = &kb.last_surface else {
             return;
         };
-        let view = self.views.from_surface(&surface);
+        let Some(view) = self.views.from_surface(&surface) else {
+            warn!("keyboard event `update_modifiers` dispatched for a surface without a view");
+            return;
+        };
         view.keyboard_event(
             &mut self.shareable,
             &KeyEventKind::ModifiersChanged(modifiers),
         );
+        // Tools can show a different cursor while a modifier is held.
+        for (_, themed_pointer) in self.pointers.values() {
+            view.update_cursor(&mut self.shareable, themed_pointer);
+        }
     }
 }
@@ -7,6 +7,7 @@ use std::{
 };
 
 use anyhow::{Context, Result};
+use canvas::pipeline::ColorCorrection;
 use canvas::RedrawRequest;
 use fractional_scale::FractionalScaleHandler;
 use keyboard::Keyboard;
@@ -14,14 +15,15 @@ use tracing::{info, instrument, trace, warn};
 
 use smithay_client_toolkit::{
     compositor::{CompositorHandler, CompositorState},
-    delegate_compositor, delegate_keyboard, delegate_layer, delegate_output, delegate_pointer,
-    delegate_registry, delegate_seat, delegate_shm,
-    output::{OutputHandler, OutputState},
+    delegate_compositor, delegate_data_device, delegate_keyboard, delegate_layer, delegate_output,
+    delegate_pointer, delegate_registry, delegate_seat, delegate_shm,
+    output::{OutputHandler, OutputInfo, OutputState},
     reexports::{
         calloop::{
             self,
+            signals::{Signal, Signals},
             timer::{TimeoutAction, Timer},
-            EventLoop, LoopHandle,
+            EventLoop, LoopHandle, RegistrationToken,
         },
         calloop_wayland_source::WaylandSource,
     },
@@ -36,22 +38,63 @@ use views::LayerShellView;
 use wayland_backend::client::ObjectId;
 use wayland_client::{
     globals::registry_queue_init,
-    protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    protocol::{
+        wl_output::{Transform, WlOutput},
+        wl_surface::WlSurface,
+    },
     Connection, EventQueue, QueueHandle,
 };
 
 pub mod clipboard;
 pub mod fractional_scale;
 pub mod global_binds;
+pub mod idle;
 mod keyboard;
 mod pointer;
 mod seat;
+pub mod surface_size;
+pub mod tablet;
 pub mod viewporter;
 pub mod views;
 
 use crate::global_binds::{ShortcutEvents, Shortcuts, WaylandKeybinds};
+use crate::idle::IdleTimeout;
+use crate::tablet::{TabletEvent, TabletHandler, TabletSeat};
 use crate::views::{View, ViewManager};
 
+/// Catches SIGINT and SIGTERM for [`WaylandConnection::run`], so the overlay stops and cleans up
+/// instead of being killed.
+///
+/// The signals are only blocked for the calling thread and the threads it spawns afterwards, so
+/// this has to be called before any other thread is started, otherwise they are still delivered
+/// to those threads and kill the process.
+pub fn termination_signals() -> Result<Signals> {
+    Signals::new(&[Signal::SIGINT, Signal::SIGTERM])
+        .context("failed to block the termination signals")
+}
+
+/// Where the canvas saves its sessions, under the user data directory.
+pub fn get_session_directory() -> Result<std::path::PathBuf> {
+    Ok(dirs::data_dir()
+        .context("failed to get the data dir, please set $XDG_DATA_HOME or $HOME")?
+        .join("draw")
+        .join("sessions"))
+}
+
+/// The file the canvas keeps its interface settings in, under the user data directory.
+pub fn get_ui_settings_path() -> Result<std::path::PathBuf> {
+    Ok(dirs::data_dir()
+        .context("failed to get the data dir, please set $XDG_DATA_HOME or $HOME")?
+        .join("draw")
+        .join("ui.json"))
+}
+
+pub fn get_export_directory() -> Result<std::path::PathBuf> {
+    Ok(dirs::picture_dir()
+        .context("failed to get the pictures dir, please set $XDG_PICTURES_DIR or $HOME")?
+        .join("draw"))
+}
+
 // FIXME: use this
 #[allow(unused)]
 pub fn is_wayland() -> bool {
@@ -62,7 +105,8 @@ pub fn is_wayland() -> bool {
 }
 
 pub struct WaylandConnection {
-    event_queue: EventQueue<State>,
+    /// Moved into the event loop once it runs, see [`WaylandConnection::run`].
+    event_queue: Option<EventQueue<State>>,
     event_loop: EventLoop<'static, State>,
     pub state: State,
 }
@@ -72,6 +116,7 @@ pub struct State {
 
     pointers: HashMap<ObjectId, (ObjectId, ThemedPointer)>,
     keyboards: HashMap<ObjectId, Keyboard>,
+    tablet_seats: HashMap<ObjectId, TabletSeat>,
 
     pub views: ViewManager,
 }
@@ -83,8 +128,14 @@ pub struct WaylandState {
     queue_handle: QueueHandle<State>,
     registry_state: RegistryState,
     seat_state: SeatState,
-    fractional_state: crate::fractional_scale::FractionalScaleState,
-    viewporter: crate::viewporter::ViewporterState,
+    /// Optional, without it the surface is scaled by the integer output scale.
+    fractional_state: Option<crate::fractional_scale::FractionalScaleState>,
+    /// Optional, without it the surface is scaled by the integer output scale.
+    viewporter: Option<crate::viewporter::ViewporterState>,
+    /// Optional, without it tablets are only seen as pointers and report no pressure.
+    tablet_state: Option<crate::tablet::TabletState>,
+    /// Optional, without it images can't be pasted from other clients.
+    clipboard: Option<crate::clipboard::WlClipboard>,
     shm_state: Shm,
     layer_shell: LayerShell,
 }
@@ -95,12 +146,21 @@ pub struct ShareableState {
 
     wgpu: renderer::State,
     app_pipeline: canvas::pipeline::DrawPipeline,
+    /// Incremented whenever the device is recreated, views compare it against the generation
+    /// their resources were created with.
+    device_generation: u64,
     loop_handle: LoopHandle<'static, State>,
 }
 
 pub struct Data {
     pub mode: OverlayMode,
     pub first_surface: Option<WlSurface>,
+    pub idle_timeout: IdleTimeout,
+    idle_timer: Option<RegistrationToken>,
+    /// Primitives copied from any canvas view, so they can be pasted on another output.
+    pub clipboard: Option<String>,
+    /// The color correction of each output by name, outputs missing from here are left as is.
+    pub color_corrections: HashMap<String, ColorCorrection>,
     // pub toolbar: crate::iced::IcedProgram<Toolbar>,
 }
 
@@ -121,6 +181,26 @@ pub enum OverlayMode {
     Hidden,
 }
 
+impl ShareableState {
+    /// Recreates the device and the shared pipeline if the device was lost, the views recreate
+    /// their own resources once they see the new [`ShareableState::device_generation`].
+    pub(crate) fn recover_lost_device(&mut self) -> Result<()> {
+        if !self.wgpu.is_lost() {
+            return Ok(());
+        }
+        warn!("the gpu device was lost, recreating it");
+        // Rendering happens from the event loop inside the tokio runtime, and creating a device
+        // resolves immediately on native backends.
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(self.wgpu.recover())
+        })?;
+        self.app_pipeline =
+            canvas::pipeline::DrawPipeline::new(&self.wgpu.device, self.wgpu.texture_format);
+        self.device_generation += 1;
+        Ok(())
+    }
+}
+
 impl WaylandConnection {
     #[instrument(name = "WaylandConnection::new")]
     pub async fn new() -> Result<WaylandConnection> {
@@ -154,17 +234,30 @@ impl WaylandConnection {
         let layer_shell =
             LayerShell::bind(&globals, &queue_handle).context("layer shell is not available")?;
 
+        // Fractional scaling needs both of these, otherwise we fall back to integer scaling.
         let fractional_state =
             crate::fractional_scale::FractionalScaleState::bind(&globals, &queue_handle)
-                .context("fractional scale manager is not available")?;
-
+                .inspect_err(|err| warn!("fractional scale manager is not available: {err}"))
+                .ok();
         let viewporter = crate::viewporter::ViewporterState::bind(&globals, &queue_handle)
-            .context("viewporter is not available")?;
+            .inspect_err(|err| warn!("viewporter is not available: {err}"))
+            .ok();
+        if fractional_state.is_some() && viewporter.is_some() {
+            info!("using wp_fractional_scale_v1 and wp_viewporter for fractional scaling");
+        } else {
+            info!("using wl_surface buffer scale for integer scaling");
+        }
+        let tablet_state = crate::tablet::TabletState::bind(&globals, &queue_handle)
+            .inspect_err(|err| warn!("tablet manager is not available: {err}"))
+            .ok();
+        let clipboard = crate::clipboard::WlClipboard::bind(&globals, &queue_handle)
+            .inspect_err(|err| warn!("data device manager is not available: {err}"))
+            .ok();
 
         trace!("binding to shm");
         let shm_state = Shm::bind(&globals, &queue_handle).context("shm is not available")?;
 
-        let wgpu = renderer::State::init().await?;
+        let wgpu = renderer::State::init(Default::default()).await?;
 
         let wayland = WaylandState {
             connection,
@@ -176,11 +269,17 @@ impl WaylandConnection {
             shm_state,
             fractional_state,
             viewporter,
+            tablet_state,
+            clipboard,
             layer_shell,
         };
         let data = Data {
             mode: OverlayMode::Hidden,
             first_surface: None,
+            idle_timeout: IdleTimeout::default(),
+            idle_timer: None,
+            clipboard: None,
+            color_corrections: HashMap::new(),
         };
 
         let app_pipeline = canvas::pipeline::DrawPipeline::new(&wgpu.device, wgpu.texture_format);
@@ -190,6 +289,7 @@ impl WaylandConnection {
             data,
             wgpu,
             app_pipeline,
+            device_generation: 0,
             loop_handle: event_loop.handle(),
         };
 
@@ -202,13 +302,14 @@ impl WaylandConnection {
 
             pointers: HashMap::new(),
             keyboards: HashMap::new(),
+            tablet_seats: HashMap::new(),
 
             views,
         };
 
         let mut wayland_connection = WaylandConnection {
             event_loop,
-            event_queue,
+            event_queue: Some(event_queue),
             state,
         };
 
@@ -223,12 +324,16 @@ impl WaylandConnection {
     #[instrument(name = "WaylandConnection::round_trip", skip_all)]
     fn round_trip(&mut self) -> Result<usize> {
         self.event_queue
+            .as_mut()
+            .context("the event queue was moved into the event loop")?
             .roundtrip(&mut self.state)
             .context("event queue failed to do a round trip")
     }
 
-    #[instrument(name = "WaylandConnection::run", skip(self))]
-    pub async fn run(mut self) -> Result<()> {
+    /// Runs the event loop until the overlay closes or `signals` reports a termination signal,
+    /// see [`termination_signals`].
+    #[instrument(name = "WaylandConnection::run", skip_all)]
+    pub async fn run(mut self, signals: Result<Signals>) -> Result<()> {
         let surface = self
             .state
             .shareable
@@ -241,10 +346,11 @@ impl WaylandConnection {
         // not cleaned up before it returns.
         let keybind_source = WaylandKeybinds::build_source().await;
 
-        let ws = WaylandSource::new(
-            self.state.shareable.wayland.connection.clone(),
-            self.event_queue,
-        );
+        let event_queue = self
+            .event_queue
+            .take()
+            .context("the event loop is already running")?;
+        let ws = WaylandSource::new(self.state.shareable.wayland.connection.clone(), event_queue);
         ws.insert(self.event_loop.handle())
             .expect("Failed to insert wayland event source into event loop");
 
@@ -260,9 +366,60 @@ impl WaylandConnection {
             }
         }
 
-        self.event_loop
+        let signal_token = match signals {
+            Ok(source) => {
+                let signal = self.event_loop.get_signal();
+                let token = self
+                    .event_loop
+                    .handle()
+                    .insert_source(source, move |event, _, _| {
+                        info!("received {:?}, shutting down", event.signal());
+                        signal.stop();
+                    })
+                    .expect("failed to insert the signal source into event loop");
+                Some(token)
+            }
+            Err(e) => {
+                warn!("failed to listen for termination signals: {e:?}");
+                None
+            }
+        };
+
+        let result = self
+            .event_loop
             .run(None, &mut self.state, |_| {})
-            .context("failed to run event loop")
+            .context("failed to run event loop");
+        // Dropping the source unblocks the signals again, so a second one exits right away in
+        // case shutting down hangs.
+        if let Some(token) = signal_token {
+            self.event_loop.handle().remove(token);
+        }
+        self.shutdown();
+        result
+    }
+
+    /// Tears down every view and flushes the destroy requests to the compositor, which runs once
+    /// the event loop stops. Calling it again does nothing.
+    ///
+    /// The gpu is given time to finish first, then each view drops its gpu resources before its
+    /// surfaces. The device itself is dropped with the connection, after everything created from
+    /// it is gone.
+    #[instrument(name = "WaylandConnection::shutdown", skip_all)]
+    pub fn shutdown(&mut self) {
+        let state = &mut self.state;
+        if let Err(err) = state.shareable.wgpu.wait_idle() {
+            warn!("{err:#}");
+        }
+        for view in state.views.layer_shell_views.drain(..) {
+            view.destroy();
+        }
+        state.shareable.data.first_surface = None;
+        state.pointers.clear();
+        state.keyboards.clear();
+        if let Err(err) = state.shareable.wayland.connection.flush() {
+            warn!("failed to flush the final requests to the compositor: {err}");
+        }
+        info!("shut down");
     }
     #[instrument(name = "WaylandConnection::get_displays", skip(self))]
     fn get_displays(&self) -> Result<(u32, u32, u32)> {
@@ -274,16 +431,13 @@ impl WaylandConnection {
             .find_map(|output| {
                 trace!("Checking output {:?}", output);
                 if let Some(info) = self.state.shareable.wayland.output_state.info(&output) {
-                    trace!("Found info: output modes");
-                    let current_mode = info.modes.iter().find(|mode| {
-                        trace!("{mode:?}");
-                        mode.current
-                    })?;
+                    trace!("Found info: output modes {:?}", info.modes);
+                    let (width, height) = output_dimensions(&info)?;
                     return Some((
-                        TryInto::<u32>::try_into(current_mode.dimensions.0)
+                        TryInto::<u32>::try_into(width)
                             .context("display width must be positive")
                             .ok()?,
-                        TryInto::<u32>::try_into(current_mode.dimensions.1)
+                        TryInto::<u32>::try_into(height)
                             .context("display height must be positive")
                             .ok()?,
                         TryInto::<u32>::try_into(info.scale_factor)
@@ -298,6 +452,27 @@ impl WaylandConnection {
             .context("failed to find the current monitor")
     }
 
+    /// Changes how long the overlay stays interactive without input.
+    pub fn set_idle_timeout(&mut self, idle_timeout: IdleTimeout) {
+        self.state.shareable.data.idle_timeout = idle_timeout;
+        self.state.reset_idle_timer();
+    }
+
+    /// Sets the color correction of the output with the given name, including its current view.
+    pub fn set_color_correction(&mut self, output_name: String, correction: ColorCorrection) {
+        let shareable = &mut self.state.shareable;
+        for view in self.state.views.canvas_views() {
+            if view.output_name(shareable).as_ref() == Some(&output_name) {
+                view.canvas
+                    .set_color_correction(&shareable.wgpu, correction);
+            }
+        }
+        shareable
+            .data
+            .color_corrections
+            .insert(output_name, correction);
+    }
+
     #[instrument(name = "WaylandConnection::outputs", skip_all)]
     pub fn outputs(&self) -> impl Iterator<Item = WlOutput> {
         self.state.shareable.wayland.output_state.outputs()
@@ -320,11 +495,7 @@ impl WaylandConnection {
                     OverlayMode::Interactive | OverlayMode::Keybind => OverlayMode::Visible,
                     OverlayMode::Visible | OverlayMode::Hidden => OverlayMode::Interactive,
                 };
-                state.shareable.data.mode = mode;
-                for view in &mut state.views.canvas_views() {
-                    view.set_mode(&mut state.shareable, mode)
-                        .expect("failed to change mode");
-                }
+                state.set_overlay_mode(mode);
             }
             _ => {}
         }
@@ -332,16 +503,60 @@ impl WaylandConnection {
     }
 }
 
+/// Refresh rates outside of this range are assumed to be misreported.
+const PLAUSIBLE_REFRESH_RATES: std::ops::RangeInclusive<u64> = 24..=500;
+const FALLBACK_REFRESH_RATE: u64 = 60;
+
+/// The refresh rate of the current mode of the output in hertz, falling back to 60 when the
+/// output has no current mode or reports an implausible rate.
+pub(crate) fn output_refresh_rate(info: &OutputInfo) -> u64 {
+    // Modes report their refresh rate in millihertz.
+    let refresh_rate = info
+        .modes
+        .iter()
+        .find(|mode| mode.current)
+        .map(|mode| (mode.refresh_rate.max(0) as u64 + 500) / 1000);
+    match refresh_rate {
+        Some(refresh_rate) if PLAUSIBLE_REFRESH_RATES.contains(&refresh_rate) => refresh_rate,
+        refresh_rate => {
+            warn!("implausible refresh rate {refresh_rate:?}, using {FALLBACK_REFRESH_RATE}hz");
+            FALLBACK_REFRESH_RATE
+        }
+    }
+}
+
+/// The size of the current mode of the output, in the orientation it is shown in.
+///
+/// Modes are always reported in the untransformed orientation, so the dimensions of outputs
+/// rotated by 90 or 270 degrees are swapped.
+pub(crate) fn output_dimensions(info: &OutputInfo) -> Option<(i32, i32)> {
+    let mode = info.modes.iter().find(|mode| mode.current)?;
+    Some(transformed_dimensions(mode.dimensions, info.transform))
+}
+
+/// The dimensions of a mode once the output transform is applied.
+fn transformed_dimensions((width, height): (i32, i32), transform: Transform) -> (i32, i32) {
+    match transform {
+        Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270 => {
+            (height, width)
+        }
+        _ => (width, height),
+    }
+}
+
 impl CompositorHandler for State {
     #[instrument(name = "WaylandState::scale_factor_changed", skip_all)]
     fn scale_factor_changed(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _surface: &wayland_client::protocol::wl_surface::WlSurface,
-        _new_factor: i32,
+        surface: &wayland_client::protocol::wl_surface::WlSurface,
+        new_factor: i32,
     ) {
         info!("`scale_factor_changed` called");
+        if let Some(view) = self.views.from_surface(surface) {
+            view.integer_scale_changed(&mut self.shareable, new_factor);
+        }
     }
 
     #[instrument(name = "WaylandState::transform_changed", skip_all)]
@@ -350,9 +565,11 @@ impl CompositorHandler for State {
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
         _surface: &wayland_client::protocol::wl_surface::WlSurface,
-        _new_transform: wayland_client::protocol::wl_output::Transform,
+        new_transform: wayland_client::protocol::wl_output::Transform,
     ) {
-        info!("`transform_changed` called");
+        // Buffers are always submitted untransformed, the compositor rotates them to match the
+        // output. Output sizes are handled in `update_output`.
+        info!("`transform_changed` called with {new_transform:?}");
     }
 
     #[instrument(name = "WaylandState::frame", skip_all)]
@@ -399,9 +616,15 @@ impl OutputHandler for State {
         // TODO: check if monitor is in disallow config.
     }
 
-    fn update_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {
+    fn update_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, output: WlOutput) {
         info!("`update_output` called");
-        // TODO: work out scaling for canvas
+        // The mode or transform of the output may have changed, which changes the size of the
+        // canvas.
+        for view in self.views.canvas_views() {
+            if view.output == output {
+                view.output_changed(&mut self.shareable);
+            }
+        }
     }
 
     fn output_destroyed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {
@@ -422,7 +645,7 @@ impl LayerShellHandler for State {
             .iter_mut()
             .position(|v| &v.layer_surface == layer)
         {
-            self.views.layer_shell_views.remove(view_idx);
+            self.views.layer_shell_views.remove(view_idx).destroy();
         }
     }
 
@@ -440,12 +663,15 @@ impl LayerShellHandler for State {
             configure.new_size.0, configure.new_size.1
         );
 
-        let view = self
+        let Some(view) = self
             .views
             .layer_shell_views
             .iter_mut()
             .find(|v| &v.layer_surface == layer)
-            .expect("`configure` called for a layer shell view not in the manager");
+        else {
+            warn!("`configure` called for a layer shell view not in the manager");
+            return;
+        };
 
         view.configure(
             &mut self.shareable,
@@ -469,7 +695,11 @@ impl FractionalScaleHandler for State {
         surface: &WlSurface,
         scale: u32,
     ) {
-        self.views.from_surface(surface).set_scale_factor(
+        let Some(view) = self.views.from_surface(surface) else {
+            warn!("`preferred_scale` called for a surface without a view");
+            return;
+        };
+        view.set_scale_factor(
             &mut self.shareable,
             <u32 as TryInto<f64>>::try_into(scale)
                 .expect("fractional scale factor doesn't fit in f64")
@@ -478,6 +708,23 @@ impl FractionalScaleHandler for State {
     }
 }
 
+impl TabletHandler for State {
+    fn tablet_frame(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        events: &[TabletEvent],
+    ) {
+        for event in events {
+            let Some(view) = self.views.from_surface(&event.surface) else {
+                warn!("`tablet_frame` called for a surface without a view");
+                continue;
+            };
+            view.tablet_event(&mut self.shareable, event);
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct RedrawManager {
     loop_handle: LoopHandle<'static, State>,
@@ -489,6 +736,12 @@ pub struct RedrawManager {
     animation_end: Rc<Cell<Option<Instant>>>,
 }
 
+/// How long each frame lasts at the given rate, in hertz, without rounding it to whole
+/// milliseconds.
+fn frame_duration(fps: u64) -> Duration {
+    Duration::from_secs_f64(1. / fps.max(1) as f64)
+}
+
 impl RedrawManager {
     pub fn new(
         loop_handle: LoopHandle<'static, State>,
@@ -497,8 +750,8 @@ impl RedrawManager {
     ) -> Self {
         Self {
             loop_handle,
-            time_per_frame: fps.map(|x| std::time::Duration::from_millis(1000 / x)),
-            animation_time_per_frame: std::time::Duration::from_millis(1000 / animation_fps),
+            time_per_frame: fps.map(frame_duration),
+            animation_time_per_frame: frame_duration(animation_fps),
 
             pending_redraw: Rc::new(Cell::new(false)),
             last_redraw: Rc::new(Cell::new(Instant::now())),
@@ -506,6 +759,13 @@ impl RedrawManager {
         }
     }
 
+    /// Paces redraws and animations to the given refresh rate, in hertz.
+    pub fn set_refresh_rate(&mut self, refresh_rate: u64) {
+        let time_per_frame = frame_duration(refresh_rate);
+        self.time_per_frame = Some(time_per_frame);
+        self.animation_time_per_frame = time_per_frame;
+    }
+
     pub fn insert(&self, timer: Timer) {
         let last_redraw = self.last_redraw.clone();
         let pending_redraw = self.pending_redraw.clone();
@@ -576,6 +836,7 @@ impl RedrawRequest for RedrawManager {
 delegate_compositor!(State);
 delegate_output!(State);
 delegate_seat!(State);
+delegate_data_device!(State);
 delegate_shm!(State);
 
 delegate_keyboard!(State);
@@ -584,6 +845,7 @@ delegate_pointer!(State);
 delegate_layer!(State);
 crate::delegate_fractional_scale!(State);
 crate::delegate_viewporter!(State);
+crate::delegate_tablet!(State);
 
 delegate_registry!(State);
 
@@ -593,3 +855,41 @@ impl ProvidesRegistryState for State {
     }
     registry_handlers![OutputState, SeatState];
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_not_rounded_to_milliseconds() {
+        assert_eq!(frame_duration(60), Duration::from_nanos(16_666_667));
+        assert_eq!(frame_duration(144), Duration::from_nanos(6_944_444));
+        assert_eq!(frame_duration(0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn rotated_outputs_swap_their_dimensions() {
+        for transform in [
+            Transform::_90,
+            Transform::_270,
+            Transform::Flipped90,
+            Transform::Flipped270,
+        ] {
+            assert_eq!(
+                transformed_dimensions((1920, 1080), transform),
+                (1080, 1920)
+            );
+        }
+        for transform in [
+            Transform::Normal,
+            Transform::_180,
+            Transform::Flipped,
+            Transform::Flipped180,
+        ] {
+            assert_eq!(
+                transformed_dimensions((1920, 1080), transform),
+                (1920, 1080)
+            );
+        }
+    }
+}
@@ -1,13 +1,24 @@
 mod logging;
 
 use anyhow::{bail, Context, Result};
+use smithay_client_toolkit::reexports::calloop::signals::Signals;
 use tracing::info;
 
 use wayland::views::LayerShellCanvasView;
 use wayland::WaylandConnection;
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
+    // Before the runtime and the log writer spawn their threads, so that they inherit the blocked
+    // signals.
+    let signals = wayland::termination_signals();
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("failed to build the tokio runtime")?
+        .block_on(run(signals))
+}
+
+async fn run(signals: Result<Signals>) -> Result<()> {
     let _guard = logging::init_logging()?;
     let mut conn = WaylandConnection::new().await?;
 
@@ -26,6 +37,6 @@ async fn main() -> Result<()> {
         conn.state.views.add_canvas_view(view);
     }
 
-    conn.run().await.context("test")?;
+    conn.run(signals).await.context("test")?;
     Ok(())
 }
@@ -11,6 +11,7 @@ impl PointerHandler for State {
         pointer: &wl_pointer::WlPointer,
         events: &[smithay_client_toolkit::seat::pointer::PointerEvent],
     ) {
+        self.reset_idle_timer();
         for event in events {
             _ = self
                 .shareable
@@ -18,7 +19,10 @@ impl PointerHandler for State {
                 .first_surface
                 .get_or_insert(event.surface.clone());
 
-            let view = self.views.from_surface(&event.surface);
+            let Some(view) = self.views.from_surface(&event.surface) else {
+                tracing::warn!("pointer event dispatched for a surface without a view");
+                continue;
+            };
 
             if let Some((_, themed_pointer)) =
                 self.pointers.values_mut().find(|x| x.0 == pointer.id())
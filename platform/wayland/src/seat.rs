@@ -13,14 +13,25 @@ impl SeatHandler for State {
         &mut self.shareable.wayland.seat_state
     }
 
-    fn new_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _seat: WlSeat) {
-        trace!("Adding new seat...")
+    fn new_seat(&mut self, _conn: &Connection, qh: &QueueHandle<Self>, seat: WlSeat) {
+        trace!("Adding new seat...");
+        if let Some(tablet_state) = &self.shareable.wayland.tablet_state {
+            self.tablet_seats
+                .insert(seat.id(), tablet_state.get_tablet_seat(&seat, qh));
+        }
+        if let Some(clipboard) = &mut self.shareable.wayland.clipboard {
+            clipboard.add_seat(&seat, qh);
+        }
     }
     fn remove_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, seat: WlSeat) {
         trace!("Removing seat...");
         // remove the pointer and keyboard if they haven't been already
         self.pointers.remove(&seat.id());
         self.keyboards.remove(&seat.id());
+        self.tablet_seats.remove(&seat.id());
+        if let Some(clipboard) = &mut self.shareable.wayland.clipboard {
+            clipboard.remove_seat(&seat);
+        }
     }
 
     #[instrument(name = "SeatHandler::new_capability", skip_all)]
@@ -43,7 +54,9 @@ impl SeatHandler for State {
                     let Some(surface) = &kb.last_surface else {
                         return;
                     };
-                    let view = state.views.from_surface(&surface);
+                    let Some(view) = state.views.from_surface(&surface) else {
+                        return;
+                    };
 
                     view.keyboard_event(
                         &mut state.shareable,
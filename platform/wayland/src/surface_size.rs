@@ -0,0 +1,114 @@
+//! The sizes of a surface covering an output, which have to agree with each other at every
+//! scale for the canvas to be drawn crisp and uncropped.
+
+use euclid::default::Size2D;
+
+/// The sizes of a surface that covers an output at a given scale.
+///
+/// The compositor maps the logical size onto the output, so the buffer has to hold the logical
+/// size at the scale exactly. With the viewporter the buffer is scaled down by the fractional
+/// scale, the buffer being `round(logical * scale)` as the fractional scale protocol expects.
+/// Without it the buffer can only be scaled by whole numbers, so a fractional scale is rounded
+/// up and the compositor scales the buffer down slightly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SurfaceSize {
+    /// The size of the surface in logical coordinates, given to the layer surface and the
+    /// viewport destination.
+    pub logical: Size2D<u32>,
+    /// The size of the buffer in pixels, which the wgpu surface is configured with.
+    pub buffer: Size2D<u32>,
+    /// The integer buffer scale of the surface, one when the viewporter scales the buffer.
+    pub buffer_scale: i32,
+}
+
+impl SurfaceSize {
+    /// The sizes of a surface covering an output of `physical` pixels at `scale_factor`, which is
+    /// applied through the viewporter when `fractional` is set.
+    pub fn new(physical: Size2D<u32>, scale_factor: f64, fractional: bool) -> Self {
+        let scale_factor = if scale_factor > 0. { scale_factor } else { 1. };
+        // The output is as large in logical coordinates either way, only the buffer differs.
+        let logical = Size2D::new(
+            logical_length(physical.width, scale_factor),
+            logical_length(physical.height, scale_factor),
+        );
+        if fractional {
+            let buffer_length = |length: u32| (length as f64 * scale_factor).round() as u32;
+            Self {
+                logical,
+                buffer: Size2D::new(buffer_length(logical.width), buffer_length(logical.height)),
+                buffer_scale: 1,
+            }
+        } else {
+            let buffer_scale = scale_factor.ceil() as u32;
+            Self {
+                logical,
+                buffer: logical * buffer_scale,
+                buffer_scale: buffer_scale as i32,
+            }
+        }
+    }
+
+    /// The size the canvas projection and gui are laid out in, which is the buffer in pixels.
+    pub fn viewport(&self) -> Size2D<f32> {
+        self.buffer.cast()
+    }
+}
+
+/// The logical length covering `physical` pixels, rounding up so the output is covered except
+/// where the division is only off by floating point error.
+fn logical_length(physical: u32, scale_factor: f64) -> u32 {
+    let logical = physical as f64 / scale_factor;
+    if (logical - logical.round()).abs() < 1e-6 {
+        logical.round() as u32
+    } else {
+        logical.ceil() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unscaled_sizes_match_the_output() {
+        for fractional in [false, true] {
+            let size = SurfaceSize::new(Size2D::new(1920, 1080), 1., fractional);
+            assert_eq!(size.logical, Size2D::new(1920, 1080));
+            assert_eq!(size.buffer, Size2D::new(1920, 1080));
+            assert_eq!(size.buffer_scale, 1);
+        }
+    }
+
+    #[test]
+    fn fractional_scales_cover_the_output() {
+        let size = SurfaceSize::new(Size2D::new(2880, 1800), 1.5, true);
+        assert_eq!(size.logical, Size2D::new(1920, 1200));
+        assert_eq!(size.buffer, Size2D::new(2880, 1800));
+        assert_eq!(size.buffer_scale, 1);
+
+        // 2560 / 1.75 is not whole, so the surface reaches just past the output.
+        let size = SurfaceSize::new(Size2D::new(2560, 1440), 1.75, true);
+        assert_eq!(size.logical, Size2D::new(1463, 823));
+        assert_eq!(size.buffer, Size2D::new(2560, 1440));
+    }
+
+    #[test]
+    fn integer_scales_round_up() {
+        let size = SurfaceSize::new(Size2D::new(3840, 2160), 2., false);
+        assert_eq!(size.logical, Size2D::new(1920, 1080));
+        assert_eq!(size.buffer, Size2D::new(3840, 2160));
+        assert_eq!(size.buffer_scale, 2);
+
+        // The logical size still covers the output at 1.5, the buffer is drawn at twice it.
+        let size = SurfaceSize::new(Size2D::new(2561, 1440), 1.5, false);
+        assert_eq!(size.buffer_scale, 2);
+        assert_eq!(size.logical, Size2D::new(1708, 960));
+        assert_eq!(size.buffer, Size2D::new(3416, 1920));
+    }
+
+    #[test]
+    fn invalid_scales_are_unscaled() {
+        let size = SurfaceSize::new(Size2D::new(800, 600), 0., true);
+        assert_eq!(size.buffer, Size2D::new(800, 600));
+    }
+}
@@ -0,0 +1,350 @@
+use std::sync::Mutex;
+
+use wayland_client::globals::{BindError, GlobalList};
+use wayland_client::protocol::{wl_seat::WlSeat, wl_surface::WlSurface};
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
+
+use wayland_protocols::wp::tablet::zv2::client::{
+    zwp_tablet_manager_v2::ZwpTabletManagerV2,
+    zwp_tablet_pad_group_v2::{self, ZwpTabletPadGroupV2},
+    zwp_tablet_pad_ring_v2::ZwpTabletPadRingV2,
+    zwp_tablet_pad_strip_v2::ZwpTabletPadStripV2,
+    zwp_tablet_pad_v2::{self, ZwpTabletPadV2},
+    zwp_tablet_seat_v2::{self, ZwpTabletSeatV2},
+    zwp_tablet_tool_v2::{self, ZwpTabletToolV2},
+    zwp_tablet_v2::{self, ZwpTabletV2},
+};
+
+use smithay_client_toolkit::globals::GlobalData;
+
+/// The largest pressure the compositor reports.
+const MAX_PRESSURE: f32 = 65535.;
+
+#[derive(Debug)]
+pub struct TabletState {
+    manager: ZwpTabletManagerV2,
+}
+
+/// An owned instance of ZwpTabletSeatV2, when this is dropped, the underlying interface is
+/// destroyed, and hence the tablets of the seat are no longer announced.
+#[derive(Debug)]
+pub struct TabletSeat {
+    tablet_seat: ZwpTabletSeatV2,
+}
+
+/// What a tablet tool did in one frame, the compositor groups its events into frames like it
+/// does for pointers.
+#[derive(Debug, Clone)]
+pub struct TabletEvent {
+    /// The surface the tool is in proximity of.
+    pub surface: WlSurface,
+    /// The position in surface local coordinates.
+    pub position: (f64, f64),
+    /// How hard the tool is pressed, from 0 to 1.
+    pub pressure: f32,
+    pub time: u32,
+    pub kind: TabletEventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabletEventKind {
+    ProximityIn,
+    ProximityOut,
+    /// The tool touched the tablet.
+    Down,
+    /// The tool was lifted off the tablet.
+    Up,
+    Motion,
+}
+
+/// The events of a tool since its last frame.
+#[derive(Debug, Default)]
+pub struct ToolData {
+    frame: Mutex<ToolFrame>,
+}
+
+#[derive(Debug, Default)]
+struct ToolFrame {
+    surface: Option<WlSurface>,
+    position: (f64, f64),
+    pressure: f32,
+    pending: Vec<TabletEventKind>,
+}
+
+impl ToolFrame {
+    fn push(&mut self, kind: TabletEventKind) {
+        if !self.pending.contains(&kind) {
+            self.pending.push(kind);
+        }
+    }
+}
+
+impl TabletState {
+    pub fn bind<State>(
+        globals: &GlobalList,
+        queue_handle: &QueueHandle<State>,
+    ) -> Result<Self, BindError>
+    where
+        State: Dispatch<ZwpTabletManagerV2, GlobalData, State> + TabletHandler + 'static,
+    {
+        let manager = globals.bind(queue_handle, 1..=1, GlobalData)?;
+        Ok(TabletState { manager })
+    }
+
+    pub fn get_tablet_seat<State>(
+        &self,
+        seat: &WlSeat,
+        queue_handle: &QueueHandle<State>,
+    ) -> TabletSeat
+    where
+        State: Dispatch<ZwpTabletSeatV2, ()> + 'static,
+    {
+        TabletSeat {
+            tablet_seat: self.manager.get_tablet_seat(seat, queue_handle, ()),
+        }
+    }
+}
+
+impl Drop for TabletSeat {
+    fn drop(&mut self) {
+        self.tablet_seat.destroy();
+    }
+}
+
+impl<D> Dispatch<ZwpTabletManagerV2, GlobalData, D> for TabletState
+where
+    D: Dispatch<ZwpTabletManagerV2, GlobalData> + TabletHandler + 'static,
+{
+    fn event(
+        _: &mut D,
+        _: &ZwpTabletManagerV2,
+        _: <ZwpTabletManagerV2 as Proxy>::Event,
+        _: &GlobalData,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("ZwpTabletManagerV2 has no events")
+    }
+}
+
+impl<D> Dispatch<ZwpTabletSeatV2, (), D> for TabletState
+where
+    D: Dispatch<ZwpTabletSeatV2, ()>
+        + Dispatch<ZwpTabletV2, ()>
+        + Dispatch<ZwpTabletToolV2, ToolData>
+        + Dispatch<ZwpTabletPadV2, ()>
+        + TabletHandler
+        + 'static,
+{
+    fn event(
+        _: &mut D,
+        _: &ZwpTabletSeatV2,
+        _: zwp_tablet_seat_v2::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        // The added tablets, tools and pads are created with the user data below, only the tools
+        // are listened to.
+    }
+
+    wayland_client::event_created_child!(D, ZwpTabletSeatV2, [
+        zwp_tablet_seat_v2::EVT_TABLET_ADDED_OPCODE => (ZwpTabletV2, ()),
+        zwp_tablet_seat_v2::EVT_TOOL_ADDED_OPCODE => (ZwpTabletToolV2, ToolData::default()),
+        zwp_tablet_seat_v2::EVT_PAD_ADDED_OPCODE => (ZwpTabletPadV2, ()),
+    ]);
+}
+
+impl<D> Dispatch<ZwpTabletV2, (), D> for TabletState
+where
+    D: Dispatch<ZwpTabletV2, ()> + TabletHandler + 'static,
+{
+    fn event(
+        _: &mut D,
+        tablet: &ZwpTabletV2,
+        event: zwp_tablet_v2::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        if let zwp_tablet_v2::Event::Removed = event {
+            tablet.destroy();
+        }
+    }
+}
+
+impl<D> Dispatch<ZwpTabletToolV2, ToolData, D> for TabletState
+where
+    D: Dispatch<ZwpTabletToolV2, ToolData> + TabletHandler + 'static,
+{
+    fn event(
+        state: &mut D,
+        tool: &ZwpTabletToolV2,
+        event: zwp_tablet_tool_v2::Event,
+        data: &ToolData,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        let mut frame = data.frame.lock().unwrap();
+        match event {
+            zwp_tablet_tool_v2::Event::ProximityIn { surface, .. } => {
+                frame.surface = Some(surface);
+                frame.push(TabletEventKind::ProximityIn);
+            }
+            zwp_tablet_tool_v2::Event::ProximityOut => frame.push(TabletEventKind::ProximityOut),
+            zwp_tablet_tool_v2::Event::Down { .. } => frame.push(TabletEventKind::Down),
+            zwp_tablet_tool_v2::Event::Up => frame.push(TabletEventKind::Up),
+            zwp_tablet_tool_v2::Event::Motion { x, y } => {
+                frame.position = (x, y);
+                frame.push(TabletEventKind::Motion);
+            }
+            zwp_tablet_tool_v2::Event::Pressure { pressure } => {
+                frame.pressure = pressure as f32 / MAX_PRESSURE;
+            }
+            zwp_tablet_tool_v2::Event::Frame { time } => {
+                let Some(surface) = frame.surface.clone() else {
+                    frame.pending.clear();
+                    return;
+                };
+                let (position, pressure) = (frame.position, frame.pressure);
+                let events = frame
+                    .pending
+                    .drain(..)
+                    .map(|kind| TabletEvent {
+                        surface: surface.clone(),
+                        position,
+                        pressure,
+                        time,
+                        kind,
+                    })
+                    .collect::<Vec<_>>();
+                if events
+                    .iter()
+                    .any(|event| event.kind == TabletEventKind::ProximityOut)
+                {
+                    frame.surface = None;
+                    frame.pressure = 0.;
+                }
+                // The handler may dispatch more events, so the frame is not kept locked.
+                drop(frame);
+                state.tablet_frame(conn, qh, &events);
+            }
+            zwp_tablet_tool_v2::Event::Removed => tool.destroy(),
+            _ => {}
+        }
+    }
+}
+
+impl<D> Dispatch<ZwpTabletPadV2, (), D> for TabletState
+where
+    D: Dispatch<ZwpTabletPadV2, ()> + Dispatch<ZwpTabletPadGroupV2, ()> + TabletHandler + 'static,
+{
+    fn event(
+        _: &mut D,
+        pad: &ZwpTabletPadV2,
+        event: zwp_tablet_pad_v2::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        if let zwp_tablet_pad_v2::Event::Removed = event {
+            pad.destroy();
+        }
+    }
+
+    wayland_client::event_created_child!(D, ZwpTabletPadV2, [
+        zwp_tablet_pad_v2::EVT_GROUP_OPCODE => (ZwpTabletPadGroupV2, ()),
+    ]);
+}
+
+impl<D> Dispatch<ZwpTabletPadGroupV2, (), D> for TabletState
+where
+    D: Dispatch<ZwpTabletPadGroupV2, ()>
+        + Dispatch<ZwpTabletPadRingV2, ()>
+        + Dispatch<ZwpTabletPadStripV2, ()>
+        + TabletHandler
+        + 'static,
+{
+    fn event(
+        _: &mut D,
+        _: &ZwpTabletPadGroupV2,
+        _: zwp_tablet_pad_group_v2::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+    }
+
+    wayland_client::event_created_child!(D, ZwpTabletPadGroupV2, [
+        zwp_tablet_pad_group_v2::EVT_RING_OPCODE => (ZwpTabletPadRingV2, ()),
+        zwp_tablet_pad_group_v2::EVT_STRIP_OPCODE => (ZwpTabletPadStripV2, ()),
+    ]);
+}
+
+impl<D> Dispatch<ZwpTabletPadRingV2, (), D> for TabletState
+where
+    D: Dispatch<ZwpTabletPadRingV2, ()> + TabletHandler + 'static,
+{
+    fn event(
+        _: &mut D,
+        _: &ZwpTabletPadRingV2,
+        _: <ZwpTabletPadRingV2 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+    }
+}
+
+impl<D> Dispatch<ZwpTabletPadStripV2, (), D> for TabletState
+where
+    D: Dispatch<ZwpTabletPadStripV2, ()> + TabletHandler + 'static,
+{
+    fn event(
+        _: &mut D,
+        _: &ZwpTabletPadStripV2,
+        _: <ZwpTabletPadStripV2 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+    }
+}
+
+pub trait TabletHandler: Sized {
+    /// Called at the end of every frame of a tablet tool that is in proximity of a surface.
+    ///
+    /// Tools that are bound through this protocol no longer move the pointer, so this is the only
+    /// place where their input arrives.
+    fn tablet_frame(&mut self, conn: &Connection, qh: &QueueHandle<Self>, events: &[TabletEvent]);
+}
+
+#[macro_export]
+macro_rules! delegate_tablet {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        wayland_client::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols::wp::tablet::zv2::client::zwp_tablet_manager_v2::ZwpTabletManagerV2: smithay_client_toolkit::globals::GlobalData
+        ] => $crate::tablet::TabletState);
+        wayland_client::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols::wp::tablet::zv2::client::zwp_tablet_seat_v2::ZwpTabletSeatV2: ()
+        ] => $crate::tablet::TabletState);
+        wayland_client::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols::wp::tablet::zv2::client::zwp_tablet_v2::ZwpTabletV2: ()
+        ] => $crate::tablet::TabletState);
+        wayland_client::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols::wp::tablet::zv2::client::zwp_tablet_tool_v2::ZwpTabletToolV2: $crate::tablet::ToolData
+        ] => $crate::tablet::TabletState);
+        wayland_client::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols::wp::tablet::zv2::client::zwp_tablet_pad_v2::ZwpTabletPadV2: ()
+        ] => $crate::tablet::TabletState);
+        wayland_client::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols::wp::tablet::zv2::client::zwp_tablet_pad_group_v2::ZwpTabletPadGroupV2: ()
+        ] => $crate::tablet::TabletState);
+        wayland_client::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols::wp::tablet::zv2::client::zwp_tablet_pad_ring_v2::ZwpTabletPadRingV2: ()
+        ] => $crate::tablet::TabletState);
+        wayland_client::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols::wp::tablet::zv2::client::zwp_tablet_pad_strip_v2::ZwpTabletPadStripV2: ()
+        ] => $crate::tablet::TabletState);
+    };
+}
@@ -1,8 +1,9 @@
 use std::{num::NonZero, ptr::NonNull};
 
 use anyhow::{Context, Result};
+use canvas::session::SessionManager;
 use euclid::default::{Point2D, Size2D};
-use input::{sctk::KeyEventKind, CursorIcon, MouseEvent, MouseEventKind};
+use input::{sctk::KeyEventKind, CursorIcon, MouseButton, MouseEvent, MouseEventKind};
 use smithay_client_toolkit::{
     compositor::Region,
     seat::pointer::{PointerEvent, ThemedPointer},
@@ -17,6 +18,9 @@ use wayland_client::{
     Proxy,
 };
 
+use crate::clipboard::{self, WlClipboard};
+use crate::surface_size::SurfaceSize;
+use crate::tablet::{TabletEvent, TabletEventKind};
 use crate::{fractional_scale::FractionalScale, viewporter::Viewport};
 use crate::{OverlayMode, RedrawManager, ShareableState};
 
@@ -25,8 +29,12 @@ use crate::views::{LayerShellView, View};
 // Layer shell view implementation
 pub struct LayerShellCanvasView {
     pub layer_surface: LayerSurface,
-    pub fractional_scale: FractionalScale,
-    pub viewport: Viewport,
+    /// The output the canvas covers.
+    pub output: WlOutput,
+    /// Only set when both fractional scaling protocols are available, otherwise the surface uses
+    /// the integer buffer scale.
+    pub fractional_scale: Option<FractionalScale>,
+    pub viewport: Option<Viewport>,
     pub wgpu_surface: wgpu::Surface<'static>,
 
     pub scale_factor: Option<f64>,
@@ -37,6 +45,8 @@ pub struct LayerShellCanvasView {
 
     pub mode: OverlayMode,
     pub configured: bool,
+    /// The [`ShareableState`] device generation the gpu resources of the view belong to.
+    device_generation: u64,
 }
 
 impl LayerShellCanvasView {
@@ -63,30 +73,32 @@ impl LayerShellCanvasView {
 
         let info = state.wayland.output_state.info(output).unwrap();
         trace!("Found info for: {:?}: {:?}", info.name, info.modes);
-        tracing::info!("modes: {:?}", info.modes);
-        let current_mode = info
-            .modes
-            .iter()
-            .find(|mode| mode.current)
+        tracing::info!("modes: {:?}, transform: {:?}", info.modes, info.transform);
+        let dimensions = crate::output_dimensions(&info)
             .context("cannot determine output size: display has no current mode")?;
 
-        let physical_size: Size2D<u32> =
-            Size2D::new(current_mode.dimensions.0, current_mode.dimensions.1)
-                .try_cast()
-                .expect("monitor dimensions should be positive");
-
-        let fractional_scale = state
-            .wayland
-            .fractional_state
-            .get_scale(layer_surface.wl_surface(), &state.wayland.queue_handle);
-
-        let viewport = state
-            .wayland
-            .viewporter
-            .get_viewport(layer_surface.wl_surface(), &state.wayland.queue_handle);
-        viewport.set_destination(current_mode.dimensions.0, current_mode.dimensions.1);
-
-        layer_surface.set_size(physical_size.width, physical_size.height);
+        let physical_size: Size2D<u32> = Size2D::new(dimensions.0, dimensions.1)
+            .try_cast()
+            .expect("monitor dimensions should be positive");
+
+        let (fractional_scale, viewport) =
+            match (&state.wayland.fractional_state, &state.wayland.viewporter) {
+                (Some(fractional_state), Some(viewporter)) => {
+                    let fractional_scale = fractional_state
+                        .get_scale(layer_surface.wl_surface(), &state.wayland.queue_handle);
+                    let viewport = viewporter
+                        .get_viewport(layer_surface.wl_surface(), &state.wayland.queue_handle);
+                    (Some(fractional_scale), Some(viewport))
+                }
+                _ => (None, None),
+            };
+        // The scale is not known until the compositor sends it, until then the output is covered
+        // unscaled.
+        let size = SurfaceSize::new(physical_size, 1., viewport.is_some());
+        if let Some(viewport) = &viewport {
+            viewport.set_destination(size.logical.width as i32, size.logical.height as i32);
+        }
+        layer_surface.set_size(size.logical.width, size.logical.height);
         // initial commit before we attach wgpu to the surface.
 
         // INFO: WGPU stuff
@@ -117,24 +129,177 @@ impl LayerShellCanvasView {
         layer_surface.set_exclusive_zone(-1);
 
         layer_surface.commit();
-        let redraw_manager = RedrawManager::new(state.loop_handle.clone(), Some(240), 60);
+        // Each output paces its own canvas, so a faster monitor animates faster.
+        let refresh_rate = crate::output_refresh_rate(&info);
+        info!("pacing the canvas to {refresh_rate}hz");
+        let redraw_manager =
+            RedrawManager::new(state.loop_handle.clone(), Some(refresh_rate), refresh_rate);
+
+        let mut canvas = canvas::view::View::new(
+            &state.wgpu,
+            size.viewport(),
+            1.,
+            redraw_manager,
+            Default::default(),
+        );
+        if let Some(correction) = info
+            .name
+            .as_ref()
+            .and_then(|name| state.data.color_corrections.get(name))
+        {
+            canvas.set_color_correction(&state.wgpu, *correction);
+        }
+        match crate::get_session_directory() {
+            Ok(directory) => canvas.sessions = Some(SessionManager::new(directory)),
+            Err(err) => warn!("sessions are unavailable: {err:#}"),
+        }
+        match crate::get_export_directory() {
+            Ok(directory) => canvas.exports = Some(directory),
+            Err(err) => warn!("exporting is unavailable: {err:#}"),
+        }
+        match crate::get_ui_settings_path() {
+            Ok(path) => canvas.load_ui_settings(path),
+            Err(err) => warn!("the interface settings are not kept: {err:#}"),
+        }
 
         Ok(Self {
             layer_surface,
+            output: output.clone(),
             wgpu_surface,
             viewport,
             fractional_scale,
 
             mode,
             physical_size,
-            canvas: canvas::view::View::new(&state.wgpu, physical_size.cast(), 1., redraw_manager),
+            canvas,
             previous_cursor_icon: None,
 
             scale_factor: None,
 
             configured: false,
+            device_generation: state.device_generation,
         })
     }
+
+    /// Tears the view down, dropping the gpu resources before the surfaces they present to and
+    /// then unmapping and destroying the layer surface.
+    #[instrument(name = "LayerShellCanvasView::destroy", skip_all)]
+    pub fn destroy(self) {
+        let Self {
+            layer_surface,
+            wgpu_surface,
+            canvas,
+            fractional_scale,
+            viewport,
+            ..
+        } = self;
+        drop(canvas);
+        // The swapchain of the wgpu surface refers to the wayland surface, so it goes first.
+        drop(wgpu_surface);
+        drop(fractional_scale);
+        drop(viewport);
+        // Committing without a buffer unmaps the surface, dropping the layer surface destroys it
+        // along with its wayland surface.
+        layer_surface.wl_surface().attach(None, 0, 0);
+        layer_surface.commit();
+        drop(layer_surface);
+    }
+
+    /// Resizes the canvas after the mode or transform of its output changed.
+    #[instrument(name = "LayerShellCanvasView::output_changed", skip_all)]
+    pub fn output_changed(&mut self, state: &mut ShareableState) {
+        let info = state.wayland.output_state.info(&self.output);
+        if let Some(info) = &info {
+            self.canvas
+                .redraw_manager
+                .set_refresh_rate(crate::output_refresh_rate(info));
+        }
+        let Some(dimensions) = info.as_ref().and_then(crate::output_dimensions) else {
+            warn!("cannot determine output size: display has no current mode");
+            return;
+        };
+        let Some(physical_size) = Size2D::new(dimensions.0, dimensions.1).try_cast::<u32>() else {
+            warn!("ignoring output with a negative size: {dimensions:?}");
+            return;
+        };
+        if physical_size != self.physical_size {
+            info!(
+                "output resized from {:?} to {physical_size:?}",
+                self.physical_size
+            );
+            self.physical_size = physical_size;
+            // Re-applying the scale factor resizes the surface, the new size is picked up when
+            // the surface is configured.
+            self.set_scale_factor(state, self.get_scale_factor());
+        }
+    }
+
+    fn set_cursor(
+        &mut self,
+        state: &ShareableState,
+        themed_pointer: &ThemedPointer,
+        cursor_icon: CursorIcon,
+    ) {
+        if self.previous_cursor_icon != Some(cursor_icon) {
+            self.previous_cursor_icon = Some(cursor_icon);
+            _ = themed_pointer.set_cursor(
+                &state.wayland.connection,
+                input::sctk::cursor_icon(cursor_icon),
+            );
+        }
+    }
+
+    /// The name of the output the canvas covers, e.g. `DP-1`.
+    pub fn output_name(&self, state: &ShareableState) -> Option<String> {
+        state.wayland.output_state.info(&self.output)?.name
+    }
+
+    /// Recreates the resources of the view after the shared device was recreated.
+    fn recover_device(&mut self, state: &mut ShareableState) {
+        info!("recreating the canvas resources on the new device");
+        self.device_generation = state.device_generation;
+        self.configure(state, self.physical_size.width, self.physical_size.height);
+        self.canvas.recover_device(&state.wgpu);
+    }
+
+    /// The sizes of the surface at the current scale.
+    fn surface_size(&self) -> SurfaceSize {
+        SurfaceSize::new(
+            self.physical_size,
+            self.get_scale_factor(),
+            self.viewport.is_some(),
+        )
+    }
+
+    /// Configures the wgpu surface with the buffer size at the current scale.
+    fn configure_wgpu_surface(&mut self, state: &ShareableState) {
+        let size = self.surface_size();
+        self.wgpu_surface.configure(
+            &state.wgpu.device,
+            &wgpu::SurfaceConfiguration {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format: state.wgpu.texture_format,
+                width: size.buffer.width,
+                height: size.buffer.height,
+                present_mode: wgpu::PresentMode::Mailbox,
+                desired_maximum_frame_latency: 0,
+                alpha_mode: wgpu::CompositeAlphaMode::PreMultiplied,
+                view_formats: vec![],
+            },
+        );
+    }
+
+    /// Whether the canvas is mid-interaction, see [`canvas::view::View::is_busy`].
+    pub fn is_busy(&self) -> bool {
+        self.canvas.is_busy()
+    }
+
+    /// Pastes a png read from the clipboard, see [`canvas::view::View::paste_image`].
+    pub fn paste_image(&mut self, state: &ShareableState, png: &[u8]) {
+        if let Err(err) = self.canvas.paste_image(png, &state.wgpu) {
+            warn!("failed to paste the clipboard image: {err:#}");
+        }
+    }
 }
 
 impl LayerShellView for LayerShellCanvasView {
@@ -175,6 +340,10 @@ impl View for LayerShellCanvasView {
             return Ok(());
         }
         trace!("rendering canvas view");
+        state.recover_lost_device()?;
+        if self.device_generation != state.device_generation {
+            self.recover_device(state);
+        }
         if matches!(self.mode, OverlayMode::Hidden) {
             tracing::warn!("rendering in mode: Hidden");
             match self.wgpu_surface.get_current_texture() {
@@ -218,23 +387,9 @@ impl View for LayerShellCanvasView {
             "configuring canvas with size: {width}x{height} : self.monitor_size: {:?}",
             self.physical_size
         );
-
-        // let logical_size = (self.physical_size.cast() / self.get_scale_factor())
-        //     .ceil()
-        //     .cast();
-        self.wgpu_surface.configure(
-            &state.wgpu.device,
-            &wgpu::SurfaceConfiguration {
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-                format: state.wgpu.texture_format,
-                width: self.physical_size.width,
-                height: self.physical_size.height,
-                present_mode: wgpu::PresentMode::Mailbox,
-                desired_maximum_frame_latency: 0,
-                alpha_mode: wgpu::CompositeAlphaMode::PreMultiplied,
-                view_formats: vec![],
-            },
-        );
+        // The configured size is logical, the buffer size follows from the output and the scale
+        // instead so that it always matches what the surface was sized to.
+        self.configure_wgpu_surface(state);
 
         if !self.configured {
             self.configured = true;
@@ -259,46 +414,124 @@ impl View for LayerShellCanvasView {
 
         let cursor_icon = self
             .canvas
-            .mouse_event(MouseEvent { position, kind }, &state.wgpu);
+            .mouse_event(MouseEvent::new(position, kind), &state.wgpu);
 
         if let Some(cursor_icon) = cursor_icon {
-            if self.previous_cursor_icon != Some(cursor_icon) {
-                self.previous_cursor_icon = Some(cursor_icon);
-                _ = themed_pointer.set_cursor(
-                    &state.wayland.connection,
-                    input::sctk::cursor_icon(cursor_icon),
-                );
-            }
+            self.set_cursor(state, themed_pointer, cursor_icon);
         };
     }
 
+    fn tablet_event(&mut self, state: &mut ShareableState, event: &TabletEvent) {
+        let position: Point2D<f32> =
+            (Point2D::from(event.position) * self.scale_factor.unwrap_or(1.)).cast();
+        let time = event.time;
+        // The tip is the only button of a tool that draws.
+        let kind = match event.kind {
+            TabletEventKind::ProximityIn => MouseEventKind::Enter,
+            TabletEventKind::ProximityOut => MouseEventKind::Leave,
+            TabletEventKind::Down => MouseEventKind::Press {
+                time,
+                button: MouseButton::Left,
+            },
+            TabletEventKind::Up => MouseEventKind::Release {
+                time,
+                button: MouseButton::Left,
+            },
+            TabletEventKind::Motion => MouseEventKind::Motion { time },
+        };
+        // Tools keep the cursor the compositor gives them, so the wanted cursor is not set.
+        self.canvas.mouse_event(
+            MouseEvent::new(position, kind).with_pressure(event.pressure),
+            &state.wgpu,
+        );
+    }
+
+    fn update_cursor(&mut self, state: &mut ShareableState, themed_pointer: &ThemedPointer) {
+        if let Some(cursor_icon) = self.canvas.current_cursor() {
+            self.set_cursor(state, themed_pointer, cursor_icon);
+        }
+    }
+
     fn keyboard_event(&mut self, state: &mut ShareableState, kind: &KeyEventKind) {
         match kind {
             KeyEventKind::Press((event, _modifiers)) => {
-                tracing::warn!("char: {:?}", event.keysym.key_char());
+                tracing::trace!("char: {:?}", event.keysym.key_char());
             }
             _ => {}
         }
 
         let event = input::sctk::keyboard_event(&kind);
+        if self.canvas.accepts_shortcuts() {
+            if event.is_ctrl_shortcut('c') {
+                if let Some(copied) = self.canvas.copy_selection() {
+                    state.data.clipboard = Some(copied);
+                    if let Some(clipboard) = &mut state.wayland.clipboard {
+                        clipboard.copied();
+                    }
+                }
+                return;
+            } else if event.is_ctrl_shortcut('x') {
+                if let Some(copied) = self.canvas.cut_selection(&state.wgpu) {
+                    state.data.clipboard = Some(copied);
+                    if let Some(clipboard) = &mut state.wayland.clipboard {
+                        clipboard.copied();
+                    }
+                }
+                return;
+            } else if event.is_ctrl_shortcut('v') {
+                // An image copied from another client after anything in the overlay is pasted
+                // instead, anything else it offers falls through to the copied primitives.
+                if let Some(offer) = state
+                    .wayland
+                    .clipboard
+                    .as_ref()
+                    .and_then(WlClipboard::png_offer)
+                {
+                    clipboard::paste_png(state, &offer, self.layer_surface.wl_surface().clone());
+                } else if let Some(copied) = &state.data.clipboard {
+                    self.canvas.paste(copied, &state.wgpu);
+                }
+                return;
+            }
+        }
         self.canvas.keyboard_event(event, &state.wgpu);
     }
+    fn captures_escape(&self) -> bool {
+        self.canvas.captures_escape()
+    }
     fn set_scale_factor(&mut self, state: &mut ShareableState, scale_factor: f64) {
-        tracing::warn!("scale factor for canvas: {scale_factor}");
-        let logical_size = (self.physical_size.cast() / scale_factor).ceil().cast();
+        info!("scale factor for canvas: {scale_factor}");
+        self.scale_factor = Some(scale_factor);
+        let size = self.surface_size();
+        match &self.viewport {
+            Some(viewport) => {
+                viewport.set_destination(size.logical.width as i32, size.logical.height as i32);
+            }
+            None => {
+                self.layer_surface
+                    .wl_surface()
+                    .set_buffer_scale(size.buffer_scale);
+            }
+        }
         self.layer_surface
-            .set_size(self.physical_size.width, self.physical_size.height);
-        self.canvas
-            .update_viewport(self.physical_size.cast(), self.get_scale_factor());
-        self.viewport
-            .set_destination(logical_size.width, logical_size.height);
+            .set_size(size.logical.width, size.logical.height);
+        self.canvas.update_viewport(size.viewport(), scale_factor);
         self.layer_surface.commit();
 
-        self.scale_factor = Some(scale_factor);
+        // The compositor only configures again when the logical size changes, while the buffer
+        // size can change without it.
+        if self.configured {
+            self.configure_wgpu_surface(state);
+        }
         _ = self.render(state);
     }
 
     fn get_scale_factor(&self) -> f64 {
         self.scale_factor.unwrap_or(1.)
     }
+    fn integer_scale_changed(&mut self, state: &mut ShareableState, scale: i32) {
+        if self.fractional_scale.is_none() {
+            self.set_scale_factor(state, scale.into());
+        }
+    }
 }
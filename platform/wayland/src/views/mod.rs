@@ -8,6 +8,7 @@ mod canvas;
 
 pub use canvas::LayerShellCanvasView;
 
+use crate::tablet::TabletEvent;
 use crate::ShareableState;
 
 pub struct ViewManager {
@@ -20,16 +21,19 @@ impl ViewManager {
         self.layer_shell_views.push(view)
     }
 
-    pub fn from_surface(&mut self, surface: &WlSurface) -> &mut dyn View {
-        for layer in &mut self.layer_shell_views {
-            if layer.surface() == surface {
-                return layer;
-            }
-        }
-        // if self.toolbar_view.surface() == surface {
-        //     return &mut self.toolbar_view;
-        // }
-        unreachable!("failed to find a view from the given surface");
+    /// Finds the view presenting the given surface.
+    ///
+    /// Events can still arrive for a surface after its view was closed, which gives `None`.
+    pub fn from_surface(&mut self, surface: &WlSurface) -> Option<&mut dyn View> {
+        self.views_mut().find(|view| view.surface() == surface)
+    }
+
+    /// Every registered view, whatever kind of surface it presents.
+    pub fn views_mut(&mut self) -> impl Iterator<Item = &mut dyn View> {
+        // Other kinds of views, like a separate toolbar surface, are chained on here.
+        self.layer_shell_views
+            .iter_mut()
+            .map(|view| view as &mut dyn View)
     }
 
     pub fn canvas_views(&mut self) -> Vec<&mut LayerShellCanvasView> {
@@ -44,6 +48,10 @@ pub trait View {
 
     fn set_scale_factor(&mut self, state: &mut ShareableState, scale_factor: f64);
     fn get_scale_factor(&self) -> f64;
+    /// Called when the integer scale of the surface changes, views that receive fractional
+    /// scales should ignore this.
+    #[allow(unused_variables)]
+    fn integer_scale_changed(&mut self, state: &mut ShareableState, scale: i32) {}
 
     fn pointer_event(
         &mut self,
@@ -53,6 +61,20 @@ pub trait View {
     );
 
     fn keyboard_event(&mut self, state: &mut ShareableState, kind: &KeyEventKind);
+
+    /// Called for the input of tablet tools, which unlike pointers report a pressure.
+    #[allow(unused_variables)]
+    fn tablet_event(&mut self, state: &mut ShareableState, event: &TabletEvent) {}
+
+    /// Sets the cursor to the one the view currently wants, without waiting for pointer motion.
+    #[allow(unused_variables)]
+    fn update_cursor(&mut self, state: &mut ShareableState, themed_pointer: &ThemedPointer) {}
+
+    /// Whether the view currently uses the escape key itself, in which case it should not hide
+    /// the overlay.
+    fn captures_escape(&self) -> bool {
+        false
+    }
 }
 
 pub trait LayerShellView: View {